@@ -1,3 +1,109 @@
+use std::collections::{HashMap, VecDeque};
+use std::{env, fs, path::Path};
+
+/// Crates of the codec libraries, whose versions `cramjam.compress(..., return_info=True)` reports
+const CODEC_CRATES: &[&str] = &[
+    "snap",
+    "lz4-sys",
+    "bzip2-sys",
+    "brotli",
+    "lzma-sys",
+    "zstd-sys",
+    "miniz_oxide",
+    "libz-ng-sys",
+    "blosc2-sys",
+    "isal-sys",
+];
+
 fn main() {
     pyo3_build_config::use_pyo3_cfgs();
+    codec_versions();
+}
+
+/// A `[[package]]` of Cargo.lock
+struct Package<'a> {
+    name: &'a str,
+    version: &'a str,
+    /// `name` or, when the lock has more than one version of it, `name version`
+    dependencies: Vec<&'a str>,
+}
+
+/// Set `CRAMJAM_VERSION_<CRATE>` to the version in Cargo.lock of each of `CODEC_CRATES` this
+/// crate depends on. Should the lock have more than one version of one, it's the version the
+/// dependency closest to this crate is on, which for the `-sys` crates is the only one linked.
+fn codec_versions() {
+    let lock = Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).join("Cargo.lock");
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed={}", lock.display());
+    let Ok(lock) = fs::read_to_string(&lock) else {
+        println!(
+            "cargo:warning=No Cargo.lock, so return_info=True won't report codec versions; run `cargo generate-lockfile`"
+        );
+        return;
+    };
+    let packages = parse_lock(&lock);
+    let find = |dependency: &str| {
+        let mut parts = dependency.split_whitespace();
+        let (name, version) = (parts.next(), parts.next());
+        packages
+            .iter()
+            .position(|package| Some(package.name) == name && version.is_none_or(|v| v == package.version))
+    };
+
+    // breadth first from this crate, so the first version of a crate found is the closest
+    let root = env::var("CARGO_PKG_NAME").unwrap();
+    let mut versions = HashMap::new();
+    let mut seen = vec![false; packages.len()];
+    let mut queue = packages
+        .iter()
+        .position(|package| package.name == root)
+        .into_iter()
+        .collect::<VecDeque<_>>();
+    while let Some(index) = queue.pop_front() {
+        if std::mem::replace(&mut seen[index], true) {
+            continue;
+        }
+        let package = &packages[index];
+        if CODEC_CRATES.contains(&package.name) {
+            versions.entry(package.name).or_insert(package.version);
+        }
+        queue.extend(package.dependencies.iter().filter_map(|dependency| find(dependency)));
+    }
+    for (krate, version) in versions {
+        println!(
+            "cargo:rustc-env=CRAMJAM_VERSION_{}={}",
+            krate.replace('-', "_").to_uppercase(),
+            version
+        );
+    }
+}
+
+fn parse_lock(lock: &str) -> Vec<Package<'_>> {
+    let mut packages = vec![];
+    let mut in_dependencies = false;
+    for line in lock.lines() {
+        if line == "[[package]]" {
+            packages.push(Package {
+                name: "",
+                version: "",
+                dependencies: vec![],
+            });
+            in_dependencies = false;
+        } else if let Some(package) = packages.last_mut() {
+            if let Some(name) = line.strip_prefix("name = ") {
+                package.name = name.trim_matches('"');
+            } else if let Some(version) = line.strip_prefix("version = ") {
+                package.version = version.trim_matches('"');
+            } else if line == "dependencies = [" {
+                in_dependencies = true;
+            } else if line == "]" {
+                in_dependencies = false;
+            } else if in_dependencies {
+                // older locks follow the version with the source in parentheses
+                let dependency = line.trim().trim_end_matches(',').trim_matches('"');
+                package.dependencies.push(dependency.split(" (").next().unwrap());
+            }
+        }
+    }
+    packages
 }
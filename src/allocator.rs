@@ -0,0 +1,171 @@
+//! Allocate the output of one-shot decompression with a Python callback, so it's written straight
+//! into memory a framework manages, ie. CUDA pinned host memory, instead of being copied there
+//! from a `cramjam.Buffer` afterwards.
+//!
+//! ### Python Example
+//! ```python
+//! >>> cramjam.set_output_allocator(lambda n: torch.empty(n, dtype=torch.uint8).pin_memory().numpy())
+//! >>> view = cramjam.zstd.decompress(compressed, output_len=n)  # memoryview of the pinned tensor
+//! >>> torch.frombuffer(view, dtype=torch.uint8).to("cuda", non_blocking=True)
+//! ```
+use std::io::{Error, ErrorKind, Write};
+use std::sync::Mutex;
+
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyMemoryView, PySlice};
+
+use crate::io::{PythonBuffer, RustyBuffer};
+
+/// The allocator set with `set_output_allocator`, if any
+static OUTPUT_ALLOCATOR: Mutex<Option<PyObject>> = Mutex::new(None);
+
+/// Set the allocator for the output of `decompress` calls giving `output_len`, or `None` to
+/// remove it.
+///
+/// `allocator` is called with the `output_len` in bytes, and returns a writable, C contiguous
+/// object implementing the buffer protocol with at least that many bytes. The data is then
+/// decompressed directly into it, and `decompress` returns a `memoryview` of the bytes written;
+/// data which decompresses larger than `output_len` raises `DecompressionError`. Calls without
+/// `output_len` or with `into=` return a `cramjam.Buffer` as usual.
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> cramjam.set_output_allocator(lambda n: numpy.empty(n, dtype=numpy.uint8))
+/// >>> cramjam.gzip.decompress(compressed_bytes, output_len=n)
+/// <memory at 0x...>
+/// >>> cramjam.set_output_allocator(None)
+/// ```
+#[pyfunction]
+#[pyo3(signature = (allocator))]
+pub fn set_output_allocator(allocator: Option<Bound<'_, PyAny>>) -> PyResult<()> {
+    if let Some(allocator) = allocator.as_ref().filter(|allocator| !allocator.is_callable()) {
+        return Err(PyTypeError::new_err(format!(
+            "allocator must be callable, got {}",
+            allocator.get_type().name()?
+        )));
+    }
+    *OUTPUT_ALLOCATOR.lock().unwrap() = allocator.map(Bound::unbind);
+    Ok(())
+}
+
+/// The allocator set with `set_output_allocator`, `None` if there isn't one.
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> cramjam.get_output_allocator()
+/// ```
+#[pyfunction]
+pub fn get_output_allocator(py: Python) -> Option<PyObject> {
+    OUTPUT_ALLOCATOR
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|allocator| allocator.clone_ref(py))
+}
+
+/// Output of a one-shot decompression allocated by the output allocator
+pub struct AllocatedOutput {
+    view: PyObject,
+    buffer: PythonBuffer,
+}
+
+/// Output from the output allocator for `len` bytes, if one is set and `into=` wasn't given
+pub(crate) fn allocate_output(
+    py: Python,
+    len: Option<usize>,
+    into: &Option<Bound<RustyBuffer>>,
+) -> PyResult<Option<AllocatedOutput>> {
+    let (len, allocator) = match (len, into, get_output_allocator(py)) {
+        (Some(len), None, Some(allocator)) => (len, allocator),
+        _ => return Ok(None),
+    };
+    let object = allocator.call1(py, (len,))?;
+    let view = PyMemoryView::from_bound(object.bind(py))?;
+    if view.getattr("readonly")?.is_truthy()? || !view.getattr("c_contiguous")?.is_truthy()? {
+        return Err(PyTypeError::new_err(
+            "The output allocator must return a writable, C contiguous buffer",
+        ));
+    }
+    let view = view.call_method1("cast", ("B",))?;
+    let buffer = PythonBuffer::try_from(&view)?;
+    if buffer.len_bytes() < len {
+        return Err(PyValueError::new_err(format!(
+            "The output allocator returned {} bytes, but {} were requested",
+            buffer.len_bytes(),
+            len
+        )));
+    }
+    Ok(Some(AllocatedOutput {
+        view: view.unbind(),
+        buffer,
+    }))
+}
+
+impl AllocatedOutput {
+    /// The bytes written
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        &self.buffer.as_slice()[..self.buffer.position()]
+    }
+}
+
+impl Write for AllocatedOutput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self.buffer.write(buf)? {
+            0 if !buf.is_empty() => Err(Error::new(
+                ErrorKind::WriteZero,
+                format!(
+                    "Decompressed output exceeds the {} bytes from the output allocator, see `output_len`",
+                    self.buffer.len_bytes()
+                ),
+            )),
+            n => Ok(n),
+        }
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl IntoPy<PyObject> for AllocatedOutput {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let slice = PySlice::new_bound(py, 0, self.buffer.position() as isize, 1);
+        self.view
+            .bind(py)
+            .get_item(slice)
+            .expect("slicing a memoryview within its length")
+            .unbind()
+    }
+}
+
+/// Where a one-shot decompression writes: its own output, or that from the output allocator
+pub(crate) enum Sink<'a, W: Write> {
+    Output(W),
+    Allocated(&'a mut AllocatedOutput),
+}
+
+impl<'a, W: Write> Sink<'a, W> {
+    pub(crate) fn new(output: W, allocated: Option<&'a mut AllocatedOutput>) -> Self {
+        match allocated {
+            Some(allocated) => Sink::Allocated(allocated),
+            None => Sink::Output(output),
+        }
+    }
+}
+
+impl<'a, W: Write> Write for Sink<'a, W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Sink::Output(output) => output.write(buf),
+            Sink::Allocated(allocated) => allocated.write(buf),
+        }
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Sink::Output(output) => output.flush(),
+            Sink::Allocated(allocated) => allocated.flush(),
+        }
+    }
+}
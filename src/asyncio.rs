@@ -0,0 +1,240 @@
+//! De/compress off the event loop, returning awaitables, ie. for compressing large responses in
+//! an asyncio server without blocking other requests.
+//!
+//! ### Python Example
+//! ```python
+//! >>> compressed = await cramjam.asyncio.compress("zstd", b'some bytes here', level=3)
+//! >>> bytes(await cramjam.asyncio.decompress("zstd", compressed))
+//! b'some bytes here'
+//! ```
+use std::sync::{Condvar, Mutex};
+
+use pyo3::prelude::*;
+
+/// Number of jobs queued or running, which the interpreter waits for before exiting
+static PENDING: (Mutex<usize>, Condvar) = (Mutex::new(0), Condvar::new());
+
+/// Counts a job as pending until it's dropped, once it's run
+struct Pending;
+
+impl Pending {
+    fn new() -> Self {
+        *PENDING.0.lock().unwrap() += 1;
+        Pending
+    }
+}
+
+impl Drop for Pending {
+    fn drop(&mut self) {
+        *PENDING.0.lock().unwrap() -= 1;
+        PENDING.1.notify_all();
+    }
+}
+
+/// Wait for pending jobs at exit, as a worker still holding the GIL while the interpreter
+/// finalizes would crash it
+#[pyfunction]
+fn wait_pending(py: Python) {
+    py.allow_threads(|| {
+        let mut pending = PENDING.0.lock().unwrap();
+        while *pending > 0 {
+            pending = PENDING.1.wait(pending).unwrap();
+        }
+    })
+}
+
+/// Set the result, or exception, of `future` on its loop, unless it's been cancelled
+#[pyfunction]
+fn resolve(future: &Bound<'_, PyAny>, result: PyObject, error: PyObject) -> PyResult<()> {
+    if future.call_method0("done")?.is_truthy()? {
+        return Ok(());
+    }
+    if error.is_none(future.py()) {
+        future.call_method1("set_result", (result,))?;
+    } else {
+        future.call_method1("set_exception", (error,))?;
+    }
+    Ok(())
+}
+
+/// Awaitable de/compression, run on a pool of threads.
+///
+/// Each call returns an `asyncio.Future` of the running event loop, resolved once one of
+/// cramjam's worker threads, one per CPU and started on first use, has de/compressed the data.
+/// Unlike `loop.run_in_executor`, the work never needs the GIL, so the loop isn't held up by it.
+#[pymodule]
+pub mod asyncio {
+    use std::sync::mpsc::{channel, Receiver, Sender};
+    use std::sync::{Arc, Mutex, OnceLock};
+
+    use pyo3::exceptions::{PyTypeError, PyValueError};
+    use pyo3::prelude::*;
+
+    use crate::codecs::{codec_compress, codec_decompress, is_codec};
+    use crate::exceptions::{CompressionError, DecompressionError};
+    use crate::io::{AsBytes, PythonBuffer, RustyBuffer};
+    use crate::limits::output_limit;
+    use crate::BytesType;
+
+    type Job = Box<dyn FnOnce() + Send>;
+
+    #[pymodule_init]
+    fn init(m: &Bound<'_, PyModule>) -> PyResult<()> {
+        let wait_pending = wrap_pyfunction_bound!(super::wait_pending, m.py())?;
+        m.py()
+            .import_bound("atexit")?
+            .call_method1("register", (wait_pending,))?;
+        Ok(())
+    }
+
+    /// Input handed to a worker: a buffer protocol object stays exported, so it can't be
+    /// resized or freed meanwhile, others are copied.
+    enum Input {
+        Exported(PythonBuffer),
+        Copied(Vec<u8>),
+    }
+
+    impl Input {
+        fn new(data: BytesType) -> PyResult<Self> {
+            match data {
+                BytesType::PyBuffer(buffer) => Ok(Input::Exported(buffer)),
+                BytesType::RustyBuffer(buffer) => Ok(Input::Copied(buffer.borrow().as_bytes().to_vec())),
+                BytesType::RustyFile(_) | BytesType::SpooledBuffer(_) => Err(PyTypeError::new_err(
+                    "Files aren't supported, as reading them would block the event loop",
+                )),
+            }
+        }
+
+        fn as_slice(&self) -> &[u8] {
+            match self {
+                Input::Exported(buffer) => buffer.as_slice(),
+                Input::Copied(bytes) => bytes,
+            }
+        }
+    }
+
+    /// Queue of the worker threads, started on first use
+    fn pool() -> &'static Sender<Job> {
+        static POOL: OnceLock<Sender<Job>> = OnceLock::new();
+        POOL.get_or_init(|| {
+            let (tx, rx) = channel::<Job>();
+            let rx = Arc::new(Mutex::new(rx));
+            let workers = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+            for i in 0..workers {
+                let rx = Arc::clone(&rx);
+                std::thread::Builder::new()
+                    .name(format!("cramjam-asyncio-{}", i))
+                    .spawn(move || work(&rx))
+                    .expect("failed to start a cramjam.asyncio worker thread");
+            }
+            tx
+        })
+    }
+
+    fn work(rx: &Mutex<Receiver<Job>>) {
+        loop {
+            // the lock is released before running the job, so others may take the next one
+            let job = match rx.lock().unwrap().recv() {
+                Ok(job) => job,
+                Err(_) => return,
+            };
+            job();
+        }
+    }
+
+    /// Run `op` over `data` on the pool, returning a future of the running loop for its result
+    fn spawn<'py, F>(py: Python<'py>, data: BytesType, op: F) -> PyResult<Bound<'py, PyAny>>
+    where
+        F: FnOnce(&[u8]) -> PyResult<Vec<u8>> + Send + 'static,
+    {
+        let event_loop = py.import_bound("asyncio")?.call_method0("get_running_loop")?;
+        let future = event_loop.call_method0("create_future")?;
+        let input = Input::new(data)?;
+        let (event_loop, future_ref) = (event_loop.unbind(), future.clone().unbind());
+        let pending = super::Pending::new();
+        let job: Job = Box::new(move || {
+            let _pending = pending;
+            let result = op(input.as_slice());
+            // the interpreter may have exited while this ran
+            if unsafe { pyo3::ffi::Py_IsInitialized() } == 0 {
+                std::mem::forget(input);
+                return;
+            }
+            Python::with_gil(|py| {
+                drop(input);
+                let result = match result {
+                    Ok(output) => (RustyBuffer::from(output).into_py(py), py.None()),
+                    Err(err) => (py.None(), err.into_value(py).into_any()),
+                };
+                // fails only once the loop is closed, when nothing is awaiting the future
+                if let Ok(resolve) = wrap_pyfunction_bound!(super::resolve, py) {
+                    let _ =
+                        event_loop.call_method1(py, "call_soon_threadsafe", (resolve, future_ref, result.0, result.1));
+                }
+            });
+        });
+        pool()
+            .send(job)
+            .map_err(|_| PyValueError::new_err("cramjam.asyncio's worker threads have stopped"))?;
+        Ok(future)
+    }
+
+    /// Check `codec` is one which can be run on the pool
+    fn check_codec(codec: &str) -> PyResult<()> {
+        if is_codec(codec) {
+            Ok(())
+        } else {
+            Err(PyValueError::new_err(format!("Unknown codec: '{}'", codec)))
+        }
+    }
+
+    /// Compress `data` with `codec`, ie. `"zstd"`, at `level`, on a worker thread; returns an
+    /// awaitable of the compressed Buffer. Any bytes-like object or Buffer may be given, but not
+    /// a File.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> compressed = await cramjam.asyncio.compress("zstd", b'some bytes here', level=Optional[int])
+    /// ```
+    #[pyfunction]
+    #[pyo3(signature = (codec, data, level=None))]
+    pub fn compress<'py>(
+        py: Python<'py>,
+        codec: &str,
+        data: BytesType,
+        level: Option<i32>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        check_codec(codec)?;
+        let codec = codec.to_string();
+        spawn(py, data, move |input| {
+            codec_compress(&codec, input, level).map_err(CompressionError::from_err)
+        })
+    }
+
+    /// Decompress `data` with `codec` on a worker thread; returns an awaitable of the
+    /// decompressed Buffer. The output is limited as by the codec's `decompress`, to
+    /// `max_expansion_ratio` times the size of `data`, or the process wide ratio, and `output_len_max`.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> decompressed = await cramjam.asyncio.decompress("zstd", compressed_bytes, max_expansion_ratio=Optional[float])
+    /// ```
+    #[pyfunction]
+    #[pyo3(signature = (codec, data, max_expansion_ratio=None, *, output_len_max=None))]
+    pub fn decompress<'py>(
+        py: Python<'py>,
+        codec: &str,
+        data: BytesType,
+        max_expansion_ratio: Option<f64>,
+        output_len_max: Option<usize>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        check_codec(codec)?;
+        let codec = codec.to_string();
+        let limit = output_limit(data.len(), max_expansion_ratio, output_len_max)?;
+        spawn(py, data, move |input| {
+            codec_decompress(&codec, input, limit).map_err(DecompressionError::from_err)
+        })
+    }
+}
@@ -0,0 +1,95 @@
+//! Decompress data handed over without its codec, recognizing it from its leading magic bytes.
+//!
+//! ### Python Example
+//! ```python
+//! >>> cramjam.guess_codec(compressed_bytes)
+//! 'zstd'
+//! >>> cramjam.auto.decompress(compressed_bytes)
+//! cramjam.Buffer<len=15>
+//! ```
+use pyo3::prelude::*;
+
+use crate::detect;
+use crate::BytesType;
+
+/// Name of the cramjam module which can decompress `data`, guessed from its leading magic
+/// bytes, or `None` if it isn't recognized.
+///
+/// Recognizes gzip, zstd, lz4 frame, xz (and legacy lzma), bzip2, snappy framed and zlib,
+/// along with `cramjam.compress_ndarray` output, which is named `"ndarray"`. Raw formats
+/// without a signature, like brotli, deflate or lz4 block, can't be recognized. A
+/// `cramjam.File` is read from its current position, which is left where it was.
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> cramjam.guess_codec(cramjam.gzip.compress(b'some bytes here'))
+/// 'gzip'
+/// ```
+#[pyfunction]
+pub fn guess_codec(data: BytesType) -> PyResult<Option<&'static str>> {
+    Ok(detect::detect(&data.peek_bytes(detect::DETECT_LEN)?))
+}
+
+/// Decompress data of an unknown codec, see `cramjam.guess_codec`.
+#[pymodule]
+pub mod auto {
+    use pyo3::prelude::*;
+    use pyo3::types::PyDict;
+
+    use crate::exceptions::DecompressionError;
+    use crate::BytesType;
+
+    /// Decompress `data` with the codec recognized from its magic bytes, see
+    /// `cramjam.guess_codec`. Brotli has no signature, so when it's available, data which isn't
+    /// otherwise recognized is tried as brotli before giving up with a `DecompressionError`.
+    ///
+    /// `output_len` and `max_expansion_ratio` are passed on to the codec's `decompress`.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.auto.decompress(compressed_bytes, output_len=Optional[int])
+    /// ```
+    #[pyfunction]
+    #[pyo3(signature = (data, output_len=None, max_expansion_ratio=None, *, output_len_max=None))]
+    pub fn decompress<'py>(
+        py: Python<'py>,
+        data: &Bound<'py, PyAny>,
+        output_len: Option<usize>,
+        max_expansion_ratio: Option<f64>,
+        output_len_max: Option<usize>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let cramjam = py.import_bound("cramjam")?;
+        let header = data
+            .extract::<BytesType>()?
+            .peek_bytes(crate::detect::DETECT_LEN)?
+            .into_owned();
+        let codec = crate::detect::detect(&header);
+        let kwargs = PyDict::new_bound(py);
+        kwargs.set_item("output_len", output_len)?;
+        kwargs.set_item("max_expansion_ratio", max_expansion_ratio)?;
+        kwargs.set_item("output_len_max", output_len_max)?;
+
+        match codec {
+            Some("ndarray") => cramjam.call_method1("decompress_ndarray", (data,)),
+            Some(codec) => match cramjam.getattr(codec) {
+                Ok(module) => module.call_method("decompress", (data,), Some(&kwargs)),
+                Err(_) => Err(DecompressionError::new_err(format!(
+                    "Data looks to be {}, but cramjam was built without it",
+                    codec
+                ))),
+            },
+            None => match cramjam.getattr("brotli") {
+                Ok(brotli) => brotli
+                    .call_method("decompress", (data,), Some(&kwargs))
+                    .map_err(|_| unrecognized()),
+                Err(_) => Err(unrecognized()),
+            },
+        }
+    }
+
+    fn unrecognized() -> PyErr {
+        DecompressionError::new_err("Unable to recognize the codec of the data from its magic bytes")
+    }
+}
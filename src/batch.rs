@@ -0,0 +1,244 @@
+//! De/compress many independent buffers in one call, spread over a pool of threads.
+//!
+//! Results are deterministic: whatever `nthreads` is and however items are scheduled, outputs
+//! and errors are returned in the order of the inputs, each error names the index of its item,
+//! and every output is a new Buffer, so no two items ever share, or write to, the same output.
+//!
+//! ### Python Example
+//! ```python
+//! >>> outputs, errors = cramjam.decompress_batch([a, b, corrupt], codec="zstd", nthreads=4)
+//! >>> [bytes(o) if o is not None else None for o in outputs]
+//! [b'...', b'...', None]
+//! >>> errors
+//! [None, None, DecompressionError('...')]
+//! ```
+//!
+//! Codec modules have their own `compress_batch` and `decompress_batch`, which return just the
+//! outputs, raising the error of the first item to fail, if any.
+//! ```python
+//! >>> pages = cramjam.snappy.compress_batch([page0, page1, page2], nthreads=4)
+//! ```
+use std::borrow::Cow;
+use std::io::Error;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::codecs::{codec_compress, codec_decompress, is_codec};
+use crate::exceptions::{CompressionError, DecompressionError};
+use crate::io::RustyBuffer;
+use crate::limits::{output_limit, Limit, Limited};
+use crate::BytesType;
+
+/// Outputs and errors of each item in a batch, either of which is `None`
+type BatchResult = (Vec<Option<RustyBuffer>>, Vec<Option<PyErr>>);
+
+/// Per thread decoder state, reused from one item to the next
+#[derive(Default)]
+struct Context {
+    #[cfg(feature = "zstd")]
+    zstd: Option<libcramjam::zstd::zstd::zstd_safe::DCtx<'static>>,
+}
+
+impl Context {
+    fn decompress(&mut self, codec: &str, input: &[u8], limit: Option<Limit>) -> Result<Vec<u8>, Error> {
+        if input.is_empty() {
+            return Ok(vec![]);
+        }
+        #[cfg(feature = "zstd")]
+        if codec == "zstd" {
+            use libcramjam::zstd::zstd::{stream::read::Decoder, zstd_safe};
+
+            let ctx = self.zstd.get_or_insert_with(zstd_safe::DCtx::create);
+            // a previous item may have failed part way through a frame
+            ctx.reset(zstd_safe::ResetDirective::SessionOnly)
+                .map_err(|code| Error::other(zstd_safe::get_error_name(code)))?;
+            let mut output = vec![];
+            std::io::copy(
+                &mut Decoder::with_context(input, ctx),
+                &mut Limited::new(&mut output, limit),
+            )?;
+            return Ok(output);
+        }
+        codec_decompress(codec, input, limit)
+    }
+}
+
+/// Decompress each of `inputs` with `codec`, on up to `nthreads` threads (default: one per CPU).
+///
+/// Returns `(outputs, errors)`, both in the order of `inputs`. A failure is isolated to its own
+/// item: its output is `None` and its `DecompressionError` is in `errors`, which is otherwise
+/// `None`. The error's message starts with, and its `index` attribute is, the item's index.
+/// Decoder state, such as zstd's context, is reused across the items each thread handles.
+///
+/// The same object may be given as several inputs; each of its items gets its own output.
+/// A File is read from its current position, so given again it's empty after the first time.
+///
+/// Each item's output is limited as by the codec's `decompress`, to `max_expansion_ratio` times
+/// the item's size, or the process wide ratio, and `output_len_max`; an item crossing the limit
+/// fails with a `DecompressionError` like any other.
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> outputs, errors = cramjam.decompress_batch(inputs, codec="zstd", nthreads=Optional[int], max_expansion_ratio=Optional[float])
+/// ```
+#[pyfunction]
+#[pyo3(signature = (inputs, codec, nthreads=None, *, max_expansion_ratio=None, output_len_max=None))]
+pub fn decompress_batch(
+    py: Python,
+    inputs: Vec<BytesType>,
+    codec: &str,
+    nthreads: Option<usize>,
+    max_expansion_ratio: Option<f64>,
+    output_len_max: Option<usize>,
+) -> PyResult<BatchResult> {
+    if !is_codec(codec) {
+        return Err(PyValueError::new_err(format!("Unknown codec: '{}'", codec)));
+    }
+    check_nthreads(nthreads)?;
+    let inputs = read_inputs(&inputs)?;
+    let items = limited(&inputs, max_expansion_ratio, output_len_max)?;
+    let results = py.allow_threads(|| {
+        run(&items, nthreads, |ctx, (input, limit)| {
+            ctx.decompress(codec, input, *limit)
+        })
+    });
+
+    let mut outputs = Vec::with_capacity(results.len());
+    let mut errors = Vec::with_capacity(results.len());
+    for (index, result) in results.into_iter().enumerate() {
+        match result {
+            Ok(output) => {
+                outputs.push(Some(RustyBuffer::from(output)));
+                errors.push(None);
+            }
+            Err(err) => {
+                outputs.push(None);
+                errors.push(Some(item_error(py, index, err, DecompressionError::new_err)?));
+            }
+        }
+    }
+    Ok((outputs, errors))
+}
+
+/// Compress each of `inputs` with `codec` at `level`, for the codec modules' `compress_batch`,
+/// raising the error of the first item which fails
+pub(crate) fn codec_compress_batch(
+    py: Python,
+    codec: &str,
+    inputs: Vec<BytesType>,
+    level: Option<i32>,
+    nthreads: Option<usize>,
+) -> PyResult<Vec<RustyBuffer>> {
+    check_nthreads(nthreads)?;
+    let inputs = read_inputs(&inputs)?;
+    let results = py.allow_threads(|| run(&inputs, nthreads, |_, input| codec_compress(codec, input, level)));
+    outputs(py, results, CompressionError::new_err)
+}
+
+/// Decompress each of `inputs` with `codec`, for the codec modules' `decompress_batch`, each
+/// limited as by `decompress_batch`, raising the error of the first item which fails
+pub(crate) fn codec_decompress_batch(
+    py: Python,
+    codec: &str,
+    inputs: Vec<BytesType>,
+    nthreads: Option<usize>,
+    max_expansion_ratio: Option<f64>,
+    output_len_max: Option<usize>,
+) -> PyResult<Vec<RustyBuffer>> {
+    check_nthreads(nthreads)?;
+    let inputs = read_inputs(&inputs)?;
+    let items = limited(&inputs, max_expansion_ratio, output_len_max)?;
+    let results = py.allow_threads(|| {
+        run(&items, nthreads, |ctx, (input, limit)| {
+            ctx.decompress(codec, input, *limit)
+        })
+    });
+    outputs(py, results, DecompressionError::new_err)
+}
+
+/// Bytes of each of `inputs`, Files read from their current position
+fn read_inputs<'a>(inputs: &'a [BytesType]) -> PyResult<Vec<Cow<'a, [u8]>>> {
+    Ok(inputs
+        .iter()
+        .map(|input| input.read_bytes())
+        .collect::<Result<_, _>>()?)
+}
+
+/// Each of `inputs` along with the limit of its output
+fn limited<'a>(
+    inputs: &'a [Cow<'a, [u8]>],
+    ratio: Option<f64>,
+    output_len_max: Option<usize>,
+) -> PyResult<Vec<(&'a [u8], Option<Limit>)>> {
+    inputs
+        .iter()
+        .map(|input| Ok((&input[..], output_limit(input.len(), ratio, output_len_max)?)))
+        .collect()
+}
+
+fn check_nthreads(nthreads: Option<usize>) -> PyResult<()> {
+    if nthreads == Some(0) {
+        return Err(PyValueError::new_err("nthreads must be at least 1"));
+    }
+    Ok(())
+}
+
+/// The outputs of every item, or the error of the first which failed
+fn outputs(
+    py: Python,
+    results: Vec<Result<Vec<u8>, Error>>,
+    new_err: fn(String) -> PyErr,
+) -> PyResult<Vec<RustyBuffer>> {
+    results
+        .into_iter()
+        .enumerate()
+        .map(|(index, result)| match result {
+            Ok(output) => Ok(RustyBuffer::from(output)),
+            Err(err) => Err(item_error(py, index, err, new_err)?),
+        })
+        .collect()
+}
+
+/// Error of the item at `index`, naming it in the message and as its `index` attribute
+fn item_error(py: Python, index: usize, err: Error, new_err: fn(String) -> PyErr) -> PyResult<PyErr> {
+    let err = new_err(format!("item {}: {}", index, err));
+    err.value_bound(py).setattr("index", index)?;
+    Ok(err)
+}
+
+/// Apply `op` to every input on a pool of worker threads, each with its own [`Context`],
+/// returning the results in input order
+fn run<T: Sync, F>(inputs: &[T], nthreads: Option<usize>, op: F) -> Vec<Result<Vec<u8>, Error>>
+where
+    F: Fn(&mut Context, &T) -> Result<Vec<u8>, Error> + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(inputs.len()));
+    let workers = nthreads
+        .unwrap_or_else(|| std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1))
+        .min(inputs.len());
+
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                let mut ctx = Context::default();
+                loop {
+                    let i = next.fetch_add(1, Ordering::SeqCst);
+                    if i >= inputs.len() {
+                        return;
+                    }
+                    let result = op(&mut ctx, &inputs[i]);
+                    results.lock().unwrap().push((i, result));
+                }
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, result)| result).collect()
+}
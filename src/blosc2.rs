@@ -8,7 +8,7 @@ pub mod blosc2 {
     use std::io::{self, BufReader, Cursor};
 
     use crate::exceptions::{CompressionError, DecompressionError};
-    use crate::io::{AsBytes, RustyBuffer};
+    use crate::io::{AsBytes, PathLike, RustyBuffer};
     use crate::BytesType;
     use libcramjam::blosc2::blosc2::schunk::{Chunk, SChunk, Storage};
     use libcramjam::blosc2::blosc2::{CLevel, CParams, Codec, DParams, Filter};
@@ -112,13 +112,25 @@ pub mod blosc2 {
     /// Decompress a SChunk into buffer
     #[pyfunction]
     #[allow(unused_variables)]
-    #[pyo3(signature = (input, output_len=None))]
-    pub fn decompress(py: Python, input: BytesType, output_len: Option<usize>) -> PyResult<RustyBuffer> {
+    #[pyo3(signature = (input, output_len=None, max_expansion_ratio=None, *, output_len_max=None))]
+    pub fn decompress(
+        py: Python,
+        input: BytesType,
+        output_len: Option<usize>,
+        max_expansion_ratio: Option<f64>,
+        output_len_max: Option<usize>,
+    ) -> PyResult<RustyBuffer> {
         if input.is_empty() {
             return Ok(RustyBuffer::from(vec![]));
         }
-        return crate::generic!(py, libcramjam::blosc2::decompress[input], output_len = output_len)
-            .map_err(DecompressionError::from_err);
+        return crate::generic!(
+            py,
+            libcramjam::blosc2::decompress[input],
+            max_expansion_ratio = max_expansion_ratio,
+            output_len_max = output_len_max,
+            output_len = output_len
+        )
+        .map_err(DecompressionError::from_err);
     }
 
     /// decompress into output
@@ -127,7 +139,7 @@ pub mod blosc2 {
         if input.is_empty() {
             return Ok(0);
         }
-        crate::generic!(py, libcramjam::blosc2::decompress[input, output]).map_err(DecompressionError::from_err)
+        crate::generic!(py, decompress: libcramjam::blosc2::decompress[input, output]).map_err(DecompressionError::from_err)
     }
 
     /// Blosc2 decompression.
@@ -135,13 +147,13 @@ pub mod blosc2 {
     /// Python Example
     /// --------------
     /// ```python
-    /// >>> cramjam.blosc2.decompress(compressed_bytes, output_len=Optional[None])
+    /// >>> cramjam.blosc2.decompress(compressed_bytes, output_len=Optional[None], max_expansion_ratio=Optional[float])
     /// ```
     #[pyfunction]
     #[allow(unused_variables)]
     #[pyo3(signature = (data, output_len=None))]
     pub fn decompress_chunk(py: Python, data: BytesType, output_len: Option<usize>) -> PyResult<RustyBuffer> {
-        let bytes = data.as_bytes();
+        let bytes = &*data.read_bytes()?;
         let buf = py
             .allow_threads(|| libcramjam::blosc2::decompress_chunk(bytes))
             .map(RustyBuffer::from)?;
@@ -151,7 +163,7 @@ pub mod blosc2 {
     /// Decompress a Chunk into output
     #[pyfunction]
     pub fn decompress_chunk_into(py: Python, input: BytesType, mut output: BytesType) -> PyResult<usize> {
-        let bytes = input.as_bytes();
+        let bytes = &*input.read_bytes()?;
         let out = output.as_bytes_mut()?;
         let nbytes = py.allow_threads(|| libcramjam::blosc2::decompress_chunk_into(bytes, out))?;
         Ok(nbytes)
@@ -175,7 +187,7 @@ pub mod blosc2 {
         filter: Option<PyFilter>,
         codec: Option<PyCodec>,
     ) -> PyResult<RustyBuffer> {
-        let bytes = data.as_bytes();
+        let bytes = &*data.read_bytes()?;
         py.allow_threads(|| {
             let clevel = clevel.map(Into::into);
             let filter = filter.map(Into::into);
@@ -198,7 +210,7 @@ pub mod blosc2 {
         filter: Option<PyFilter>,
         codec: Option<PyCodec>,
     ) -> PyResult<usize> {
-        let bytes = input.as_bytes();
+        let bytes = &*input.read_bytes()?;
         let out = output.as_bytes_mut()?;
         py.allow_threads(|| {
             let clevel = clevel.map(Into::into);
@@ -209,6 +221,19 @@ pub mod blosc2 {
         .map_err(CompressionError::from_err)
     }
 
+    /// blosc2 takes the urlpath as a C string, and would otherwise lossily convert
+    /// non-UTF8 paths; so refuse those rather than write to an unexpected location.
+    fn set_urlpath(storage: Storage, path: PathLike) -> PyResult<Storage> {
+        let PathLike(path) = path;
+        if path.to_str().is_none() {
+            return Err(exceptions::PyValueError::new_err(format!(
+                "blosc2 does not support non-UTF8 paths: {}",
+                path.to_string_lossy()
+            )));
+        }
+        storage.set_urlpath(path).map_err(CompressionError::from_err)
+    }
+
     /// A Compressor interface, using blosc2's SChunk
     #[pyclass]
     #[derive(Clone)]
@@ -222,7 +247,7 @@ pub mod blosc2 {
         #[new]
         #[pyo3(signature = (path=None, typesize=None, clevel=None, filter=None, codec=None, nthreads=None))]
         pub fn __init__(
-            path: Option<String>,
+            path: Option<PathLike>,
             typesize: Option<usize>,
             clevel: Option<PyCLevel>,
             filter: Option<PyFilter>,
@@ -242,7 +267,7 @@ pub mod blosc2 {
                 .set_cparams(cparams)
                 .set_dparams(dparams);
             if let Some(pth) = path {
-                storage = storage.set_urlpath(pth).map_err(CompressionError::from_err)?;
+                storage = set_urlpath(storage, pth)?;
             }
 
             let schunk = SChunk::new(storage);
@@ -250,10 +275,11 @@ pub mod blosc2 {
         }
 
         /// Compress input into the current compressor's stream.
-        pub fn compress(&mut self, input: BytesType) -> PyResult<usize> {
+        pub fn compress(&mut self, py: Python, input: BytesType) -> PyResult<usize> {
+            let bytes = &*input.read_bytes()?;
             match self.0.as_mut() {
-                Some(schunk) => schunk
-                    .append_buffer(input.as_bytes())
+                Some(schunk) => py
+                    .allow_threads(|| schunk.append_buffer(bytes))
                     .map_err(CompressionError::from_err),
                 None => Err(CompressionError::new_err("Compressor has been consumed")),
             }
@@ -312,8 +338,8 @@ pub mod blosc2 {
             let clevel = clevel.map(Into::into);
             let filter = filter.map(Into::into);
             let codec = codec.map(Into::into);
-            let chunk =
-                Chunk::compress(src.as_bytes(), typesize, clevel, filter, codec).map_err(CompressionError::from_err)?;
+            let chunk = Chunk::compress(&src.read_bytes()?, typesize, clevel, filter, codec)
+                .map_err(CompressionError::from_err)?;
             Ok(Self(chunk))
         }
 
@@ -364,12 +390,12 @@ pub mod blosc2 {
         F: FnOnce(&[u8]) -> PyResult<T>,
     {
         match buf.extract::<BytesType>(py) {
-            Ok(bt) => op(bt.as_bytes()),
+            Ok(bt) => op(&bt.read_bytes()?),
             Err(_) => {
                 if let Some(to_bytes_cb) = &converter {
                     let obj = to_bytes_cb.call_bound(py, (&buf,), None)?;
                     let bytestype = obj.extract::<BytesType>(py)?;
-                    op(bytestype.as_bytes())
+                    op(&bytestype.read_bytes()?)
                 } else {
                     let msg = "Could not convert to variant of `BytesType` and no `to_bytes_cb` function set";
                     return Err(CompressionError::new_err(msg));
@@ -404,7 +430,7 @@ pub mod blosc2 {
             to_bytes_cb=None,
         ))]
         pub fn __init__(
-            path: Option<String>,
+            path: Option<PathLike>,
             typesize: Option<usize>,
             clevel: Option<PyCLevel>,
             filter: Option<PyFilter>,
@@ -426,7 +452,7 @@ pub mod blosc2 {
                 .set_cparams(cparams)
                 .set_dparams(dparams);
             if let Some(pth) = path {
-                storage = storage.set_urlpath(pth).map_err(CompressionError::from_err)?;
+                storage = set_urlpath(storage, pth)?;
             }
 
             let schunk = SChunk::new(storage);
@@ -6,101 +6,539 @@ use pyo3::prelude::*;
 pub mod brotli {
 
     use crate::exceptions::{CompressionError, DecompressionError};
-    use crate::io::RustyBuffer;
+    use crate::io::{IntoLength, OutputBuffer, RustyBuffer};
+    use crate::limits::Budget;
     use crate::{AsBytes, BytesType};
+    use libcramjam::brotli::brotli::enc::backward_references::{BrotliEncoderMode, BrotliEncoderParams};
+    use libcramjam::brotli::brotli::enc::StandardAlloc;
+    use libcramjam::brotli::brotli::{BrotliDecompressStream, BrotliResult, BrotliState};
     use pyo3::prelude::*;
+    use pyo3::types::PyBytes;
     use pyo3::PyResult;
-    use std::io::{Cursor, Write};
+    use std::io::{Cursor, Read, Write};
 
     const DEFAULT_COMPRESSION_LEVEL: u32 = 11;
+    // brotli's BROTLI_MIN_QUALITY and BROTLI_MAX_QUALITY
+    const MIN_COMPRESSION_LEVEL: u32 = 0;
+    const MAX_COMPRESSION_LEVEL: u32 = 11;
     const BUF_SIZE: usize = 1 << 17; // Taken from brotli kCompressFragementTwoPassBlockSize
     const LGWIN: u32 = 22;
+    // brotli's BROTLI_MIN_WINDOW_BITS and BROTLI_MAX_WINDOW_BITS, without large windows
+    const MIN_LGWIN: u32 = 10;
+    const MAX_LGWIN: u32 = 24;
+
+    /// What's compressed, which the encoder is tuned to, as brotli's `--mode`
+    #[derive(Clone, Debug, PartialEq)]
+    #[pyclass(eq, eq_int)]
+    pub enum Mode {
+        /// Nothing in particular (default)
+        GENERIC,
+        /// UTF-8 text, ie. HTML, CSS or JavaScript
+        TEXT,
+        /// WOFF 2.0 fonts
+        FONT,
+    }
+
+    impl From<Mode> for BrotliEncoderMode {
+        fn from(mode: Mode) -> Self {
+            match mode {
+                Mode::GENERIC => BrotliEncoderMode::BROTLI_MODE_GENERIC,
+                Mode::TEXT => BrotliEncoderMode::BROTLI_MODE_TEXT,
+                Mode::FONT => BrotliEncoderMode::BROTLI_MODE_FONT,
+            }
+        }
+    }
+
+    /// Encoder parameters of `level`, `lgwin`, the base two log of the window size, and `mode`
+    fn encoder_params(level: Option<u32>, lgwin: Option<u32>, mode: Option<Mode>) -> PyResult<BrotliEncoderParams> {
+        let lgwin = lgwin.unwrap_or(LGWIN);
+        if !(MIN_LGWIN..=MAX_LGWIN).contains(&lgwin) {
+            return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                "lgwin must be between {} and {}, got {}",
+                MIN_LGWIN, MAX_LGWIN, lgwin
+            )));
+        }
+        Ok(BrotliEncoderParams {
+            quality: level.unwrap_or(DEFAULT_COMPRESSION_LEVEL) as i32,
+            lgwin: lgwin as i32,
+            mode: mode.unwrap_or(Mode::GENERIC).into(),
+            ..Default::default()
+        })
+    }
+
+    /// Brotli compression as `libcramjam::brotli::compress`, with all of `params`
+    fn compress_with_params<W: Write + ?Sized, R: Read>(
+        input: R,
+        output: &mut W,
+        params: &BrotliEncoderParams,
+    ) -> std::io::Result<usize> {
+        let mut encoder = libcramjam::brotli::brotli::CompressorReader::with_params(input, BUF_SIZE, params);
+        let n_bytes = std::io::copy(&mut encoder, output)?;
+        Ok(n_bytes as usize)
+    }
 
     /// Brotli decompression.
     ///
     /// Python Example
     /// --------------
     /// ```python
-    /// >>> cramjam.brotli.decompress(compressed_bytes, output_len=Optional[int])
+    /// >>> cramjam.brotli.decompress(compressed_bytes, output_len=Optional[int], max_expansion_ratio=Optional[float], into=Optional[Buffer])
     /// ```
     #[pyfunction]
-    #[pyo3(signature = (data, output_len=None))]
-    pub fn decompress(py: Python, data: BytesType, output_len: Option<usize>) -> PyResult<RustyBuffer> {
-        crate::generic!(py, libcramjam::brotli::decompress[data], output_len = output_len)
-            .map_err(DecompressionError::from_err)
+    #[pyo3(signature = (data, output_len=None, max_expansion_ratio=None, into=None, *, output_len_max=None))]
+    pub fn decompress<'py>(
+        py: Python<'py>,
+        data: BytesType,
+        output_len: Option<usize>,
+        max_expansion_ratio: Option<f64>,
+        into: Option<Bound<'py, RustyBuffer>>,
+        output_len_max: Option<usize>,
+    ) -> PyResult<OutputBuffer<'py>> {
+        crate::generic!(
+            py,
+            libcramjam::brotli::decompress[data],
+            max_expansion_ratio = max_expansion_ratio,
+            output_len_max = output_len_max,
+            into = into,
+            output_len = output_len
+        )
+        .map_err(DecompressionError::from_err)
     }
 
     /// Brotli compression.
     ///
+    /// `lgwin` is the base two log of the window size, `10` to `24`, defaulting to `22`, and
+    /// `mode` a `cramjam.brotli.Mode` of what's compressed, as the brotli CLI's `--lgwin` and
+    /// `--mode`; ie. `mode=Mode.TEXT, lgwin=24` for web assets.
+    ///
     /// Python Example
     /// --------------
     /// ```python
     /// >>> cramjam.brotli.compress(b'some bytes here', level=9, output_len=Option[int])  # level defaults to 11
+    /// >>> cramjam.brotli.compress(b'some bytes here', quality=2)  # stdlib style alias of level
+    /// >>> cramjam.brotli.compress(b'some bytes here', lgwin=24, mode=cramjam.brotli.Mode.TEXT)
     /// ```
     #[pyfunction]
-    #[pyo3(signature = (data, level=None, output_len=None))]
+    #[pyo3(signature = (data, level=None, output_len=None, *, quality=None, lgwin=None, mode=None))]
     pub fn compress(
         py: Python,
         data: BytesType,
         level: Option<u32>,
         output_len: Option<usize>,
+        quality: Option<u32>,
+        lgwin: Option<u32>,
+        mode: Option<Mode>,
     ) -> PyResult<RustyBuffer> {
-        crate::generic!(py, libcramjam::brotli::compress[data], output_len = output_len, level)
+        let level = crate::level_alias(level, "quality", quality)?;
+        let params = &encoder_params(level, lgwin, mode)?;
+        crate::generic!(py, compress_with_params[data], output_len = output_len, params)
             .map_err(CompressionError::from_err)
     }
 
     /// Compress directly into an output buffer
     #[pyfunction]
-    #[pyo3(signature = (input, output, level=None))]
-    pub fn compress_into(py: Python, input: BytesType, mut output: BytesType, level: Option<u32>) -> PyResult<usize> {
-        crate::generic!(py, libcramjam::brotli::compress[input, output], level).map_err(CompressionError::from_err)
+    #[pyo3(signature = (input, output, level=None, *, quality=None, align=None, lgwin=None, mode=None))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn compress_into(
+        py: Python,
+        input: BytesType,
+        mut output: BytesType,
+        level: Option<u32>,
+        quality: Option<u32>,
+        align: Option<usize>,
+        lgwin: Option<u32>,
+        mode: Option<Mode>,
+    ) -> PyResult<IntoLength> {
+        let level = crate::level_alias(level, "quality", quality)?;
+        let params = &encoder_params(level, lgwin, mode)?;
+        let written =
+            crate::generic!(py, compress_with_params[input, output], params).map_err(CompressionError::from_err)?;
+        crate::io::pad_output(&mut output, written, align)
+    }
+
+    /// Lowest compression `level` accepted.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.brotli.min_level()
+    /// 0
+    /// ```
+    #[pyfunction]
+    pub fn min_level() -> u32 {
+        MIN_COMPRESSION_LEVEL
+    }
+
+    /// Highest compression `level` accepted.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.brotli.max_level()
+    /// 11
+    /// ```
+    #[pyfunction]
+    pub fn max_level() -> u32 {
+        MAX_COMPRESSION_LEVEL
+    }
+
+    /// Compression `level` used by `compress` when none is given.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.brotli.default_level()
+    /// 11
+    /// ```
+    #[pyfunction]
+    pub fn default_level() -> u32 {
+        DEFAULT_COMPRESSION_LEVEL
     }
 
     /// Decompress directly into an output buffer
     #[pyfunction]
     pub fn decompress_into(py: Python, input: BytesType, mut output: BytesType) -> PyResult<usize> {
-        crate::generic!(py, libcramjam::brotli::decompress[input, output]).map_err(DecompressionError::from_err)
+        crate::generic!(py, decompress: libcramjam::brotli::decompress[input, output])
+            .map_err(DecompressionError::from_err)
     }
 
+    mod _paths {
+        use super::*;
+        crate::make_path_helpers!("brotli", "br");
+    }
+    #[pymodule_export]
+    use _paths::{compress_path, decompress_path};
+
     /// Brotli Compressor object for streaming compression
     #[pyclass]
     pub struct Compressor {
         inner: Option<libcramjam::brotli::brotli::CompressorWriter<Cursor<Vec<u8>>>>,
+        returns_output: bool,
     }
 
     #[pymethods]
     impl Compressor {
-        /// Initialize a new `Compressor` instance.
+        /// Initialize a new `Compressor` instance, taking `lgwin` and `mode` as `compress` does.
+        ///
+        /// `returns_output=True` makes `compress` return the compressed bytes produced by each
+        /// call, rather than the number of bytes consumed, to stream output without `flush()`.
         #[new]
-        #[pyo3(signature = (level=None))]
-        pub fn __init__(level: Option<u32>) -> PyResult<Self> {
-            let level = level.unwrap_or_else(|| DEFAULT_COMPRESSION_LEVEL);
-            let inner = libcramjam::brotli::brotli::CompressorWriter::new(Cursor::new(vec![]), BUF_SIZE, level, LGWIN);
-            Ok(Self { inner: Some(inner) })
+        #[pyo3(signature = (level=None, *, quality=None, lgwin=None, mode=None, returns_output=false))]
+        pub fn __init__(
+            level: Option<u32>,
+            quality: Option<u32>,
+            lgwin: Option<u32>,
+            mode: Option<Mode>,
+            returns_output: bool,
+        ) -> PyResult<Self> {
+            let level = crate::level_alias(level, "quality", quality)?;
+            let params = encoder_params(level, lgwin, mode)?;
+            let inner =
+                libcramjam::brotli::brotli::CompressorWriter::with_params(Cursor::new(vec![]), BUF_SIZE, &params);
+            Ok(Self {
+                inner: Some(inner),
+                returns_output,
+            })
         }
 
         /// Compress input into the current compressor's stream.
-        pub fn compress(&mut self, input: &[u8]) -> PyResult<usize> {
-            crate::io::stream_compress(&mut self.inner, input)
+        pub fn compress(&mut self, py: Python, input: &[u8]) -> PyResult<PyObject> {
+            crate::io::stream_compress_output(py, &mut self.inner, input, self.returns_output, 0, |e| e.get_mut())
         }
 
         /// Flush and return current compressed stream
-        pub fn flush(&mut self) -> PyResult<RustyBuffer> {
-            crate::io::stream_flush(&mut self.inner, |e| e.get_mut())
+        ///
+        /// This ends the current meta-block and pads to a byte boundary, without ending the stream,
+        /// so a decoder which is given everything returned so far can decode all the input
+        /// compressed so far; ie. for sending each chunk of an HTTP response with brotli
+        /// content-encoding as it's produced.
+        ///
+        /// Python Example
+        /// --------------
+        /// ```python
+        /// >>> compressor = cramjam.brotli.Compressor()
+        /// >>> compressor.compress(b'data: first event\n\n')
+        /// >>> send(compressor.flush())  # decodable by the client now
+        /// >>> compressor.compress(b'data: second event\n\n')
+        /// >>> send(compressor.flush())
+        /// >>> send(compressor.finish())
+        /// ```
+        pub fn flush(&mut self, py: Python) -> PyResult<RustyBuffer> {
+            py.allow_threads(|| crate::io::stream_flush(&mut self.inner, 0, |e| e.get_mut()))
         }
 
         /// Consume the current compressor state and return the compressed stream
         /// **NB** The compressor will not be usable after this method is called.
-        pub fn finish(&mut self) -> PyResult<RustyBuffer> {
-            crate::io::stream_finish(&mut self.inner, |mut inner| {
-                inner.flush().map(|_| inner.into_inner().into_inner())
+        pub fn finish(&mut self, py: Python) -> PyResult<RustyBuffer> {
+            py.allow_threads(|| {
+                crate::io::stream_finish(&mut self.inner, |mut inner| {
+                    inner.flush().map(|_| inner.into_inner().into_inner())
+                })
             })
         }
     }
-    mod _decompressor {
-        use super::*;
-        crate::make_decompressor!(brotli);
+
+    type State = BrotliState<StandardAlloc, StandardAlloc, StandardAlloc>;
+
+    /// Decompressor object for streaming decompression, decoding input incrementally as it's
+    /// given rather than buffering it, so bodies far larger than memory can be decompressed as
+    /// they arrive; ie. from an HTTP response with brotli content-encoding. Only the decompressed
+    /// output not yet taken by `flush()` or `read()` is held.
+    ///
+    /// `needs_more_input` is `True` while the current stream hasn't ended, and `eof` once it has.
+    /// Input given after the end of a stream is decoded as another stream.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> decompressor = cramjam.brotli.Decompressor()
+    /// >>> for chunk in response.iter_content():
+    /// ...     decompressor.decompress(chunk)
+    /// ...     write(decompressor.flush())
+    /// >>> decompressor.needs_more_input
+    /// False
+    /// >>> decompressor.finish()
+    /// ```
+    #[pyclass]
+    pub struct Decompressor {
+        state: State,
+        /// A stream has ended, and no other has started since
+        eof: bool,
+        /// Bytes of input given to the current stream
+        stream_in: usize,
+        /// Decompressed output, `None` once finished
+        output: Option<Vec<u8>>,
+    }
+
+    #[pymethods]
+    #[allow(clippy::len_without_is_empty)]
+    impl Decompressor {
+        /// Initialize a new `Decompressor` instance.
+        #[new]
+        pub fn __init__() -> PyResult<Self> {
+            Ok(Self {
+                state: new_state(),
+                eof: false,
+                stream_in: 0,
+                output: Some(vec![]),
+            })
+        }
+
+        /// Length of internal buffer containing decompressed data.
+        pub fn len(&self) -> usize {
+            self.output.as_ref().map(|o| o.len()).unwrap_or_default()
+        }
+
+        /// Decompress this input into the inner buffer, returning the number of bytes decompressed.
+        /// Its output is limited as `decompress` limits it, by `max_expansion_ratio` times the
+        /// size of this input and `output_len_max`.
+        #[pyo3(signature = (input, max_expansion_ratio=None, *, output_len_max=None))]
+        pub fn decompress(
+            &mut self,
+            py: Python,
+            input: BytesType,
+            max_expansion_ratio: Option<f64>,
+            output_len_max: Option<usize>,
+        ) -> PyResult<usize> {
+            if self.output.is_none() {
+                return Err(DecompressionError::new_err(
+                    "Appears `finish()` was called on this instance",
+                ));
+            }
+            let limit = crate::limits::output_limit(input.len(), max_expansion_ratio, output_len_max)?;
+            let mut budget = Budget::new(limit);
+            match input {
+                BytesType::RustyFile(f) => {
+                    let mut borrowed = f.borrow_mut();
+                    let f_in = &mut *borrowed;
+                    py.allow_threads(|| self.advance_reader(f_in, &mut budget))
+                }
+                BytesType::SpooledBuffer(b) => {
+                    let mut borrowed = b.borrow_mut();
+                    let b_in = &mut *borrowed;
+                    py.allow_threads(|| self.advance_reader(b_in, &mut budget))
+                }
+                _ => {
+                    let bytes = input.as_bytes();
+                    py.allow_threads(|| self.advance(bytes, &mut budget))
+                }
+            }
+            .map_err(DecompressionError::from_err)
+        }
+
+        /// Whether the end of a stream has been reached, with no further stream started.
+        #[getter]
+        pub fn eof(&self) -> bool {
+            self.eof
+        }
+
+        /// Whether the current stream hasn't ended yet, so more input is needed to complete it.
+        #[getter]
+        pub fn needs_more_input(&self) -> bool {
+            !self.eof
+        }
+
+        /// Flush and return current decompressed stream.
+        pub fn flush(&mut self) -> PyResult<RustyBuffer> {
+            match self.output.as_mut() {
+                Some(output) => Ok(RustyBuffer::from(std::mem::take(output))),
+                None => Err(DecompressionError::new_err(
+                    "Appears `finish()` was called on this instance",
+                )),
+            }
+        }
+
+        /// Consume the current Decompressor state and return the decompressed stream
+        /// **NB** The Decompressor will not be usable after this method is called.
+        pub fn finish(&mut self) -> PyResult<RustyBuffer> {
+            let output = match std::mem::take(&mut self.output) {
+                Some(output) => output,
+                None => {
+                    return Err(DecompressionError::new_err(
+                        "Appears `finish()` was called on this instance",
+                    ))
+                }
+            };
+            if !self.eof && self.stream_in > 0 {
+                return Err(DecompressionError::new_err(
+                    "Compressed data ended before the end-of-stream marker was reached",
+                ));
+            }
+            Ok(RustyBuffer::from(output))
+        }
+
+        /// Read and remove up to `n` bytes, or all by default, from the front of the decompressed
+        /// data; with `readinto` and friends a Decompressor can be wrapped by `io.TextIOWrapper`
+        /// or given to parsers expecting a readable binary file, once it's been fed the input.
+        #[pyo3(signature = (n=-1))]
+        pub fn read<'py>(&mut self, py: Python<'py>, n: isize) -> PyResult<Bound<'py, PyBytes>> {
+            crate::io::stream_read(py, self.output.as_mut(), n)
+        }
+
+        /// Same as `read`, for `io` wrappers
+        #[pyo3(signature = (n=-1))]
+        pub fn read1<'py>(&mut self, py: Python<'py>, n: isize) -> PyResult<Bound<'py, PyBytes>> {
+            self.read(py, n)
+        }
+
+        /// Read decompressed data into `output`, returning the number of bytes read.
+        pub fn readinto(&mut self, mut output: BytesType) -> PyResult<usize> {
+            let buf = output.as_bytes_mut()?;
+            crate::io::stream_readinto(self.output.as_mut(), buf)
+        }
+
+        /// Whether `read` may be called; `True` until the Decompressor is finished or closed.
+        pub fn readable(&self) -> bool {
+            !self.closed()
+        }
+
+        /// Always `False`
+        pub fn writable(&self) -> bool {
+            false
+        }
+
+        /// Always `False`
+        pub fn seekable(&self) -> bool {
+            false
+        }
+
+        /// Discard the Decompressor's state, like `finish()` without returning the output.
+        pub fn close(&mut self) {
+            self.output = None;
+        }
+
+        /// Whether `finish()` or `close()` has been called.
+        #[getter]
+        pub fn closed(&self) -> bool {
+            self.output.is_none()
+        }
+
+        fn __len__(&self) -> usize {
+            self.len()
+        }
+        fn __contains__(&self, py: Python, x: BytesType) -> PyResult<bool> {
+            let bytes = &*x.peek_bytes(usize::MAX)?;
+            Ok(py.allow_threads(|| {
+                self.output
+                    .as_ref()
+                    .map(|o| o.windows(bytes.len()).any(|w| w == bytes))
+                    .unwrap_or_default()
+            }))
+        }
+        fn __repr__(&self) -> String {
+            format!("Decompressor<len={}>", self.len())
+        }
+        fn __bool__(&self) -> bool {
+            self.output.is_some() && self.len() > 0
+        }
+    }
+
+    impl Decompressor {
+        /// Decode all that's read from `reader`, a chunk at a time
+        fn advance_reader<R: Read>(&mut self, reader: &mut R, budget: &mut Budget) -> std::io::Result<usize> {
+            let mut chunk = vec![0; BUF_SIZE];
+            let mut n_bytes = 0;
+            loop {
+                match reader.read(&mut chunk)? {
+                    0 => break Ok(n_bytes),
+                    n => n_bytes += self.advance(&chunk[..n], budget)?,
+                }
+            }
+        }
+
+        /// Decode `data`, returning the number of bytes decompressed
+        fn advance(&mut self, mut data: &[u8], budget: &mut Budget) -> std::io::Result<usize> {
+            let output = self.output.as_mut().expect("checked by caller");
+            let output_start = output.len();
+            let mut needs_more_output = false;
+            while !data.is_empty() || needs_more_output {
+                if self.eof {
+                    self.state = new_state();
+                    self.eof = false;
+                    self.stream_in = 0;
+                }
+                let (mut available_in, mut input_offset) = (data.len(), 0);
+                let step_start = output.len();
+                let mut output_offset = step_start;
+                let (mut available_out, mut total_out) = (BUF_SIZE, 0);
+                output.resize(output_offset + BUF_SIZE, 0);
+                let result = BrotliDecompressStream(
+                    &mut available_in,
+                    &mut input_offset,
+                    data,
+                    &mut available_out,
+                    &mut output_offset,
+                    output,
+                    &mut total_out,
+                    &mut self.state,
+                );
+                output.truncate(output_offset);
+                budget.spend(output_offset - step_start)?;
+                self.stream_in += input_offset;
+                data = &data[input_offset..];
+                needs_more_output = false;
+                match result {
+                    BrotliResult::ResultSuccess => self.eof = true,
+                    BrotliResult::NeedsMoreInput => (),
+                    BrotliResult::NeedsMoreOutput => needs_more_output = true,
+                    BrotliResult::ResultFailure => {
+                        return Err(std::io::Error::new(
+                            std::io::ErrorKind::InvalidData,
+                            "Invalid brotli data",
+                        ))
+                    }
+                }
+            }
+            Ok(output.len() - output_start)
+        }
+    }
+
+    /// Decoder state for a single brotli stream
+    fn new_state() -> State {
+        State::new(
+            StandardAlloc::default(),
+            StandardAlloc::default(),
+            StandardAlloc::default(),
+        )
     }
-    #[pymodule_export]
-    use _decompressor::Decompressor;
 }
@@ -6,11 +6,13 @@ use pyo3::prelude::*;
 pub mod bzip2 {
 
     use crate::exceptions::{CompressionError, DecompressionError};
-    use crate::io::RustyBuffer;
+    use crate::io::{IntoLength, OutputBuffer, RustyBuffer};
+    use crate::limits::Budget;
     use crate::{AsBytes, BytesType};
     use pyo3::prelude::*;
+    use pyo3::types::PyBytes;
     use pyo3::PyResult;
-    use std::io::Cursor;
+    use std::io::{Cursor, Read};
 
     const DEFAULT_COMPRESSION_LEVEL: u32 = 6;
 
@@ -19,13 +21,27 @@ pub mod bzip2 {
     /// Python Example
     /// --------------
     /// ```python
-    /// >>> cramjam.bzip2.decompress(compressed_bytes, output_len=Optional[int])
+    /// >>> cramjam.bzip2.decompress(compressed_bytes, output_len=Optional[int], max_expansion_ratio=Optional[float], into=Optional[Buffer])
     /// ```
     #[pyfunction]
-    #[pyo3(signature = (data, output_len=None))]
-    pub fn decompress(py: Python, data: BytesType, output_len: Option<usize>) -> PyResult<RustyBuffer> {
-        crate::generic!(py, libcramjam::bzip2::decompress[data], output_len = output_len)
-            .map_err(DecompressionError::from_err)
+    #[pyo3(signature = (data, output_len=None, max_expansion_ratio=None, into=None, *, output_len_max=None))]
+    pub fn decompress<'py>(
+        py: Python<'py>,
+        data: BytesType,
+        output_len: Option<usize>,
+        max_expansion_ratio: Option<f64>,
+        into: Option<Bound<'py, RustyBuffer>>,
+        output_len_max: Option<usize>,
+    ) -> PyResult<OutputBuffer<'py>> {
+        crate::generic!(
+            py,
+            libcramjam::bzip2::decompress[data],
+            max_expansion_ratio = max_expansion_ratio,
+            output_len_max = output_len_max,
+            into = into,
+            output_len = output_len
+        )
+        .map_err(DecompressionError::from_err)
     }
 
     /// bzip2 compression.
@@ -34,71 +50,402 @@ pub mod bzip2 {
     /// --------------
     /// ```python
     /// >>> cramjam.bzip2.compress(b'some bytes here', level=6, output_len=Option[int])  # level defaults to 6
+    /// >>> cramjam.bzip2.compress(b'some bytes here', compresslevel=2)  # stdlib style alias of level
     /// ```
     #[pyfunction]
-    #[pyo3(signature = (data, level=None, output_len=None))]
+    #[pyo3(signature = (data, level=None, output_len=None, *, compresslevel=None))]
     pub fn compress(
         py: Python,
         data: BytesType,
         level: Option<u32>,
         output_len: Option<usize>,
+        compresslevel: Option<u32>,
     ) -> PyResult<RustyBuffer> {
+        let level = crate::level_alias(level, "compresslevel", compresslevel)?;
         crate::generic!(py, libcramjam::bzip2::compress[data], output_len = output_len, level)
             .map_err(CompressionError::from_err)
     }
 
     /// Compress directly into an output buffer
     #[pyfunction]
-    #[pyo3(signature = (input, output, level=None))]
-    pub fn compress_into(py: Python, input: BytesType, mut output: BytesType, level: Option<u32>) -> PyResult<usize> {
-        crate::generic!(py, libcramjam::bzip2::compress[input, output], level).map_err(CompressionError::from_err)
+    #[pyo3(signature = (input, output, level=None, *, compresslevel=None, align=None))]
+    pub fn compress_into(
+        py: Python,
+        input: BytesType,
+        mut output: BytesType,
+        level: Option<u32>,
+        compresslevel: Option<u32>,
+        align: Option<usize>,
+    ) -> PyResult<IntoLength> {
+        let level = crate::level_alias(level, "compresslevel", compresslevel)?;
+        let written = crate::generic!(py, libcramjam::bzip2::compress[input, output], level)
+            .map_err(CompressionError::from_err)?;
+        crate::io::pad_output(&mut output, written, align)
+    }
+
+    /// Lowest compression `level` accepted.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.bzip2.min_level()
+    /// 1
+    /// ```
+    #[pyfunction]
+    pub fn min_level() -> u32 {
+        libcramjam::bzip2::bzip2::Compression::fast().level()
+    }
+
+    /// Highest compression `level` accepted.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.bzip2.max_level()
+    /// 9
+    /// ```
+    #[pyfunction]
+    pub fn max_level() -> u32 {
+        libcramjam::bzip2::bzip2::Compression::best().level()
+    }
+
+    /// Compression `level` used by `compress` when none is given.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.bzip2.default_level()
+    /// 6
+    /// ```
+    #[pyfunction]
+    pub fn default_level() -> u32 {
+        DEFAULT_COMPRESSION_LEVEL
     }
 
     /// Decompress directly into an output buffer
     #[pyfunction]
     pub fn decompress_into(py: Python, input: BytesType, mut output: BytesType) -> PyResult<usize> {
-        crate::generic!(py, libcramjam::bzip2::decompress[input, output]).map_err(DecompressionError::from_err)
+        crate::generic!(py, decompress: libcramjam::bzip2::decompress[input, output])
+            .map_err(DecompressionError::from_err)
+    }
+
+    mod _paths {
+        use super::*;
+        crate::make_path_helpers!("bzip2", "bz2");
     }
+    #[pymodule_export]
+    use _paths::{compress_path, decompress_path};
 
     /// bzip2 Compressor object for streaming compression
     #[pyclass]
     pub struct Compressor {
         inner: Option<libcramjam::bzip2::bzip2::write::BzEncoder<Cursor<Vec<u8>>>>,
+        returns_output: bool,
     }
 
     #[pymethods]
     impl Compressor {
         /// Initialize a new `Compressor` instance.
+        ///
+        /// `returns_output=True` makes `compress` return the compressed bytes produced by each
+        /// call, rather than the number of bytes consumed, to stream output without `flush()`.
         #[new]
-        #[pyo3(signature = (level=None))]
-        pub fn __init__(level: Option<u32>) -> PyResult<Self> {
+        #[pyo3(signature = (level=None, *, compresslevel=None, returns_output=false))]
+        pub fn __init__(level: Option<u32>, compresslevel: Option<u32>, returns_output: bool) -> PyResult<Self> {
+            let level = crate::level_alias(level, "compresslevel", compresslevel)?;
             let level = level.unwrap_or_else(|| DEFAULT_COMPRESSION_LEVEL);
             let comp = libcramjam::bzip2::bzip2::Compression::new(level);
             let inner = libcramjam::bzip2::bzip2::write::BzEncoder::new(Cursor::new(vec![]), comp);
-            Ok(Self { inner: Some(inner) })
+            Ok(Self {
+                inner: Some(inner),
+                returns_output,
+            })
         }
 
         /// Compress input into the current compressor's stream.
-        pub fn compress(&mut self, input: &[u8]) -> PyResult<usize> {
-            crate::io::stream_compress(&mut self.inner, input)
+        pub fn compress(&mut self, py: Python, input: &[u8]) -> PyResult<PyObject> {
+            crate::io::stream_compress_output(py, &mut self.inner, input, self.returns_output, 0, |e| e.get_mut())
         }
 
         /// Flush and return current compressed stream
-        pub fn flush(&mut self) -> PyResult<RustyBuffer> {
-            crate::io::stream_flush(&mut self.inner, |e| e.get_mut())
+        pub fn flush(&mut self, py: Python) -> PyResult<RustyBuffer> {
+            py.allow_threads(|| crate::io::stream_flush(&mut self.inner, 0, |e| e.get_mut()))
         }
 
         /// Consume the current compressor state and return the compressed stream
         /// **NB** The compressor will not be usable after this method is called.
+        pub fn finish(&mut self, py: Python) -> PyResult<RustyBuffer> {
+            py.allow_threads(|| {
+                crate::io::stream_finish(&mut self.inner, |inner| inner.finish().map(|c| c.into_inner()))
+            })
+        }
+    }
+
+    /// Where a [`Decompressor`] is within the current bzip2 stream
+    enum Stage {
+        /// Decoding a stream, or waiting for the first one
+        Stream,
+        /// A stream has ended, and the next bytes tell whether another one follows
+        StreamEnd,
+        /// The data ended with bytes which aren't a bzip2 stream; they're kept as `unused_data`
+        Unused,
+    }
+
+    /// Decompressor object for streaming decompression, decoding input incrementally as it's
+    /// given, across any number of concatenated bzip2 streams, as in multi-stream `.bz2` files
+    /// like Wikipedia's dumps. Only the decompressed output not yet taken by `flush()` or
+    /// `read()` is held.
+    ///
+    /// Like `bz2.BZ2Decompressor`, `eof` is set once a stream has ended, and `needs_input` until
+    /// then; unlike it, another stream may follow, which clears `eof` again. Bytes after the last
+    /// stream which aren't another stream are collected in `unused_data`.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> decompressor = cramjam.bzip2.Decompressor()
+    /// >>> with open("enwiki-pages-articles-multistream.xml.bz2", "rb") as f:
+    /// ...     while chunk := f.read(1024 * 1024):
+    /// ...         decompressor.decompress(chunk)
+    /// ...         handle(decompressor.flush())
+    /// >>> decompressor.eof, decompressor.needs_input
+    /// (True, False)
+    /// ```
+    #[pyclass]
+    pub struct Decompressor {
+        inner: libcramjam::bzip2::bzip2::Decompress,
+        stage: Stage,
+        /// Input not yet consumed, ie. the start of a stream's magic bytes split across calls
+        pending: Vec<u8>,
+        /// Decompressed output, `None` once finished
+        output: Option<Vec<u8>>,
+        unused_data: Vec<u8>,
+    }
+
+    #[pymethods]
+    #[allow(clippy::len_without_is_empty)]
+    impl Decompressor {
+        /// Initialize a new `Decompressor` instance.
+        #[new]
+        pub fn __init__() -> PyResult<Self> {
+            Ok(Self {
+                inner: libcramjam::bzip2::bzip2::Decompress::new(false),
+                stage: Stage::Stream,
+                pending: vec![],
+                output: Some(vec![]),
+                unused_data: vec![],
+            })
+        }
+
+        /// Length of internal buffer containing decompressed data.
+        pub fn len(&self) -> usize {
+            self.output.as_ref().map(|o| o.len()).unwrap_or_default()
+        }
+
+        /// Decompress this input into the inner buffer, returning the number of bytes decompressed.
+        /// Its output is limited as `decompress` limits it, by `max_expansion_ratio` times the
+        /// size of this input and `output_len_max`.
+        #[pyo3(signature = (input, max_expansion_ratio=None, *, output_len_max=None))]
+        pub fn decompress(
+            &mut self,
+            py: Python,
+            input: BytesType,
+            max_expansion_ratio: Option<f64>,
+            output_len_max: Option<usize>,
+        ) -> PyResult<usize> {
+            if self.output.is_none() {
+                return Err(DecompressionError::new_err(
+                    "Appears `finish()` was called on this instance",
+                ));
+            }
+            let mut budget = Budget::new(crate::limits::output_limit(
+                input.len(),
+                max_expansion_ratio,
+                output_len_max,
+            )?);
+            match input {
+                BytesType::RustyFile(f) => {
+                    let mut borrowed = f.borrow_mut();
+                    let f_in = &mut *borrowed;
+                    py.allow_threads(|| f_in.read_to_end(&mut self.pending))?;
+                }
+                BytesType::SpooledBuffer(b) => {
+                    let mut borrowed = b.borrow_mut();
+                    let b_in = &mut *borrowed;
+                    py.allow_threads(|| b_in.read_to_end(&mut self.pending))?;
+                }
+                _ => self.pending.extend_from_slice(input.as_bytes()),
+            }
+            py.allow_threads(|| self.advance(&mut budget))
+                .map_err(DecompressionError::from_err)
+        }
+
+        /// Whether the end of a bzip2 stream has been reached, with no further stream started.
+        #[getter]
+        pub fn eof(&self) -> bool {
+            matches!(self.stage, Stage::StreamEnd | Stage::Unused)
+        }
+
+        /// Whether the current stream hasn't ended yet, so more input is needed to complete it.
+        #[getter]
+        pub fn needs_input(&self) -> bool {
+            !self.eof()
+        }
+
+        /// Bytes found after the end of the last bzip2 stream.
+        #[getter]
+        pub fn unused_data<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+            PyBytes::new_bound(py, &self.unused_data)
+        }
+
+        /// Flush and return current decompressed stream.
+        pub fn flush(&mut self) -> PyResult<RustyBuffer> {
+            match self.output.as_mut() {
+                Some(output) => Ok(RustyBuffer::from(std::mem::take(output))),
+                None => Err(DecompressionError::new_err(
+                    "Appears `finish()` was called on this instance",
+                )),
+            }
+        }
+
+        /// Consume the current Decompressor state and return the decompressed stream
+        /// **NB** The Decompressor will not be usable after this method is called.
         pub fn finish(&mut self) -> PyResult<RustyBuffer> {
-            crate::io::stream_finish(&mut self.inner, |inner| inner.finish().map(|c| c.into_inner()))
+            let output = match std::mem::take(&mut self.output) {
+                Some(output) => output,
+                None => {
+                    return Err(DecompressionError::new_err(
+                        "Appears `finish()` was called on this instance",
+                    ))
+                }
+            };
+            match self.stage {
+                Stage::Stream if self.inner.total_in() == 0 && self.pending.is_empty() => Ok(RustyBuffer::from(output)),
+                Stage::StreamEnd | Stage::Unused => Ok(RustyBuffer::from(output)),
+                _ => Err(DecompressionError::new_err(
+                    "Compressed data ended before the end-of-stream marker was reached",
+                )),
+            }
+        }
+
+        /// Read and remove up to `n` bytes, or all by default, from the front of the decompressed
+        /// data; with `readinto` and friends a Decompressor can be wrapped by `io.TextIOWrapper`
+        /// or given to parsers expecting a readable binary file, once it's been fed the input.
+        #[pyo3(signature = (n=-1))]
+        pub fn read<'py>(&mut self, py: Python<'py>, n: isize) -> PyResult<Bound<'py, PyBytes>> {
+            crate::io::stream_read(py, self.output.as_mut(), n)
+        }
+
+        /// Same as `read`, for `io` wrappers
+        #[pyo3(signature = (n=-1))]
+        pub fn read1<'py>(&mut self, py: Python<'py>, n: isize) -> PyResult<Bound<'py, PyBytes>> {
+            self.read(py, n)
+        }
+
+        /// Read decompressed data into `output`, returning the number of bytes read.
+        pub fn readinto(&mut self, mut output: BytesType) -> PyResult<usize> {
+            let buf = output.as_bytes_mut()?;
+            crate::io::stream_readinto(self.output.as_mut(), buf)
+        }
+
+        /// Whether `read` may be called; `True` until the Decompressor is finished or closed.
+        pub fn readable(&self) -> bool {
+            !self.closed()
+        }
+
+        /// Always `False`
+        pub fn writable(&self) -> bool {
+            false
+        }
+
+        /// Always `False`
+        pub fn seekable(&self) -> bool {
+            false
+        }
+
+        /// Discard the Decompressor's state, like `finish()` without returning the output.
+        pub fn close(&mut self) {
+            self.output = None;
+        }
+
+        /// Whether `finish()` or `close()` has been called.
+        #[getter]
+        pub fn closed(&self) -> bool {
+            self.output.is_none()
+        }
+
+        fn __len__(&self) -> usize {
+            self.len()
+        }
+        fn __contains__(&self, py: Python, x: BytesType) -> PyResult<bool> {
+            let bytes = &*x.peek_bytes(usize::MAX)?;
+            Ok(py.allow_threads(|| {
+                self.output
+                    .as_ref()
+                    .map(|o| o.windows(bytes.len()).any(|w| w == bytes))
+                    .unwrap_or_default()
+            }))
+        }
+        fn __repr__(&self) -> String {
+            format!("Decompressor<len={}>", self.len())
+        }
+        fn __bool__(&self) -> bool {
+            self.output.is_some() && self.len() > 0
         }
     }
 
-    mod _decompressor {
-        use super::*;
-        crate::make_decompressor!(bzip2);
+    impl Decompressor {
+        /// Decode as much of `pending` as possible, returning the number of bytes decompressed
+        fn advance(&mut self, budget: &mut Budget) -> std::io::Result<usize> {
+            use libcramjam::bzip2::bzip2::{Decompress, Status};
+
+            let output = self.output.as_mut().expect("checked by caller");
+            let output_start = output.len();
+            let mut pos = 0;
+            loop {
+                let data = &self.pending[pos..];
+                match self.stage {
+                    Stage::Stream => {
+                        if data.is_empty() {
+                            break;
+                        }
+                        if output.capacity() - output.len() < 1024 {
+                            output.reserve(data.len().saturating_mul(4).max(32 * 1024));
+                        }
+                        let (in_before, out_before) = (self.inner.total_in(), output.len());
+                        let status = self
+                            .inner
+                            .decompress_vec(data, output)
+                            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+                        pos += (self.inner.total_in() - in_before) as usize;
+                        budget.spend(output.len() - out_before)?;
+                        if status == Status::StreamEnd {
+                            self.stage = Stage::StreamEnd;
+                        } else if self.inner.total_in() == in_before && output.len() == out_before {
+                            // needs more input
+                            break;
+                        }
+                    }
+                    Stage::StreamEnd => {
+                        let magic = crate::detect::BZIP2_MAGIC;
+                        if data.len() < magic.len() && magic.starts_with(data) {
+                            break;
+                        } else if data.starts_with(magic) {
+                            self.inner = Decompress::new(false);
+                            self.stage = Stage::Stream;
+                        } else {
+                            self.stage = Stage::Unused;
+                        }
+                    }
+                    Stage::Unused => {
+                        self.unused_data.extend_from_slice(data);
+                        pos = self.pending.len();
+                        break;
+                    }
+                }
+            }
+            self.pending.drain(..pos);
+            Ok(output.len() - output_start)
+        }
     }
-    #[pymodule_export]
-    use _decompressor::Decompressor;
 }
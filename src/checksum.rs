@@ -0,0 +1,408 @@
+//! Checksums used by compression container formats, from the libraries cramjam already links:
+//! CRC32 and Adler-32 as in zlib and gzip, CRC32C as in snappy's framing, and with `lz4`
+//! compiled in, the xxHash XXH32 and XXH64 used by lz4 and zstd frames.
+//!
+//! ### Python Example
+//! ```python
+//! >>> cramjam.checksum.crc32(b"some bytes")
+//! >>> hasher = cramjam.checksum.Xxh64(seed=0)
+//! >>> for chunk in chunks:
+//! ...     hasher.update(chunk)
+//! >>> hasher.digest()
+//! ```
+use pyo3::prelude::*;
+
+/// Checksums used by compression container formats, as functions of the data and as classes
+/// updated with it piece by piece. A `File` is read from its current position, a chunk at a time.
+#[pymodule]
+pub mod checksum {
+    use crate::io::AsBytes;
+    use crate::BytesType;
+    use pyo3::prelude::*;
+    use std::io::{ErrorKind, Read};
+
+    /// A checksum updated with data as it comes, starting from `Init`
+    pub(crate) trait Checksum: Clone + Send {
+        type Init;
+        type Value;
+        fn new(init: Self::Init) -> Self;
+        fn update(&mut self, data: &[u8]);
+        fn value(&self) -> Self::Value;
+    }
+
+    impl Checksum for crc32fast::Hasher {
+        type Init = u32;
+        type Value = u32;
+        fn new(init: u32) -> Self {
+            crc32fast::Hasher::new_with_initial(init)
+        }
+        fn update(&mut self, data: &[u8]) {
+            crc32fast::Hasher::update(self, data)
+        }
+        fn value(&self) -> u32 {
+            self.clone().finalize()
+        }
+    }
+
+    impl Checksum for adler2::Adler32 {
+        type Init = u32;
+        type Value = u32;
+        fn new(init: u32) -> Self {
+            adler2::Adler32::from_checksum(init)
+        }
+        fn update(&mut self, data: &[u8]) {
+            self.write_slice(data)
+        }
+        fn value(&self) -> u32 {
+            self.checksum()
+        }
+    }
+
+    /// CRC32C (Castagnoli), computed here as snap keeps its own private
+    #[derive(Clone)]
+    pub(crate) struct Crc32cState(u32);
+
+    /// Polynomial of CRC32C, reversed
+    const CRC32C_POLYNOMIAL: u32 = 0x82f6_3b78;
+
+    /// Lookup tables for CRC32C 8 bytes at a time: the first for a byte, each next for a byte
+    /// followed by one more zero byte than the last
+    const CRC32C_TABLES: [[u32; 256]; 8] = crc32c_tables();
+
+    const fn crc32c_tables() -> [[u32; 256]; 8] {
+        let mut tables = [[0; 256]; 8];
+        let mut i = 0;
+        while i < 256 {
+            let mut crc = i as u32;
+            let mut bit = 0;
+            while bit < 8 {
+                crc = if crc & 1 == 1 {
+                    (crc >> 1) ^ CRC32C_POLYNOMIAL
+                } else {
+                    crc >> 1
+                };
+                bit += 1;
+            }
+            tables[0][i] = crc;
+            i += 1;
+        }
+        let mut table = 1;
+        while table < 8 {
+            let mut i = 0;
+            while i < 256 {
+                let crc = tables[table - 1][i];
+                tables[table][i] = (crc >> 8) ^ tables[0][(crc & 0xff) as usize];
+                i += 1;
+            }
+            table += 1;
+        }
+        tables
+    }
+
+    impl Checksum for Crc32cState {
+        type Init = u32;
+        type Value = u32;
+        fn new(init: u32) -> Self {
+            Self(init)
+        }
+        fn update(&mut self, data: &[u8]) {
+            let t = &CRC32C_TABLES;
+            let mut crc = !self.0;
+            let mut chunks = data.chunks_exact(8);
+            for chunk in &mut chunks {
+                let lo = crc ^ u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+                let hi = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]);
+                crc = t[7][(lo & 0xff) as usize]
+                    ^ t[6][((lo >> 8) & 0xff) as usize]
+                    ^ t[5][((lo >> 16) & 0xff) as usize]
+                    ^ t[4][(lo >> 24) as usize]
+                    ^ t[3][(hi & 0xff) as usize]
+                    ^ t[2][((hi >> 8) & 0xff) as usize]
+                    ^ t[1][((hi >> 16) & 0xff) as usize]
+                    ^ t[0][(hi >> 24) as usize];
+            }
+            for byte in chunks.remainder() {
+                crc = (crc >> 8) ^ t[0][((crc ^ *byte as u32) & 0xff) as usize];
+            }
+            self.0 = !crc;
+        }
+        fn value(&self) -> u32 {
+            self.0
+        }
+    }
+
+    /// Bytes of a `File` or `SpooledBuffer` read to update a checksum with at a time
+    const CHUNK_LEN: usize = 64 * 1024;
+
+    /// Update `checksum` with `data`, the same bytes `BytesType::read_bytes` gives, but reading a
+    /// `File` or `SpooledBuffer` a chunk at a time rather than all at once.
+    pub(crate) fn update<C: Checksum>(py: Python, checksum: &mut C, data: &BytesType) -> PyResult<()> {
+        match data.with_stream(|reader| update_from(py, checksum, reader)) {
+            Some(result) => result,
+            None => {
+                let bytes = data.as_bytes();
+                py.allow_threads(|| checksum.update(bytes));
+                Ok(())
+            }
+        }
+    }
+
+    /// Update `checksum` with what's left of `reader`
+    fn update_from<C: Checksum>(py: Python, checksum: &mut C, reader: &mut (impl Read + Send + ?Sized)) -> PyResult<()> {
+        py.allow_threads(|| {
+            let mut chunk = vec![0; CHUNK_LEN];
+            loop {
+                match reader.read(&mut chunk) {
+                    Ok(0) => return Ok(()),
+                    Ok(n) => checksum.update(&chunk[..n]),
+                    Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                    Err(err) => return Err(err.into()),
+                }
+            }
+        })
+    }
+
+    /// The checksum of `data`, starting from `init`
+    fn checksum<C: Checksum>(py: Python, data: BytesType, init: C::Init) -> PyResult<C::Value> {
+        let mut checksum = C::new(init);
+        update(py, &mut checksum, &data)?;
+        Ok(checksum.value())
+    }
+
+    /// CRC32 of `data` as used by gzip and zip, the same as `zlib.crc32`; give the CRC32 of
+    /// preceding data as `value` to continue from it.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.checksum.crc32(b'some bytes', value=0)
+    /// ```
+    #[pyfunction]
+    #[pyo3(signature = (data, value=0))]
+    pub fn crc32(py: Python, data: BytesType, value: u32) -> PyResult<u32> {
+        checksum::<crc32fast::Hasher>(py, data, value)
+    }
+
+    /// Adler-32 of `data` as used by zlib, the same as `zlib.adler32`; give the Adler-32 of
+    /// preceding data as `value` to continue from it.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.checksum.adler32(b'some bytes', value=1)
+    /// ```
+    #[pyfunction]
+    #[pyo3(signature = (data, value=1))]
+    pub fn adler32(py: Python, data: BytesType, value: u32) -> PyResult<u32> {
+        checksum::<adler2::Adler32>(py, data, value)
+    }
+
+    /// CRC32C (Castagnoli) of `data`, as used, masked, by snappy's framing; give the CRC32C of
+    /// preceding data as `value` to continue from it.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.checksum.crc32c(b'some bytes', value=0)
+    /// ```
+    #[pyfunction]
+    #[pyo3(signature = (data, value=0))]
+    pub fn crc32c(py: Python, data: BytesType, value: u32) -> PyResult<u32> {
+        checksum::<Crc32cState>(py, data, value)
+    }
+
+    mod _classes {
+        use super::*;
+        crate::make_checksum_class!(
+            /// CRC32, updated piece by piece, see `crc32`
+            Crc32(crc32fast::Hasher), value: u32 = 0
+        );
+        crate::make_checksum_class!(
+            /// Adler-32, updated piece by piece, see `adler32`
+            Adler32(adler2::Adler32), value: u32 = 1
+        );
+        crate::make_checksum_class!(
+            /// CRC32C, updated piece by piece, see `crc32c`
+            Crc32c(Crc32cState), value: u32 = 0
+        );
+    }
+    #[pymodule_export]
+    use _classes::{Adler32, Crc32, Crc32c};
+
+    #[cfg(feature = "lz4")]
+    mod _xxhash {
+        use super::*;
+        use std::ffi::c_void;
+
+        // xxHash as compiled into lz4-sys' liblz4, which exports it unprefixed
+        extern "C" {
+            fn XXH32_createState() -> *mut c_void;
+            fn XXH32_freeState(state: *mut c_void) -> i32;
+            fn XXH32_copyState(dst: *mut c_void, src: *const c_void);
+            fn XXH32_reset(state: *mut c_void, seed: u32) -> i32;
+            fn XXH32_update(state: *mut c_void, input: *const c_void, len: usize) -> i32;
+            fn XXH32_digest(state: *const c_void) -> u32;
+            fn XXH64_createState() -> *mut c_void;
+            fn XXH64_freeState(state: *mut c_void) -> i32;
+            fn XXH64_copyState(dst: *mut c_void, src: *const c_void);
+            fn XXH64_reset(state: *mut c_void, seed: u64) -> i32;
+            fn XXH64_update(state: *mut c_void, input: *const c_void, len: usize) -> i32;
+            fn XXH64_digest(state: *const c_void) -> u64;
+        }
+
+        macro_rules! xxh_state {
+            ($name:ident, $int:ty, $create:ident, $free:ident, $copy:ident, $reset:ident, $update:ident, $digest:ident) => {
+                /// xxHash state, allocated by the library
+                pub(crate) struct $name(*mut c_void);
+
+                // the state is only used through `&mut self`, and isn't shared
+                unsafe impl Send for $name {}
+
+                impl $name {
+                    fn create() -> *mut c_void {
+                        let state = unsafe { $create() };
+                        assert!(!state.is_null(), "Failed to allocate xxHash state");
+                        state
+                    }
+                }
+
+                impl Clone for $name {
+                    fn clone(&self) -> Self {
+                        let state = Self::create();
+                        unsafe { $copy(state, self.0) };
+                        Self(state)
+                    }
+                }
+
+                impl Drop for $name {
+                    fn drop(&mut self) {
+                        unsafe { $free(self.0) };
+                    }
+                }
+
+                impl Checksum for $name {
+                    type Init = $int;
+                    type Value = $int;
+                    fn new(seed: $int) -> Self {
+                        let state = Self::create();
+                        unsafe { $reset(state, seed) };
+                        Self(state)
+                    }
+                    fn update(&mut self, data: &[u8]) {
+                        unsafe { $update(self.0, data.as_ptr() as *const c_void, data.len()) };
+                    }
+                    fn value(&self) -> $int {
+                        unsafe { $digest(self.0) }
+                    }
+                }
+            };
+        }
+        xxh_state!(
+            Xxh32State,
+            u32,
+            XXH32_createState,
+            XXH32_freeState,
+            XXH32_copyState,
+            XXH32_reset,
+            XXH32_update,
+            XXH32_digest
+        );
+        xxh_state!(
+            Xxh64State,
+            u64,
+            XXH64_createState,
+            XXH64_freeState,
+            XXH64_copyState,
+            XXH64_reset,
+            XXH64_update,
+            XXH64_digest
+        );
+
+        /// XXH32 of `data` with `seed`, as used by lz4 frames. Only available with lz4 compiled in.
+        ///
+        /// Python Example
+        /// --------------
+        /// ```python
+        /// >>> cramjam.checksum.xxh32(b'some bytes', seed=0)
+        /// ```
+        #[pyfunction]
+        #[pyo3(signature = (data, seed=0))]
+        pub fn xxh32(py: Python, data: BytesType, seed: u32) -> PyResult<u32> {
+            checksum::<Xxh32State>(py, data, seed)
+        }
+
+        /// XXH64 of `data` with `seed`, as used by zstd frames. Only available with lz4 compiled in.
+        ///
+        /// Python Example
+        /// --------------
+        /// ```python
+        /// >>> cramjam.checksum.xxh64(b'some bytes', seed=0)
+        /// ```
+        #[pyfunction]
+        #[pyo3(signature = (data, seed=0))]
+        pub fn xxh64(py: Python, data: BytesType, seed: u64) -> PyResult<u64> {
+            checksum::<Xxh64State>(py, data, seed)
+        }
+
+        crate::make_checksum_class!(
+            /// XXH32, updated piece by piece, see `xxh32`
+            Xxh32(Xxh32State), seed: u32 = 0
+        );
+        crate::make_checksum_class!(
+            /// XXH64, updated piece by piece, see `xxh64`
+            Xxh64(Xxh64State), seed: u64 = 0
+        );
+    }
+    #[cfg(feature = "lz4")]
+    #[pymodule_export]
+    use _xxhash::{xxh32, xxh64, Xxh32, Xxh64};
+}
+
+/// Generate a checksum class, updated with data piece by piece, from a [`checksum::Checksum`]
+/// started from `$init`.
+#[macro_export]
+macro_rules! make_checksum_class {
+    ($(#[$doc:meta])* $name:ident($state:ty), $init:ident: $init_ty:ty = $default:literal) => {
+        $(#[$doc])*
+        ///
+        /// Python Example
+        /// --------------
+        /// ```python
+        #[doc = concat!(">>> checksum = cramjam.checksum.", stringify!($name), "(", stringify!($init), "=", stringify!($default), ")")]
+        /// >>> checksum.update(b'some bytes')
+        /// >>> checksum.digest()
+        /// ```
+        #[pyclass]
+        #[derive(Clone)]
+        pub struct $name($state);
+
+        #[pymethods]
+        impl $name {
+            /// Initialize a new checksum
+            #[new]
+            #[pyo3(signature = ($init = $default))]
+            pub fn __init__($init: $init_ty) -> Self {
+                Self(<$state as $crate::checksum::checksum::Checksum>::new($init))
+            }
+
+            /// Update the checksum with `data`; a `File` is read from its current position
+            pub fn update(&mut self, py: Python, data: BytesType) -> PyResult<()> {
+                $crate::checksum::checksum::update(py, &mut self.0, &data)
+            }
+
+            /// The checksum of the data given so far
+            pub fn digest(&self) -> $init_ty {
+                $crate::checksum::checksum::Checksum::value(&self.0)
+            }
+
+            /// A copy of the checksum so far, to be updated separately
+            pub fn copy(&self) -> Self {
+                self.clone()
+            }
+
+            fn __repr__(&self) -> String {
+                format!("{}<{:#x}>", stringify!($name), self.digest())
+            }
+        }
+    };
+}
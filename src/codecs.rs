@@ -0,0 +1,127 @@
+//! Codecs by name, de/compressing in Rust for the functions taking a codec's name rather than
+//! going through its Python module, ie. `decompress_batch`, `framed` and `pipeline`.
+use std::io::{Cursor, Error, ErrorKind, Read, Write};
+
+use crate::limits::{Limit, Limited};
+
+pub(crate) fn is_codec(name: &str) -> bool {
+    matches!(
+        name,
+        "store" | "snappy" | "lz4" | "zstd" | "brotli" | "gzip" | "bzip2" | "zlib" | "deflate" | "xz"
+    )
+}
+
+pub(crate) fn codec_compress(name: &str, input: &[u8], level: Option<i32>) -> Result<Vec<u8>, Error> {
+    let mut output = vec![];
+    codec_compress_into(name, input, &mut Cursor::new(&mut output), level)?;
+    Ok(output)
+}
+
+pub(crate) fn codec_compress_into<R: Read, W: Write + ?Sized>(
+    name: &str,
+    input: R,
+    out: &mut W,
+    level: Option<i32>,
+) -> Result<usize, Error> {
+    #[allow(unused_variables)]
+    let level_u32 = level.map(|l| l as u32);
+    match name {
+        "store" => crate::store::store::copy(input, out),
+        #[cfg(feature = "snappy")]
+        "snappy" => libcramjam::snappy::compress(input, out),
+        #[cfg(feature = "lz4")]
+        "lz4" => libcramjam::lz4::compress(input, out, level_u32),
+        #[cfg(feature = "zstd")]
+        "zstd" => libcramjam::zstd::compress(input, out, level),
+        #[cfg(feature = "brotli")]
+        "brotli" => libcramjam::brotli::compress(input, out, level_u32),
+        #[cfg(any(feature = "gzip", feature = "gzip-static", feature = "gzip-shared"))]
+        "gzip" => libcramjam::gzip::compress(input, out, level_u32),
+        #[cfg(feature = "bzip2")]
+        "bzip2" => libcramjam::bzip2::compress(input, out, level_u32),
+        #[cfg(any(feature = "zlib", feature = "zlib-static", feature = "zlib-shared"))]
+        "zlib" => libcramjam::zlib::compress(input, out, level_u32),
+        #[cfg(any(feature = "deflate", feature = "deflate-static", feature = "deflate-shared"))]
+        "deflate" => libcramjam::deflate::compress(input, out, level_u32),
+        #[cfg(any(feature = "xz", feature = "xz-static", feature = "xz-shared"))]
+        "xz" => libcramjam::xz::compress(
+            input,
+            out,
+            level_u32,
+            None::<libcramjam::xz::Format>,
+            None::<libcramjam::xz::Check>,
+            None::<libcramjam::xz::Filters>,
+            None::<libcramjam::xz::LzmaOptions>,
+        ),
+        _ => Err(unavailable(name)),
+    }
+}
+
+/// Decompress `input` with codec `name`, failing once the output crosses `limit`
+pub(crate) fn codec_decompress(name: &str, input: &[u8], limit: Option<Limit>) -> Result<Vec<u8>, Error> {
+    let mut output = vec![];
+    codec_decompress_into(name, input, &mut Limited::new(Cursor::new(&mut output), limit))?;
+    Ok(output)
+}
+
+pub(crate) fn codec_decompress_into<W: Write + ?Sized>(name: &str, input: &[u8], out: &mut W) -> Result<usize, Error> {
+    match name {
+        "store" => crate::store::store::copy(input, out),
+        #[cfg(feature = "snappy")]
+        "snappy" => libcramjam::snappy::decompress(input, out),
+        #[cfg(feature = "lz4")]
+        "lz4" => libcramjam::lz4::decompress(input, out),
+        #[cfg(feature = "zstd")]
+        "zstd" => libcramjam::zstd::decompress(input, out),
+        #[cfg(feature = "brotli")]
+        "brotli" => libcramjam::brotli::decompress(input, out),
+        #[cfg(any(feature = "gzip", feature = "gzip-static", feature = "gzip-shared"))]
+        "gzip" => crate::gzip::gzip::decompress_stream(input, out),
+        #[cfg(feature = "bzip2")]
+        "bzip2" => libcramjam::bzip2::decompress(input, out),
+        #[cfg(any(feature = "zlib", feature = "zlib-static", feature = "zlib-shared"))]
+        "zlib" => crate::wbits::decompress_wbits(input, out, 15),
+        #[cfg(any(feature = "deflate", feature = "deflate-static", feature = "deflate-shared"))]
+        "deflate" => libcramjam::deflate::decompress(input, out),
+        #[cfg(any(feature = "xz", feature = "xz-static", feature = "xz-shared"))]
+        "xz" => crate::xz::xz::decompress_streams(input, out),
+        _ => Err(unavailable(name)),
+    }
+}
+
+/// Reader decompressing `input` with codec `name` as it's read, for output too large to hold
+pub(crate) fn codec_reader<'a, R: Read + Send + 'a>(name: &str, input: R) -> Result<Box<dyn Read + Send + 'a>, Error> {
+    Ok(match name {
+        "store" => Box::new(input),
+        #[cfg(feature = "snappy")]
+        "snappy" => Box::new(libcramjam::snappy::snap::read::FrameDecoder::new(input)),
+        #[cfg(feature = "lz4")]
+        "lz4" => Box::new(libcramjam::lz4::lz4::Decoder::new(input)?),
+        #[cfg(feature = "zstd")]
+        "zstd" => Box::new(libcramjam::zstd::zstd::stream::read::Decoder::new(input)?),
+        #[cfg(feature = "brotli")]
+        "brotli" => Box::new(libcramjam::brotli::brotli::Decompressor::new(input, 4096)),
+        #[cfg(any(feature = "gzip", feature = "gzip-static", feature = "gzip-shared"))]
+        "gzip" => Box::new(libcramjam::gzip::flate2::read::MultiGzDecoder::new(input)),
+        #[cfg(feature = "bzip2")]
+        "bzip2" => Box::new(libcramjam::bzip2::bzip2::read::MultiBzDecoder::new(input)),
+        #[cfg(any(feature = "zlib", feature = "zlib-static", feature = "zlib-shared"))]
+        "zlib" => Box::new(libcramjam::zlib::flate2::read::ZlibDecoder::new(input)),
+        #[cfg(any(feature = "deflate", feature = "deflate-static", feature = "deflate-shared"))]
+        "deflate" => Box::new(libcramjam::deflate::flate2::read::DeflateDecoder::new(input)),
+        #[cfg(any(feature = "xz", feature = "xz-static", feature = "xz-shared"))]
+        "xz" => {
+            use libcramjam::xz::xz2::stream::{Stream, CONCATENATED};
+            let stream = Stream::new_auto_decoder(u64::MAX, CONCATENATED)?;
+            Box::new(libcramjam::xz::xz2::read::XzDecoder::new_stream(input, stream))
+        }
+        _ => return Err(unavailable(name)),
+    })
+}
+
+fn unavailable(name: &str) -> Error {
+    Error::new(
+        ErrorKind::Unsupported,
+        format!("Codec '{}' is not available in this build of cramjam", name),
+    )
+}
@@ -6,98 +6,281 @@ use pyo3::prelude::*;
 pub mod deflate {
 
     use crate::exceptions::{CompressionError, DecompressionError};
-    use crate::io::RustyBuffer;
+    use crate::io::{IntoLength, OutputBuffer, RustyBuffer};
+    use crate::wbits::WindowBits;
     use crate::{AsBytes, BytesType};
     use pyo3::prelude::*;
     use pyo3::PyResult;
-    use std::io::Cursor;
+    use std::io::{Cursor, Read, Write};
 
     const DEFAULT_COMPRESSION_LEVEL: u32 = 6;
 
     /// Deflate decompression.
     ///
+    /// Raw deflate has no checksum of its own, so decoding data with the wrong parameters, ie. a
+    /// deflate stream taken from a container at the wrong offset, can succeed with garbage.
+    /// `expected_len` and `expected_crc32`, the CRC32 of the decompressed data as from
+    /// `zlib.crc32`, make a mismatch raise `DecompressionError` instead.
+    ///
+    /// `wbits` selects the container and window size as for `zlib.decompress`: `-15` to `-9` for
+    /// raw deflate, `9` to `15` for zlib or `25` to `31` for gzip.
+    ///
     /// Python Example
     /// --------------
     /// ```python
-    /// >>> cramjam.deflate.decompress(compressed_bytes, output_len=Optional[int])
+    /// >>> cramjam.deflate.decompress(compressed_bytes, output_len=Optional[int], max_expansion_ratio=Optional[float], into=Optional[Buffer])
+    /// >>> cramjam.deflate.decompress(zip_member, expected_len=info.file_size, expected_crc32=info.CRC)
+    /// >>> cramjam.deflate.decompress(websocket_message, wbits=-10)
     /// ```
     #[pyfunction]
-    #[pyo3(signature = (data, output_len=None))]
-    pub fn decompress(py: Python, data: BytesType, output_len: Option<usize>) -> PyResult<RustyBuffer> {
-        crate::generic!(py, libcramjam::deflate::decompress[data], output_len = output_len)
-            .map_err(DecompressionError::from_err)
+    #[pyo3(signature = (data, output_len=None, max_expansion_ratio=None, into=None, *, output_len_max=None, expected_len=None, expected_crc32=None, wbits=-15))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn decompress<'py>(
+        py: Python<'py>,
+        data: BytesType,
+        output_len: Option<usize>,
+        max_expansion_ratio: Option<f64>,
+        into: Option<Bound<'py, RustyBuffer>>,
+        output_len_max: Option<usize>,
+        expected_len: Option<usize>,
+        expected_crc32: Option<u32>,
+        wbits: i32,
+    ) -> PyResult<OutputBuffer<'py>> {
+        let wbits = WindowBits::decompression(wbits)?;
+        let output = crate::generic!(
+            py,
+            crate::wbits::decompress[data],
+            max_expansion_ratio = max_expansion_ratio,
+            output_len_max = output_len_max,
+            into = into,
+            output_len = output_len,
+            wbits
+        )
+        .map_err(DecompressionError::from_err)?;
+        crate::io::verify_output(output, expected_len, expected_crc32)
     }
 
     /// Deflate compression.
     ///
+    /// `wbits` selects the container and window size as for `zlib.compressobj`: `-15` to `-9`
+    /// for raw deflate, `9` to `15` for zlib or `25` to `31` for gzip. Windows smaller than `15`
+    /// need cramjam built with the `zlib-ng` feature.
+    ///
     /// Python Example
     /// --------------
     /// ```python
     /// >>> cramjam.deflate.compress(b'some bytes here', level=5, output_len=Optional[int])  # level defaults to 6
+    /// >>> cramjam.deflate.compress(b'some bytes here', wbits=31)  # gzip wrapped
     /// ```
     #[pyfunction]
-    #[pyo3(signature = (data, level=None, output_len=None))]
+    #[pyo3(signature = (data, level=None, output_len=None, *, wbits=-15))]
     pub fn compress(
         py: Python,
         data: BytesType,
         level: Option<u32>,
         output_len: Option<usize>,
+        wbits: i32,
     ) -> PyResult<RustyBuffer> {
-        crate::generic!(py, libcramjam::deflate::compress[data], output_len = output_len, level)
+        let wbits = WindowBits::compression(wbits)?;
+        crate::generic!(py, crate::wbits::compress[data], output_len = output_len, level, wbits)
             .map_err(CompressionError::from_err)
     }
 
     /// Compress directly into an output buffer
     #[pyfunction]
-    #[pyo3(signature = (input, output, level=None))]
-    pub fn compress_into(py: Python, input: BytesType, mut output: BytesType, level: Option<u32>) -> PyResult<usize> {
-        crate::generic!(py, libcramjam::deflate::compress[input, output], level).map_err(CompressionError::from_err)
+    #[pyo3(signature = (input, output, level=None, *, align=None, wbits=-15))]
+    pub fn compress_into(
+        py: Python,
+        input: BytesType,
+        mut output: BytesType,
+        level: Option<u32>,
+        align: Option<usize>,
+        wbits: i32,
+    ) -> PyResult<IntoLength> {
+        let wbits = WindowBits::compression(wbits)?;
+        let written = crate::generic!(py, crate::wbits::compress[input, output], level, wbits)
+            .map_err(CompressionError::from_err)?;
+        crate::io::pad_output(&mut output, written, align)
+    }
+
+    /// Deflate compression of a ZIP member, returning
+    /// `(compressed, crc32, uncompressed_size, compressed_size)`: the raw deflate data as a
+    /// `cramjam.Buffer` and the fields of its local file header, the CRC32 taken in the same
+    /// pass over `data` as compression, without the GIL.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> compressed, crc32, file_size, compress_size = cramjam.deflate.compress_member(b'some bytes here', level=6)
+    /// >>> (crc32, file_size) == (zlib.crc32(b'some bytes here'), 15)
+    /// True
+    /// ```
+    #[pyfunction]
+    #[pyo3(signature = (data, level=None, output_len=None))]
+    pub fn compress_member(
+        py: Python,
+        data: BytesType,
+        level: Option<u32>,
+        output_len: Option<usize>,
+    ) -> PyResult<(RustyBuffer, u32, u64, usize)> {
+        let mut member = Member::default();
+        let member_ref = &mut member;
+        let compressed = crate::generic!(py, compress_crc32[data], output_len = output_len, level, member_ref)
+            .map_err(CompressionError::from_err)?;
+        let compressed_size = compressed.len();
+        Ok((compressed, member.crc32.finalize(), member.size, compressed_size))
+    }
+
+    /// CRC32 and size of a ZIP member's uncompressed data
+    #[derive(Default)]
+    struct Member {
+        crc32: crc32fast::Hasher,
+        size: u64,
+    }
+
+    /// Deflate compression as `libcramjam::deflate::compress`, taking the CRC32 and size of the
+    /// input into `member` as it's read
+    fn compress_crc32<W: Write + ?Sized, R: Read>(
+        input: R,
+        output: &mut W,
+        level: Option<u32>,
+        member: &mut Member,
+    ) -> std::io::Result<usize> {
+        let input = MemberReader { inner: input, member };
+        libcramjam::deflate::compress(input, output, level)
+    }
+
+    /// Reader taking the CRC32 and size of what's read through it
+    struct MemberReader<'a, R> {
+        inner: R,
+        member: &'a mut Member,
+    }
+
+    impl<R: Read> Read for MemberReader<'_, R> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = self.inner.read(buf)?;
+            self.member.crc32.update(&buf[..n]);
+            self.member.size += n as u64;
+            Ok(n)
+        }
+    }
+
+    /// Lowest compression `level` accepted.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.deflate.min_level()
+    /// 0
+    /// ```
+    #[pyfunction]
+    pub fn min_level() -> u32 {
+        libcramjam::deflate::flate2::Compression::none().level()
+    }
+
+    /// Highest compression `level` accepted.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.deflate.max_level()
+    /// 9
+    /// ```
+    #[pyfunction]
+    pub fn max_level() -> u32 {
+        libcramjam::deflate::flate2::Compression::best().level()
+    }
+
+    /// Compression `level` used by `compress` when none is given.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.deflate.default_level()
+    /// 6
+    /// ```
+    #[pyfunction]
+    pub fn default_level() -> u32 {
+        DEFAULT_COMPRESSION_LEVEL
+    }
+
+    /// Implementation deflate data is de/compressed with: `"zlib-ng"` when cramjam was built with
+    /// the `zlib-ng` feature, otherwise `"miniz_oxide"`.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.deflate.backend()
+    /// 'miniz_oxide'
+    /// ```
+    #[pyfunction]
+    pub fn backend() -> &'static str {
+        crate::flate2_backend()
     }
 
     /// Decompress directly into an output buffer
     #[pyfunction]
-    pub fn decompress_into(py: Python, input: BytesType, mut output: BytesType) -> PyResult<usize> {
-        crate::generic!(py, libcramjam::deflate::decompress[input, output]).map_err(DecompressionError::from_err)
+    #[pyo3(signature = (input, output, *, wbits=-15))]
+    pub fn decompress_into(py: Python, input: BytesType, mut output: BytesType, wbits: i32) -> PyResult<usize> {
+        let wbits = WindowBits::decompression(wbits)?;
+        crate::generic!(py, decompress: crate::wbits::decompress[input, output], wbits)
+            .map_err(DecompressionError::from_err)
     }
 
+    mod _paths {
+        use super::*;
+        crate::make_path_helpers!("deflate", "deflate");
+    }
+    #[pymodule_export]
+    use _paths::{compress_path, decompress_path};
+
     /// Deflate Compressor object for streaming compression
     #[pyclass]
     pub struct Compressor {
-        inner: Option<libcramjam::deflate::flate2::write::DeflateEncoder<Cursor<Vec<u8>>>>,
+        inner: Option<crate::wbits::Encoder<Cursor<Vec<u8>>>>,
+        returns_output: bool,
     }
 
     #[pymethods]
     impl Compressor {
-        /// Initialize a new `Compressor` instance.
+        /// Initialize a new `Compressor` instance, in the container and window size of `wbits`
+        /// as for `compress`.
+        ///
+        /// `returns_output=True` makes `compress` return the compressed bytes produced by each
+        /// call, rather than the number of bytes consumed, to stream output without `flush()`.
         #[new]
-        #[pyo3(signature = (level=None))]
-        pub fn __init__(level: Option<u32>) -> PyResult<Self> {
+        #[pyo3(signature = (level=None, wbits=-15, *, returns_output=false))]
+        pub fn __init__(level: Option<u32>, wbits: i32, returns_output: bool) -> PyResult<Self> {
             let level = level.unwrap_or_else(|| DEFAULT_COMPRESSION_LEVEL);
-            let compression = libcramjam::deflate::flate2::Compression::new(level);
-            let inner = libcramjam::deflate::flate2::write::DeflateEncoder::new(Cursor::new(vec![]), compression);
-            Ok(Self { inner: Some(inner) })
+            let inner = WindowBits::compression(wbits)?.encoder(Cursor::new(vec![]), Some(level))?;
+            Ok(Self {
+                inner: Some(inner),
+                returns_output,
+            })
         }
 
         /// Compress input into the current compressor's stream.
-        pub fn compress(&mut self, input: &[u8]) -> PyResult<usize> {
-            crate::io::stream_compress(&mut self.inner, input)
+        pub fn compress(&mut self, py: Python, input: &[u8]) -> PyResult<PyObject> {
+            crate::io::stream_compress_output(py, &mut self.inner, input, self.returns_output, 0, |e| e.get_mut())
         }
 
         /// Flush and return current compressed stream
-        pub fn flush(&mut self) -> PyResult<RustyBuffer> {
-            crate::io::stream_flush(&mut self.inner, |e| e.get_mut())
+        pub fn flush(&mut self, py: Python) -> PyResult<RustyBuffer> {
+            py.allow_threads(|| crate::io::stream_flush(&mut self.inner, 0, |e| e.get_mut()))
         }
 
         /// Consume the current compressor state and return the compressed stream
         /// **NB** The compressor will not be usable after this method is called.
-        pub fn finish(&mut self) -> PyResult<RustyBuffer> {
-            crate::io::stream_finish(&mut self.inner, |inner| inner.finish().map(|c| c.into_inner()))
+        pub fn finish(&mut self, py: Python) -> PyResult<RustyBuffer> {
+            py.allow_threads(|| {
+                crate::io::stream_finish(&mut self.inner, |inner| inner.finish().map(|c| c.into_inner()))
+            })
         }
     }
 
     mod _decompressor {
         use super::*;
-        crate::make_decompressor!(deflate);
+        crate::make_decompressor!(deflate, crate::wbits::decompress_wbits, wbits: i32 = -15);
     }
     #[pymodule_export]
     use _decompressor::Decompressor;
@@ -0,0 +1,72 @@
+//! Recognize compressed payloads from their leading magic bytes.
+//!
+//! Only formats with a reliable signature are recognized; raw formats such as
+//! brotli, deflate, snappy raw or lz4 block cannot be detected this way.
+
+/// gzip member header
+pub(crate) const GZIP_MAGIC: &[u8] = b"\x1f\x8b";
+/// zstd frame
+pub(crate) const ZSTD_MAGIC: &[u8] = b"\x28\xb5\x2f\xfd";
+/// lz4 frame format
+pub(crate) const LZ4_MAGIC: &[u8] = b"\x04\x22\x4d\x18";
+/// xz stream header
+pub(crate) const XZ_MAGIC: &[u8] = b"\xfd7zXZ\x00";
+/// bzip2 stream header, followed by the block size digit
+pub(crate) const BZIP2_MAGIC: &[u8] = b"BZh";
+/// snappy framing format stream identifier
+pub(crate) const SNAPPY_MAGIC: &[u8] = b"\xff\x06\x00\x00sNaPpY";
+/// cramjam.compress_ndarray header
+pub(crate) const NDARRAY_MAGIC: &[u8] = crate::ndarray::MAGIC;
+
+/// Most leading bytes `detect` looks at, those of the legacy lzma 'alone' header
+pub(crate) const DETECT_LEN: usize = 13;
+
+/// Skippable frames shared by the zstd and lz4 frame formats, `0x184D2A5?` little endian
+pub(crate) fn is_skippable_frame(data: &[u8]) -> bool {
+    data.len() >= 4 && data[0] & 0xf0 == 0x50 && data[1..4] == [0x2a, 0x4d, 0x18]
+}
+
+/// Name of the codec `data` appears to be compressed with, matching the name
+/// of the cramjam module which can decompress it.
+pub(crate) fn detect(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(GZIP_MAGIC) {
+        Some("gzip")
+    } else if data.starts_with(ZSTD_MAGIC) {
+        Some("zstd")
+    } else if data.starts_with(LZ4_MAGIC) {
+        Some("lz4")
+    } else if data.starts_with(XZ_MAGIC) {
+        Some("xz")
+    } else if data.starts_with(BZIP2_MAGIC) && data.len() > 3 && (b'1'..=b'9').contains(&data[3]) {
+        Some("bzip2")
+    } else if data.starts_with(SNAPPY_MAGIC) {
+        Some("snappy")
+    } else if data.starts_with(NDARRAY_MAGIC) {
+        Some("ndarray")
+    } else if is_zlib_header(data) {
+        Some("zlib")
+    } else if is_lzma_alone_header(data) {
+        // legacy .lzma, decompressed by the xz module
+        Some("xz")
+    } else {
+        None
+    }
+}
+
+/// zlib's CMF/FLG pair: deflate method with a valid window size and header check
+pub(crate) fn is_zlib_header(data: &[u8]) -> bool {
+    data.len() >= 2
+        && data[0] & 0x0f == 8
+        && data[0] >> 4 <= 7
+        && (u16::from(data[0]) << 8 | u16::from(data[1])) % 31 == 0
+}
+
+/// Legacy lzma 'alone' header; there's no magic, but the default properties byte
+/// and a power of two dictionary size are a reasonable signature.
+pub(crate) fn is_lzma_alone_header(data: &[u8]) -> bool {
+    if data.len() < 13 || data[0] != 0x5d {
+        return false;
+    }
+    let dict_size = u32::from_le_bytes([data[1], data[2], data[3], data[4]]);
+    dict_size.is_power_of_two() && dict_size >= 1 << 12
+}
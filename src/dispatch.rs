@@ -0,0 +1,143 @@
+//! De/compress with a codec chosen at runtime, ie. from a config file or a file's header.
+//!
+//! ### Python Example
+//! ```python
+//! >>> codec = config["compression"]  # ie. "zstd"
+//! >>> compressed = cramjam.compress(codec, b'some bytes here', level=3)
+//! >>> bytes(cramjam.decompress(codec, compressed))
+//! b'some bytes here'
+//! ```
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyModule, PyString, PyTuple};
+use std::time::Instant;
+
+use crate::BytesType;
+
+/// Compress `data` with `codec`, the name of one of cramjam's codec modules (ie. `"zstd"`,
+/// experimental ones and loaded plugins included) or the module itself. Keyword arguments are
+/// passed on to that module's `compress`, ie. `level` and `output_len`.
+///
+/// With `return_info=True`, returns `(compressed, info)`, `info` being a dict of provenance
+/// metadata to keep alongside the output:
+///
+/// - `codec`: name of the codec module
+/// - `codec_version`: the library compressing, as `"<crate> <version>"`, or `None` if unknown,
+///   ie. for plugins
+/// - `cramjam_version`: `cramjam.__version__`
+/// - `level`: level compressed at, the given or else the codec's default, or `None` if it has none
+/// - `bytes_in`, `bytes_out` and `time_ms`: as in `cramjam.stats()`
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> cramjam.compress("gzip", b'some bytes here', level=9, output_len=Optional[int])
+/// >>> compressed, info = cramjam.compress("zstd", b'some bytes here', return_info=True)
+/// >>> info
+/// {'codec': 'zstd', 'codec_version': 'zstd-sys 2.0.13+zstd.1.5.6', 'cramjam_version': '2.9.1', 'level': 3, 'bytes_in': 15, 'bytes_out': 24, 'time_ms': 0.02}
+/// ```
+#[pyfunction]
+#[pyo3(signature = (codec, data, *, return_info=false, **kwargs))]
+pub fn compress<'py>(
+    py: Python<'py>,
+    codec: &Bound<'py, PyAny>,
+    data: &Bound<'py, PyAny>,
+    return_info: bool,
+    kwargs: Option<&Bound<'py, PyDict>>,
+) -> PyResult<Bound<'py, PyAny>> {
+    let module = codec_module(py, codec)?;
+    if !return_info {
+        return module.call_method("compress", (data,), kwargs);
+    }
+    let start = Instant::now();
+    let compressed = module.call_method("compress", (data,), kwargs)?;
+    let time = start.elapsed();
+
+    let name = module.getattr("__name__")?.extract::<String>()?;
+    let name = name.rsplit('.').next().unwrap_or(&name).to_string();
+    let info = PyDict::new_bound(py);
+    info.set_item("codec", &name)?;
+    info.set_item("codec_version", codec_version(&name))?;
+    info.set_item("cramjam_version", env!("CARGO_PKG_VERSION"))?;
+    info.set_item("level", level(&module, kwargs)?)?;
+    info.set_item("bytes_in", data.extract::<BytesType>().ok().map(|data| data.len()))?;
+    info.set_item("bytes_out", compressed.len().ok())?;
+    info.set_item("time_ms", time.as_secs_f64() * 1000.)?;
+    Ok(PyTuple::new_bound(py, [compressed, info.into_any()]).into_any())
+}
+
+/// Decompress `data` with `codec`, see `compress`. Keyword arguments are passed on to that
+/// module's `decompress`, ie. `output_len` and `max_expansion_ratio`.
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> cramjam.decompress("gzip", compressed_bytes, output_len=Optional[int])
+/// ```
+#[pyfunction]
+#[pyo3(signature = (codec, data, **kwargs))]
+pub fn decompress<'py>(
+    py: Python<'py>,
+    codec: &Bound<'py, PyAny>,
+    data: &Bound<'py, PyAny>,
+    kwargs: Option<&Bound<'py, PyDict>>,
+) -> PyResult<Bound<'py, PyAny>> {
+    codec_module(py, codec)?.call_method("decompress", (data,), kwargs)
+}
+
+/// The codec module named by, or given as, `codec`
+fn codec_module<'py>(py: Python<'py>, codec: &Bound<'py, PyAny>) -> PyResult<Bound<'py, PyAny>> {
+    let module = match codec.downcast::<PyString>() {
+        Ok(name) => {
+            let name = name.to_str()?.to_lowercase();
+            let cramjam = py.import_bound("cramjam")?;
+            cramjam
+                .getattr(name.as_str())
+                .or_else(|_| cramjam.getattr("experimental")?.getattr(name.as_str()))
+                .ok()
+        }
+        Err(_) => Some(codec.clone()),
+    };
+    match module {
+        Some(module)
+            if module.is_instance_of::<PyModule>() && module.hasattr("compress")? && module.hasattr("decompress")? =>
+        {
+            Ok(module)
+        }
+        _ => Err(PyValueError::new_err(format!("Unknown codec: {}", codec.repr()?))),
+    }
+}
+
+/// Level given in `kwargs`, under any codec's name for it, or else the codec's default
+fn level<'py>(module: &Bound<'py, PyAny>, kwargs: Option<&Bound<'py, PyDict>>) -> PyResult<Option<Bound<'py, PyAny>>> {
+    for name in ["level", "compresslevel", "quality", "preset", "clevel"] {
+        match kwargs.map(|kwargs| kwargs.get_item(name)).transpose()?.flatten() {
+            Some(level) if !level.is_none() => return Ok(Some(level)),
+            _ => (),
+        }
+    }
+    match module.hasattr("default_level")? {
+        true => module.call_method0("default_level").map(Some),
+        false => Ok(None),
+    }
+}
+
+/// Library the codec named `codec` compresses with, and its version, of those built in
+fn codec_version(codec: &str) -> Option<String> {
+    let (krate, version) = match codec {
+        "snappy" => ("snap", option_env!("CRAMJAM_VERSION_SNAP")),
+        "lz4" => ("lz4-sys", option_env!("CRAMJAM_VERSION_LZ4_SYS")),
+        "bzip2" => ("bzip2-sys", option_env!("CRAMJAM_VERSION_BZIP2_SYS")),
+        "brotli" => ("brotli", option_env!("CRAMJAM_VERSION_BROTLI")),
+        "xz" | "lzma" => ("lzma-sys", option_env!("CRAMJAM_VERSION_LZMA_SYS")),
+        "zstd" => ("zstd-sys", option_env!("CRAMJAM_VERSION_ZSTD_SYS")),
+        "gzip" | "zlib" | "deflate" if cfg!(feature = "zlib-ng") => {
+            ("libz-ng-sys", option_env!("CRAMJAM_VERSION_LIBZ_NG_SYS"))
+        }
+        "gzip" | "zlib" | "deflate" => ("miniz_oxide", option_env!("CRAMJAM_VERSION_MINIZ_OXIDE")),
+        "blosc2" => ("blosc2-sys", option_env!("CRAMJAM_VERSION_BLOSC2_SYS")),
+        "igzip" | "ideflate" | "izlib" => ("isal-sys", option_env!("CRAMJAM_VERSION_ISAL_SYS")),
+        _ => return None,
+    };
+    version.map(|version| format!("{} {}", krate, version))
+}
@@ -1,10 +1,40 @@
 #![allow(missing_docs)]
 //! cramjam specific Python exceptions
 use pyo3::create_exception;
-use pyo3::exceptions::PyException;
+use pyo3::exceptions::{PyException, PyImportError};
 
 create_exception!(cramjam, CompressionError, PyException);
 create_exception!(cramjam, DecompressionError, PyException);
+create_exception!(
+    cramjam,
+    CramjamNotBuiltError,
+    PyImportError,
+    "The module used wasn't compiled into this build of cramjam, see `cramjam.features()`"
+);
+create_exception!(
+    cramjam,
+    CodecUnavailableError,
+    CramjamNotBuiltError,
+    "The codec used wasn't compiled into this build of cramjam; the message names the Cargo feature which builds it, see `cramjam.available_codecs()`"
+);
+create_exception!(
+    cramjam.lz4,
+    MissingStoredSizeError,
+    DecompressionError,
+    "LZ4 block data doesn't start with a plausible stored size, ie. it was compressed with `store_size=False`"
+);
+create_exception!(
+    cramjam.lz4,
+    CorruptBlockError,
+    DecompressionError,
+    "LZ4 block data is corrupt, or doesn't decompress to its stored size"
+);
+create_exception!(
+    cramjam.snappy,
+    ChecksumError,
+    DecompressionError,
+    "Snappy framed data failed its CRC32C checksum, see `verify_checksums` to decompress it regardless"
+);
 
 impl CompressionError {
     // From<ToString> already impl
@@ -33,4 +33,29 @@ pub mod experimental {
     ))]
     #[pymodule_export]
     use crate::izlib::izlib;
+
+    #[cfg(not(any(feature = "blosc2", feature = "blosc2-static", feature = "blosc2-shared")))]
+    #[pymodule_export]
+    use crate::not_built::blosc2;
+
+    #[cfg(not(all(
+        any(feature = "ideflate", feature = "ideflate-static", feature = "ideflate-shared"),
+        target_pointer_width = "64"
+    )))]
+    #[pymodule_export]
+    use crate::not_built::ideflate;
+
+    #[cfg(not(all(
+        any(feature = "igzip", feature = "igzip-static", feature = "igzip-shared"),
+        target_pointer_width = "64"
+    )))]
+    #[pymodule_export]
+    use crate::not_built::igzip;
+
+    #[cfg(not(all(
+        any(feature = "izlib", feature = "izlib-static", feature = "izlib-shared"),
+        target_pointer_width = "64"
+    )))]
+    #[pymodule_export]
+    use crate::not_built::izlib;
 }
@@ -0,0 +1,117 @@
+//! Which codecs this build of cramjam was compiled with, for feature detection downstream.
+//!
+//! ### Python Example
+//! ```python
+//! >>> if cramjam.features()["igzip"]:
+//! ...     compressed = cramjam.experimental.igzip.compress(data)
+//! ... else:
+//! ...     compressed = cramjam.gzip.compress(data)
+//! ```
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+/// Dict of each codec module's name to whether it was compiled into this build of cramjam.
+///
+/// Modules which weren't compiled, ie. the ISA-L based `igzip`, `ideflate` and `izlib` on 32
+/// bit platforms, can still be imported, but raise `cramjam.CodecUnavailableError` (a
+/// `cramjam.CramjamNotBuiltError`) when used. Besides codecs, `zstdmt` and `zlib-ng` give whether
+/// zstd was built with multithreading and flate2 with zlib-ng.
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> cramjam.features()
+/// {'snappy': True, 'lz4': True, ..., 'igzip': False, ...}
+/// ```
+#[pyfunction]
+pub fn features<'py>(py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new_bound(py);
+    for (name, available) in COMPILED {
+        dict.set_item(name, available)?;
+    }
+    Ok(dict)
+}
+
+/// Names of the codecs compiled into this build of cramjam, those of `features()` which are
+/// available. Codecs which weren't compiled can still be imported, ie. `cramjam.experimental.blosc2`,
+/// but raise `cramjam.CodecUnavailableError` naming the feature which builds them when used.
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> cramjam.available_codecs()
+/// ['snappy', 'lz4', 'bzip2', 'brotli', 'xz', 'zstd', 'gzip', 'zlib', 'deflate', 'lzf', 'blosc2', 'igzip', 'ideflate', 'izlib']
+/// ```
+#[pyfunction]
+pub fn available_codecs() -> Vec<&'static str> {
+    COMPILED
+        .iter()
+        .filter(|(name, available)| *available && !matches!(*name, "zstdmt" | "zlib-ng"))
+        .map(|(name, _)| *name)
+        .collect()
+}
+
+/// Each feature of `features()`, and whether it was compiled in
+const COMPILED: &[(&str, bool)] = &[
+    ("snappy", cfg!(feature = "snappy")),
+    ("lz4", cfg!(feature = "lz4")),
+    ("bzip2", cfg!(feature = "bzip2")),
+    ("brotli", cfg!(feature = "brotli")),
+    (
+        "xz",
+        cfg!(any(feature = "xz", feature = "xz-static", feature = "xz-shared")),
+    ),
+    ("zstd", cfg!(feature = "zstd")),
+    (
+        "gzip",
+        cfg!(any(feature = "gzip", feature = "gzip-static", feature = "gzip-shared")),
+    ),
+    (
+        "zlib",
+        cfg!(any(feature = "zlib", feature = "zlib-static", feature = "zlib-shared")),
+    ),
+    (
+        "deflate",
+        cfg!(any(
+            feature = "deflate",
+            feature = "deflate-static",
+            feature = "deflate-shared"
+        )),
+    ),
+    ("lzf", cfg!(feature = "lzf")),
+    (
+        "blosc2",
+        cfg!(any(
+            feature = "blosc2",
+            feature = "blosc2-static",
+            feature = "blosc2-shared"
+        )),
+    ),
+    (
+        "igzip",
+        cfg!(all(
+            any(feature = "igzip", feature = "igzip-static", feature = "igzip-shared"),
+            target_pointer_width = "64"
+        )),
+    ),
+    (
+        "ideflate",
+        cfg!(all(
+            any(
+                feature = "ideflate",
+                feature = "ideflate-static",
+                feature = "ideflate-shared"
+            ),
+            target_pointer_width = "64"
+        )),
+    ),
+    (
+        "izlib",
+        cfg!(all(
+            any(feature = "izlib", feature = "izlib-static", feature = "izlib-shared"),
+            target_pointer_width = "64"
+        )),
+    ),
+    ("zstdmt", cfg!(feature = "zstdmt")),
+    ("zlib-ng", cfg!(feature = "zlib-ng")),
+];
@@ -0,0 +1,259 @@
+//! Streams of independently compressed records, each prefixed with its compressed length as
+//! an unsigned LEB128 varint, ie. for shipping logs or messages one at a time.
+//!
+//! ### Python Example
+//! ```python
+//! >>> with open("events.log", "wb") as f:
+//! ...     writer = cramjam.framed.Writer(f, "zstd")
+//! ...     writer.writelines([b'first event', b'second event'])
+//! >>> with open("events.log", "rb") as f:
+//! ...     [bytes(record) for record in cramjam.framed.Reader(f, "zstd")]
+//! [b'first event', b'second event']
+//! ```
+use pyo3::prelude::*;
+
+/// Length prefixed, independently compressed records.
+#[pymodule]
+pub mod framed {
+    use pyo3::exceptions::{PyTypeError, PyValueError};
+    use pyo3::prelude::*;
+    use pyo3::types::PyBytes;
+
+    use crate::codecs::{codec_compress, codec_decompress, is_codec};
+    use crate::exceptions::{CompressionError, DecompressionError};
+    use crate::io::RustyBuffer;
+    use crate::BytesType;
+
+    /// Bytes requested from a `Reader`'s source at a time
+    const READ_SIZE: usize = 64 * 1024;
+
+    /// Longest encoding of a u64 varint
+    const MAX_VARINT_LEN: usize = 10;
+
+    /// Write records to `sink`, anything with a `write` method such as a file opened for binary
+    /// writing or a `cramjam.Buffer`, each compressed by itself with `codec` at `level`.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> writer = cramjam.framed.Writer(sink, "snappy", level=Optional[int])
+    /// >>> writer.write(b'some bytes here')
+    /// ```
+    #[pyclass]
+    pub struct Writer {
+        sink: PyObject,
+        codec: String,
+        level: Option<i32>,
+    }
+
+    #[pymethods]
+    impl Writer {
+        /// Initialize a new `Writer` instance.
+        #[new]
+        #[pyo3(signature = (sink, codec, level=None))]
+        pub fn __init__(sink: Bound<'_, PyAny>, codec: &str, level: Option<i32>) -> PyResult<Self> {
+            check_codec(codec)?;
+            if !sink.hasattr("write")? {
+                return Err(PyTypeError::new_err("sink must have a `write` method"));
+            }
+            Ok(Self {
+                sink: sink.unbind(),
+                codec: codec.to_string(),
+                level,
+            })
+        }
+
+        /// Compress and write a single record, returning the number of bytes written to the sink.
+        pub fn write(&self, py: Python, record: BytesType) -> PyResult<usize> {
+            let mut frame = vec![];
+            self.encode(py, &record.read_bytes()?, &mut frame)?;
+            self.sink.call_method1(py, "write", (PyBytes::new_bound(py, &frame),))?;
+            Ok(frame.len())
+        }
+
+        /// Compress and write each of `records`, returning the number of bytes written to the sink.
+        /// The records are written in one call to the sink's `write`.
+        pub fn writelines(&self, py: Python, records: Vec<BytesType>) -> PyResult<usize> {
+            let mut frames = vec![];
+            for record in records.iter() {
+                self.encode(py, &record.read_bytes()?, &mut frames)?;
+            }
+            self.sink
+                .call_method1(py, "write", (PyBytes::new_bound(py, &frames),))?;
+            Ok(frames.len())
+        }
+
+        /// Flush the sink, if it has a `flush` method.
+        pub fn flush(&self, py: Python) -> PyResult<()> {
+            if self.sink.bind(py).hasattr("flush")? {
+                self.sink.call_method0(py, "flush")?;
+            }
+            Ok(())
+        }
+
+        fn __enter__(slf: PyRef<Self>) -> PyRef<Self> {
+            slf
+        }
+
+        #[pyo3(signature = (*_args))]
+        fn __exit__(&self, py: Python, _args: &Bound<'_, pyo3::types::PyTuple>) -> PyResult<bool> {
+            self.flush(py).map(|_| false)
+        }
+    }
+
+    impl Writer {
+        /// Append the length prefixed, compressed `record` to `frames`
+        fn encode(&self, py: Python, record: &[u8], frames: &mut Vec<u8>) -> PyResult<()> {
+            let compressed = py
+                .allow_threads(|| codec_compress(&self.codec, record, self.level))
+                .map_err(CompressionError::from_err)?;
+            write_varint(frames, compressed.len() as u64);
+            frames.extend_from_slice(&compressed);
+            Ok(())
+        }
+    }
+
+    /// Read records written by `Writer` from `source`, anything with a `read` method such as a
+    /// file opened for binary reading, or the bytes-like data itself, decompressing each with `codec`.
+    ///
+    /// Iterating gives each record as a `cramjam.Buffer`, and `read_all` gives a list of those left.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> for record in cramjam.framed.Reader(source, "snappy"):
+    /// ...     handle(record)
+    /// ```
+    #[pyclass]
+    pub struct Reader {
+        /// `None` when given the data itself, which is then all in `buffer`
+        source: Option<PyObject>,
+        codec: String,
+        buffer: Vec<u8>,
+        pos: usize,
+        eof: bool,
+    }
+
+    #[pymethods]
+    impl Reader {
+        /// Initialize a new `Reader` instance.
+        #[new]
+        pub fn __init__(source: Bound<'_, PyAny>, codec: &str) -> PyResult<Self> {
+            check_codec(codec)?;
+            let (source, buffer, eof) = match source.extract::<BytesType>() {
+                Ok(BytesType::RustyFile(_) | BytesType::SpooledBuffer(_)) | Err(_) if source.hasattr("read")? => {
+                    (Some(source.unbind()), vec![], false)
+                }
+                Ok(data) => (None, data.read_bytes()?.into_owned(), true),
+                Err(err) => return Err(err),
+            };
+            Ok(Self {
+                source,
+                codec: codec.to_string(),
+                buffer,
+                pos: 0,
+                eof,
+            })
+        }
+
+        fn __iter__(slf: PyRef<Self>) -> PyRef<Self> {
+            slf
+        }
+
+        fn __next__(&mut self, py: Python) -> PyResult<Option<RustyBuffer>> {
+            self.next_record(py)
+        }
+
+        /// Read all of the remaining records.
+        pub fn read_all(&mut self, py: Python) -> PyResult<Vec<RustyBuffer>> {
+            let mut records = vec![];
+            while let Some(record) = self.next_record(py)? {
+                records.push(record);
+            }
+            Ok(records)
+        }
+    }
+
+    impl Reader {
+        fn next_record(&mut self, py: Python) -> PyResult<Option<RustyBuffer>> {
+            let (len, varint_len) = loop {
+                match read_varint(&self.buffer[self.pos..])? {
+                    Some(varint) => break varint,
+                    None if self.eof && self.pos == self.buffer.len() => return Ok(None),
+                    None if self.eof => return Err(truncated()),
+                    None => self.fill(py)?,
+                }
+            };
+            let len = usize::try_from(len).map_err(|_| truncated())?;
+            // filling moves what's left of the buffer to its start
+            while self.buffer.len() - self.pos - varint_len < len {
+                if self.eof {
+                    return Err(truncated());
+                }
+                self.fill(py)?;
+            }
+            let start = self.pos + varint_len;
+
+            let compressed = &self.buffer[start..start + len];
+            let codec = self.codec.as_str();
+            // each record is held to the process wide expansion ratio, if one is set
+            let limit = crate::limits::output_limit(compressed.len(), None, None)?;
+            let record = py
+                .allow_threads(|| codec_decompress(codec, compressed, limit))
+                .map_err(DecompressionError::from_err)?;
+            self.pos = start + len;
+            Ok(Some(RustyBuffer::from(record)))
+        }
+
+        /// Read the next chunk from the source, dropping records already read
+        fn fill(&mut self, py: Python) -> PyResult<()> {
+            self.buffer.drain(..self.pos);
+            self.pos = 0;
+            let Some(source) = self.source.as_ref() else {
+                self.eof = true;
+                return Ok(());
+            };
+            let chunk = source.call_method1(py, "read", (READ_SIZE,))?;
+            let chunk = chunk.extract::<BytesType>(py)?;
+            let chunk = chunk.read_bytes()?;
+            self.eof = chunk.is_empty();
+            self.buffer.extend_from_slice(&chunk);
+            Ok(())
+        }
+    }
+
+    fn check_codec(codec: &str) -> PyResult<()> {
+        if is_codec(codec) {
+            Ok(())
+        } else {
+            Err(PyValueError::new_err(format!("Unknown codec: '{}'", codec)))
+        }
+    }
+
+    fn truncated() -> PyErr {
+        DecompressionError::new_err("Framed record is truncated")
+    }
+
+    fn write_varint(output: &mut Vec<u8>, mut value: u64) {
+        while value >= 0x80 {
+            output.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        output.push(value as u8);
+    }
+
+    /// The varint at the start of `data` and its encoded length, `None` if it's incomplete
+    fn read_varint(data: &[u8]) -> PyResult<Option<(u64, usize)>> {
+        let mut value = 0u64;
+        for (i, byte) in data.iter().take(MAX_VARINT_LEN).enumerate() {
+            value |= u64::from(byte & 0x7f) << (7 * i);
+            if byte & 0x80 == 0 {
+                return Ok(Some((value, i + 1)));
+            }
+        }
+        if data.len() >= MAX_VARINT_LEN {
+            return Err(DecompressionError::new_err("Framed record length is invalid"));
+        }
+        Ok(None)
+    }
+}
@@ -0,0 +1,76 @@
+//! Iterate over the frames of concatenated compressed data, for each codec module's `frames`,
+//! ie. log archives appended to one frame at a time.
+//!
+//! ### Python Example
+//! ```python
+//! >>> for offset, length, data in cramjam.zstd.frames(compressed_bytes):
+//! ...     print(f"frame of {length} bytes at {offset}: {len(data)} bytes decompressed")
+//! ```
+use pyo3::prelude::*;
+
+use crate::exceptions::DecompressionError;
+use crate::io::RustyBuffer;
+use crate::BytesType;
+
+/// Split the frame starting `data` from what follows it, giving its compressed length, and its
+/// decompressed data unless it holds none by design, as skippable frames and padding don't.
+pub(crate) type SplitFrame = fn(&[u8]) -> std::io::Result<(usize, Option<Vec<u8>>)>;
+
+/// Length of the skippable frame starting `data`, as zstd and lz4 frames share them, if it is one
+pub(crate) fn skippable_frame_len(data: &[u8]) -> Option<usize> {
+    let magic = u32::from_le_bytes(data.get(..4)?.try_into().unwrap());
+    let size = u32::from_le_bytes(data.get(4..8)?.try_into().unwrap());
+    (magic & 0xffff_fff0 == 0x184d_2a50).then_some(8 + size as usize)
+}
+
+/// Iterator over the frames of concatenated compressed data, giving
+/// `(offset, length, decompressed)` for each, see ie. `cramjam.zstd.frames`
+#[pyclass]
+pub struct FrameIterator {
+    data: Vec<u8>,
+    pos: usize,
+    split: SplitFrame,
+}
+
+impl FrameIterator {
+    /// Iterate over the frames of `data`, a `File` being read from its current position, which
+    /// offsets are then relative to
+    pub(crate) fn new(data: BytesType, split: SplitFrame) -> PyResult<Self> {
+        Ok(Self {
+            data: data.read_bytes()?.into_owned(),
+            pos: 0,
+            split,
+        })
+    }
+}
+
+#[pymethods]
+impl FrameIterator {
+    fn __iter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __next__(&mut self, py: Python) -> PyResult<Option<(usize, usize, RustyBuffer)>> {
+        while self.pos < self.data.len() {
+            let offset = self.pos;
+            let (data, split) = (&self.data[offset..], self.split);
+            match py.allow_threads(|| split(data)) {
+                Ok((len, frame)) => {
+                    self.pos += len;
+                    if let Some(frame) = frame {
+                        return Ok(Some((offset, len, RustyBuffer::from(frame))));
+                    }
+                }
+                Err(err) => {
+                    // nothing after a broken frame can be found
+                    self.pos = self.data.len();
+                    return Err(DecompressionError::new_err(format!(
+                        "Frame at offset {}: {}",
+                        offset, err
+                    )));
+                }
+            }
+        }
+        Ok(None)
+    }
+}
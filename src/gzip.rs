@@ -6,11 +6,15 @@ use pyo3::prelude::*;
 pub mod gzip {
 
     use crate::exceptions::{CompressionError, DecompressionError};
-    use crate::io::{AsBytes, RustyBuffer};
+    use crate::io::{AsBytes, IntoLength, OutputBuffer, RustyBuffer};
+    use crate::limits::Budget;
     use crate::BytesType;
+    use libcramjam::gzip::flate2::{Compression, GzBuilder};
+    use pyo3::exceptions::PyValueError;
     use pyo3::prelude::*;
+    use pyo3::types::{PyBytes, PyDict};
     use pyo3::PyResult;
-    use std::io::Cursor;
+    use std::io::{Cursor, Read, Write};
 
     const DEFAULT_COMPRESSION_LEVEL: u32 = 6;
 
@@ -19,88 +23,1175 @@ pub mod gzip {
     /// Python Example
     /// --------------
     /// ```python
-    /// >>> cramjam.gzip.decompress(compressed_bytes, output_len=Optional[int])
+    /// >>> cramjam.gzip.decompress(compressed_bytes, output_len=Optional[int], max_expansion_ratio=Optional[float], into=Optional[Buffer])
     /// ```
     #[pyfunction]
-    #[pyo3(signature = (data, output_len=None))]
-    pub fn decompress(py: Python, data: BytesType, output_len: Option<usize>) -> PyResult<RustyBuffer> {
-        crate::generic!(py, libcramjam::gzip::decompress[data], output_len = output_len)
-            .map_err(DecompressionError::from_err)
+    #[pyo3(signature = (data, output_len=None, max_expansion_ratio=None, into=None, *, output_len_max=None))]
+    pub fn decompress<'py>(
+        py: Python<'py>,
+        data: BytesType,
+        output_len: Option<usize>,
+        max_expansion_ratio: Option<f64>,
+        into: Option<Bound<'py, RustyBuffer>>,
+        output_len_max: Option<usize>,
+    ) -> PyResult<OutputBuffer<'py>> {
+        crate::generic!(
+            py,
+            decompress_stream[data],
+            max_expansion_ratio = max_expansion_ratio,
+            output_len_max = output_len_max,
+            into = into,
+            output_len = output_len
+        )
+        .map_err(DecompressionError::from_err)
     }
 
     /// Gzip compression.
     ///
+    /// The member's header may be given a `filename` and `comment`, which are encoded as
+    /// latin-1 as by the `gzip` module, an `mtime` (seconds since the epoch, 0 by default),
+    /// `os` (255, unknown, by default) and `extra` field; `inspect_header` reads them back.
+    ///
     /// Python Example
     /// --------------
     /// ```python
     /// >>> cramjam.gzip.compress(b'some bytes here', level=2, output_len=Optional[int])  # Level defaults to 6
+    /// >>> cramjam.gzip.compress(b'some bytes here', compresslevel=2)  # stdlib style alias of level
+    /// >>> cramjam.gzip.compress(b'some bytes here', filename="data.txt", mtime=int(time.time()), os=3)
     /// ```
     #[pyfunction]
-    #[pyo3(signature = (data, level=None, output_len=None))]
+    #[pyo3(signature = (data, level=None, output_len=None, *, compresslevel=None, mtime=None, filename=None, comment=None, os=None, extra=None))]
+    #[allow(clippy::too_many_arguments)]
     pub fn compress(
         py: Python,
         data: BytesType,
         level: Option<u32>,
         output_len: Option<usize>,
+        compresslevel: Option<u32>,
+        mtime: Option<u32>,
+        filename: Option<&str>,
+        comment: Option<&str>,
+        os: Option<u8>,
+        extra: Option<Vec<u8>>,
     ) -> PyResult<RustyBuffer> {
-        crate::generic!(py, libcramjam::gzip::compress[data], output_len = output_len, level)
+        let level = crate::level_alias(level, "compresslevel", compresslevel)?;
+        let header = HeaderFields::new(mtime, filename, comment, os, extra)?;
+        crate::generic!(py, compress_with_header[data], output_len = output_len, level, header)
             .map_err(CompressionError::from_err)
     }
 
+    /// Fields of a gzip member's header to write
+    struct HeaderFields {
+        mtime: Option<u32>,
+        filename: Option<Vec<u8>>,
+        comment: Option<Vec<u8>>,
+        os: Option<u8>,
+        extra: Option<Vec<u8>>,
+    }
+
+    impl HeaderFields {
+        fn new(
+            mtime: Option<u32>,
+            filename: Option<&str>,
+            comment: Option<&str>,
+            os: Option<u8>,
+            extra: Option<Vec<u8>>,
+        ) -> PyResult<Self> {
+            if let Some(extra) = extra.as_ref().filter(|extra| extra.len() > u16::MAX as usize) {
+                return Err(PyValueError::new_err(format!(
+                    "extra must be at most {} bytes, got {}",
+                    u16::MAX,
+                    extra.len()
+                )));
+            }
+            Ok(Self {
+                mtime,
+                filename: filename.map(|name| latin1("filename", name)).transpose()?,
+                comment: comment.map(|comment| latin1("comment", comment)).transpose()?,
+                os,
+                extra,
+            })
+        }
+
+        fn builder(self) -> GzBuilder {
+            let mut builder = GzBuilder::new().mtime(self.mtime.unwrap_or_default());
+            if let Some(filename) = self.filename {
+                builder = builder.filename(filename);
+            }
+            if let Some(comment) = self.comment {
+                builder = builder.comment(comment);
+            }
+            if let Some(os) = self.os {
+                builder = builder.operating_system(os);
+            }
+            if let Some(extra) = self.extra {
+                builder = builder.extra(extra);
+            }
+            builder
+        }
+    }
+
+    /// Encode a header's `field` as latin-1, as the gzip format has it
+    fn latin1(field: &str, value: &str) -> PyResult<Vec<u8>> {
+        value
+            .chars()
+            .map(|c| u8::try_from(c).ok().filter(|b| *b != 0))
+            .collect::<Option<Vec<u8>>>()
+            .ok_or_else(|| {
+                PyValueError::new_err(format!(
+                    "{} must be latin-1 encodable and not contain NUL characters, got {:?}",
+                    field, value
+                ))
+            })
+    }
+
+    /// Gzip compression with `header`, otherwise as `libcramjam::gzip::compress`
+    fn compress_with_header<W: Write + ?Sized, R: Read>(
+        input: R,
+        output: &mut W,
+        level: Option<u32>,
+        header: HeaderFields,
+    ) -> std::io::Result<usize> {
+        let level = Compression::new(level.unwrap_or(DEFAULT_COMPRESSION_LEVEL));
+        let mut encoder = header.builder().read(input, level);
+        std::io::copy(&mut encoder, output).map(|n| n as usize)
+    }
+
+    /// Read the header of the gzip member at the start of `data`, as a dict of its `filename`,
+    /// `comment` (`str` decoded as latin-1, or `None` if not set), `mtime`, `os`, `extra`
+    /// (`bytes` or `None`), `text` (whether it's flagged as probably text), `xfl` (its extra
+    /// flags, `2` for maximum compression and `4` for fastest), and `header_len`, its length in
+    /// bytes. A `File` is read from its current position, which is left as it was.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.gzip.inspect_header(cramjam.gzip.compress(b'data', filename="data.txt", mtime=1700000000))
+    /// {'filename': 'data.txt', 'comment': None, 'mtime': 1700000000, 'os': 255, 'extra': None, 'text': False, 'xfl': 0, 'header_len': 19}
+    /// ```
+    #[pyfunction]
+    pub fn inspect_header<'py>(py: Python<'py>, data: BytesType) -> PyResult<Bound<'py, PyDict>> {
+        // the filename and comment run to a NUL, so read more until the header is complete
+        let mut peek_len = HEADER_PEEK_LEN;
+        let bytes = loop {
+            let bytes = data.peek_bytes(peek_len)?;
+            if bytes.len() < peek_len || parse_header(&bytes).map_err(DecompressionError::from_err)?.is_some() {
+                break bytes;
+            }
+            peek_len = peek_len.saturating_mul(4);
+        };
+        let header = match parse_header(&bytes).map_err(DecompressionError::from_err)? {
+            Some(header) => header,
+            None => return Err(DecompressionError::new_err("gzip header is truncated")),
+        };
+        let decode = |field: Option<&[u8]>| field.map(|field| field.iter().map(|b| *b as char).collect::<String>());
+        let dict = PyDict::new_bound(py);
+        dict.set_item("filename", decode(header.filename))?;
+        dict.set_item("comment", decode(header.comment))?;
+        dict.set_item("mtime", header.mtime)?;
+        dict.set_item("os", header.os)?;
+        dict.set_item("extra", header.extra.map(|extra| PyBytes::new_bound(py, extra)))?;
+        dict.set_item("text", header.text)?;
+        dict.set_item("xfl", header.xfl)?;
+        dict.set_item("header_len", header.len)?;
+        Ok(dict)
+    }
+
     /// Compress directly into an output buffer
     #[pyfunction]
-    #[pyo3(signature = (input, output, level=None))]
-    pub fn compress_into(py: Python, input: BytesType, mut output: BytesType, level: Option<u32>) -> PyResult<usize> {
-        crate::generic!(py, libcramjam::gzip::compress[input, output], level).map_err(CompressionError::from_err)
+    #[pyo3(signature = (input, output, level=None, *, compresslevel=None, align=None))]
+    pub fn compress_into(
+        py: Python,
+        input: BytesType,
+        mut output: BytesType,
+        level: Option<u32>,
+        compresslevel: Option<u32>,
+        align: Option<usize>,
+    ) -> PyResult<IntoLength> {
+        let level = crate::level_alias(level, "compresslevel", compresslevel)?;
+        let written =
+            crate::generic!(py, libcramjam::gzip::compress[input, output], level).map_err(CompressionError::from_err)?;
+        crate::io::pad_output(&mut output, written, align)
+    }
+
+    /// Lowest compression `level` accepted.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.gzip.min_level()
+    /// 0
+    /// ```
+    #[pyfunction]
+    pub fn min_level() -> u32 {
+        libcramjam::gzip::flate2::Compression::none().level()
+    }
+
+    /// Highest compression `level` accepted.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.gzip.max_level()
+    /// 9
+    /// ```
+    #[pyfunction]
+    pub fn max_level() -> u32 {
+        libcramjam::gzip::flate2::Compression::best().level()
+    }
+
+    /// Compression `level` used by `compress` when none is given.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.gzip.default_level()
+    /// 6
+    /// ```
+    #[pyfunction]
+    pub fn default_level() -> u32 {
+        DEFAULT_COMPRESSION_LEVEL
+    }
+
+    /// Implementation gzip data is de/compressed with: `"zlib-ng"` when cramjam was built with
+    /// the `zlib-ng` feature, otherwise `"miniz_oxide"`.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.gzip.backend()
+    /// 'miniz_oxide'
+    /// ```
+    #[pyfunction]
+    pub fn backend() -> &'static str {
+        crate::flate2_backend()
+    }
+
+    /// Iterate over the lines of gzip compressed `input`, decompressing only as far as each line
+    /// taken needs, rather than materializing the whole file; see `cramjam.iter_lines`.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> with open("app.log.gz", "rb") as f:
+    /// ...     for line in cramjam.gzip.iter_lines(f, encoding="utf-8", errors="strict"):
+    /// ...         handle(line)
+    /// ```
+    #[pyfunction]
+    #[pyo3(signature = (input, encoding=Some("utf-8".to_string()), errors="strict".to_string()))]
+    pub fn iter_lines(
+        input: &Bound<'_, PyAny>,
+        encoding: Option<String>,
+        errors: String,
+    ) -> PyResult<crate::lines::LineIterator> {
+        crate::lines::iter_lines("gzip", input, encoding, errors)
     }
 
     /// Decompress directly into an output buffer
     #[pyfunction]
     pub fn decompress_into(py: Python, input: BytesType, mut output: BytesType) -> PyResult<usize> {
-        crate::generic!(py, libcramjam::gzip::decompress[input, output]).map_err(DecompressionError::from_err)
+        crate::generic!(py, decompress: decompress_stream[input, output]).map_err(DecompressionError::from_err)
+    }
+
+    /// Verify gzip data is intact, decompressing all members and checking their CRC32
+    /// checksums while discarding the output, like `gzip -t`.
+    /// Returns the number of decompressed bytes.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.gzip.test(compressed_bytes)
+    /// 15
+    /// ```
+    #[pyfunction]
+    pub fn test(py: Python, data: BytesType) -> PyResult<usize> {
+        crate::generic!(py, decompress_stream[data], discard).map_err(DecompressionError::from_err)
+    }
+
+    /// Streaming form of `libcramjam::gzip::decompress`, which buffers the output, so its size
+    /// is limited as it's written
+    pub(crate) fn decompress_stream<R: Read, W: Write + ?Sized>(input: R, output: &mut W) -> std::io::Result<usize> {
+        let mut decoder = libcramjam::gzip::flate2::read::MultiGzDecoder::new(input);
+        std::io::copy(&mut decoder, output).map(|n| n as usize)
     }
 
+    mod _paths {
+        use super::*;
+        crate::make_path_helpers!("gzip", "gz");
+    }
+    #[pymodule_export]
+    use _paths::{compress_path, decompress_path};
+
+    mod _batch {
+        use super::*;
+        crate::make_batch_helpers!("gzip", level);
+    }
+    #[pymodule_export]
+    use _batch::{compress_batch, decompress_batch};
+
     /// GZIP Compressor object for streaming compression
     #[pyclass]
     pub struct Compressor {
         inner: Option<libcramjam::gzip::flate2::write::GzEncoder<Cursor<Vec<u8>>>>,
+        returns_output: bool,
+        output_len_hint: usize,
     }
 
     #[pymethods]
     impl Compressor {
         /// Initialize a new `Compressor` instance.
+        ///
+        /// `output_len_hint` is the expected size of the compressed stream, reserved up front
+        /// rather than growing the output repeatedly while compressing large streams.
+        /// The header fields `mtime`, `filename`, `comment`, `os` and `extra` are as for `compress`.
+        ///
+        /// `returns_output=True` makes `compress` return the compressed bytes produced by each
+        /// call, rather than the number of bytes consumed, to stream output without `flush()`.
         #[new]
-        #[pyo3(signature = (level=None))]
-        pub fn __init__(level: Option<u32>) -> PyResult<Self> {
+        #[pyo3(signature = (level=None, *, compresslevel=None, output_len_hint=None, mtime=None, filename=None, comment=None, os=None, extra=None, returns_output=false))]
+        #[allow(clippy::too_many_arguments)]
+        pub fn __init__(
+            level: Option<u32>,
+            compresslevel: Option<u32>,
+            output_len_hint: Option<usize>,
+            mtime: Option<u32>,
+            filename: Option<&str>,
+            comment: Option<&str>,
+            os: Option<u8>,
+            extra: Option<Vec<u8>>,
+            returns_output: bool,
+        ) -> PyResult<Self> {
+            let level = crate::level_alias(level, "compresslevel", compresslevel)?;
+            let output_len_hint = output_len_hint.unwrap_or_default();
             let level = level.unwrap_or(DEFAULT_COMPRESSION_LEVEL);
-            let inner = libcramjam::gzip::flate2::write::GzEncoder::new(
-                Cursor::new(vec![]),
-                libcramjam::gzip::flate2::Compression::new(level),
+            let inner = HeaderFields::new(mtime, filename, comment, os, extra)?.builder().write(
+                Cursor::new(Vec::with_capacity(output_len_hint)),
+                Compression::new(level),
             );
-            Ok(Self { inner: Some(inner) })
+            Ok(Self {
+                inner: Some(inner),
+                returns_output,
+                output_len_hint,
+            })
         }
 
         /// Compress input into the current compressor's stream.
-        pub fn compress(&mut self, input: &[u8]) -> PyResult<usize> {
-            crate::io::stream_compress(&mut self.inner, input)
+        pub fn compress(&mut self, py: Python, input: &[u8]) -> PyResult<PyObject> {
+            crate::io::stream_compress_output(
+                py,
+                &mut self.inner,
+                input,
+                self.returns_output,
+                self.output_len_hint,
+                |e| e.get_mut(),
+            )
         }
 
         /// Flush and return current compressed stream
-        pub fn flush(&mut self) -> PyResult<RustyBuffer> {
-            crate::io::stream_flush(&mut self.inner, |e| e.get_mut())
+        pub fn flush(&mut self, py: Python) -> PyResult<RustyBuffer> {
+            py.allow_threads(|| crate::io::stream_flush(&mut self.inner, self.output_len_hint, |e| e.get_mut()))
         }
 
         /// Consume the current compressor state and return the compressed stream
         /// **NB** The compressor will not be usable after this method is called.
+        pub fn finish(&mut self, py: Python) -> PyResult<RustyBuffer> {
+            py.allow_threads(|| {
+                crate::io::stream_finish(&mut self.inner, |inner| inner.finish().map(|c| c.into_inner()))
+            })
+        }
+
+        /// Bytes currently reserved for the compressed output, ie. to check `output_len_hint`
+        /// was taken up; `0` once finished.
+        pub fn reserved_capacity(&self) -> usize {
+            crate::io::stream_capacity(&self.inner, |e| e.get_ref())
+        }
+    }
+
+    /// Where a [`Decompressor`] is within the current gzip member
+    enum Stage {
+        /// Waiting for the rest of a member's header
+        Header,
+        /// Inflating the member's deflate stream
+        Body,
+        /// Waiting for the CRC32 and size which end the member
+        Trailer,
+        /// A member has ended, and the next bytes tell whether another one follows
+        MemberEnd,
+        /// The data ended with bytes which aren't a gzip member; they're kept as `unused_data`
+        Unused,
+    }
+
+    /// Decompressor object for streaming decompression, decoding input incrementally as it's
+    /// given, across any number of concatenated gzip members.
+    ///
+    /// Like `zlib.decompressobj()`, `eof` is set once a member has ended, and is cleared again
+    /// if another member follows. Bytes after the last member which aren't another member are
+    /// collected in `unused_data`.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> decompressor = cramjam.gzip.Decompressor()
+    /// >>> decompressor.decompress(compressed_bytes + b'trailing')
+    /// >>> decompressor.eof, bytes(decompressor.unused_data)
+    /// (True, b'trailing')
+    /// >>> decompressor.finish()
+    /// ```
+    #[pyclass]
+    pub struct Decompressor {
+        inflate: libcramjam::gzip::flate2::Decompress,
+        crc: libcramjam::gzip::flate2::Crc,
+        stage: Stage,
+        /// Input not yet consumed, ie. a header split across calls
+        pending: Vec<u8>,
+        /// Decompressed output, `None` once finished
+        output: Option<Vec<u8>>,
+        unused_data: Vec<u8>,
+    }
+
+    #[pymethods]
+    #[allow(clippy::len_without_is_empty)]
+    impl Decompressor {
+        /// Initialize a new `Decompressor` instance.
+        #[new]
+        pub fn __init__() -> PyResult<Self> {
+            Ok(Self {
+                inflate: libcramjam::gzip::flate2::Decompress::new(false),
+                crc: libcramjam::gzip::flate2::Crc::new(),
+                stage: Stage::Header,
+                pending: vec![],
+                output: Some(vec![]),
+                unused_data: vec![],
+            })
+        }
+
+        /// Length of internal buffer containing decompressed data.
+        pub fn len(&self) -> usize {
+            self.output.as_ref().map(|o| o.len()).unwrap_or_default()
+        }
+
+        /// Decompress this input into the inner buffer, returning the number of bytes decompressed.
+        /// Its output is limited as `decompress` limits it, by `max_expansion_ratio` times the
+        /// size of this input and `output_len_max`.
+        #[pyo3(signature = (input, max_expansion_ratio=None, *, output_len_max=None))]
+        pub fn decompress(
+            &mut self,
+            py: Python,
+            input: BytesType,
+            max_expansion_ratio: Option<f64>,
+            output_len_max: Option<usize>,
+        ) -> PyResult<usize> {
+            if self.output.is_none() {
+                return Err(DecompressionError::new_err(
+                    "Appears `finish()` was called on this instance",
+                ));
+            }
+            let mut budget = Budget::new(crate::limits::output_limit(
+                input.len(),
+                max_expansion_ratio,
+                output_len_max,
+            )?);
+            match input {
+                BytesType::RustyFile(f) => {
+                    let mut borrowed = f.borrow_mut();
+                    let f_in = &mut *borrowed;
+                    py.allow_threads(|| f_in.read_to_end(&mut self.pending))?;
+                }
+                BytesType::SpooledBuffer(b) => {
+                    let mut borrowed = b.borrow_mut();
+                    let b_in = &mut *borrowed;
+                    py.allow_threads(|| b_in.read_to_end(&mut self.pending))?;
+                }
+                _ => self.pending.extend_from_slice(input.as_bytes()),
+            }
+            py.allow_threads(|| self.advance(&mut budget))
+                .map_err(DecompressionError::from_err)
+        }
+
+        /// Whether the end of a gzip member has been reached, with no further member started.
+        #[getter]
+        pub fn eof(&self) -> bool {
+            matches!(self.stage, Stage::MemberEnd | Stage::Unused)
+        }
+
+        /// Bytes found after the end of the last gzip member.
+        #[getter]
+        pub fn unused_data<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+            PyBytes::new_bound(py, &self.unused_data)
+        }
+
+        /// Flush and return current decompressed stream.
+        pub fn flush(&mut self) -> PyResult<RustyBuffer> {
+            match self.output.as_mut() {
+                Some(output) => Ok(RustyBuffer::from(std::mem::take(output))),
+                None => Err(DecompressionError::new_err(
+                    "Appears `finish()` was called on this instance",
+                )),
+            }
+        }
+
+        /// Consume the current Decompressor state and return the decompressed stream
+        /// **NB** The Decompressor will not be usable after this method is called.
         pub fn finish(&mut self) -> PyResult<RustyBuffer> {
-            crate::io::stream_finish(&mut self.inner, |inner| inner.finish().map(|c| c.into_inner()))
+            let output = match std::mem::take(&mut self.output) {
+                Some(output) => output,
+                None => {
+                    return Err(DecompressionError::new_err(
+                        "Appears `finish()` was called on this instance",
+                    ))
+                }
+            };
+            match self.stage {
+                Stage::Header if self.pending.is_empty() => Ok(RustyBuffer::from(output)),
+                Stage::MemberEnd | Stage::Unused => Ok(RustyBuffer::from(output)),
+                _ => Err(DecompressionError::new_err(
+                    "Compressed data ended before the end-of-stream marker was reached",
+                )),
+            }
+        }
+
+        /// Read and remove up to `n` bytes, or all by default, from the front of the decompressed
+        /// data; with `readinto` and friends a Decompressor can be wrapped by `io.TextIOWrapper`
+        /// or given to parsers expecting a readable binary file, once it's been fed the input.
+        #[pyo3(signature = (n=-1))]
+        pub fn read<'py>(&mut self, py: Python<'py>, n: isize) -> PyResult<Bound<'py, PyBytes>> {
+            crate::io::stream_read(py, self.output.as_mut(), n)
+        }
+
+        /// Same as `read`, for `io` wrappers
+        #[pyo3(signature = (n=-1))]
+        pub fn read1<'py>(&mut self, py: Python<'py>, n: isize) -> PyResult<Bound<'py, PyBytes>> {
+            self.read(py, n)
+        }
+
+        /// Read decompressed data into `output`, returning the number of bytes read.
+        pub fn readinto(&mut self, mut output: BytesType) -> PyResult<usize> {
+            let buf = output.as_bytes_mut()?;
+            crate::io::stream_readinto(self.output.as_mut(), buf)
+        }
+
+        /// Whether `read` may be called; `True` until the Decompressor is finished or closed.
+        pub fn readable(&self) -> bool {
+            !self.closed()
+        }
+
+        /// Always `False`
+        pub fn writable(&self) -> bool {
+            false
+        }
+
+        /// Always `False`
+        pub fn seekable(&self) -> bool {
+            false
+        }
+
+        /// Discard the Decompressor's state, like `finish()` without returning the output.
+        pub fn close(&mut self) {
+            self.output = None;
+        }
+
+        /// Whether `finish()` or `close()` has been called.
+        #[getter]
+        pub fn closed(&self) -> bool {
+            self.output.is_none()
+        }
+
+        fn __len__(&self) -> usize {
+            self.len()
+        }
+        fn __contains__(&self, py: Python, x: BytesType) -> PyResult<bool> {
+            let bytes = &*x.peek_bytes(usize::MAX)?;
+            Ok(py.allow_threads(|| {
+                self.output
+                    .as_ref()
+                    .map(|o| o.windows(bytes.len()).any(|w| w == bytes))
+                    .unwrap_or_default()
+            }))
+        }
+        fn __repr__(&self) -> String {
+            format!("Decompressor<len={}>", self.len())
+        }
+        fn __bool__(&self) -> bool {
+            self.output.is_some() && self.len() > 0
         }
     }
 
-    mod _decompressor {
+    impl Decompressor {
+        /// Decode as much of `pending` as possible, returning the number of bytes decompressed
+        fn advance(&mut self, budget: &mut Budget) -> std::io::Result<usize> {
+            use libcramjam::gzip::flate2::{FlushDecompress, Status};
+
+            let output = self.output.as_mut().expect("checked by caller");
+            let output_start = output.len();
+            let mut pos = 0;
+            loop {
+                let data = &self.pending[pos..];
+                match self.stage {
+                    Stage::Header => match header_len(data)? {
+                        Some(n) => {
+                            pos += n;
+                            self.inflate.reset(false);
+                            self.crc.reset();
+                            self.stage = Stage::Body;
+                        }
+                        None => break,
+                    },
+                    Stage::Body => {
+                        if output.capacity() - output.len() < 1024 {
+                            output.reserve(data.len().saturating_mul(4).max(32 * 1024));
+                        }
+                        let (in_before, out_before) = (self.inflate.total_in(), output.len());
+                        let status = self
+                            .inflate
+                            .decompress_vec(data, output, FlushDecompress::None)
+                            .map_err(std::io::Error::other)?;
+                        pos += (self.inflate.total_in() - in_before) as usize;
+                        budget.spend(output.len() - out_before)?;
+                        self.crc.update(&output[out_before..]);
+                        if status == Status::StreamEnd {
+                            self.stage = Stage::Trailer;
+                        } else if self.inflate.total_in() == in_before && output.len() == out_before {
+                            // needs more input
+                            break;
+                        }
+                    }
+                    Stage::Trailer => {
+                        if data.len() < 8 {
+                            break;
+                        }
+                        let crc = u32::from_le_bytes(data[..4].try_into().unwrap());
+                        let size = u32::from_le_bytes(data[4..8].try_into().unwrap());
+                        if crc != self.crc.sum() || size != self.crc.amount() {
+                            return Err(std::io::Error::new(
+                                std::io::ErrorKind::InvalidData,
+                                "gzip member failed its CRC32 or size check",
+                            ));
+                        }
+                        pos += 8;
+                        self.stage = Stage::MemberEnd;
+                    }
+                    Stage::MemberEnd => match data {
+                        [] | [0x1f] => break,
+                        [0x1f, 0x8b, ..] => self.stage = Stage::Header,
+                        _ => self.stage = Stage::Unused,
+                    },
+                    Stage::Unused => {
+                        self.unused_data.extend_from_slice(data);
+                        pos = self.pending.len();
+                        break;
+                    }
+                }
+            }
+            self.pending.drain(..pos);
+            Ok(output.len() - output_start)
+        }
+    }
+
+    mod _index {
         use super::*;
-        crate::make_decompressor!(gzip);
+        use crate::inflate::{Bits, Inflate, Stop, WINDOW_SIZE};
+        use crate::io::{ReadSeek, ReadSource};
+        use libcramjam::gzip::flate2::Crc;
+        use std::io::{BufRead, BufReader, Error, ErrorKind, SeekFrom};
+
+        /// Bytes decompressed at a time building an index or reading
+        const CHUNK_SIZE: usize = 64 * 1024;
+
+        const DEFAULT_SPACING: u64 = 1 << 20;
+
+        /// Start of an index's serialized form, ending with its version
+        const INDEX_MAGIC: &[u8; 8] = b"CJGZIDX1";
+
+        /// Build an index of access points into the gzip data of `file`, a `cramjam.File` or
+        /// `SpooledBuffer` read from its current position, or the bytes-like data itself, at least
+        /// `spacing` bytes of the decompressed data apart, for a `Reader` to get to any offset by
+        /// decompressing from the access point before it rather than from the start, as zlib's
+        /// `zran.c` does. Access points are at the start of deflate blocks, so may be further
+        /// apart in data compressed into few, large blocks, as very repetitive data is.
+        ///
+        /// Building it decompresses the data once, checking each member's CRC32 and size, with a
+        /// decoder of its own which can stop at each deflate block, as flate2's can't; it takes
+        /// about one and a half times as long as `gzip.decompress` of the same data. Each
+        /// access point holds the 32KB decompressed before it, so the index takes about
+        /// `32KB / spacing` of the decompressed size; `Index.to_bytes()` gives it to keep alongside
+        /// the data, and `Index.from_bytes()` it back.
+        ///
+        /// Python Example
+        /// --------------
+        /// ```python
+        /// >>> index = cramjam.gzip.build_index(cramjam.File("data.gz", read=True), spacing=1 << 20)
+        /// >>> len(index), index.length
+        /// (1024, 1073741824)
+        /// ```
+        #[pyfunction]
+        #[pyo3(signature = (file, spacing=DEFAULT_SPACING))]
+        pub fn build_index(py: Python, file: BytesType, spacing: u64) -> PyResult<Index> {
+            if spacing == 0 {
+                return Err(PyValueError::new_err("spacing must be at least 1"));
+            }
+            ReadSource::new(file)
+                .with_reader(py, |reader| build(&mut BufReader::new(reader), spacing))
+                .map_err(DecompressionError::from_err)
+        }
+
+        /// Access points into gzip data, see `build_index`.
+        #[pyclass(frozen)]
+        pub struct Index {
+            points: Vec<AccessPoint>,
+            /// Length of the decompressed data
+            length: u64,
+            spacing: u64,
+        }
+
+        /// Where decompression can start again: a block's start, and the data decompressed before it
+        struct AccessPoint {
+            /// Position in the compressed data, in bits
+            bit: u64,
+            /// Position in the decompressed data
+            offset: u64,
+            /// Up to `WINDOW_SIZE` bytes decompressed before `offset`
+            window: Vec<u8>,
+        }
+
+        #[pymethods]
+        impl Index {
+            /// `(bit, offset)` of each access point: its position in the compressed data, in bits,
+            /// and in the decompressed data.
+            #[getter]
+            pub fn points(&self) -> Vec<(u64, u64)> {
+                self.points.iter().map(|point| (point.bit, point.offset)).collect()
+            }
+
+            /// Length of the decompressed data
+            #[getter]
+            pub fn length(&self) -> u64 {
+                self.length
+            }
+
+            /// Spacing the index was built with
+            #[getter]
+            pub fn spacing(&self) -> u64 {
+                self.spacing
+            }
+
+            /// Serialize the index, to be given back to `Index.from_bytes()`
+            pub fn to_bytes(&self) -> RustyBuffer {
+                let mut output = INDEX_MAGIC.to_vec();
+                for value in [self.spacing, self.length, self.points.len() as u64] {
+                    output.extend_from_slice(&value.to_le_bytes());
+                }
+                for point in &self.points {
+                    output.extend_from_slice(&point.bit.to_le_bytes());
+                    output.extend_from_slice(&point.offset.to_le_bytes());
+                    output.extend_from_slice(&(point.window.len() as u32).to_le_bytes());
+                    output.extend_from_slice(&point.window);
+                }
+                RustyBuffer::from(output)
+            }
+
+            /// Load an index serialized by `Index.to_bytes()`
+            #[staticmethod]
+            pub fn from_bytes(data: BytesType) -> PyResult<Self> {
+                let bytes = data.read_bytes()?;
+                let mut data = &*bytes;
+                if take(&mut data, INDEX_MAGIC.len())? != INDEX_MAGIC {
+                    return Err(PyValueError::new_err(
+                        "Invalid gzip index: not serialized by Index.to_bytes()",
+                    ));
+                }
+                let (spacing, length) = (take_u64(&mut data)?, take_u64(&mut data)?);
+                let mut points = vec![];
+                for _ in 0..take_u64(&mut data)? {
+                    let (bit, offset) = (take_u64(&mut data)?, take_u64(&mut data)?);
+                    let window_len = u32::from_le_bytes(take(&mut data, 4)?.try_into().unwrap()) as usize;
+                    if window_len > WINDOW_SIZE {
+                        return Err(PyValueError::new_err("Invalid gzip index: window is too long"));
+                    }
+                    let window = take(&mut data, window_len)?.to_vec();
+                    points.push(AccessPoint { bit, offset, window });
+                }
+                if points.first().map_or(length > 0, |point| point.offset != 0) {
+                    return Err(PyValueError::new_err(
+                        "Invalid gzip index: no access point at the start",
+                    ));
+                }
+                Ok(Self {
+                    points,
+                    length,
+                    spacing,
+                })
+            }
+
+            fn __len__(&self) -> usize {
+                self.points.len()
+            }
+
+            fn __repr__(&self) -> String {
+                format!("Index<points={}, length={}>", self.points.len(), self.length)
+            }
+        }
+
+        /// The next `n` bytes of a serialized index
+        fn take<'a>(data: &mut &'a [u8], n: usize) -> PyResult<&'a [u8]> {
+            let taken = data
+                .get(..n)
+                .ok_or_else(|| PyValueError::new_err("Invalid gzip index: truncated"))?;
+            *data = &data[n..];
+            Ok(taken)
+        }
+
+        fn take_u64(data: &mut &[u8]) -> PyResult<u64> {
+            take(data, 8).map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+        }
+
+        /// Read gzip data `file`, a `cramjam.File`, `SpooledBuffer` or the bytes-like data itself,
+        /// from any offset of the decompressed data with an `index` of it from `build_index`,
+        /// decompressing from the access point before the offset. A `cramjam.File` or
+        /// `SpooledBuffer` is read from as needed, from the position it's at on creating the
+        /// reader, which should be that the index was built from.
+        ///
+        /// Reads following each other carry on decompressing where the last one stopped.
+        ///
+        /// Python Example
+        /// --------------
+        /// ```python
+        /// >>> reader = cramjam.gzip.Reader(cramjam.File("data.gz", read=True), index)
+        /// >>> reader.seek(500_000_000)
+        /// >>> reader.read(100)
+        /// ```
+        #[pyclass]
+        pub struct Reader {
+            source: ReadSource,
+            /// Position of the gzip data in the source
+            start: u64,
+            index: Py<Index>,
+            /// Position in the decompressed data
+            pos: u64,
+            decoder: Option<Decoder>,
+        }
+
+        #[pymethods]
+        #[allow(clippy::len_without_is_empty)]
+        impl Reader {
+            /// Initialize a new `Reader` instance.
+            #[new]
+            pub fn __init__(py: Python, file: BytesType, index: Py<Index>) -> PyResult<Self> {
+                let mut source = ReadSource::new(file);
+                let start = source
+                    .with_reader(py, |reader| reader.stream_position())
+                    .map_err(DecompressionError::from_err)?;
+                Ok(Self {
+                    source,
+                    start,
+                    index,
+                    pos: 0,
+                    decoder: None,
+                })
+            }
+
+            /// Read `n_bytes` of the decompressed data from the current position, or all that's
+            /// left if not given or negative.
+            #[pyo3(signature = (n_bytes=None))]
+            pub fn read(&mut self, py: Python, n_bytes: Option<isize>) -> PyResult<RustyBuffer> {
+                let index = self.index.get();
+                let end = match n_bytes {
+                    Some(n) if n >= 0 => self.pos.saturating_add(n as u64).min(index.length),
+                    _ => index.length,
+                };
+                // the index's length isn't trusted until decompressing gets there
+                let mut output = Vec::with_capacity(end.saturating_sub(self.pos).min(CHUNK_SIZE as u64) as usize);
+                if self.pos < end {
+                    let (start, pos, decoder) = (self.start, self.pos, &mut self.decoder);
+                    self.source
+                        .with_reader(py, |reader| {
+                            read_range(reader, start, index, decoder, pos..end, &mut output)
+                        })
+                        .map_err(DecompressionError::from_err)?;
+                    self.pos = end;
+                }
+                Ok(RustyBuffer::from(output))
+            }
+
+            /// Seek to a position of the decompressed data, relative to `whence`, returning it.
+            ///
+            /// whence:
+            /// 0: from start of the data
+            /// 1: from current position
+            /// 2: from end of the data
+            ///
+            /// Positions past the end are allowed, with nothing to read from there.
+            #[pyo3(signature = (position, whence=0))]
+            pub fn seek(&mut self, position: i64, whence: usize) -> PyResult<u64> {
+                let base = match whence {
+                    0 => 0,
+                    1 => self.pos,
+                    2 => self.len(),
+                    _ => {
+                        return Err(PyValueError::new_err(
+                            "whence should be one of 0: seek from start, 1: seek from current, or 2: seek from end",
+                        ))
+                    }
+                };
+                self.pos = base.checked_add_signed(position).ok_or_else(|| {
+                    PyValueError::new_err(format!("Negative seek position {}", base as i64 + position))
+                })?;
+                Ok(self.pos)
+            }
+
+            /// Current position in the decompressed data
+            pub fn tell(&self) -> u64 {
+                self.pos
+            }
+
+            /// Length of the decompressed data, as given by the index
+            pub fn len(&self) -> u64 {
+                self.index.get().length
+            }
+
+            /// Here for compatibility with file objects, always True.
+            pub fn seekable(&self) -> bool {
+                true
+            }
+
+            /// Here for compatibility with file objects, always True.
+            pub fn readable(&self) -> bool {
+                true
+            }
+        }
+
+        /// Where a `Reader` got to decompressing
+        struct Decoder {
+            bits: Bits,
+            /// `None` between members
+            inflate: Option<Inflate>,
+            /// Data decompressed last, with up to `WINDOW_SIZE` bytes before it
+            history: Vec<u8>,
+            /// Position in the decompressed data of the end of `history`
+            offset: u64,
+        }
+
+        impl Decoder {
+            /// Decompress some more onto `history`, `false` at the end of the data
+            fn advance<R: BufRead>(&mut self, input: &mut R) -> std::io::Result<bool> {
+                if self.history.len() >= WINDOW_SIZE + CHUNK_SIZE {
+                    self.history.drain(..self.history.len() - WINDOW_SIZE);
+                }
+                match self.inflate.as_mut() {
+                    Some(inflate) => {
+                        let len = self.history.len();
+                        let stop = inflate.run(&mut self.bits, input, &mut self.history, len + CHUNK_SIZE)?;
+                        self.offset += (self.history.len() - len) as u64;
+                        if stop == Stop::End {
+                            read_trailer(&mut self.bits, input)?;
+                            self.inflate = None;
+                        }
+                        Ok(true)
+                    }
+                    None if read_header(&mut self.bits, input)? => {
+                        self.inflate = Some(Inflate::new());
+                        Ok(true)
+                    }
+                    None => Ok(false),
+                }
+            }
+        }
+
+        /// Decompress the `range` of the decompressed data onto `output`, carrying on from
+        /// `decoder` if it got to between the access point before the range and its start
+        fn read_range(
+            reader: &mut dyn ReadSeek,
+            start: u64,
+            index: &Index,
+            decoder: &mut Option<Decoder>,
+            range: std::ops::Range<u64>,
+            output: &mut Vec<u8>,
+        ) -> std::io::Result<()> {
+            let point = &index.points[index.points.partition_point(|point| point.offset <= range.start) - 1];
+            let resume = decoder.as_ref().is_some_and(|decoder| {
+                decoder.offset - decoder.history.len() as u64 <= range.start && point.offset <= decoder.offset
+            });
+            if !resume {
+                reader.seek(SeekFrom::Start(start + point.bit / 8))?;
+                *decoder = Some(Decoder {
+                    bits: Bits::at(&mut BufReader::new(&mut *reader), point.bit)?,
+                    inflate: Some(Inflate::new()),
+                    history: point.window.clone(),
+                    offset: point.offset,
+                });
+            }
+            let decoder = decoder.as_mut().unwrap();
+            reader.seek(SeekFrom::Start(start + decoder.bits.input_position()))?;
+            let mut input = BufReader::new(reader);
+
+            let mut pos = range.start;
+            loop {
+                if pos < decoder.offset {
+                    let history_start = decoder.offset - decoder.history.len() as u64;
+                    let end = range.end.min(decoder.offset);
+                    output.extend_from_slice(
+                        &decoder.history[(pos - history_start) as usize..(end - history_start) as usize],
+                    );
+                    pos = end;
+                }
+                if pos == range.end {
+                    return Ok(());
+                }
+                if !decoder.advance(&mut input)? {
+                    return Err(Error::new(
+                        ErrorKind::UnexpectedEof,
+                        "gzip data ended before the length given by its index",
+                    ));
+                }
+            }
+        }
+
+        /// Index `input`, decompressing all of it, see `build_index`
+        fn build<R: BufRead>(input: &mut R, spacing: u64) -> std::io::Result<Index> {
+            let mut bits = Bits::at(input, 0)?;
+            let mut history = Vec::with_capacity(WINDOW_SIZE + CHUNK_SIZE);
+            let (mut points, mut offset) = (Vec::<AccessPoint>::new(), 0);
+            while read_header(&mut bits, input)? {
+                let (mut inflate, mut crc, mut stop) = (Inflate::new(), Crc::new(), Stop::Block);
+                while stop != Stop::End {
+                    if stop == Stop::Block && points.last().is_none_or(|point| offset - point.offset >= spacing) {
+                        points.push(AccessPoint {
+                            bit: bits.position(),
+                            offset,
+                            window: history[history.len().saturating_sub(WINDOW_SIZE)..].to_vec(),
+                        });
+                    }
+                    if history.len() >= WINDOW_SIZE + CHUNK_SIZE {
+                        history.drain(..history.len() - WINDOW_SIZE);
+                    }
+                    let len = history.len();
+                    stop = inflate.run(&mut bits, input, &mut history, len + CHUNK_SIZE)?;
+                    crc.update(&history[len..]);
+                    offset += (history.len() - len) as u64;
+                }
+                if read_trailer(&mut bits, input)? != (crc.sum(), crc.amount()) {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        "gzip member failed its CRC32 or size check",
+                    ));
+                }
+            }
+            Ok(Index {
+                points,
+                length: offset,
+                spacing,
+            })
+        }
+
+        /// Read the header of the next member, `false` at the end of the data. Zeros before it,
+        /// padding the data out to a block size as tar does, are skipped, as gzip itself and
+        /// Python's gzip module skip them.
+        fn read_header<R: BufRead>(bits: &mut Bits, input: &mut R) -> std::io::Result<bool> {
+            let mut header = vec![];
+            loop {
+                if header.is_empty() && bits.is_done(input)? {
+                    return Ok(false);
+                }
+                match bits.byte(input)? {
+                    0 if header.is_empty() => continue,
+                    byte => header.push(byte),
+                }
+                if header_len(&header)?.is_some() {
+                    return Ok(true);
+                }
+            }
+        }
+
+        /// Read the trailer of the member just ended, giving its CRC32 and size
+        fn read_trailer<R: BufRead>(bits: &mut Bits, input: &mut R) -> std::io::Result<(u32, u32)> {
+            let mut trailer = [0; 8];
+            for byte in trailer.iter_mut() {
+                *byte = bits.byte(input)?;
+            }
+            Ok((
+                u32::from_le_bytes(trailer[..4].try_into().unwrap()),
+                u32::from_le_bytes(trailer[4..].try_into().unwrap()),
+            ))
+        }
     }
     #[pymodule_export]
-    use _decompressor::Decompressor;
+    use _index::{build_index, Index, Reader};
+
+    /// Fields of a gzip member's header, see `inspect_header`
+    struct Header<'a> {
+        len: usize,
+        text: bool,
+        mtime: u32,
+        xfl: u8,
+        os: u8,
+        extra: Option<&'a [u8]>,
+        filename: Option<&'a [u8]>,
+        comment: Option<&'a [u8]>,
+    }
+
+    /// Length of the gzip member header at the start of `data`, `None` if it's incomplete
+    fn header_len(data: &[u8]) -> std::io::Result<Option<usize>> {
+        Ok(parse_header(data)?.map(|header| header.len))
+    }
+
+    /// Bytes first read by `inspect_header`, enough for any header without a long filename,
+    /// comment or extra field
+    const HEADER_PEEK_LEN: usize = 1024;
+
+    /// The gzip member header at the start of `data`, `None` if it's incomplete
+    fn parse_header(data: &[u8]) -> std::io::Result<Option<Header<'_>>> {
+        const FTEXT: u8 = 0x01;
+        const FHCRC: u8 = 0x02;
+        const FEXTRA: u8 = 0x04;
+        const FNAME: u8 = 0x08;
+        const FCOMMENT: u8 = 0x10;
+
+        let magic = &crate::detect::GZIP_MAGIC[..data.len().min(crate::detect::GZIP_MAGIC.len())];
+        if !data.starts_with(magic) || data.get(2).is_some_and(|method| *method != 8) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "Invalid gzip header",
+            ));
+        }
+        if data.len() < 10 {
+            return Ok(None);
+        }
+        let flags = data[3];
+        let mut header = Header {
+            len: 10,
+            text: flags & FTEXT != 0,
+            mtime: u32::from_le_bytes([data[4], data[5], data[6], data[7]]),
+            xfl: data[8],
+            os: data[9],
+            extra: None,
+            filename: None,
+            comment: None,
+        };
+        if flags & FEXTRA != 0 {
+            let xlen = match data.get(header.len..header.len + 2) {
+                Some(xlen) => u16::from_le_bytes([xlen[0], xlen[1]]) as usize,
+                None => return Ok(None),
+            };
+            header.len += 2;
+            header.extra = data.get(header.len..header.len + xlen);
+            header.len += xlen;
+        }
+        for (flag, field) in [(FNAME, &mut header.filename), (FCOMMENT, &mut header.comment)] {
+            if flags & flag != 0 {
+                match data
+                    .get(header.len..)
+                    .and_then(|rest| rest.iter().position(|b| *b == 0))
+                {
+                    Some(end) => {
+                        *field = Some(&data[header.len..header.len + end]);
+                        header.len += end + 1;
+                    }
+                    None => return Ok(None),
+                }
+            }
+        }
+        if flags & FHCRC != 0 {
+            header.len += 2;
+        }
+        Ok((header.len <= data.len()).then_some(header))
+    }
 }
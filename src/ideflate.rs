@@ -6,7 +6,7 @@ use pyo3::prelude::*;
 pub mod ideflate {
 
     use crate::exceptions::{CompressionError, DecompressionError};
-    use crate::io::{AsBytes, RustyBuffer};
+    use crate::io::{AsBytes, IntoLength, OutputBuffer, RustyBuffer, SendEncoder};
     use crate::BytesType;
     use pyo3::prelude::*;
     use pyo3::PyResult;
@@ -19,13 +19,27 @@ pub mod ideflate {
     /// Python Example
     /// --------------
     /// ```python
-    /// >>> cramjam.gzip.decompress(compressed_bytes, output_len=Optional[int])
+    /// >>> cramjam.gzip.decompress(compressed_bytes, output_len=Optional[int], max_expansion_ratio=Optional[float], into=Optional[Buffer])
     /// ```
     #[pyfunction]
-    #[pyo3(signature = (data, output_len=None))]
-    pub fn decompress(py: Python, data: BytesType, output_len: Option<usize>) -> PyResult<RustyBuffer> {
-        crate::generic!(py, libcramjam::ideflate::decompress[data], output_len = output_len)
-            .map_err(DecompressionError::from_err)
+    #[pyo3(signature = (data, output_len=None, max_expansion_ratio=None, into=None, *, output_len_max=None))]
+    pub fn decompress<'py>(
+        py: Python<'py>,
+        data: BytesType,
+        output_len: Option<usize>,
+        max_expansion_ratio: Option<f64>,
+        into: Option<Bound<'py, RustyBuffer>>,
+        output_len_max: Option<usize>,
+    ) -> PyResult<OutputBuffer<'py>> {
+        crate::generic!(
+            py,
+            libcramjam::ideflate::decompress[data],
+            max_expansion_ratio = max_expansion_ratio,
+            output_len_max = output_len_max,
+            into = into,
+            output_len = output_len
+        )
+        .map_err(DecompressionError::from_err)
     }
 
     /// ideflate compression.
@@ -49,52 +63,72 @@ pub mod ideflate {
 
     /// Compress directly into an output buffer
     #[pyfunction]
-    #[pyo3(signature = (input, output, level=None))]
-    pub fn compress_into(py: Python, input: BytesType, mut output: BytesType, level: Option<u32>) -> PyResult<usize> {
-        crate::generic!(py, libcramjam::ideflate::compress[input, output], level).map_err(CompressionError::from_err)
+    #[pyo3(signature = (input, output, level=None, *, align=None))]
+    pub fn compress_into(
+        py: Python,
+        input: BytesType,
+        mut output: BytesType,
+        level: Option<u32>,
+        align: Option<usize>,
+    ) -> PyResult<IntoLength> {
+        let written = crate::generic!(py, libcramjam::ideflate::compress[input, output], level)
+            .map_err(CompressionError::from_err)?;
+        crate::io::pad_output(&mut output, written, align)
     }
 
     /// Decompress directly into an output buffer
     #[pyfunction]
     pub fn decompress_into(py: Python, input: BytesType, mut output: BytesType) -> PyResult<usize> {
-        crate::generic!(py, libcramjam::ideflate::decompress[input, output]).map_err(DecompressionError::from_err)
+        crate::generic!(py, decompress: libcramjam::ideflate::decompress[input, output])
+            .map_err(DecompressionError::from_err)
     }
 
     /// ideflate Compressor object for streaming compression
-    #[pyclass(unsendable)] // TODO: make sendable
+    #[pyclass]
     pub struct Compressor {
-        inner: Option<libcramjam::ideflate::isal::write::GzipEncoder<Cursor<Vec<u8>>>>,
+        inner: Option<SendEncoder<libcramjam::ideflate::isal::write::GzipEncoder<Cursor<Vec<u8>>>>>,
+        returns_output: bool,
     }
 
     #[pymethods]
     impl Compressor {
         /// Initialize a new `Compressor` instance.
+        ///
+        /// `returns_output=True` makes `compress` return the compressed bytes produced by each
+        /// call, rather than the number of bytes consumed, to stream output without `flush()`.
         #[new]
-        #[pyo3(signature = (level=None))]
-        pub fn __init__(level: Option<u32>) -> PyResult<Self> {
+        #[pyo3(signature = (level=None, *, returns_output=false))]
+        pub fn __init__(level: Option<u32>, returns_output: bool) -> PyResult<Self> {
             let level = level.unwrap_or(DEFAULT_COMPRESSION_LEVEL);
             let inner = libcramjam::ideflate::isal::write::GzipEncoder::new(
                 Cursor::new(vec![]),
                 libcramjam::ideflate::isal::CompressionLevel::try_from(level as isize)
                     .map_err(CompressionError::from_err)?,
             );
-            Ok(Self { inner: Some(inner) })
+            Ok(Self {
+                inner: Some(SendEncoder(inner)),
+                returns_output,
+            })
         }
 
         /// Compress input into the current compressor's stream.
-        pub fn compress(&mut self, input: &[u8]) -> PyResult<usize> {
-            crate::io::stream_compress(&mut self.inner, input)
+        pub fn compress(&mut self, py: Python, input: &[u8]) -> PyResult<PyObject> {
+            crate::io::stream_compress_output(py, &mut self.inner, input, self.returns_output, 0, |e| {
+                e.0.get_ref_mut()
+            })
         }
 
         /// Flush and return current compressed stream
-        pub fn flush(&mut self) -> PyResult<RustyBuffer> {
-            crate::io::stream_flush(&mut self.inner, |e| e.get_ref_mut())
+        pub fn flush(&mut self, py: Python) -> PyResult<RustyBuffer> {
+            py.allow_threads(|| crate::io::stream_flush(&mut self.inner, 0, |e| e.0.get_ref_mut()))
         }
 
         /// Consume the current compressor state and return the compressed stream
         /// **NB** The compressor will not be usable after this method is called.
-        pub fn finish(&mut self) -> PyResult<RustyBuffer> {
-            crate::io::stream_finish(&mut self.inner, |inner| inner.finish().map(|c| c.into_inner()))
+        pub fn finish(&mut self, py: Python) -> PyResult<RustyBuffer> {
+            py.allow_threads(|| {
+                crate::io::stream_finish(&mut self.inner, |inner| inner.0.finish().map(|c| c.into_inner()))
+            })
         }
     }
 
@@ -0,0 +1,395 @@
+//! A deflate decoder which stops at, and starts again from, any block boundary given the 32KB
+//! of data decompressed before it, for random access into gzip data through an index of such
+//! access points, as zlib's `zran.c` example does with `inflatePrime` and `inflateSetDictionary`.
+//! Neither flate2 backend gives access to those, nor to the decoder's position to the bit, so it
+//! is only used for that; everything else decompresses through flate2.
+use std::io::{self, BufRead, Error, ErrorKind};
+use std::sync::OnceLock;
+
+/// Furthest back references reach, so the data kept to start decompressing at an access point
+pub(crate) const WINDOW_SIZE: usize = 32 * 1024;
+
+const MAX_BITS: usize = 15;
+
+/// Bits of a code looked up at once, longer codes are decoded a bit at a time
+const FAST_BITS: u32 = 9;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145,
+    8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13,
+];
+
+/// Order of the code length code's lengths in a dynamic block header
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+fn invalid(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("Invalid deflate data: {}", msg))
+}
+
+/// Bits of the input, least significant first, keeping count of the position in it to the bit
+pub(crate) struct Bits {
+    buf: u64,
+    len: u32,
+    /// Bytes taken from the input
+    pos: u64,
+}
+
+impl Bits {
+    /// Start at `bit` of the input, `input` being at its byte `bit / 8`
+    pub(crate) fn at<R: BufRead>(input: &mut R, bit: u64) -> io::Result<Self> {
+        let mut bits = Self {
+            buf: 0,
+            len: 0,
+            pos: bit / 8,
+        };
+        bits.take(input, (bit % 8) as u32)?;
+        Ok(bits)
+    }
+
+    /// Position in the input, in bits
+    pub(crate) fn position(&self) -> u64 {
+        self.pos * 8 - self.len as u64
+    }
+
+    /// Bytes taken from the input, where to have it continue from
+    pub(crate) fn input_position(&self) -> u64 {
+        self.pos
+    }
+
+    /// Whether the input is done, with no bits left
+    pub(crate) fn is_done<R: BufRead>(&self, input: &mut R) -> io::Result<bool> {
+        Ok(self.len == 0 && input.fill_buf()?.is_empty())
+    }
+
+    fn refill<R: BufRead>(&mut self, input: &mut R) -> io::Result<()> {
+        let available = input.fill_buf()?;
+        let n = available.len().min(((64 - self.len) / 8) as usize);
+        for byte in &available[..n] {
+            self.buf |= (*byte as u64) << self.len;
+            self.len += 8;
+        }
+        input.consume(n);
+        self.pos += n as u64;
+        Ok(())
+    }
+
+    fn need<R: BufRead>(&mut self, input: &mut R, n: u32) -> io::Result<()> {
+        while self.len < n {
+            let len = self.len;
+            self.refill(input)?;
+            if self.len == len {
+                return Err(Error::new(ErrorKind::UnexpectedEof, "Compressed data is truncated"));
+            }
+        }
+        Ok(())
+    }
+
+    fn peek(&self, n: u32) -> u32 {
+        (self.buf & ((1 << n) - 1)) as u32
+    }
+
+    fn skip(&mut self, n: u32) {
+        self.buf >>= n;
+        self.len -= n;
+    }
+
+    fn take<R: BufRead>(&mut self, input: &mut R, n: u32) -> io::Result<u32> {
+        self.need(input, n)?;
+        let value = self.peek(n);
+        self.skip(n);
+        Ok(value)
+    }
+
+    /// The next byte, once aligned to one
+    pub(crate) fn byte<R: BufRead>(&mut self, input: &mut R) -> io::Result<u8> {
+        self.take(input, 8).map(|byte| byte as u8)
+    }
+
+    /// Skip to the next byte boundary
+    pub(crate) fn align(&mut self) {
+        self.skip(self.len % 8);
+    }
+}
+
+/// Canonical Huffman code, decoded as `puff.c` does, with a table for codes of up to `FAST_BITS`
+#[derive(Clone)]
+struct Huffman {
+    counts: [u16; MAX_BITS + 1],
+    /// Symbols in order of their codes
+    symbols: Vec<u16>,
+    /// Symbol and length of the code starting each `FAST_BITS` of input, as `symbol << 4 | length`,
+    /// `0` for longer codes
+    fast: Vec<u16>,
+}
+
+impl Huffman {
+    /// Code of the symbols' `lengths`, incomplete only if `incomplete` is allowed
+    fn new(lengths: &[u8], incomplete: bool) -> io::Result<Self> {
+        let mut counts = [0u16; MAX_BITS + 1];
+        for length in lengths {
+            counts[*length as usize] += 1;
+        }
+        let mut left = 1i32;
+        for count in &counts[1..] {
+            left = (left << 1) - *count as i32;
+            if left < 0 {
+                return Err(invalid("over-subscribed Huffman code"));
+            }
+        }
+        // as zlib, only a single code of one bit may be incomplete, or none at all
+        let used = lengths.len() - counts[0] as usize;
+        if left > 0 && !(incomplete || used <= 1) {
+            return Err(invalid("incomplete Huffman code"));
+        }
+
+        let mut offsets = [0usize; MAX_BITS + 2];
+        for length in 1..=MAX_BITS {
+            offsets[length + 1] = offsets[length] + counts[length] as usize;
+        }
+        let mut symbols = vec![0; used];
+        for (symbol, length) in lengths.iter().enumerate() {
+            if *length > 0 {
+                symbols[offsets[*length as usize]] = symbol as u16;
+                offsets[*length as usize] += 1;
+            }
+        }
+
+        let mut fast = vec![0; 1 << FAST_BITS];
+        let (mut code, mut index) = (0u32, 0);
+        for length in 1..=FAST_BITS {
+            for _ in 0..counts[length as usize] {
+                let reversed = code.reverse_bits() >> (32 - length);
+                for entry in fast.iter_mut().skip(reversed as usize).step_by(1 << length) {
+                    *entry = symbols[index] << 4 | length as u16;
+                }
+                code += 1;
+                index += 1;
+            }
+            code <<= 1;
+        }
+        Ok(Self { counts, symbols, fast })
+    }
+
+    fn decode<R: BufRead>(&self, bits: &mut Bits, input: &mut R) -> io::Result<u16> {
+        if bits.len < FAST_BITS {
+            bits.refill(input)?;
+        }
+        if bits.len >= FAST_BITS {
+            let entry = self.fast[bits.peek(FAST_BITS) as usize];
+            if entry != 0 {
+                bits.skip((entry & 0xf) as u32);
+                return Ok(entry >> 4);
+            }
+        }
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for count in &self.counts[1..] {
+            code |= bits.take(input, 1)? as i32;
+            let count = *count as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(invalid("invalid Huffman code"))
+    }
+}
+
+/// Literal/length and distance codes of the fixed Huffman blocks
+fn fixed_codes() -> &'static (Huffman, Huffman) {
+    static FIXED: OnceLock<(Huffman, Huffman)> = OnceLock::new();
+    FIXED.get_or_init(|| {
+        let mut lengths = [8u8; 288];
+        lengths[144..256].fill(9);
+        lengths[256..280].fill(7);
+        let literal = Huffman::new(&lengths, true).expect("fixed code is valid");
+        let distance = Huffman::new(&[5; 30], true).expect("fixed code is valid");
+        (literal, distance)
+    })
+}
+
+/// Literal/length and distance codes of a dynamic block, from its header
+fn dynamic_codes<R: BufRead>(bits: &mut Bits, input: &mut R) -> io::Result<(Huffman, Huffman)> {
+    let n_literal = bits.take(input, 5)? as usize + 257;
+    let n_distance = bits.take(input, 5)? as usize + 1;
+    let n_code_length = bits.take(input, 4)? as usize + 4;
+    if n_literal > 286 || n_distance > 30 {
+        return Err(invalid("too many length or distance codes"));
+    }
+    let mut lengths = [0u8; 19];
+    for index in &CODE_LENGTH_ORDER[..n_code_length] {
+        lengths[*index] = bits.take(input, 3)? as u8;
+    }
+    let code_length = Huffman::new(&lengths, false)?;
+
+    let mut lengths = Vec::with_capacity(n_literal + n_distance);
+    while lengths.len() < n_literal + n_distance {
+        let (length, repeat) = match code_length.decode(bits, input)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => match lengths.last() {
+                Some(previous) => (*previous, 3 + bits.take(input, 2)?),
+                None => return Err(invalid("repeated length with no first length")),
+            },
+            17 => (0, 3 + bits.take(input, 3)?),
+            _ => (0, 11 + bits.take(input, 7)?),
+        };
+        if lengths.len() + repeat as usize > n_literal + n_distance {
+            return Err(invalid("too many code lengths"));
+        }
+        lengths.extend(std::iter::repeat_n(length, repeat as usize));
+    }
+    if lengths[256] == 0 {
+        return Err(invalid("no end of block code"));
+    }
+    let literal = Huffman::new(&lengths[..n_literal], false)?;
+    let distance = Huffman::new(&lengths[n_literal..], false)?;
+    Ok((literal, distance))
+}
+
+enum Block {
+    /// At the start of a block's header
+    Start,
+    Stored(u16),
+    Codes(Box<(Huffman, Huffman)>),
+    End,
+}
+
+/// Where `Inflate::run` stopped
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Stop {
+    /// At the start of a block, where decompression may start again
+    Block,
+    /// The output reached its limit
+    Limit,
+    /// The end of the final block, with `Bits` aligned to the byte after it
+    End,
+}
+
+/// Raw deflate decoder, see the module docs
+pub(crate) struct Inflate {
+    block: Block,
+    last: bool,
+}
+
+impl Inflate {
+    /// Decoder at the start of a block
+    pub(crate) fn new() -> Self {
+        Self {
+            block: Block::Start,
+            last: false,
+        }
+    }
+
+    /// Decompress from `bits` of `input` onto the end of `output`, whose data back references reach
+    /// into, until it's at least `limit` bytes long, the end of a block, or of the final block.
+    pub(crate) fn run<R: BufRead>(
+        &mut self,
+        bits: &mut Bits,
+        input: &mut R,
+        output: &mut Vec<u8>,
+        limit: usize,
+    ) -> io::Result<Stop> {
+        loop {
+            if output.len() >= limit && !matches!(self.block, Block::Start | Block::End) {
+                return Ok(Stop::Limit);
+            }
+            match &mut self.block {
+                Block::End => return Ok(Stop::End),
+                Block::Start => {
+                    self.last = bits.take(input, 1)? == 1;
+                    self.block = match bits.take(input, 2)? {
+                        0 => {
+                            bits.align();
+                            let len = bits.take(input, 16)?;
+                            if bits.take(input, 16)? != !len & 0xffff {
+                                return Err(invalid("stored block length doesn't match its complement"));
+                            }
+                            Block::Stored(len as u16)
+                        }
+                        1 => Block::Codes(Box::new(fixed_codes().clone())),
+                        2 => Block::Codes(Box::new(dynamic_codes(bits, input)?)),
+                        _ => return Err(invalid("invalid block type")),
+                    };
+                }
+                Block::Stored(remaining) => {
+                    while *remaining > 0 && output.len() < limit {
+                        output.push(bits.byte(input)?);
+                        *remaining -= 1;
+                    }
+                    if *remaining == 0 {
+                        return Ok(self.end_block(bits));
+                    }
+                }
+                Block::Codes(codes) => {
+                    if decode_codes(codes, bits, input, output, limit)? {
+                        return Ok(self.end_block(bits));
+                    }
+                }
+            }
+        }
+    }
+
+    fn end_block(&mut self, bits: &mut Bits) -> Stop {
+        if self.last {
+            bits.align();
+            self.block = Block::End;
+            Stop::End
+        } else {
+            self.block = Block::Start;
+            Stop::Block
+        }
+    }
+}
+
+/// Decode symbols of a Huffman block onto `output` until it's `limit` bytes long, `true` if the
+/// block ended first
+fn decode_codes<R: BufRead>(
+    codes: &(Huffman, Huffman),
+    bits: &mut Bits,
+    input: &mut R,
+    output: &mut Vec<u8>,
+    limit: usize,
+) -> io::Result<bool> {
+    let (literal, distance) = codes;
+    while output.len() < limit {
+        let symbol = literal.decode(bits, input)?;
+        if symbol < 256 {
+            output.push(symbol as u8);
+            continue;
+        } else if symbol == 256 {
+            return Ok(true);
+        }
+        let symbol = symbol as usize - 257;
+        if symbol >= LENGTH_BASE.len() {
+            return Err(invalid("invalid length code"));
+        }
+        let length = LENGTH_BASE[symbol] as usize + bits.take(input, LENGTH_EXTRA[symbol] as u32)? as usize;
+        let symbol = distance.decode(bits, input)? as usize;
+        if symbol >= DISTANCE_BASE.len() {
+            return Err(invalid("invalid distance code"));
+        }
+        let distance = DISTANCE_BASE[symbol] as usize + bits.take(input, DISTANCE_EXTRA[symbol] as u32)? as usize;
+        if distance > output.len() {
+            return Err(invalid("distance too far back"));
+        }
+        let start = output.len() - distance;
+        if distance >= length {
+            output.extend_from_within(start..start + length);
+        } else {
+            for i in start..start + length {
+                output.push(output[i]);
+            }
+        }
+    }
+    Ok(false)
+}
@@ -0,0 +1,485 @@
+//! Describe a compressed payload from its headers.
+//!
+//! ### Python Example
+//! ```python
+//! >>> cramjam.info(cramjam.zstd.compress(b"some bytes here"))
+//! {'codec': 'zstd', 'level': None, 'content_size': None, 'checksum': None, 'frames': 1, 'dict_id': None}
+//! ```
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::detect;
+use crate::exceptions::DecompressionError;
+use crate::BytesType;
+
+/// What could be learned about a payload from its headers
+#[derive(Default)]
+struct Report {
+    codec: Option<&'static str>,
+    level: Option<PyObject>,
+    content_size: Option<u64>,
+    checksum: Option<&'static str>,
+    frames: Option<usize>,
+    extra: Vec<(&'static str, PyObject)>,
+}
+
+/// Bounds checked cursor over the payload, where running out of bytes is reported
+/// as a truncated payload of the given codec.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    codec: &'static str,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8], codec: &'static str) -> Self {
+        Self { data, pos: 0, codec }
+    }
+    fn take(&mut self, n: usize) -> PyResult<&'a [u8]> {
+        match self.data.get(self.pos..self.pos.saturating_add(n)) {
+            Some(bytes) => {
+                self.pos += n;
+                Ok(bytes)
+            }
+            None => Err(DecompressionError::new_err(format!("Truncated {} payload", self.codec))),
+        }
+    }
+    fn u8(&mut self) -> PyResult<u8> {
+        Ok(self.take(1)?[0])
+    }
+    fn le_uint(&mut self, n: usize) -> PyResult<u64> {
+        Ok(self.take(n)?.iter().rev().fold(0, |acc, b| acc << 8 | u64::from(*b)))
+    }
+    fn remaining(&self) -> &'a [u8] {
+        &self.data[self.pos..]
+    }
+    fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+}
+
+/// Describe the compressed `data`, returning a dict with the keys:
+///
+/// - `codec`: name of the cramjam module which can decompress it, `None` if not recognized
+/// - `level`: compression level, if recorded by the format
+/// - `content_size`: decompressed size, if recorded by the format
+/// - `checksum`: checksum protecting the content, if any
+/// - `frames`: number of frames, members or streams in the payload
+///
+/// And any codec specific header fields, ie. `mtime` and `filename` for gzip. Only formats
+/// with a recognizable signature are described; gzip members are
+/// counted by decoding them, all other formats are described from headers alone.
+/// A `cramjam.File` is described from its current position, which is left where it was.
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> cramjam.info(compressed_bytes)
+/// ```
+#[pyfunction]
+pub fn info<'py>(py: Python<'py>, data: BytesType) -> PyResult<Bound<'py, PyDict>> {
+    let bytes = &*data.peek_bytes(usize::MAX)?;
+    let report = match detect::detect(bytes) {
+        Some("gzip") => gzip_info(py, bytes)?,
+        Some("zstd") => zstd_info(py, bytes)?,
+        Some("lz4") => lz4_info(py, bytes)?,
+        Some("xz") if bytes.starts_with(detect::XZ_MAGIC) => xz_info(py, bytes)?,
+        Some("xz") => lzma_alone_info(py, bytes)?,
+        Some("bzip2") => bzip2_info(py, bytes)?,
+        Some("snappy") => snappy_info(py, bytes)?,
+        Some("zlib") => zlib_info(py, bytes)?,
+        Some("ndarray") => ndarray_info(py, bytes)?,
+        _ => Report::default(),
+    };
+
+    let dict = PyDict::new_bound(py);
+    dict.set_item("codec", report.codec)?;
+    dict.set_item("level", report.level)?;
+    dict.set_item("content_size", report.content_size)?;
+    dict.set_item("checksum", report.checksum)?;
+    dict.set_item("frames", report.frames)?;
+    for (key, value) in report.extra {
+        dict.set_item(key, value)?;
+    }
+    Ok(dict)
+}
+
+fn gzip_info(py: Python, data: &[u8]) -> PyResult<Report> {
+    const FEXTRA: u8 = 0x04;
+    const FNAME: u8 = 0x08;
+
+    let mut reader = Reader::new(data, "gzip");
+    reader.take(3)?;
+    let flags = reader.u8()?;
+    let mtime = reader.le_uint(4)?;
+    let level = match reader.u8()? {
+        2 => Some("best"),
+        4 => Some("fastest"),
+        _ => None,
+    };
+    let os = reader.u8()?;
+    if flags & FEXTRA != 0 {
+        let xlen = reader.le_uint(2)?;
+        reader.take(xlen as usize)?;
+    }
+    let filename = match flags & FNAME {
+        0 => None,
+        _ => {
+            let name = reader.remaining();
+            let end = name.iter().position(|b| *b == 0).unwrap_or(name.len());
+            Some(String::from_utf8_lossy(&name[..end]).to_string())
+        }
+    };
+
+    #[allow(unused_mut)]
+    let mut report = Report {
+        codec: Some("gzip"),
+        level: level.map(|l| l.into_py(py)),
+        checksum: Some("crc32"),
+        extra: vec![
+            ("mtime", mtime.into_py(py)),
+            ("os", os.into_py(py)),
+            ("filename", filename.into_py(py)),
+        ],
+        ..Default::default()
+    };
+
+    // there's no index of members, so they're found by decoding each in turn
+    #[cfg(any(feature = "gzip", feature = "gzip-static", feature = "gzip-shared"))]
+    {
+        let (frames, content_size) = py
+            .allow_threads(|| {
+                let mut remaining = data;
+                let mut frames = 0;
+                let mut content_size = 0;
+                while remaining.starts_with(detect::GZIP_MAGIC) {
+                    let mut decoder = libcramjam::gzip::flate2::bufread::GzDecoder::new(remaining);
+                    content_size += std::io::copy(&mut decoder, &mut std::io::sink())?;
+                    remaining = decoder.into_inner();
+                    frames += 1;
+                }
+                Ok::<_, std::io::Error>((frames, content_size))
+            })
+            .map_err(DecompressionError::from_err)?;
+        report.frames = Some(frames);
+        report.content_size = Some(content_size);
+    }
+    Ok(report)
+}
+
+fn zstd_info(py: Python, data: &[u8]) -> PyResult<Report> {
+    let mut reader = Reader::new(data, "zstd");
+    let mut frames = 0;
+    let mut content_size = Some(0);
+    let mut checksum = false;
+    let mut dict_id = None;
+
+    while !reader.is_empty() {
+        if detect::is_skippable_frame(reader.remaining()) {
+            reader.take(4)?;
+            let size = reader.le_uint(4)?;
+            reader.take(size as usize)?;
+            continue;
+        }
+        if reader.take(4)? != detect::ZSTD_MAGIC {
+            return Err(DecompressionError::new_err("Unrecognized data following zstd frame"));
+        }
+        let descriptor = reader.u8()?;
+        let single_segment = descriptor & 0x20 != 0;
+        if !single_segment {
+            reader.u8()?; // window descriptor
+        }
+        let dict_id_len = [0, 1, 2, 4][(descriptor & 0x03) as usize];
+        let id = reader.le_uint(dict_id_len)?;
+        if frames == 0 && id != 0 {
+            dict_id = Some(id);
+        }
+        let frame_size = match (descriptor >> 6, single_segment) {
+            (0, false) => None,
+            (0, true) => Some(reader.le_uint(1)?),
+            (1, _) => Some(reader.le_uint(2)? + 256),
+            (2, _) => Some(reader.le_uint(4)?),
+            _ => Some(reader.le_uint(8)?),
+        };
+        content_size = content_size.zip(frame_size).map(|(total, size)| total + size);
+
+        // walk blocks to find the end of the frame
+        loop {
+            let header = reader.le_uint(3)?;
+            let size = match (header >> 1) & 0x03 {
+                1 => 1, // RLE block, a single byte repeated
+                _ => header >> 3,
+            };
+            reader.take(size as usize)?;
+            if header & 0x01 == 1 {
+                break;
+            }
+        }
+        if descriptor & 0x04 != 0 {
+            reader.take(4)?;
+            checksum = true;
+        }
+        frames += 1;
+    }
+    Ok(Report {
+        codec: Some("zstd"),
+        content_size,
+        checksum: checksum.then_some("xxh64"),
+        frames: Some(frames),
+        extra: vec![("dict_id", dict_id.into_py(py))],
+        ..Default::default()
+    })
+}
+
+fn lz4_info(py: Python, data: &[u8]) -> PyResult<Report> {
+    let mut reader = Reader::new(data, "lz4");
+    let mut frames = 0;
+    let mut content_size = Some(0);
+    let mut content_checksum = false;
+    let mut block_checksum = false;
+    let mut block_size = None;
+    let mut block_linked = None;
+
+    while !reader.is_empty() {
+        if detect::is_skippable_frame(reader.remaining()) {
+            reader.take(4)?;
+            let size = reader.le_uint(4)?;
+            reader.take(size as usize)?;
+            continue;
+        }
+        if reader.take(4)? != detect::LZ4_MAGIC {
+            return Err(DecompressionError::new_err("Unrecognized data following lz4 frame"));
+        }
+        let flags = reader.u8()?;
+        let block_descriptor = reader.u8()?;
+        let has_block_checksum = flags & 0x10 != 0;
+        let has_content_checksum = flags & 0x04 != 0;
+        let frame_size = match flags & 0x08 {
+            0 => None,
+            _ => Some(reader.le_uint(8)?),
+        };
+        if flags & 0x01 != 0 {
+            reader.take(4)?; // dictionary ID
+        }
+        reader.u8()?; // header checksum
+        content_size = content_size.zip(frame_size).map(|(total, size)| total + size);
+        if frames == 0 {
+            block_linked = Some(flags & 0x20 == 0);
+            block_size = match (block_descriptor >> 4) & 0x07 {
+                4 => Some(64 * 1024),
+                5 => Some(256 * 1024),
+                6 => Some(1024 * 1024),
+                7 => Some(4 * 1024 * 1024),
+                _ => None,
+            };
+        }
+
+        loop {
+            let size = reader.le_uint(4)? & 0x7fff_ffff;
+            if size == 0 {
+                break;
+            }
+            reader.take(size as usize)?;
+            if has_block_checksum {
+                reader.take(4)?;
+            }
+        }
+        if has_content_checksum {
+            reader.take(4)?;
+        }
+        content_checksum |= has_content_checksum;
+        block_checksum |= has_block_checksum;
+        frames += 1;
+    }
+    let checksum = match (content_checksum, block_checksum) {
+        (false, false) => None,
+        _ => Some("xxh32"),
+    };
+    Ok(Report {
+        codec: Some("lz4"),
+        content_size,
+        checksum,
+        frames: Some(frames),
+        extra: vec![
+            ("block_size", block_size.into_py(py)),
+            ("block_linked", block_linked.into_py(py)),
+            ("content_checksum", content_checksum.into_py(py)),
+            ("block_checksum", block_checksum.into_py(py)),
+        ],
+        ..Default::default()
+    })
+}
+
+/// Variable length integer used in the xz index
+fn xz_vli(reader: &mut Reader) -> PyResult<u64> {
+    let mut value = 0;
+    for i in 0..9 {
+        let byte = reader.u8()?;
+        value |= u64::from(byte & 0x7f) << (i * 7);
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(DecompressionError::new_err("Invalid xz variable length integer"))
+}
+
+fn xz_check_name(flags: u8) -> Option<&'static str> {
+    match flags & 0x0f {
+        0x01 => Some("crc32"),
+        0x04 => Some("crc64"),
+        0x0a => Some("sha256"),
+        _ => None,
+    }
+}
+
+fn xz_info(_py: Python, data: &[u8]) -> PyResult<Report> {
+    const HEADER_SIZE: usize = 12;
+    let truncated = || DecompressionError::new_err("Truncated xz payload");
+
+    // streams are walked from the end, as the index and footer give the size of
+    // everything before them, while blocks don't necessarily record their own size.
+    let mut end = data.len();
+    let mut frames = 0;
+    let mut content_size = 0;
+    let mut checksum = None;
+    while end > 0 {
+        // stream padding
+        while end >= 4 && data[end - 4..end] == [0, 0, 0, 0] {
+            end -= 4;
+        }
+        if end < HEADER_SIZE * 2 {
+            return Err(truncated());
+        }
+        let footer = &data[end - HEADER_SIZE..end];
+        if &footer[10..] != b"YZ" {
+            return Err(DecompressionError::new_err("Invalid xz stream footer"));
+        }
+        let backward_size = (u32::from_le_bytes([footer[4], footer[5], footer[6], footer[7]]) as usize + 1) * 4;
+        let index_start = (end - HEADER_SIZE).checked_sub(backward_size).ok_or_else(truncated)?;
+
+        let mut index = Reader::new(&data[index_start..end - HEADER_SIZE], "xz");
+        if index.u8()? != 0 {
+            return Err(DecompressionError::new_err("Invalid xz index"));
+        }
+        let mut blocks_size = 0;
+        for _ in 0..xz_vli(&mut index)? {
+            let unpadded = xz_vli(&mut index)? as usize;
+            blocks_size += unpadded.div_ceil(4) * 4;
+            content_size += xz_vli(&mut index)?;
+        }
+
+        let start = index_start
+            .checked_sub(blocks_size + HEADER_SIZE)
+            .ok_or_else(truncated)?;
+        if !data[start..].starts_with(detect::XZ_MAGIC) {
+            return Err(DecompressionError::new_err("Invalid xz stream header"));
+        }
+        checksum = xz_check_name(data[start + 7]);
+        frames += 1;
+        end = start;
+    }
+    Ok(Report {
+        codec: Some("xz"),
+        content_size: Some(content_size),
+        checksum,
+        frames: Some(frames),
+        ..Default::default()
+    })
+}
+
+fn lzma_alone_info(py: Python, data: &[u8]) -> PyResult<Report> {
+    let mut reader = Reader::new(data, "lzma");
+    reader.u8()?;
+    let dict_size = reader.le_uint(4)?;
+    let content_size = match reader.le_uint(8)? {
+        u64::MAX => None,
+        size => Some(size),
+    };
+    Ok(Report {
+        codec: Some("xz"),
+        content_size,
+        frames: Some(1),
+        extra: vec![("format", "alone".into_py(py)), ("dict_size", dict_size.into_py(py))],
+        ..Default::default()
+    })
+}
+
+fn bzip2_info(py: Python, data: &[u8]) -> PyResult<Report> {
+    // block size in 100k units, which is what the compression level sets
+    let level = u32::from(data[3] - b'0');
+    Ok(Report {
+        codec: Some("bzip2"),
+        level: Some(level.into_py(py)),
+        checksum: Some("crc32"),
+        ..Default::default()
+    })
+}
+
+fn snappy_info(_py: Python, data: &[u8]) -> PyResult<Report> {
+    let mut reader = Reader::new(data, "snappy");
+    let mut content_size = 0;
+    let mut frames = 0;
+    while !reader.is_empty() {
+        let chunk_type = reader.u8()?;
+        let len = reader.le_uint(3)? as usize;
+        let chunk = reader.take(len)?;
+        match chunk_type {
+            0xff => frames += 1,
+            // compressed data, the raw snappy preamble is the uncompressed length
+            0x00 => {
+                let mut preamble = Reader::new(chunk.get(4..).unwrap_or_default(), "snappy");
+                let mut size = 0;
+                for i in 0..5 {
+                    let byte = preamble.u8()?;
+                    size |= u64::from(byte & 0x7f) << (i * 7);
+                    if byte & 0x80 == 0 {
+                        break;
+                    }
+                }
+                content_size += size;
+            }
+            0x01 => content_size += len.saturating_sub(4) as u64,
+            _ => (),
+        }
+    }
+    Ok(Report {
+        codec: Some("snappy"),
+        content_size: Some(content_size),
+        checksum: Some("crc32c"),
+        frames: Some(frames),
+        ..Default::default()
+    })
+}
+
+fn zlib_info(py: Python, data: &[u8]) -> PyResult<Report> {
+    let level = match data[1] >> 6 {
+        0 => "fastest",
+        1 => "fast",
+        2 => "default",
+        _ => "best",
+    };
+    Ok(Report {
+        codec: Some("zlib"),
+        level: Some(level.into_py(py)),
+        checksum: Some("adler32"),
+        frames: Some(1),
+        extra: vec![
+            ("window_size", (1u32 << ((data[0] >> 4) + 8)).into_py(py)),
+            ("preset_dictionary", (data[1] & 0x20 != 0).into_py(py)),
+        ],
+        ..Default::default()
+    })
+}
+
+fn ndarray_info(py: Python, mut data: &[u8]) -> PyResult<Report> {
+    let header = crate::ndarray::Header::decode(&mut data)?;
+    Ok(Report {
+        codec: Some("ndarray"),
+        extra: vec![
+            ("array_codec", header.codec.as_str().into_py(py)),
+            ("dtype", header.descr(py)?.unbind()),
+            ("shape", pyo3::types::PyTuple::new_bound(py, header.shape).into_py(py)),
+        ],
+        ..Default::default()
+    })
+}
@@ -8,14 +8,72 @@ use std::io::{copy, Cursor, Read, Seek, SeekFrom, Write};
 use std::mem;
 use std::os::raw::c_int;
 
-use crate::exceptions::CompressionError;
+use crate::exceptions::{CompressionError, DecompressionError};
 use crate::BytesType;
 use pyo3::exceptions::{self, PyBufferError};
 use pyo3::ffi;
 use pyo3::prelude::*;
-use pyo3::types::PyBytes;
+use pyo3::types::{PyBytes, PyTuple};
 use std::path::PathBuf;
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::thread::JoinHandle;
 
+pyo3::import_exception!(io, UnsupportedOperation);
+
+/// A filesystem path given from Python as `str`, `bytes` or any `os.PathLike`.
+///
+/// Conversion goes through `os.fsdecode`, so non-UTF8 filenames on Unix round trip
+/// unchanged, and on Windows absolute paths are given the extended-length prefix so
+/// they're not limited to `MAX_PATH`.
+#[derive(Debug, Clone)]
+pub struct PathLike(pub(crate) PathBuf);
+
+impl<'py> FromPyObject<'py> for PathLike {
+    fn extract_bound(obj: &Bound<'py, PyAny>) -> PyResult<Self> {
+        let fsdecode = obj.py().import_bound("os")?.getattr("fsdecode")?;
+        let path = fsdecode.call1((obj,))?.extract::<PathBuf>()?;
+        Ok(Self(extended_length_path(path)))
+    }
+}
+
+#[cfg(windows)]
+fn extended_length_path(path: PathBuf) -> PathBuf {
+    use std::path::{Component, Prefix};
+    // already verbatim (\\?\) or a device path, leave it be
+    if let Some(Component::Prefix(prefix)) = path.components().next() {
+        match prefix.kind() {
+            Prefix::Verbatim(_) | Prefix::VerbatimUNC(..) | Prefix::VerbatimDisk(_) | Prefix::DeviceNS(_) => {
+                return path
+            }
+            _ => (),
+        }
+    }
+    match std::path::absolute(&path) {
+        Ok(absolute) => match absolute.components().next() {
+            Some(Component::Prefix(prefix)) if matches!(prefix.kind(), Prefix::UNC(..)) => {
+                let mut verbatim = std::ffi::OsString::from(r"\\?\UNC\");
+                verbatim.push(&absolute.as_os_str().to_string_lossy()[2..]);
+                PathBuf::from(verbatim)
+            }
+            Some(Component::Prefix(_)) => {
+                let mut verbatim = std::ffi::OsString::from(r"\\?\");
+                verbatim.push(absolute.as_os_str());
+                PathBuf::from(verbatim)
+            }
+            _ => path,
+        },
+        Err(_) => path,
+    }
+}
+
+#[cfg(not(windows))]
+fn extended_length_path(path: PathBuf) -> PathBuf {
+    path
+}
+
+/// The bytes of data in memory. A `BytesType` of a `File` or `SpooledBuffer` is read with
+/// `BytesType::read_bytes`, `peek_bytes` or `with_stream` instead, which don't panic when it's
+/// on disk.
 pub(crate) trait AsBytes {
     fn as_bytes(&self) -> &[u8];
     fn as_bytes_mut(&mut self) -> PyResult<&mut [u8]>;
@@ -36,10 +94,159 @@ pub(crate) trait AsBytes {
 /// Presently, the file's handle is managed by Rust's lifetime rules, in that
 /// once it's garbage collected from Python's side, it will be closed.
 ///
+/// When `readahead` is given, sequential reads are served by a background thread
+/// which reads the next `readahead` bytes while the current chunk is consumed;
+/// useful for decompressing cold files on network filesystems.
+///
+/// With `buffering`, writes are gathered in memory until that many bytes are pending, rather
+/// than each being its own system call; they're written out before any read, seek, `flush` or
+/// `close`, and when the File is garbage collected. `exclusive=True` creates the file, failing
+/// if it already exists, like Python's `"x"` mode.
+///
+/// It can also be used as a context manager, which closes the file on exit. With
+/// `delete_on_error=True` the file is removed if an exception escapes the block,
+/// so partially written outputs aren't left behind.
+/// ```python
+/// with File("/tmp/out.snappy", delete_on_error=True) as out:
+///     cramjam.snappy.compress_into(data, out)
+/// ```
+///
 #[pyclass(name = "File")]
 pub struct RustyFile {
     pub(crate) path: PathBuf,
-    pub(crate) inner: File,
+    pub(crate) inner: Option<File>,
+    pub(crate) readahead: Option<usize>,
+    pub(crate) prefetch: Option<ReadAhead>,
+    pub(crate) delete_on_error: bool,
+    /// Equivalent of the options opened with as a Python file mode, ie. `"rb+"`
+    pub(crate) mode: &'static str,
+    /// Bytes of writes to gather in `pending` before writing them out, `0` if unbuffered
+    pub(crate) buffering: usize,
+    pub(crate) pending: Vec<u8>,
+}
+
+/// Background reader for [`RustyFile`], double buffering chunks read from a
+/// cloned handle of the file while the previous chunk is consumed.
+pub(crate) struct ReadAhead {
+    start: u64,
+    consumed: u64,
+    current: Cursor<Vec<u8>>,
+    rx: Option<Receiver<std::io::Result<Vec<u8>>>>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl ReadAhead {
+    fn new(mut file: &File, chunk_size: usize) -> std::io::Result<Self> {
+        let start = file.stream_position()?;
+        let mut handle = file.try_clone()?;
+        let (tx, rx) = sync_channel(1);
+        let worker = std::thread::spawn(move || loop {
+            let mut chunk = vec![0; chunk_size];
+            let mut n = 0;
+            while n < chunk_size {
+                match handle.read(&mut chunk[n..]) {
+                    Ok(0) => break,
+                    Ok(nbytes) => n += nbytes,
+                    Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                    Err(e) => {
+                        let _ = tx.send(Err(e));
+                        return;
+                    }
+                }
+            }
+            chunk.truncate(n);
+            if n == 0 || tx.send(Ok(chunk)).is_err() {
+                return;
+            }
+        });
+        Ok(Self {
+            start,
+            consumed: 0,
+            current: Cursor::new(vec![]),
+            rx: Some(rx),
+            worker: Some(worker),
+        })
+    }
+
+    /// Logical position in the file, ie. what's been handed out to the reader
+    fn position(&self) -> u64 {
+        self.start + self.consumed
+    }
+
+    /// Read from the prefetched chunks, filling `buf` across chunk boundaries as a regular
+    /// file read would; returns `None` once the worker is exhausted and nothing was read.
+    fn read(&mut self, buf: &mut [u8]) -> Option<std::io::Result<usize>> {
+        let mut n = 0;
+        while n < buf.len() {
+            if self.current.position() as usize >= self.current.get_ref().len() {
+                match self.rx.as_ref().and_then(|rx| rx.recv().ok()) {
+                    Some(Ok(chunk)) => self.current = Cursor::new(chunk),
+                    Some(Err(e)) => return Some(Err(e)),
+                    None if n == 0 => return None,
+                    None => break,
+                }
+            }
+            let nbytes = match self.current.read(&mut buf[n..]) {
+                Ok(nbytes) => nbytes,
+                Err(e) => return Some(Err(e)),
+            };
+            self.consumed += nbytes as u64;
+            n += nbytes;
+        }
+        Some(Ok(n))
+    }
+}
+
+impl Drop for ReadAhead {
+    fn drop(&mut self) {
+        // dropping the receiver unblocks the worker's pending send
+        self.rx.take();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl RustyFile {
+    /// The underlying file handle, or an error if the file has been closed.
+    pub(crate) fn handle(&self) -> std::io::Result<&File> {
+        self.inner
+            .as_ref()
+            .ok_or_else(|| std::io::Error::other("I/O operation on closed file"))
+    }
+
+    /// Write out any buffered writes, stop any readahead in progress and restore
+    /// the file's position to what has actually been consumed by the reader.
+    pub(crate) fn settle(&mut self) -> std::io::Result<()> {
+        self.write_pending()?;
+        if let Some(prefetch) = self.prefetch.take() {
+            let position = prefetch.position();
+            drop(prefetch);
+            self.handle()?.seek(SeekFrom::Start(position))?;
+        }
+        Ok(())
+    }
+
+    /// Write out writes gathered while `buffering`, keeping the buffer's allocation.
+    pub(crate) fn write_pending(&mut self) -> std::io::Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let mut pending = mem::take(&mut self.pending);
+        let result = self.handle()?.write_all(&pending);
+        pending.clear();
+        self.pending = pending;
+        result
+    }
+
+    /// Copy the remainder of this file into `output` directly between the file handles,
+    /// so the OS can use `copy_file_range`/`sendfile` where available instead of
+    /// copying through userspace buffers.
+    pub(crate) fn copy_to(&mut self, output: &mut RustyFile) -> std::io::Result<u64> {
+        self.settle()?;
+        output.settle()?;
+        copy(&mut self.handle()?, &mut output.handle()?)
+    }
 }
 
 impl AsBytes for RustyFile {
@@ -67,30 +274,71 @@ impl RustyFile {
     /// file.seek(2)
     /// file.read()
     /// b'tes'
+    /// # Prefetch 4MB at a time in a background thread while reading
+    /// file = File("/mnt/nfs/data.zst", readahead=4 * 1024 * 1024)
+    /// # Any str, bytes or os.PathLike path is accepted
+    /// file = File(pathlib.Path("/tmp") / "file.txt")
+    /// # Create a new file, gathering small writes into 64KB ones
+    /// file = File("/tmp/new.snappy", exclusive=True, buffering=64 * 1024)
     /// ```
     #[new]
-    #[pyo3(signature = (path, read = None, write = None, truncate = None, append = None))]
+    #[pyo3(signature = (path, read = None, write = None, truncate = None, append = None, readahead = None, delete_on_error = None, exclusive = None, buffering = None))]
+    #[allow(clippy::too_many_arguments)]
     pub fn __init__(
-        path: &str,
+        path: PathLike,
         read: Option<bool>,
         write: Option<bool>,
         truncate: Option<bool>,
         append: Option<bool>,
+        readahead: Option<usize>,
+        delete_on_error: Option<bool>,
+        exclusive: Option<bool>,
+        buffering: Option<usize>,
     ) -> PyResult<Self> {
+        let PathLike(path) = path;
+        let (read, write) = (read.unwrap_or(true), write.unwrap_or(true));
+        let (truncate, append) = (truncate.unwrap_or(false), append.unwrap_or(false));
+        let exclusive = exclusive.unwrap_or(false);
+        let file = OpenOptions::new()
+            .read(read)
+            .write(write)
+            .truncate(truncate)
+            .create(true) // create if doesn't exist, but open if it does.
+            .create_new(exclusive)
+            .append(append)
+            .open(&path)?;
+        let mode = match (read, write || append, append, truncate) {
+            (true, _, false, _) if exclusive => "xb+",
+            (false, _, false, _) if exclusive => "xb",
+            (true, _, true, _) => "ab+",
+            (false, _, true, _) => "ab",
+            (true, true, _, true) => "wb+",
+            (true, true, _, false) => "rb+",
+            // `open` has no mode for writing without truncating, this is what `io.FileIO` gives
+            (false, true, _, _) => "wb",
+            _ => "rb",
+        };
         Ok(Self {
-            path: PathBuf::from(path),
-            inner: OpenOptions::new()
-                .read(read.unwrap_or_else(|| true))
-                .write(write.unwrap_or_else(|| true))
-                .truncate(truncate.unwrap_or_else(|| false))
-                .create(true) // create if doesn't exist, but open if it does.
-                .append(append.unwrap_or_else(|| false))
-                .open(path)?,
+            path,
+            inner: Some(file),
+            readahead: readahead.filter(|n| *n > 0),
+            prefetch: None,
+            delete_on_error: delete_on_error.unwrap_or(false),
+            mode,
+            buffering: buffering.unwrap_or_default(),
+            pending: vec![],
         })
     }
     /// Write some bytes to the file, where input data can be anything in [`BytesType`](../enum.BytesType.html)
-    pub fn write(&mut self, mut input: BytesType) -> PyResult<usize> {
-        let r = write(&mut input, self)?;
+    pub fn write(&mut self, py: Python, mut input: BytesType) -> PyResult<usize> {
+        let r = match &input {
+            BytesType::RustyFile(file) => {
+                let mut file = file.borrow_mut();
+                let file = &mut *file;
+                py.allow_threads(|| file.copy_to(self))?
+            }
+            _ => write(&mut input, self)?,
+        };
         Ok(r as usize)
     }
     /// Read from the file in its current position, returns `bytes`; optionally specify number of
@@ -132,13 +380,15 @@ impl RustyFile {
     }
     /// Give the current position of the file.
     pub fn tell(&mut self) -> PyResult<usize> {
-        let r = self.inner.seek(SeekFrom::Current(0))?;
+        self.settle()?;
+        let r = self.handle()?.seek(SeekFrom::Current(0))?;
         Ok(r as usize)
     }
     /// Set the length of the file. If less than current length, it will truncate to the size given;
     /// otherwise will be null byte filled to the size.
     pub fn set_len(&mut self, size: usize) -> PyResult<()> {
-        self.inner.set_len(size as u64)?;
+        self.settle()?;
+        self.handle()?.set_len(size as u64)?;
         Ok(())
     }
     /// Truncate the file.
@@ -146,26 +396,109 @@ impl RustyFile {
         self.set_len(0)
     }
     /// Length of the file in bytes
-    pub fn len(&self) -> PyResult<usize> {
+    pub fn len(&mut self) -> PyResult<usize> {
+        self.write_pending()?;
         let meta = self
-            .inner
-            .metadata()
+            .handle()
+            .and_then(|f| f.metadata())
             .map_err(|e| pyo3::exceptions::PyOSError::new_err(e.to_string()))?;
         Ok(meta.len() as usize)
     }
+    /// Whether the file was opened for reading.
+    pub fn readable(&self) -> bool {
+        self.mode.starts_with('r') || self.mode.ends_with('+')
+    }
+    /// Whether the file was opened for writing.
+    pub fn writable(&self) -> bool {
+        self.mode != "rb"
+    }
+    /// Always False, here for compatibility with Python file objects.
+    pub fn isatty(&self) -> bool {
+        false
+    }
+    /// The underlying file descriptor, positioned where this file is at; only available on Unix.
+    pub fn fileno(&mut self) -> PyResult<i64> {
+        #[cfg(unix)]
+        {
+            use std::os::fd::AsRawFd;
+            self.settle()?;
+            Ok(self.handle()?.as_raw_fd() as i64)
+        }
+        #[cfg(not(unix))]
+        {
+            Err(UnsupportedOperation::new_err("fileno is only supported on Unix"))
+        }
+    }
+    /// Flush any data written, including any gathered while `buffering`, to the OS.
+    pub fn flush(&mut self) -> PyResult<()> {
+        Write::flush(self)?;
+        Ok(())
+    }
+    /// Mode equivalent to the options the file was opened with, as `open` would take it, ie. `"rb+"`.
+    ///
+    /// It's approximate for a write only file opened without `truncate`, which `open` has no
+    /// mode for: that's `"wb"`, as `io.FileIO` reports for one, though the file wasn't truncated.
+    #[getter]
+    pub fn mode(&self) -> &'static str {
+        self.mode
+    }
+    /// Path the file was opened with.
+    #[getter]
+    pub fn name(&self) -> PathBuf {
+        self.path.clone()
+    }
+    /// Whether the file has been closed.
+    #[getter]
+    pub fn closed(&self) -> bool {
+        self.inner.is_none()
+    }
+    /// Flush and close the file, further operations on it will raise an error.
+    /// Calling `close` more than once is allowed.
+    pub fn close(&mut self) -> PyResult<()> {
+        if self.inner.is_some() {
+            self.settle()?;
+            self.flush()?;
+        }
+        self.inner = None;
+        Ok(())
+    }
 
-    fn __repr__(&self) -> PyResult<String> {
+    fn __enter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+    #[pyo3(signature = (exc_type, _exc_value=None, _traceback=None))]
+    fn __exit__(
+        &mut self,
+        exc_type: Option<Bound<'_, PyAny>>,
+        _exc_value: Option<Bound<'_, PyAny>>,
+        _traceback: Option<Bound<'_, PyAny>>,
+    ) -> PyResult<bool> {
+        let result = self.close();
+        if exc_type.is_some() && self.delete_on_error {
+            self.inner = None;
+            match std::fs::remove_file(&self.path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                _ => (),
+            }
+        }
+        result.map(|_| false)
+    }
+
+    fn __repr__(&mut self) -> PyResult<String> {
         let path = match self.path.as_path().to_str() {
             Some(path) => path.to_string(),
             None => self.path.to_string_lossy().to_string(),
         };
-        let repr = format!("cramjam.File<path={}, len={:?}>", path, self.len()?);
+        let repr = match self.inner {
+            Some(_) => format!("cramjam.File<path={}, len={:?}>", path, self.len()?),
+            None => format!("cramjam.File<path={}, closed>", path),
+        };
         Ok(repr)
     }
-    fn __bool__(&self) -> PyResult<bool> {
+    fn __bool__(&mut self) -> PyResult<bool> {
         Ok(self.len()? > 0)
     }
-    fn __len__(&self) -> PyResult<usize> {
+    fn __len__(&mut self) -> PyResult<usize> {
         self.len()
     }
 }
@@ -176,6 +509,9 @@ impl RustyFile {
 pub struct PythonBuffer {
     pub(crate) inner: std::pin::Pin<Box<ffi::Py_buffer>>,
     pub(crate) pos: usize,
+    /// C ordered copy of a buffer which isn't C contiguous, ie. a column slice of a 2D array,
+    /// which reads are served from instead
+    pub(crate) gathered: Option<Vec<u8>>,
     #[cfg(PyPy)]
     pub(crate) owner: PyObject,
 }
@@ -203,10 +539,16 @@ impl PythonBuffer {
     }
     /// Get the underlying buffer as a slice of bytes
     pub fn as_slice(&self) -> &[u8] {
+        if let Some(gathered) = self.gathered.as_ref() {
+            return gathered;
+        }
         unsafe { std::slice::from_raw_parts(self.buf_ptr() as *const u8, self.len_bytes()) }
     }
     /// Get the underlying buffer as a mutable slice of bytes
     pub fn as_slice_mut(&mut self) -> PyResult<&mut [u8]> {
+        if self.gathered.is_some() {
+            return Err(PyBufferError::new_err("Output buffer must be C contiguous"));
+        }
         #[cfg(PyPy)]
         {
             Python::with_gil(|py| {
@@ -264,16 +606,22 @@ impl<'a, 'py> TryFrom<&'a Bound<'py, PyAny>> for PythonBuffer {
     type Error = PyErr;
     fn try_from(obj: &'a Bound<'py, PyAny>) -> Result<Self, Self::Error> {
         let mut buf = Box::new(mem::MaybeUninit::uninit());
-        let rc = unsafe { ffi::PyObject_GetBuffer(obj.as_ptr(), buf.as_mut_ptr(), ffi::PyBUF_CONTIG_RO) };
+        let mut rc = unsafe { ffi::PyObject_GetBuffer(obj.as_ptr(), buf.as_mut_ptr(), ffi::PyBUF_CONTIG_RO) };
+        if rc != 0 {
+            // not C contiguous, so ask for it with strides to gather it below
+            drop(PyErr::take(obj.py()));
+            rc = unsafe { ffi::PyObject_GetBuffer(obj.as_ptr(), buf.as_mut_ptr(), ffi::PyBUF_RECORDS_RO) };
+        }
         if rc != 0 {
             return Err(exceptions::PyBufferError::new_err(
-                "Failed to get buffer, is it C contiguous, and shape is not null?",
+                "Failed to get buffer, does it support the buffer protocol?",
             ));
         }
         let buf = Box::new(unsafe { mem::MaybeUninit::<ffi::Py_buffer>::assume_init(*buf) });
-        let buf = Self {
+        let mut buf = Self {
             inner: std::pin::Pin::from(buf),
             pos: 0,
+            gathered: None,
             #[cfg(PyPy)]
             owner: Python::with_gil(|py| obj.to_object(py)),
         };
@@ -281,7 +629,21 @@ impl<'a, 'py> TryFrom<&'a Bound<'py, PyAny>> for PythonBuffer {
         if buf.inner.shape.is_null() {
             Err(exceptions::PyBufferError::new_err("shape is null"))
         } else if !buf.is_c_contiguous() {
-            Err(PyBufferError::new_err("Buffer is not C contiguous"))
+            let mut gathered = vec![0; buf.len_bytes()];
+            let rc = unsafe {
+                ffi::PyBuffer_ToContiguous(
+                    gathered.as_mut_ptr() as *mut std::os::raw::c_void,
+                    &mut *buf.inner as *mut ffi::Py_buffer,
+                    gathered.len() as ffi::Py_ssize_t,
+                    b'C' as std::os::raw::c_char,
+                )
+            };
+            if rc != 0 {
+                return Err(PyErr::take(obj.py())
+                    .unwrap_or_else(|| PyBufferError::new_err("Failed to gather non-contiguous buffer")));
+            }
+            buf.gathered = Some(gathered);
+            Ok(buf)
         } else {
             Ok(buf)
         }
@@ -369,11 +731,12 @@ impl From<Vec<u8>> for RustyBuffer {
 /// ```
 #[pymethods]
 impl RustyBuffer {
-    /// Instantiate the object, optionally with any supported bytes-like object in [BytesType](../enum.BytesType.html)
+    /// Instantiate the object, optionally with any supported bytes-like object in [BytesType](../enum.BytesType.html),
+    /// and `capacity` bytes allocated up front so writing that much won't need to reallocate.
     #[new]
-    #[pyo3(signature = (data=None))]
-    pub fn __init__(mut data: Option<BytesType<'_>>) -> PyResult<Self> {
-        let mut buf = vec![];
+    #[pyo3(signature = (data=None, capacity=None))]
+    pub fn __init__(mut data: Option<BytesType<'_>>, capacity: Option<usize>) -> PyResult<Self> {
+        let mut buf = Vec::with_capacity(capacity.unwrap_or_default());
         if let Some(bytes) = data.as_mut() {
             bytes.read_to_end(&mut buf)?;
         }
@@ -382,6 +745,20 @@ impl RustyBuffer {
         })
     }
 
+    /// A buffer of `n` null bytes, as an exact size output for `*_into` functions; allocated
+    /// zeroed in one step, rather than `Buffer()` then `set_len(n)`.
+    ///
+    /// ### Python Example
+    /// ```python
+    /// >>> output = Buffer.zeros(15)
+    /// >>> cramjam.snappy.decompress_into(compressed, output)
+    /// 15
+    /// ```
+    #[staticmethod]
+    pub fn zeros(n: usize) -> Self {
+        Self::from(vec![0; n])
+    }
+
     /// Length of the underlying buffer
     pub fn len(&self) -> usize {
         self.inner.get_ref().len()
@@ -443,19 +820,133 @@ impl RustyBuffer {
         self.inner.set_position(0);
         Ok(())
     }
+    /// Bytes the buffer can hold before it has to reallocate.
+    pub fn capacity(&self) -> usize {
+        self.inner.get_ref().capacity()
+    }
+    /// Allocate room for at least `additional` more bytes than the buffer's length; unlike
+    /// `set_len` the length is unchanged and nothing is zero filled.
+    pub fn reserve(&mut self, additional: usize) {
+        self.inner.get_mut().reserve(additional);
+    }
+    /// Append any bytes-like object in [BytesType](../enum.BytesType.html) to the end of the
+    /// buffer, leaving the position where it is.
+    pub fn extend(slf: &Bound<'_, Self>, mut data: BytesType) -> PyResult<()> {
+        match &mut data {
+            BytesType::RustyBuffer(buffer) if buffer.is(slf) => {
+                let mut slf = slf.borrow_mut();
+                let output = slf.inner.get_mut();
+                output.extend_from_within(..);
+            }
+            BytesType::RustyFile(_) | BytesType::SpooledBuffer(_) => {
+                data.read_to_end(slf.borrow_mut().inner.get_mut())?;
+            }
+            _ => slf.borrow_mut().inner.get_mut().extend_from_slice(data.as_bytes()),
+        }
+        Ok(())
+    }
+    /// Release any capacity beyond the buffer's length.
+    pub fn shrink_to_fit(&mut self) {
+        self.inner.get_mut().shrink_to_fit();
+    }
+    /// Take the next `n` bytes from the current position as a new Buffer, advancing the position
+    /// past them; fewer bytes are taken if the buffer ends first.
+    pub fn take(&mut self, n: usize) -> RustyBuffer {
+        let data = self.inner.get_ref();
+        let start = (self.inner.position() as usize).min(data.len());
+        let end = start.saturating_add(n).min(data.len());
+        let taken = RustyBuffer::from(data[start..end].to_vec());
+        self.inner.set_position(end as u64);
+        taken
+    }
+    /// Split the first `n` bytes off into a new Buffer, which keeps this buffer's allocation,
+    /// leaving the rest here. The position moves back by `n`, stopping at 0.
+    pub fn split_to(&mut self, n: usize) -> PyResult<RustyBuffer> {
+        let len = self.inner.get_ref().len();
+        if n > len {
+            return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                "Cannot split {} bytes from a buffer of length {}",
+                n, len
+            )));
+        }
+        let rest = self.inner.get_mut().split_off(n);
+        let position = self.inner.position().saturating_sub(n as u64);
+        let head = std::mem::replace(&mut self.inner, Cursor::new(rest));
+        self.inner.set_position(position);
+        Ok(RustyBuffer::from(head.into_inner()))
+    }
+    /// Always True, here for compatibility with Python file objects.
+    pub fn readable(&self) -> bool {
+        true
+    }
+    /// Always True, here for compatibility with Python file objects.
+    pub fn writable(&self) -> bool {
+        true
+    }
+    /// Always False, here for compatibility with Python file objects.
+    pub fn isatty(&self) -> bool {
+        false
+    }
+    /// Raises `io.UnsupportedOperation`, as an in-memory buffer has no file descriptor.
+    pub fn fileno(&self) -> PyResult<i64> {
+        Err(UnsupportedOperation::new_err("Buffer has no file descriptor"))
+    }
+    /// Does nothing, here for compatibility with Python file objects.
+    pub fn flush(&self) {}
+    /// Always `"rb+"`, here for compatibility with Python file objects.
+    #[getter]
+    pub fn mode(&self) -> &'static str {
+        "rb+"
+    }
+    /// Always `"<Buffer>"`, here for compatibility with Python file objects.
+    #[getter]
+    pub fn name(&self) -> &'static str {
+        "<Buffer>"
+    }
+    /// Always False, here for compatibility with Python file objects.
+    #[getter]
+    pub fn closed(&self) -> bool {
+        false
+    }
+    /// Whether the buffer starts with `prefix`, any bytes-like object or a tuple of them,
+    /// like `bytes.startswith`; compared in place without copying the buffer.
+    pub fn startswith(&self, prefix: &Bound<'_, PyAny>) -> PyResult<bool> {
+        affixes(prefix, |affix| self.inner.get_ref().starts_with(affix))
+    }
+    /// Whether the buffer ends with `suffix`, any bytes-like object or a tuple of them,
+    /// like `bytes.endswith`; compared in place without copying the buffer.
+    pub fn endswith(&self, suffix: &Bound<'_, PyAny>) -> PyResult<bool> {
+        affixes(suffix, |affix| self.inner.get_ref().ends_with(affix))
+    }
 
     fn __len__(&self) -> usize {
         self.len()
     }
-    fn __contains__(&self, py: Python, x: BytesType) -> bool {
-        let bytes = x.as_bytes();
-        py.allow_threads(|| self.inner.get_ref().windows(bytes.len()).any(|w| w == bytes))
+    fn __contains__(&self, py: Python, x: BytesType) -> PyResult<bool> {
+        let bytes = &*x.peek_bytes(usize::MAX)?;
+        Ok(py.allow_threads(|| self.inner.get_ref().windows(bytes.len()).any(|w| w == bytes)))
     }
     fn __repr__(&self) -> String {
         format!("cramjam.Buffer<len={:?}>", self.len())
     }
-    fn __eq__(&self, other: &Self) -> bool {
-        self.inner == other.inner
+    fn __enter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+    #[pyo3(signature = (_exc_type=None, _exc_value=None, _traceback=None))]
+    fn __exit__(
+        &mut self,
+        _exc_type: Option<Bound<'_, PyAny>>,
+        _exc_value: Option<Bound<'_, PyAny>>,
+        _traceback: Option<Bound<'_, PyAny>>,
+    ) -> bool {
+        false
+    }
+    fn __eq__(&self, other: BytesType) -> bool {
+        match other {
+            // comparing would mean reading the whole file
+            BytesType::RustyFile(_) | BytesType::SpooledBuffer(_) => false,
+            other => self.inner.get_ref().as_slice() == other.as_bytes(),
+        }
     }
     fn __bool__(&self) -> bool {
         self.len() > 0
@@ -503,21 +994,330 @@ impl RustyBuffer {
     unsafe fn __releasebuffer__(&self, _view: *mut ffi::Py_buffer) {}
 }
 
+/// Where a [`SpooledBuffer`] holds its bytes
+pub(crate) enum Spool {
+    Memory(Cursor<Vec<u8>>),
+    Disk(File),
+}
+
+/// A Buffer which holds its bytes in memory until it grows past `max_memory`, then spills them
+/// to a temporary file and carries on there, like Python's `tempfile.SpooledTemporaryFile`.
+/// It's accepted anywhere a Buffer or File is, so outputs of unknown size can be bounded in
+/// memory without writing small ones to disk.
+///
+/// ### Python Example
+/// ```python
+/// >>> from cramjam import SpooledBuffer
+/// >>> out = SpooledBuffer(max_memory=64 * 1024 * 1024)
+/// >>> cramjam.zstd.decompress_into(compressed, out)
+/// >>> out.spilled  # True if the output didn't fit in 64MB
+/// False
+/// ```
+///
+/// ### Notes
+/// The temporary file is created in `dir`, or the system's temporary directory, and removed
+/// when the SpooledBuffer is garbage collected; on Unix it's unlinked as soon as it's created,
+/// so it's never left behind. Once spilled, it stays on disk, even if truncated.
+#[pyclass(name = "SpooledBuffer")]
+pub struct SpooledBuffer {
+    pub(crate) inner: Spool,
+    pub(crate) max_memory: usize,
+    pub(crate) dir: Option<PathBuf>,
+    /// Path of the file spilled to, while it needs removing
+    pub(crate) path: Option<PathBuf>,
+}
+
+impl SpooledBuffer {
+    /// Move the bytes held in memory to a temporary file, keeping the position.
+    pub(crate) fn spill(&mut self) -> std::io::Result<()> {
+        let Spool::Memory(cursor) = &self.inner else {
+            return Ok(());
+        };
+        let (mut file, path) = spill_file(self.dir.as_deref())?;
+        file.write_all(cursor.get_ref())?;
+        file.seek(SeekFrom::Start(cursor.position()))?;
+        self.inner = Spool::Disk(file);
+        self.path = path;
+        Ok(())
+    }
+}
+
+/// Create a file to spill to in `dir`, or the system's temporary directory, along with its path
+/// if it has to be removed once done with.
+fn spill_file(dir: Option<&std::path::Path>) -> std::io::Result<(File, Option<PathBuf>)> {
+    static COUNTER: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+    let dir = dir.map_or_else(std::env::temp_dir, PathBuf::from);
+    loop {
+        let n = COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let path = dir.join(format!("cramjam-spool-{}-{}", std::process::id(), n));
+        match OpenOptions::new().read(true).write(true).create_new(true).open(&path) {
+            // the open handle keeps an unlinked file around until it's closed
+            Ok(file) if cfg!(unix) => {
+                std::fs::remove_file(&path)?;
+                return Ok((file, None));
+            }
+            Ok(file) => return Ok((file, Some(path))),
+            // left behind by an earlier process with the same pid
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+impl AsBytes for SpooledBuffer {
+    fn as_bytes(&self) -> &[u8] {
+        match &self.inner {
+            Spool::Memory(cursor) => cursor.get_ref().as_slice(),
+            // callers read a `BytesType::SpooledBuffer` as a stream, by `read_bytes`, `peek_bytes`
+            // or `with_stream`, so never get here
+            Spool::Disk(_) => unreachable!(
+                "Converting a SpooledBuffer which has spilled to disk to bytes is not supported, \
+            as it'd require reading it all back into memory; use BytesType::read_bytes"
+            ),
+        }
+    }
+    fn as_bytes_mut(&mut self) -> PyResult<&mut [u8]> {
+        match &mut self.inner {
+            Spool::Memory(cursor) => Ok(cursor.get_mut().as_mut_slice()),
+            Spool::Disk(_) => Err(exceptions::PyTypeError::new_err(
+                "A SpooledBuffer which has spilled to disk can't be written into as a buffer, \
+                only as a file, ie. by decompress_into",
+            )),
+        }
+    }
+}
+
+#[pymethods]
+#[allow(clippy::len_without_is_empty)] // `__bool__` instead, for Python
+impl SpooledBuffer {
+    /// ### Example
+    /// ```python
+    /// from cramjam import SpooledBuffer
+    /// buf = SpooledBuffer(max_memory=1024, dir="/var/tmp")
+    /// buf.write(b"x" * 2048)
+    /// buf.spilled
+    /// True
+    /// ```
+    #[new]
+    #[pyo3(signature = (max_memory, data=None, dir=None))]
+    pub fn __init__(max_memory: usize, data: Option<BytesType<'_>>, dir: Option<PathLike>) -> PyResult<Self> {
+        let mut buf = Self {
+            inner: Spool::Memory(Cursor::new(vec![])),
+            max_memory,
+            dir: dir.map(|PathLike(dir)| dir),
+            path: None,
+        };
+        if let Some(mut data) = data {
+            write(&mut data, &mut buf)?;
+            Seek::seek(&mut buf, SeekFrom::Start(0))?;
+        }
+        Ok(buf)
+    }
+    /// Length of the buffer in bytes, in memory or on disk.
+    pub fn len(&self) -> PyResult<usize> {
+        match &self.inner {
+            Spool::Memory(cursor) => Ok(cursor.get_ref().len()),
+            Spool::Disk(file) => Ok(file.metadata()?.len() as usize),
+        }
+    }
+    /// Write some bytes to the buffer, where input data can be anything in [BytesType](../enum.BytesType.html),
+    /// spilling to disk if it grows past `max_memory`.
+    pub fn write(&mut self, mut input: BytesType) -> PyResult<usize> {
+        let r = write(&mut input, self)?;
+        Ok(r as usize)
+    }
+    /// Read from the buffer in its current position, returns bytes; optionally specify number of bytes to read.
+    #[pyo3(signature = (n_bytes=None))]
+    pub fn read<'a>(&mut self, py: Python<'a>, n_bytes: Option<usize>) -> PyResult<Bound<'a, PyBytes>> {
+        read(self, py, n_bytes)
+    }
+    /// Read from the buffer in its current position, into a [BytesType](../enum.BytesType.html) object.
+    pub fn readinto(&mut self, mut output: BytesType) -> PyResult<usize> {
+        let r = copy(self, &mut output)?;
+        Ok(r as usize)
+    }
+    /// Seek to a position within the buffer. whence follows the same values as IOBase.seek where:
+    /// ```bash
+    /// 0: from start of the stream
+    /// 1: from current stream position
+    /// 2: from end of the stream
+    /// ```
+    #[pyo3(signature = (position, whence=None))]
+    pub fn seek(&mut self, position: isize, whence: Option<usize>) -> PyResult<usize> {
+        let pos = match whence.unwrap_or(0) {
+            0 => SeekFrom::Start(position as u64),
+            1 => SeekFrom::Current(position as i64),
+            2 => SeekFrom::End(position as i64),
+            _ => {
+                return Err(pyo3::exceptions::PyValueError::new_err(
+                    "whence should be one of 0: seek from start, 1: seek from current, or 2: seek from end",
+                ))
+            }
+        };
+        let r = Seek::seek(self, pos)?;
+        Ok(r as usize)
+    }
+    /// Whether the buffer is seekable; here just for compatibility, it always returns True.
+    pub fn seekable(&self) -> bool {
+        true
+    }
+    /// Give the current position of the buffer.
+    pub fn tell(&mut self) -> PyResult<usize> {
+        let r = Seek::seek(self, SeekFrom::Current(0))?;
+        Ok(r as usize)
+    }
+    /// Set the length of the buffer, spilling to disk if that's past `max_memory`. If less than
+    /// current length, it will truncate to the size given; otherwise will be null byte filled to the size.
+    pub fn set_len(&mut self, size: usize) -> PyResult<()> {
+        if size > self.max_memory {
+            self.spill()?;
+        }
+        match &mut self.inner {
+            Spool::Memory(cursor) => cursor.get_mut().resize(size, 0),
+            Spool::Disk(file) => file.set_len(size as u64)?,
+        }
+        Ok(())
+    }
+    /// Truncate the buffer
+    pub fn truncate(&mut self) -> PyResult<()> {
+        self.set_len(0)?;
+        Seek::seek(self, SeekFrom::Start(0))?;
+        Ok(())
+    }
+    /// Spill the buffer to disk now, if it hasn't already.
+    pub fn rollover(&mut self) -> PyResult<()> {
+        self.spill()?;
+        Ok(())
+    }
+    /// Whether the buffer has spilled to disk.
+    #[getter]
+    pub fn spilled(&self) -> bool {
+        matches!(self.inner, Spool::Disk(_))
+    }
+    /// Bytes held in memory before spilling to disk.
+    #[getter]
+    pub fn max_memory(&self) -> usize {
+        self.max_memory
+    }
+    /// Always True, here for compatibility with Python file objects.
+    pub fn readable(&self) -> bool {
+        true
+    }
+    /// Always True, here for compatibility with Python file objects.
+    pub fn writable(&self) -> bool {
+        true
+    }
+    /// Always False, here for compatibility with Python file objects.
+    pub fn isatty(&self) -> bool {
+        false
+    }
+    /// The file descriptor of the file spilled to, spilling first as `SpooledTemporaryFile` does;
+    /// only available on Unix.
+    pub fn fileno(&mut self) -> PyResult<i64> {
+        #[cfg(unix)]
+        {
+            use std::os::fd::AsRawFd;
+            self.spill()?;
+            match &self.inner {
+                Spool::Disk(file) => Ok(file.as_raw_fd() as i64),
+                Spool::Memory(_) => unreachable!("just spilled to disk"),
+            }
+        }
+        #[cfg(not(unix))]
+        {
+            Err(UnsupportedOperation::new_err("fileno is only supported on Unix"))
+        }
+    }
+    /// Flush any data written to disk to the OS.
+    pub fn flush(&mut self) -> PyResult<()> {
+        Write::flush(self)?;
+        Ok(())
+    }
+    /// Always `"rb+"`, here for compatibility with Python file objects.
+    #[getter]
+    pub fn mode(&self) -> &'static str {
+        "rb+"
+    }
+    /// Always `"<SpooledBuffer>"`, here for compatibility with Python file objects.
+    #[getter]
+    pub fn name(&self) -> &'static str {
+        "<SpooledBuffer>"
+    }
+    /// Always False, here for compatibility with Python file objects.
+    #[getter]
+    pub fn closed(&self) -> bool {
+        false
+    }
+
+    fn __len__(&self) -> PyResult<usize> {
+        self.len()
+    }
+    fn __repr__(&self) -> PyResult<String> {
+        Ok(format!(
+            "cramjam.SpooledBuffer<len={:?}, spilled={}>",
+            self.len()?,
+            if self.spilled() { "True" } else { "False" }
+        ))
+    }
+    fn __bool__(&self) -> PyResult<bool> {
+        Ok(self.len()? > 0)
+    }
+    fn __enter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+    #[pyo3(signature = (_exc_type=None, _exc_value=None, _traceback=None))]
+    fn __exit__(
+        &mut self,
+        _exc_type: Option<Bound<'_, PyAny>>,
+        _exc_value: Option<Bound<'_, PyAny>>,
+        _traceback: Option<Bound<'_, PyAny>>,
+    ) -> bool {
+        false
+    }
+}
+
 fn write<W: Write>(input: &mut BytesType, output: &mut W) -> std::io::Result<u64> {
     let result = match input {
         BytesType::RustyBuffer(buf) => copy(&mut buf.borrow_mut().inner, output)?,
-        BytesType::RustyFile(data) => copy(&mut data.borrow_mut().inner, output)?,
+        BytesType::RustyFile(data) => copy(&mut *data.borrow_mut(), output)?,
+        BytesType::SpooledBuffer(data) => copy(&mut *data.borrow_mut(), output)?,
         BytesType::PyBuffer(buf) => copy(buf, output)?,
     };
     Ok(result)
 }
 
+/// Test `matches` against a single bytes-like object, or any in a tuple of them
+fn affixes(affix: &Bound<'_, PyAny>, matches: impl Fn(&[u8]) -> bool) -> PyResult<bool> {
+    let matches = |item: &Bound<'_, PyAny>| match BytesType::extract_bound(item)? {
+        BytesType::RustyFile(_) => Err(exceptions::PyTypeError::new_err(
+            "Expected a bytes-like object, got File",
+        )),
+        BytesType::SpooledBuffer(_) => Err(exceptions::PyTypeError::new_err(
+            "Expected a bytes-like object, got SpooledBuffer",
+        )),
+        bytes => Ok(matches(bytes.as_bytes())),
+    };
+    match affix.downcast::<PyTuple>() {
+        Ok(tuple) => {
+            for item in tuple.iter() {
+                if matches(&item)? {
+                    return Ok(true);
+                }
+            }
+            Ok(false)
+        }
+        Err(_) => matches(affix),
+    }
+}
+
 fn read<'a, R: Read>(reader: &mut R, py: Python<'a>, n_bytes: Option<usize>) -> PyResult<Bound<'a, PyBytes>> {
     match n_bytes {
-        Some(n) => PyBytes::new_bound_with(py, n, |buf| {
-            reader.read(buf)?;
-            Ok(())
-        }),
+        // up to `n` bytes, fewer once the end is reached
+        Some(n) => {
+            let mut buf = Vec::with_capacity(n);
+            reader.take(n as u64).read_to_end(&mut buf)?;
+            Ok(PyBytes::new_bound(py, buf.as_slice()))
+        }
         None => {
             let mut buf = vec![];
             reader.read_to_end(&mut buf)?;
@@ -533,7 +1333,16 @@ impl Seek for RustyBuffer {
 }
 impl Seek for RustyFile {
     fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
-        self.inner.seek(pos)
+        self.settle()?;
+        self.handle()?.seek(pos)
+    }
+}
+impl Seek for SpooledBuffer {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        match &mut self.inner {
+            Spool::Memory(cursor) => cursor.seek(pos),
+            Spool::Disk(file) => file.seek(pos),
+        }
     }
 }
 impl Seek for PythonBuffer {
@@ -558,10 +1367,57 @@ impl Write for RustyBuffer {
 }
 impl Write for RustyFile {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        self.inner.write(buf)
+        if self.prefetch.is_some() || self.buffering == 0 {
+            self.settle()?;
+        }
+        if self.pending.len() + buf.len() > self.buffering {
+            self.write_pending()?;
+        }
+        if buf.len() >= self.buffering {
+            return self.handle()?.write(buf);
+        }
+        self.handle()?;
+        self.pending.extend_from_slice(buf);
+        Ok(buf.len())
     }
     fn flush(&mut self) -> std::io::Result<()> {
-        self.inner.flush()
+        self.write_pending()?;
+        self.handle()?.flush()
+    }
+}
+impl Write for SpooledBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if let Spool::Memory(cursor) = &self.inner {
+            let end = cursor.position() as usize + buf.len();
+            if end.max(cursor.get_ref().len()) > self.max_memory {
+                self.spill()?;
+            }
+        }
+        match &mut self.inner {
+            Spool::Memory(cursor) => cursor.write(buf),
+            Spool::Disk(file) => file.write(buf),
+        }
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        match &mut self.inner {
+            Spool::Memory(_) => Ok(()),
+            Spool::Disk(file) => file.flush(),
+        }
+    }
+}
+impl Drop for SpooledBuffer {
+    fn drop(&mut self) {
+        if let Some(path) = self.path.take() {
+            // closed first, as Windows won't remove an open file
+            self.inner = Spool::Memory(Cursor::new(vec![]));
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+impl Drop for RustyFile {
+    fn drop(&mut self) {
+        // buffered writes would otherwise be lost; there's nowhere to report an error to
+        let _ = self.write_pending();
     }
 }
 impl Read for RustyBuffer {
@@ -569,12 +1425,88 @@ impl Read for RustyBuffer {
         self.inner.read(buf)
     }
 }
+impl Read for SpooledBuffer {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match &mut self.inner {
+            Spool::Memory(cursor) => cursor.read(buf),
+            Spool::Disk(file) => file.read(buf),
+        }
+    }
+}
 impl Read for RustyFile {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
-        self.inner.read(buf)
+        self.write_pending()?;
+        let chunk_size = match self.readahead {
+            Some(n) => n,
+            None => return self.handle()?.read(buf),
+        };
+        if self.prefetch.is_none() {
+            self.prefetch = Some(ReadAhead::new(self.handle()?, chunk_size)?);
+        }
+        match self.prefetch.as_mut().and_then(|p| p.read(buf)) {
+            Some(result) => result,
+            None => {
+                // worker exhausted the file; next read will start a new one
+                // in case the file has grown in the meantime.
+                self.settle()?;
+                Ok(0)
+            }
+        }
     }
 }
 
+/// Encoder whose state holds raw pointers into its own heap buffers, as ISA-L's does, so isn't
+/// `Send` though nothing in it is tied to the thread which created it. Moving it between threads
+/// is sound since a Compressor only ever hands out exclusive access to it, through `&mut self`.
+#[allow(dead_code)] // only used by the ISA-L Compressors, which are 64 bit only
+pub(crate) struct SendEncoder<W>(pub(crate) W);
+
+// SAFETY: see above; the encoder is never shared, only moved
+unsafe impl<W> Send for SendEncoder<W> {}
+
+impl<W: Write> Write for SendEncoder<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write(buf)
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+
+/// Compressed data read in place, for random access without copying or reading all of it:
+/// a `cramjam.File` or `SpooledBuffer` from its current position, whether or not it spilled to
+/// disk, other data copied up front.
+pub(crate) enum ReadSource {
+    Bytes(Cursor<Vec<u8>>),
+    File(Py<RustyFile>),
+    Spooled(Py<SpooledBuffer>),
+}
+
+impl ReadSource {
+    pub(crate) fn new(data: BytesType) -> Self {
+        match data {
+            BytesType::RustyFile(file) => ReadSource::File(file.unbind()),
+            BytesType::SpooledBuffer(buffer) => ReadSource::Spooled(buffer.unbind()),
+            data => ReadSource::Bytes(Cursor::new(data.as_bytes().to_vec())),
+        }
+    }
+
+    pub(crate) fn with_reader<T>(
+        &mut self,
+        py: Python,
+        f: impl FnOnce(&mut dyn ReadSeek) -> std::io::Result<T>,
+    ) -> std::io::Result<T> {
+        match self {
+            ReadSource::Bytes(cursor) => f(cursor),
+            ReadSource::File(file) => f(&mut *file.bind(py).borrow_mut()),
+            ReadSource::Spooled(buffer) => f(&mut *buffer.bind(py).borrow_mut()),
+        }
+    }
+}
+
+pub(crate) trait ReadSeek: Read + Seek {}
+impl<T: Read + Seek> ReadSeek for T {}
+
 // general stream compression interface. Can't use associated types due to pyo3::pyclass
 // not supporting generic structs.
 #[inline(always)]
@@ -613,9 +1545,10 @@ where
     }
 }
 
-// flush inner encoder data out
+// flush inner encoder data out, handing over the output's allocation rather than copying it;
+// `reserve` bytes are reserved afresh for the output to follow, ie. a Compressor's `output_len_hint`
 #[inline(always)]
-pub(crate) fn stream_flush<W, F>(encoder: &mut Option<W>, cursor_mut_ref: F) -> PyResult<RustyBuffer>
+pub(crate) fn stream_flush<W, F>(encoder: &mut Option<W>, reserve: usize, cursor_mut_ref: F) -> PyResult<RustyBuffer>
 where
     W: Write,
     F: Fn(&mut W) -> &mut Cursor<Vec<u8>>,
@@ -623,12 +1556,198 @@ where
     match encoder {
         Some(inner) => {
             inner.flush().map_err(CompressionError::from_err)?;
-            let cursor = cursor_mut_ref(inner);
-            let buf = RustyBuffer::from(cursor.get_ref().clone());
-            cursor.get_mut().truncate(0);
-            cursor.set_position(0);
-            Ok(buf)
+            Ok(take_output(cursor_mut_ref(inner), reserve))
         }
         None => Ok(RustyBuffer::from(vec![])),
     }
 }
+
+// compress as `stream_compress`, but with `returns_output`, give back the compressed bytes the
+// encoder has written out so far instead of the number of bytes consumed; without flushing, so
+// the encoder keeps compressing as well as it would otherwise
+#[inline(always)]
+pub(crate) fn stream_compress_output<W, F>(
+    py: Python,
+    encoder: &mut Option<W>,
+    input: &[u8],
+    returns_output: bool,
+    reserve: usize,
+    cursor_mut_ref: F,
+) -> PyResult<PyObject>
+where
+    W: Write + Send,
+    F: Fn(&mut W) -> &mut Cursor<Vec<u8>>,
+{
+    let n = py.allow_threads(|| stream_compress(encoder, input))?;
+    match encoder.as_mut() {
+        Some(inner) if returns_output => Ok(take_output(cursor_mut_ref(inner), reserve).into_py(py)),
+        _ => Ok(n.into_py(py)),
+    }
+}
+
+// hand over the output written so far, rather than copying it, keeping `reserve` bytes for the next
+fn take_output(cursor: &mut Cursor<Vec<u8>>, reserve: usize) -> RustyBuffer {
+    let buf = RustyBuffer::from(mem::replace(cursor.get_mut(), Vec::with_capacity(reserve)));
+    cursor.set_position(0);
+    buf
+}
+
+// capacity of the buffer a Compressor is holding its compressed output in, `0` once finished
+#[inline(always)]
+pub(crate) fn stream_capacity<W, F>(encoder: &Option<W>, cursor_ref: F) -> usize
+where
+    F: Fn(&W) -> &Cursor<Vec<u8>>,
+{
+    encoder
+        .as_ref()
+        .map_or(0, |inner| cursor_ref(inner).get_ref().capacity())
+}
+
+// read up to `n` bytes, or all if negative, from the front of a Decompressor's output
+#[inline(always)]
+pub(crate) fn stream_read<'py>(
+    py: Python<'py>,
+    output: Option<&mut Vec<u8>>,
+    n: isize,
+) -> PyResult<Bound<'py, PyBytes>> {
+    match output {
+        Some(output) => {
+            let n = usize::try_from(n).map_or(output.len(), |n| n.min(output.len()));
+            let bytes = PyBytes::new_bound(py, &output[..n]);
+            output.drain(..n);
+            Ok(bytes)
+        }
+        None => Err(DecompressionError::new_err(
+            "Appears `finish()` was called on this instance",
+        )),
+    }
+}
+
+// read from the front of a Decompressor's output to fill `buf`, as far as possible
+#[inline(always)]
+pub(crate) fn stream_readinto(output: Option<&mut Vec<u8>>, buf: &mut [u8]) -> PyResult<usize> {
+    match output {
+        Some(output) => {
+            let n = buf.len().min(output.len());
+            buf[..n].copy_from_slice(&output[..n]);
+            output.drain(..n);
+            Ok(n)
+        }
+        None => Err(DecompressionError::new_err(
+            "Appears `finish()` was called on this instance",
+        )),
+    }
+}
+
+/// Output of a one-shot decompression given `into=`: the given Buffer with the output in its
+/// reused allocation, or a new Buffer when `into` was `None`, unless the output allocator gave
+/// the output instead.
+pub enum OutputBuffer<'py> {
+    /// Newly allocated output
+    New(RustyBuffer),
+    /// The `into=` Buffer, holding the output
+    Into(Bound<'py, RustyBuffer>),
+    /// Output from the allocator set with `set_output_allocator`
+    Allocated(crate::allocator::AllocatedOutput),
+}
+
+impl<'py> IntoPy<PyObject> for OutputBuffer<'py> {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        match self {
+            OutputBuffer::New(buffer) => buffer.into_py(py),
+            OutputBuffer::Into(buffer) => buffer.into_py(py),
+            OutputBuffer::Allocated(output) => output.into_py(py),
+        }
+    }
+}
+
+/// Result of a `compress_into`: the bytes written, or given `align=`, those along with the
+/// length once zero padded to a multiple of `align`.
+pub enum IntoLength {
+    /// Bytes written, without `align`
+    Written(usize),
+    /// Bytes written, and the padded length
+    Padded(usize, usize),
+}
+
+impl IntoPy<PyObject> for IntoLength {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        match self {
+            IntoLength::Written(written) => written.into_py(py),
+            IntoLength::Padded(written, padded) => (written, padded).into_py(py),
+        }
+    }
+}
+
+// zero pad `output` after the `written` bytes just compressed into it, up to a multiple of `align`
+pub(crate) fn pad_output(output: &mut BytesType, written: usize, align: Option<usize>) -> PyResult<IntoLength> {
+    let Some(align) = align else {
+        return Ok(IntoLength::Written(written));
+    };
+    if align == 0 {
+        return Err(exceptions::PyValueError::new_err("align must be greater than 0"));
+    }
+    let padded = written.div_ceil(align) * align;
+    let too_small = || {
+        CompressionError::new_err(format!(
+            "Output is too small to pad {} compressed bytes to {}",
+            written, padded
+        ))
+    };
+    match output {
+        // written to from its start, rather than its position
+        BytesType::PyBuffer(buffer) => buffer
+            .as_slice_mut()?
+            .get_mut(written..padded)
+            .ok_or_else(too_small)?
+            .fill(0),
+        _ => output.write_all(&vec![0; padded - written]).map_err(|_| too_small())?,
+    }
+    Ok(IntoLength::Padded(written, padded))
+}
+
+// check a raw decompression's output is what's expected of it, as raw formats without a
+// checksum of their own may decode with the wrong parameters to garbage, rather than fail
+pub(crate) fn verify_output<'py>(
+    output: OutputBuffer<'py>,
+    expected_len: Option<usize>,
+    expected_crc32: Option<u32>,
+) -> PyResult<OutputBuffer<'py>> {
+    let check = |bytes: &[u8]| match (expected_len, expected_crc32) {
+        (Some(len), _) if bytes.len() != len => Err(DecompressionError::new_err(format!(
+            "Decompressed {} bytes, but expected_len is {}",
+            bytes.len(),
+            len
+        ))),
+        (_, Some(crc32)) if crc32fast::hash(bytes) != crc32 => Err(DecompressionError::new_err(format!(
+            "Decompressed data has CRC32 {:#010x}, but expected_crc32 is {:#010x}",
+            crc32fast::hash(bytes),
+            crc32
+        ))),
+        _ => Ok(()),
+    };
+    match &output {
+        OutputBuffer::New(buffer) => check(buffer.as_bytes())?,
+        OutputBuffer::Into(buffer) => check(buffer.borrow().as_bytes())?,
+        OutputBuffer::Allocated(output) => check(output.as_bytes())?,
+    }
+    Ok(output)
+}
+
+// take the `into=` Buffer's allocation, cleared, to write a one-shot decompression's output to
+pub(crate) fn reuse_output(into: &Bound<RustyBuffer>, input: &BytesType, reserve: Option<usize>) -> PyResult<Vec<u8>> {
+    if matches!(input, BytesType::RustyBuffer(buffer) if buffer.is(into)) {
+        return Err(exceptions::PyValueError::new_err(
+            "Cannot decompress a Buffer into itself",
+        ));
+    }
+    let mut output = mem::take(into.borrow_mut().inner.get_mut());
+    output.clear();
+    output.reserve(reserve.unwrap_or_default());
+    Ok(output)
+}
+
+// give the output back to the `into=` Buffer, positioned at the start as a new Buffer would be
+pub(crate) fn restore_output(into: &Bound<RustyBuffer>, output: Vec<u8>) {
+    into.borrow_mut().inner = Cursor::new(output);
+}
@@ -6,11 +6,12 @@
 //!
 //! In general, the API follows cramjam.`<<compression algorithm>>.compress` and cramjam.`<<compression algorithm>>.decompress`
 //! as well as `compress_into`/`decompress_into` where it takes an input and output combination of any of the following:
-//!  - `numpy.array` (dtype=np.uint8)
+//!  - `numpy.array` (any C contiguous array; see [`compress_ndarray`](ndarray/index.html) to also keep dtype and shape)
 //!  - `bytes`
 //!  - `bytearray`
 //!  - [`cramjam.File`](io/struct.RustyFile.html)
 //!  - [`cramjam.Buffer`](./io/struct.RustyBuffer.html)
+//!  - [`cramjam.SpooledBuffer`](./io/struct.SpooledBuffer.html), which spills to a temporary file past a size
 //!
 //! ### Simple Python Example:
 //!
@@ -50,10 +51,45 @@
 //! >>> decompressed
 //! b'some bytes here'
 //! ```
+//!
+//! ### Leaving out checksums with `unsafe_speed`
+//!
+//! Where a format's checksums are optional, `compress`, `compress_into` and `Compressor` take
+//! `unsafe_speed=True` to leave them out, trading the detection of corrupted data for speed:
+//!  - `lz4`: block and content XXH32 checksums, the bulk of the gain
+//!  - `xz`: the stream's integrity check, cheap next to LZMA itself
+//!
+//! zstd frames from cramjam carry no checksum to begin with, and gzip's CRC32, zlib's Adler-32,
+//! bzip2's CRCs and the CRC32C of snappy's frames are required by their formats, so
+//! `unsafe_speed` isn't offered for these. Only use it where the transport already guarantees
+//! integrity, ie. for ephemeral transfers within a datacenter.
 
+pub mod allocator;
+pub mod asyncio;
+pub mod auto;
+pub mod batch;
+pub mod checksum;
+pub mod codecs;
+pub mod detect;
+pub mod dispatch;
 pub mod exceptions;
 pub mod experimental;
+pub mod features;
+pub mod framed;
+pub mod frames;
+pub mod info;
 pub mod io;
+pub mod limits;
+pub mod lines;
+pub mod ndarray;
+pub mod not_built;
+pub mod optimize;
+pub mod paths;
+pub mod pipeline;
+pub mod plugins;
+pub mod stats;
+pub mod store;
+pub mod testing;
 
 #[cfg(any(feature = "blosc2", feature = "blosc2-static", feature = "blosc2-shared"))]
 pub mod blosc2;
@@ -75,6 +111,8 @@ pub mod ideflate;
     target_pointer_width = "64"
 ))]
 pub mod igzip;
+#[cfg(any(feature = "gzip", feature = "gzip-static", feature = "gzip-shared"))]
+pub mod inflate;
 #[cfg(all(
     any(feature = "izlib", feature = "izlib-static", feature = "izlib-shared"),
     target_pointer_width = "64"
@@ -82,8 +120,21 @@ pub mod igzip;
 pub mod izlib;
 #[cfg(feature = "lz4")]
 pub mod lz4;
+#[cfg(feature = "lzf")]
+pub mod lzf;
+#[cfg(any(feature = "xz", feature = "xz-static", feature = "xz-shared"))]
+pub mod lzma;
 #[cfg(feature = "snappy")]
 pub mod snappy;
+#[cfg(any(
+    feature = "zlib",
+    feature = "zlib-static",
+    feature = "zlib-shared",
+    feature = "deflate",
+    feature = "deflate-static",
+    feature = "deflate-shared"
+))]
+pub mod wbits;
 #[cfg(any(feature = "xz", feature = "xz-static", feature = "xz-shared"))]
 pub mod xz;
 #[cfg(any(feature = "zlib", feature = "zlib-static", feature = "zlib-shared"))]
@@ -94,7 +145,7 @@ pub mod zstd;
 use io::{PythonBuffer, RustyBuffer};
 use pyo3::prelude::*;
 
-use crate::io::{AsBytes, RustyFile};
+use crate::io::{AsBytes, RustyFile, SpooledBuffer};
 use exceptions::{CompressionError, DecompressionError};
 use std::io::{Read, Seek, SeekFrom, Write};
 
@@ -109,6 +160,9 @@ pub enum BytesType<'a> {
     /// [`cramjam.File`](io/struct.RustyFile.html)
     #[pyo3(transparent, annotation = "File")]
     RustyFile(Bound<'a, RustyFile>),
+    /// [`cramjam.SpooledBuffer`](io/struct.SpooledBuffer.html)
+    #[pyo3(transparent, annotation = "SpooledBuffer")]
+    SpooledBuffer(Bound<'a, SpooledBuffer>),
     /// `object` implementing the Buffer Protocol
     #[pyo3(transparent, annotation = "pybuffer")]
     PyBuffer(PythonBuffer),
@@ -128,6 +182,11 @@ impl<'a> AsBytes for BytesType<'a> {
                 let bytes = py_ref.as_bytes();
                 unsafe { std::slice::from_raw_parts(bytes.as_ptr(), bytes.len()) }
             }
+            BytesType::SpooledBuffer(b) => {
+                let py_ref = b.borrow();
+                let bytes = py_ref.as_bytes();
+                unsafe { std::slice::from_raw_parts(bytes.as_ptr(), bytes.len()) }
+            }
         }
     }
     fn as_bytes_mut(&mut self) -> PyResult<&mut [u8]> {
@@ -143,6 +202,11 @@ impl<'a> AsBytes for BytesType<'a> {
                 let bytes = py_ref.as_bytes_mut()?;
                 Ok(unsafe { std::slice::from_raw_parts_mut(bytes.as_mut_ptr(), bytes.len()) })
             }
+            BytesType::SpooledBuffer(b) => {
+                let mut py_ref = b.borrow_mut();
+                let bytes = py_ref.as_bytes_mut()?;
+                Ok(unsafe { std::slice::from_raw_parts_mut(bytes.as_mut_ptr(), bytes.len()) })
+            }
         }
     }
 }
@@ -151,15 +215,17 @@ impl<'a> Write for BytesType<'a> {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
         let result = match self {
             BytesType::RustyBuffer(out) => out.borrow_mut().inner.write(buf)?,
-            BytesType::RustyFile(out) => out.borrow_mut().inner.write(buf)?,
+            BytesType::RustyFile(out) => Write::write(&mut *out.borrow_mut(), buf)?,
+            BytesType::SpooledBuffer(out) => Write::write(&mut *out.borrow_mut(), buf)?,
             BytesType::PyBuffer(out) => out.write(buf)?,
         };
         Ok(result)
     }
     fn flush(&mut self) -> std::io::Result<()> {
         match self {
-            BytesType::RustyBuffer(b) => b.borrow_mut().flush(),
-            BytesType::RustyFile(f) => f.borrow_mut().flush(),
+            BytesType::RustyBuffer(b) => Write::flush(&mut *b.borrow_mut()),
+            BytesType::RustyFile(f) => Write::flush(&mut *f.borrow_mut()),
+            BytesType::SpooledBuffer(b) => Write::flush(&mut *b.borrow_mut()),
             BytesType::PyBuffer(_) => Ok(()),
         }
     }
@@ -168,7 +234,8 @@ impl<'a> Read for BytesType<'a> {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
         match self {
             BytesType::RustyBuffer(data) => data.borrow_mut().inner.read(buf),
-            BytesType::RustyFile(data) => data.borrow_mut().inner.read(buf),
+            BytesType::RustyFile(data) => Read::read(&mut *data.borrow_mut(), buf),
+            BytesType::SpooledBuffer(data) => Read::read(&mut *data.borrow_mut(), buf),
             BytesType::PyBuffer(data) => data.read(buf),
         }
     }
@@ -177,7 +244,8 @@ impl<'a> Seek for BytesType<'a> {
     fn seek(&mut self, style: SeekFrom) -> std::io::Result<u64> {
         match self {
             BytesType::RustyBuffer(b) => b.borrow_mut().inner.seek(style),
-            BytesType::RustyFile(f) => f.borrow_mut().inner.seek(style),
+            BytesType::RustyFile(f) => Seek::seek(&mut *f.borrow_mut(), style),
+            BytesType::SpooledBuffer(b) => Seek::seek(&mut *b.borrow_mut(), style),
             BytesType::PyBuffer(buf) => buf.seek(style),
         }
     }
@@ -187,7 +255,8 @@ impl<'a> BytesType<'a> {
     /// Length in bytes
     fn len(&self) -> usize {
         match self {
-            BytesType::RustyFile(file) => file.borrow().len().unwrap(),
+            BytesType::RustyFile(file) => file.borrow_mut().len().unwrap_or_default(),
+            BytesType::SpooledBuffer(buffer) => buffer.borrow().len().unwrap_or_default(),
             _ => self.as_bytes().len(),
         }
     }
@@ -204,115 +273,303 @@ impl<'a> BytesType<'a> {
     fn is_empty(&self) -> bool {
         self.len() == 0
     }
+    /// The bytes of the data, borrowed when in memory. A `File` or `SpooledBuffer` is read from
+    /// its current position to the end, advancing it as de/compressing it would.
+    pub(crate) fn read_bytes(&self) -> std::io::Result<std::borrow::Cow<'_, [u8]>> {
+        match self.with_stream(|reader| {
+            let mut bytes = vec![];
+            reader.read_to_end(&mut bytes).map(|_| bytes)
+        }) {
+            Some(bytes) => Ok(bytes?.into()),
+            None => Ok(self.as_bytes().into()),
+        }
+    }
+    /// Up to `len` bytes of the data, as `read_bytes` but leaving the position of a `File` or
+    /// `SpooledBuffer` where it was; for inspecting a header.
+    pub(crate) fn peek_bytes(&self, len: usize) -> std::io::Result<std::borrow::Cow<'_, [u8]>> {
+        fn peek(reader: &mut (impl Read + Seek), len: usize) -> std::io::Result<Vec<u8>> {
+            let pos = reader.stream_position()?;
+            let mut bytes = vec![];
+            let result = reader.by_ref().take(len as u64).read_to_end(&mut bytes);
+            reader.seek(SeekFrom::Start(pos))?;
+            result.map(|_| bytes)
+        }
+        match self {
+            BytesType::RustyFile(file) => Ok(peek(&mut *file.borrow_mut(), len)?.into()),
+            BytesType::SpooledBuffer(buffer) => Ok(peek(&mut *buffer.borrow_mut(), len)?.into()),
+            _ => {
+                let bytes = self.as_bytes();
+                Ok(bytes[..len.min(bytes.len())].into())
+            }
+        }
+    }
+    /// Call `f` with a reader of a `File` or `SpooledBuffer`, from its current position as
+    /// `read_bytes` reads it, for reading a chunk at a time; `None` for data in memory, which
+    /// `as_bytes` borrows.
+    pub(crate) fn with_stream<T>(&self, f: impl FnOnce(&mut (dyn Read + Send)) -> T) -> Option<T> {
+        match self {
+            BytesType::RustyFile(file) => Some(f(&mut *file.borrow_mut())),
+            BytesType::SpooledBuffer(buffer) => Some(f(&mut *buffer.borrow_mut())),
+            _ => None,
+        }
+    }
+}
+
+/// Implementation flate2 was built with, which the gzip, zlib and deflate modules de/compress with
+#[cfg(any(
+    feature = "gzip",
+    feature = "gzip-static",
+    feature = "gzip-shared",
+    feature = "zlib",
+    feature = "zlib-static",
+    feature = "zlib-shared",
+    feature = "deflate",
+    feature = "deflate-static",
+    feature = "deflate-shared"
+))]
+pub(crate) fn flate2_backend() -> &'static str {
+    if cfg!(feature = "zlib-ng") {
+        "zlib-ng"
+    } else {
+        "miniz_oxide"
+    }
+}
+
+/// Resolve `level` along with its stdlib style alias, ie. gzip's `compresslevel`, only one of which may be given
+pub(crate) fn level_alias<T>(level: Option<T>, alias: &str, value: Option<T>) -> PyResult<Option<T>> {
+    match (level, value) {
+        (Some(_), Some(_)) => Err(pyo3::exceptions::PyTypeError::new_err(format!(
+            "'level' and '{}' are the same argument, only one may be given",
+            alias
+        ))),
+        (level, value) => Ok(level.or(value)),
+    }
+}
+
+/// Whether the `input` to decompress is empty; every codec decompresses that to nothing, so
+/// it's answered without calling into the codec, allocating the output or releasing the GIL.
+/// Files and SpooledBuffers are left to the codec, as only their remaining length counts.
+pub(crate) fn empty_decompression(input: &BytesType) -> bool {
+    !matches!(input, BytesType::RustyFile(_) | BytesType::SpooledBuffer(_)) && input.is_empty()
 }
 
 /// Macro for generating the implementation of de/compression against a variant interface
 #[macro_export]
 macro_rules! generic {
-    // de/compress
-    ($py:ident, $op:path[$input:expr], output_len = $output_len:ident $(, $args:ident)*) => {
+    // de/compress, decompression passing its `max_expansion_ratio` and `output_len_max` to guard the output size.
+    // `output_len` is only a hint, the capacity preallocated for the output: it grows past that if
+    // need be, and is exactly what's written, never padded to `output_len`.
+    ($py:ident, $op:path[$input:expr], $(max_expansion_ratio = $ratio:ident, output_len_max = $len_max:ident,)? $(into = $into:ident,)? output_len = $output_len:ident $(, $args:ident)*) => {
         {
             use crate::io::RustyBuffer;
 
-            let mut output: Vec<u8> = match $output_len {
-                Some(len) => vec![0; len],
-                None => vec![]
-            };
-            match $input {
+            let empty = crate::generic!(@empty_decompression $input $(, $ratio)?);
+            let input_len = crate::generic!(@input_len $input);
+            let limit = crate::generic!(@limit $input $(, $ratio, $len_max)?);
+            $(let mut allocated = $crate::allocator::allocate_output($py, $output_len.filter(|_| !empty), &$into)?;)?
+            let $output_len = $crate::generic!(@unallocated $output_len $(, allocated, $into)?);
+            let mut output: Vec<u8> = $crate::generic!(@allocate $input, $output_len.filter(|_| !empty) $(, $into)?;
+                Vec::with_capacity($output_len.filter(|_| !empty).unwrap_or_default()));
+            let writer = $crate::generic!(@sink &mut output $(, allocated, $into)?);
+            let mut writer = crate::limits::Limited::new(writer, limit);
+            let result = match $input {
+                _ if empty => Ok(0),
                 BytesType::RustyFile(f) => {
-                    let borrowed = f.borrow();
-                    let file = &borrowed.inner;
+                    let mut borrowed = f.borrow_mut();
+                    let file = &mut *borrowed;
+                    $py.allow_threads(|| {
+                        crate::generic!(@record $op, input_len, $op(file, &mut writer $(, $args)*))
+                    })
+                },
+                BytesType::SpooledBuffer(b) => {
+                    let mut borrowed = b.borrow_mut();
+                    let spooled = &mut *borrowed;
                     $py.allow_threads(|| {
-                        $op(file, &mut Cursor::new(&mut output) $(, $args)* )
+                        crate::generic!(@record $op, input_len, $op(spooled, &mut writer $(, $args)*))
                     })
                 },
                 _ => {
                     let bytes = $input.as_bytes();
                     $py.allow_threads(|| {
-                        $op(bytes, &mut Cursor::new(&mut output) $(, $args)* )
+                        crate::generic!(@record $op, input_len, $op(bytes, &mut writer $(, $args)*))
                     })
                 }
-            }.map(|_| RustyBuffer::from(output))
+            };
+            $crate::generic!(@output result, output $(, $into, allocated)?)
         }
     };
-    // de/compress_into
+    // decompress_into, marked as decompressing for its shortcut for empty input
+    ($py:ident, decompress: $op:path[$input:ident, $output:ident] $(, $args:ident)*) => {
+        crate::generic!(@into_input $py, $op[$input, $output], true $(, $args)*)
+    };
+    // compress_into
     ($py:ident, $op:path[$input:ident, $output:ident] $(, $args:ident)*) => {
+        crate::generic!(@into_input $py, $op[$input, $output], false $(, $args)*)
+    };
+    // de/compress_into, reading from `$input` however it's held
+    (@into_input $py:ident, $op:path[$input:ident, $output:ident], $decompress:literal $(, $args:ident)*) => {
         {
+            let empty = $decompress && crate::empty_decompression(&$input);
+            let input_len = crate::generic!(@input_len $input);
             match $input {
+                _ if empty => Ok(0),
                 BytesType::RustyFile(f) => {
-                    let borrowed = f.borrow();
-                    let f_in = &borrowed.inner;
-                    match $output {
-                        BytesType::RustyFile(f) => {
-                            let mut borrowed = f.borrow_mut();
-                            let mut f_out = &mut borrowed.inner;
-                            $py.allow_threads(|| {
-                                $op(f_in, &mut f_out $(, $args)* )
-                            })
-                        },
-                        BytesType::RustyBuffer(buffer) => {
-                            let mut borrowed = buffer.borrow_mut();
-                            let mut buf_out = &mut borrowed.inner;
-                            $py.allow_threads(|| {
-                                $op(f_in, &mut buf_out $(, $args)* )
-                            })
-                        },
-                        _ => {
-                            let bytes_out = $output.as_bytes_mut()?;
-                            $py.allow_threads(|| {
-                                $op(f_in, &mut Cursor::new(bytes_out) $(, $args)* )
-                            })
-                        }
-                    }
+                    let mut borrowed = f.borrow_mut();
+                    let f_in = &mut *borrowed;
+                    crate::generic!(@into $py, $op, input_len, f_in, $output $(, $args)*)
+                },
+                BytesType::SpooledBuffer(b) => {
+                    let mut borrowed = b.borrow_mut();
+                    let b_in = &mut *borrowed;
+                    crate::generic!(@into $py, $op, input_len, b_in, $output $(, $args)*)
                 },
                 _ =>  {
                     let bytes_in = $input.as_bytes();
-                    match $output {
-                        BytesType::RustyFile(f) => {
-                            let mut borrowed = f.borrow_mut();
-                            let mut f_out = &mut borrowed.inner;
-                            $py.allow_threads(|| {
-                                $op(bytes_in, &mut f_out $(, $args)* )
-                            })
-                        },
-                        BytesType::RustyBuffer(buffer) => {
-                            let mut borrowed = buffer.borrow_mut();
-                            let mut buf_out = &mut borrowed.inner;
-                            $py.allow_threads(|| {
-                                $op(bytes_in, &mut buf_out $(, $args)* )
-                            })
-                        },
-                        _ => {
-                            let bytes_out = $output.as_bytes_mut()?;
-                            $py.allow_threads(|| {
-                                $op(bytes_in, &mut Cursor::new(bytes_out) $(, $args)*)
-                            })
-                        }
-                    }
+                    crate::generic!(@into $py, $op, input_len, bytes_in, $output $(, $args)*)
                 }
             }
         }
-    }
+    };
+    // write what's read from `$reader` into `$output`, as a stream unless it's borrowed as bytes
+    (@into $py:ident, $op:path, $input_len:ident, $reader:ident, $output:ident $(, $args:ident)*) => {
+        match &$output {
+            BytesType::RustyFile(f) => {
+                let mut borrowed = f.borrow_mut();
+                let mut f_out = &mut *borrowed;
+                $py.allow_threads(|| {
+                    crate::generic!(@record $op, $input_len, $op($reader, &mut f_out $(, $args)*))
+                })
+            },
+            BytesType::SpooledBuffer(b) => {
+                let mut borrowed = b.borrow_mut();
+                let mut b_out = &mut *borrowed;
+                $py.allow_threads(|| {
+                    crate::generic!(@record $op, $input_len, $op($reader, &mut b_out $(, $args)*))
+                })
+            },
+            BytesType::RustyBuffer(buffer) => {
+                let mut borrowed = buffer.borrow_mut();
+                let mut buf_out = &mut borrowed.inner;
+                $py.allow_threads(|| {
+                    crate::generic!(@record $op, $input_len, $op($reader, &mut buf_out $(, $args)*))
+                })
+            },
+            _ => {
+                let bytes_out = $output.as_bytes_mut()?;
+                $py.allow_threads(|| {
+                    crate::generic!(@record $op, $input_len, $op($reader, &mut Cursor::new(bytes_out) $(, $args)*))
+                })
+            }
+        }
+    };
+    // decompress, discarding output; for integrity checks
+    ($py:ident, $op:path[$input:expr], discard) => {
+        {
+            let input_len = crate::generic!(@input_len $input);
+            match $input {
+                BytesType::RustyFile(f) => {
+                    let mut borrowed = f.borrow_mut();
+                    let file = &mut *borrowed;
+                    $py.allow_threads(|| crate::generic!(@record $op, input_len, $op(file, &mut std::io::sink())))
+                },
+                BytesType::SpooledBuffer(b) => {
+                    let mut borrowed = b.borrow_mut();
+                    let spooled = &mut *borrowed;
+                    $py.allow_threads(|| crate::generic!(@record $op, input_len, $op(spooled, &mut std::io::sink())))
+                },
+                _ => {
+                    let bytes = $input.as_bytes();
+                    $py.allow_threads(|| crate::generic!(@record $op, input_len, $op(bytes, &mut std::io::sink())))
+                }
+            }
+        }
+    };
+    // output size limit; only decompression is limited
+    (@limit $input:expr) => {
+        None
+    };
+    (@limit $input:expr, $ratio:ident, $len_max:ident) => {
+        crate::limits::output_limit($input.len(), $ratio, $len_max)?
+    };
+    // output to write to, reusing the allocation of the Buffer given as `into=`, if any
+    (@allocate $input:expr, $reserve:expr; $allocate:expr) => {
+        $allocate
+    };
+    (@allocate $input:expr, $reserve:expr, $into:ident; $allocate:expr) => {
+        match &$into {
+            Some(into) => $crate::io::reuse_output(into, &$input, $reserve)?,
+            None => $allocate,
+        }
+    };
+    // writing to the output allocator's buffer instead, when there is one, so `output` needn't
+    // be allocated as well
+    (@unallocated $output_len:ident) => {
+        $output_len
+    };
+    (@unallocated $output_len:ident, $allocated:ident, $into:ident) => {
+        $output_len.filter(|_| $allocated.is_none())
+    };
+    (@sink $output:expr) => {
+        $output
+    };
+    (@sink $output:expr, $allocated:ident, $into:ident) => {
+        $crate::allocator::Sink::new($output, $allocated.as_mut())
+    };
+    (@output $result:ident, $output:ident) => {
+        $result.map(|_| RustyBuffer::from($output))
+    };
+    (@output $result:ident, $output:ident, $into:ident, $allocated:ident) => {
+        match ($into, $allocated) {
+            (Some(into), _) => {
+                $crate::io::restore_output(&into, $output);
+                $result.map(|_| $crate::io::OutputBuffer::Into(into))
+            }
+            (None, Some(allocated)) => $result.map(|_| $crate::io::OutputBuffer::Allocated(allocated)),
+            (None, None) => $result.map(|_| $crate::io::OutputBuffer::New(RustyBuffer::from($output))),
+        }
+    };
+    // empty input decompresses to nothing, whatever the codec; only decompression is limited,
+    // so is given a ratio
+    (@empty_decompression $input:expr) => {
+        false
+    };
+    (@empty_decompression $input:expr, $ratio:ident) => {
+        crate::empty_decompression(&$input)
+    };
+    // statistics, if enabled, about the operation
+    (@input_len $input:expr) => {
+        if crate::stats::enabled() { $input.len() } else { 0 }
+    };
+    (@record $op:path, $input_len:expr, $call:expr) => {
+        crate::stats::timed(module_path!(), stringify!($op), $input_len, || $call)
+    };
 }
 
 /// Generate a `Decompressor` from a library's decompressor which implements Read
 #[macro_export]
 macro_rules! make_decompressor {
     ($codec:ident) => {
+        $crate::make_decompressor!($codec, libcramjam::$codec::decompress);
+    };
+    // `$decompress(input, output, options...)` with the `$option`s given to `Decompressor()`
+    ($codec:ident, $decompress:path $(, $option:ident: $option_ty:ty = $default:literal)*) => {
         /// Decompressor object for streaming decompression
         /// **NB** This is mostly here for API complement to `Compressor`
         /// You'll almost always be statisfied with `de/compress` / `de/compress_into` functions.
         #[pyclass]
         pub struct Decompressor {
             inner: Option<Cursor<Vec<u8>>>,
+            $($option: $option_ty,)*
         }
         #[pymethods]
         impl Decompressor {
             /// Initialize a new `Decompressor` instance.
             #[new]
-            pub fn __init__() -> PyResult<Self> {
+            #[pyo3(signature = ($($option = $default),*))]
+            pub fn __init__($($option: $option_ty),*) -> PyResult<Self> {
                 Ok(Self {
                     inner: Some(Default::default()),
+                    $($option,)*
                 })
             }
 
@@ -324,22 +581,43 @@ macro_rules! make_decompressor {
                     .unwrap_or_else(|| 0)
             }
 
-            /// Decompress this input into the inner buffer.
-            pub fn decompress(&mut self, py: Python, mut input: BytesType) -> PyResult<usize> {
+            /// Decompress this input into the inner buffer. Its output is limited as `decompress`
+            /// limits it, by `max_expansion_ratio` times the size of this input and `output_len_max`.
+            #[pyo3(signature = (input, max_expansion_ratio=None, *, output_len_max=None))]
+            pub fn decompress(
+                &mut self,
+                py: Python,
+                mut input: BytesType,
+                max_expansion_ratio: Option<f64>,
+                output_len_max: Option<usize>,
+            ) -> PyResult<usize> {
+                let limit = $crate::limits::output_limit(input.len(), max_expansion_ratio, output_len_max)?;
                 match self.inner.as_mut() {
-                    Some(ref mut inner) => match &mut input {
-                        BytesType::RustyFile(f) => {
-                            let mut borrowed = f.borrow_mut();
-                            let f_in = &mut borrowed.inner;
-                            py.allow_threads(|| libcramjam::$codec::decompress(f_in, inner).map_err(Into::into))
-                        }
-                        _ => {
-                            let bytes = input.as_bytes();
-                            py.allow_threads(|| {
-                                libcramjam::$codec::decompress(&mut Cursor::new(bytes), inner).map_err(Into::into)
-                            })
-                        }
-                    },
+                    Some(inner) => {
+                        let mut output = $crate::limits::Limited::new(inner, limit);
+                        let result: PyResult<usize> = match &mut input {
+                            BytesType::RustyFile(f) => {
+                                let mut borrowed = f.borrow_mut();
+                                let f_in = &mut *borrowed;
+                                py.allow_threads(|| $decompress(f_in, &mut output $(, self.$option)*).map_err(Into::into))
+                            }
+                            BytesType::SpooledBuffer(b) => {
+                                let mut borrowed = b.borrow_mut();
+                                let b_in = &mut *borrowed;
+                                py.allow_threads(|| $decompress(b_in, &mut output $(, self.$option)*).map_err(Into::into))
+                            }
+                            _ => {
+                                let bytes = input.as_bytes();
+                                if bytes.is_empty() {
+                                    return Ok(0);
+                                }
+                                py.allow_threads(|| {
+                                    $decompress(&mut Cursor::new(bytes), &mut output $(, self.$option)*).map_err(Into::into)
+                                })
+                            }
+                        };
+                        result.map_err(|err| output.error(err))
+                    }
                     None => Err(DecompressionError::new_err(
                         "Appears `finish()` was called on this instance",
                     )),
@@ -372,17 +650,64 @@ macro_rules! make_decompressor {
                 }
             }
 
+            /// Read and remove up to `n` bytes, or all by default, from the front of the
+            /// decompressed data; with `readinto` and friends a Decompressor can be wrapped by
+            /// `io.TextIOWrapper` or given to parsers expecting a readable binary file, once it's
+            /// been fed the input.
+            #[pyo3(signature = (n=-1))]
+            pub fn read<'py>(&mut self, py: Python<'py>, n: isize) -> PyResult<Bound<'py, pyo3::types::PyBytes>> {
+                self.read_output(|output| $crate::io::stream_read(py, output, n))
+            }
+
+            /// Same as `read`, for `io` wrappers
+            #[pyo3(signature = (n=-1))]
+            pub fn read1<'py>(&mut self, py: Python<'py>, n: isize) -> PyResult<Bound<'py, pyo3::types::PyBytes>> {
+                self.read(py, n)
+            }
+
+            /// Read decompressed data into `output`, returning the number of bytes read.
+            pub fn readinto(&mut self, mut output: BytesType) -> PyResult<usize> {
+                let buf = output.as_bytes_mut()?;
+                self.read_output(|output| $crate::io::stream_readinto(output, buf))
+            }
+
+            /// Whether `read` may be called; `True` until the Decompressor is finished or closed.
+            pub fn readable(&self) -> bool {
+                !self.closed()
+            }
+
+            /// Always `False`
+            pub fn writable(&self) -> bool {
+                false
+            }
+
+            /// Always `False`
+            pub fn seekable(&self) -> bool {
+                false
+            }
+
+            /// Discard the Decompressor's state, like `finish()` without returning the output.
+            pub fn close(&mut self) {
+                self.inner = None;
+            }
+
+            /// Whether `finish()` or `close()` has been called.
+            #[getter]
+            pub fn closed(&self) -> bool {
+                self.inner.is_none()
+            }
+
             fn __len__(&self) -> usize {
                 self.len()
             }
-            fn __contains__(&self, py: Python, x: BytesType) -> bool {
-                let bytes = x.as_bytes();
-                py.allow_threads(|| {
+            fn __contains__(&self, py: Python, x: BytesType) -> PyResult<bool> {
+                let bytes = &*x.peek_bytes(usize::MAX)?;
+                Ok(py.allow_threads(|| {
                     self.inner
                         .as_ref()
                         .map(|c| c.get_ref().windows(bytes.len()).any(|w| w == bytes))
                         .unwrap_or_else(|| false)
-                })
+                }))
             }
             fn __repr__(&self) -> String {
                 format!("Decompressor<len={}>", self.len())
@@ -391,6 +716,124 @@ macro_rules! make_decompressor {
                 self.inner.is_some() && self.len() > 0
             }
         }
+
+        impl Decompressor {
+            /// Apply `read` to the decompressed data, keeping writes appending to what's left
+            fn read_output<T>(&mut self, read: impl FnOnce(Option<&mut Vec<u8>>) -> PyResult<T>) -> PyResult<T> {
+                let result = read(self.inner.as_mut().map(|c| c.get_mut()));
+                if let Some(inner) = self.inner.as_mut() {
+                    inner.set_position(inner.get_ref().len() as u64);
+                }
+                result
+            }
+        }
+    };
+}
+
+/// Add `compress_path` and `decompress_path`, de/compressing files by path with `$codec`,
+/// whose files are named with the `$extension` suffix.
+#[macro_export]
+macro_rules! make_path_helpers {
+    ($codec:literal, $extension:literal) => {
+        /// Compress the file at `src` into `dst`, by default `src` with the codec's extension
+        /// appended, returning the path written to. `dst` isn't replaced if it exists, unless
+        /// `overwrite=True`. Paths may be `str`, `bytes` or any `os.PathLike`.
+        ///
+        /// Python Example
+        /// --------------
+        /// ```python
+        #[doc = concat!(">>> cramjam.", $codec, ".compress_path(pathlib.Path('data.csv'), level=Optional[int])")]
+        #[doc = concat!("'data.csv.", $extension, "'")]
+        /// ```
+        #[pyfunction]
+        #[pyo3(signature = (src, dst=None, level=None, *, overwrite=false))]
+        pub fn compress_path(
+            py: Python,
+            src: $crate::io::PathLike,
+            dst: Option<$crate::io::PathLike>,
+            level: Option<i32>,
+            overwrite: bool,
+        ) -> PyResult<std::path::PathBuf> {
+            $crate::paths::compress_path(py, $codec, $extension, src, dst, level, overwrite)
+        }
+
+        /// Decompress the file at `src` into `dst`, by default `src` without the codec's
+        /// extension, returning the path written to. `dst` isn't replaced if it exists, unless
+        /// `overwrite=True`. Paths may be `str`, `bytes` or any `os.PathLike`.
+        ///
+        /// Python Example
+        /// --------------
+        /// ```python
+        #[doc = concat!(">>> cramjam.", $codec, ".decompress_path('data.csv.", $extension, "')")]
+        /// 'data.csv'
+        /// ```
+        #[pyfunction]
+        #[pyo3(signature = (src, dst=None, *, overwrite=false))]
+        pub fn decompress_path(
+            py: Python,
+            src: $crate::io::PathLike,
+            dst: Option<$crate::io::PathLike>,
+            overwrite: bool,
+        ) -> PyResult<std::path::PathBuf> {
+            $crate::paths::decompress_path(py, $codec, $extension, src, dst, overwrite)
+        }
+    };
+}
+
+/// Add `compress_batch` and `decompress_batch`, de/compressing a list of buffers with `$codec`
+/// on a pool of threads, the GIL released once for all of them; `level` is taken by codecs which
+/// have one.
+#[macro_export]
+macro_rules! make_batch_helpers {
+    ($codec:literal $(, $level:ident)?) => {
+        /// Compress each of `inputs`, any bytes-like objects or Buffers, on up to `nthreads` threads
+        /// (default: one per CPU), returning a Buffer for each, in the same order. Raises the
+        /// `CompressionError` of the first item which fails, whose `index` attribute is the item's.
+        ///
+        /// Python Example
+        /// --------------
+        /// ```python
+        #[doc = concat!(">>> pages = cramjam.", $codec, ".compress_batch([page0, page1, page2], nthreads=Optional[int])")]
+        /// ```
+        #[pyfunction]
+        #[pyo3(signature = (inputs, $($level=None,)? nthreads=None))]
+        pub fn compress_batch(
+            py: Python,
+            inputs: Vec<$crate::BytesType>,
+            $($level: Option<i32>,)?
+            nthreads: Option<usize>,
+        ) -> PyResult<Vec<$crate::io::RustyBuffer>> {
+            $crate::batch::codec_compress_batch(py, $codec, inputs, $crate::make_batch_helpers!(@level $($level)?), nthreads)
+        }
+
+        /// Decompress each of `inputs` on up to `nthreads` threads (default: one per CPU), returning
+        /// a Buffer for each, in the same order. Raises the `DecompressionError` of the first item
+        /// which fails, whose `index` attribute is the item's; see `cramjam.decompress_batch` to
+        /// get the outputs of the others as well. Each item's output is limited to
+        /// `max_expansion_ratio` times its size, or the process wide ratio, and `output_len_max`.
+        ///
+        /// Python Example
+        /// --------------
+        /// ```python
+        #[doc = concat!(">>> pages = cramjam.", $codec, ".decompress_batch(compressed_pages, nthreads=Optional[int], max_expansion_ratio=Optional[float])")]
+        /// ```
+        #[pyfunction]
+        #[pyo3(signature = (inputs, nthreads=None, *, max_expansion_ratio=None, output_len_max=None))]
+        pub fn decompress_batch(
+            py: Python,
+            inputs: Vec<$crate::BytesType>,
+            nthreads: Option<usize>,
+            max_expansion_ratio: Option<f64>,
+            output_len_max: Option<usize>,
+        ) -> PyResult<Vec<$crate::io::RustyBuffer>> {
+            $crate::batch::codec_decompress_batch(py, $codec, inputs, nthreads, max_expansion_ratio, output_len_max)
+        }
+    };
+    (@level) => {
+        None
+    };
+    (@level $level:ident) => {
+        $level
     };
 }
 
@@ -403,6 +846,38 @@ mod cramjam {
         m.add("__version__", env!("CARGO_PKG_VERSION"))?;
         m.add_class::<crate::io::RustyFile>()?;
         m.add_class::<crate::io::RustyBuffer>()?;
+        m.add_class::<crate::io::SpooledBuffer>()?;
+        register_io_abcs(m)?;
+        register_submodules(m, "cramjam")
+    }
+
+    /// Register `File` with `io.RawIOBase`, as `io.FileIO` is, and `Buffer` and `SpooledBuffer`
+    /// with `io.BufferedIOBase`, as `io.BytesIO` is, so they pass `isinstance(obj, io.IOBase)`
+    /// checks of libraries taking file objects.
+    fn register_io_abcs(module: &Bound<'_, PyModule>) -> PyResult<()> {
+        let io = module.py().import_bound("io")?;
+        for (class, abc) in [
+            ("File", "RawIOBase"),
+            ("Buffer", "BufferedIOBase"),
+            ("SpooledBuffer", "BufferedIOBase"),
+        ] {
+            io.getattr(abc)?.call_method1("register", (module.getattr(class)?,))?;
+        }
+        Ok(())
+    }
+
+    /// Put the submodules of `module` into `sys.modules` under `prefix`, so they may be imported
+    /// by name, ie. `from cramjam.experimental import blosc2`, which would otherwise fail as
+    /// cramjam isn't a package on disk.
+    fn register_submodules(module: &Bound<'_, PyModule>, prefix: &str) -> PyResult<()> {
+        let modules = module.py().import_bound("sys")?.getattr("modules")?;
+        for (name, value) in module.dict() {
+            if let Ok(submodule) = value.downcast::<PyModule>() {
+                let name = format!("{}.{}", prefix, name.extract::<&str>()?);
+                modules.set_item(&name, submodule)?;
+                register_submodules(submodule, &name)?;
+            }
+        }
         Ok(())
     }
 
@@ -412,42 +887,154 @@ mod cramjam {
     #[pymodule_export]
     use crate::DecompressionError;
 
+    #[pymodule_export]
+    use crate::exceptions::CramjamNotBuiltError;
+
+    #[pymodule_export]
+    use crate::exceptions::CodecUnavailableError;
+
     #[cfg(feature = "snappy")]
     #[pymodule_export]
     use crate::snappy::snappy;
 
+    #[cfg(not(feature = "snappy"))]
+    #[pymodule_export]
+    use crate::not_built::snappy;
+
     #[cfg(feature = "zstd")]
     #[pymodule_export]
     use crate::zstd::zstd;
 
+    #[cfg(not(feature = "zstd"))]
+    #[pymodule_export]
+    use crate::not_built::zstd;
+
     #[cfg(feature = "lz4")]
     #[pymodule_export]
     use crate::lz4::lz4;
 
+    #[cfg(not(feature = "lz4"))]
+    #[pymodule_export]
+    use crate::not_built::lz4;
+
+    #[cfg(feature = "lzf")]
+    #[pymodule_export]
+    use crate::lzf::lzf;
+
+    #[cfg(not(feature = "lzf"))]
+    #[pymodule_export]
+    use crate::not_built::lzf;
+
     #[cfg(any(feature = "brotli"))]
     #[pymodule_export]
     use crate::brotli::brotli;
 
+    #[cfg(not(any(feature = "brotli")))]
+    #[pymodule_export]
+    use crate::not_built::brotli;
+
     #[cfg(any(feature = "xz", feature = "xz-static", feature = "xz-shared"))]
     #[pymodule_export]
     use crate::xz::xz;
 
+    #[cfg(not(any(feature = "xz", feature = "xz-static", feature = "xz-shared")))]
+    #[pymodule_export]
+    use crate::not_built::xz;
+
+    #[cfg(any(feature = "xz", feature = "xz-static", feature = "xz-shared"))]
+    #[pymodule_export]
+    use crate::lzma::lzma;
+
+    #[cfg(not(any(feature = "xz", feature = "xz-static", feature = "xz-shared")))]
+    #[pymodule_export]
+    use crate::not_built::lzma;
+
     #[cfg(feature = "bzip2")]
     #[pymodule_export]
     use crate::bzip2::bzip2;
 
+    #[cfg(not(feature = "bzip2"))]
+    #[pymodule_export]
+    use crate::not_built::bzip2;
+
     #[cfg(any(feature = "gzip", feature = "gzip-static", feature = "gzip-shared"))]
     #[pymodule_export]
     use crate::gzip::gzip;
 
+    #[cfg(not(any(feature = "gzip", feature = "gzip-static", feature = "gzip-shared")))]
+    #[pymodule_export]
+    use crate::not_built::gzip;
+
     #[cfg(any(feature = "zlib", feature = "zlib-static", feature = "zlib-shared"))]
     #[pymodule_export]
     use crate::zlib::zlib;
 
+    #[cfg(not(any(feature = "zlib", feature = "zlib-static", feature = "zlib-shared")))]
+    #[pymodule_export]
+    use crate::not_built::zlib;
+
     #[cfg(any(feature = "deflate", feature = "deflate-static", feature = "deflate-shared"))]
     #[pymodule_export]
     use crate::deflate::deflate;
 
+    #[cfg(not(any(feature = "deflate", feature = "deflate-static", feature = "deflate-shared")))]
+    #[pymodule_export]
+    use crate::not_built::deflate;
+
+    #[pymodule_export]
+    use crate::store::store;
+
+    #[pymodule_export]
+    use crate::checksum::checksum;
+
     #[pymodule_export]
     use crate::experimental::experimental;
+
+    #[pymodule_export]
+    use crate::testing::testing;
+
+    #[pymodule_export]
+    use crate::framed::framed;
+
+    #[pymodule_export]
+    use crate::pipeline::{pipeline, pipeline_decompress};
+
+    #[pymodule_export]
+    use crate::batch::decompress_batch;
+
+    #[pymodule_export]
+    use crate::allocator::{get_output_allocator, set_output_allocator};
+
+    #[pymodule_export]
+    use crate::asyncio::asyncio;
+
+    #[pymodule_export]
+    use crate::auto::{auto, guess_codec};
+
+    #[pymodule_export]
+    use crate::dispatch::{compress, decompress};
+
+    #[pymodule_export]
+    use crate::features::{available_codecs, features};
+
+    #[pymodule_export]
+    use crate::info::info;
+
+    #[pymodule_export]
+    use crate::limits::{get_max_expansion_ratio, set_max_expansion_ratio};
+
+    #[pymodule_export]
+    use crate::lines::iter_lines;
+
+    #[pymodule_export]
+    use crate::ndarray::{compress_ndarray, decompress_ndarray};
+
+    #[pymodule_export]
+    use crate::optimize::optimize;
+
+    #[pymodule_export]
+    use crate::plugins::load_plugins;
+
+    #[pymodule_export]
+    use crate::stats::{enable_stats, stats};
 }
@@ -0,0 +1,205 @@
+//! Guard against decompression bombs by capping how far data may expand.
+//!
+//! A maximum expansion ratio, set process wide with `set_max_expansion_ratio` or per call with
+//! the `max_expansion_ratio` argument of each codec's `decompress`, limits the decompressed
+//! output to that multiple of the compressed input's size. Decompression stops with a
+//! `DecompressionError` as soon as the limit is crossed, before the output is allocated in full.
+//!
+//! Each `decompress` also takes `output_len_max`, capping the output at that many bytes however
+//! small the input, as services decoding untrusted input into fixed size memory want; when both
+//! are given, whichever is lower applies.
+//!
+//! Besides each codec's `decompress`, the limits apply to, and may be given to:
+//!  - `Decompressor.decompress`, relative to the input of each call
+//!  - `pipeline_decompress`, to the output of each codec stage
+//!  - `decompress_batch` and the codec modules' `decompress_batch`, to each item
+//!  - `asyncio.decompress`
+//!
+//! and the process wide ratio to each record of a `framed.Reader`. `decompress_into` is bounded
+//! by the size of its output already.
+//!
+//! ### Python Example
+//! ```python
+//! >>> cramjam.set_max_expansion_ratio(100)
+//! >>> cramjam.gzip.decompress(bomb)
+//! DecompressionError: Decompressed output exceeds 100x the compressed size ...
+//! >>> cramjam.gzip.decompress(trusted, max_expansion_ratio=float("inf"))  # lift it for one call
+//! >>> cramjam.gzip.decompress(untrusted, output_len_max=16 * 1024 * 1024)
+//! DecompressionError: Decompressed output exceeds output_len_max of 16777216 bytes ...
+//! ```
+use std::io::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::exceptions::DecompressionError;
+
+/// Bits of the process wide ratio as an `f64`, zero when unset
+static MAX_EXPANSION_RATIO: AtomicU64 = AtomicU64::new(0);
+
+/// Set the maximum expansion ratio applied to every decompression returning new output, or
+/// `None` to remove it.
+///
+/// Individual calls may override this with their own `max_expansion_ratio`, ie.
+/// `float("inf")` to allow any size for input which is trusted.
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> cramjam.set_max_expansion_ratio(Optional[float])
+/// ```
+#[pyfunction]
+#[pyo3(signature = (ratio))]
+pub fn set_max_expansion_ratio(ratio: Option<f64>) -> PyResult<()> {
+    let bits = match ratio {
+        Some(ratio) => validate(ratio)?.to_bits(),
+        None => 0,
+    };
+    MAX_EXPANSION_RATIO.store(bits, Ordering::SeqCst);
+    Ok(())
+}
+
+/// The maximum expansion ratio applied to every decompression returning new output, `None` if
+/// there isn't one.
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> cramjam.get_max_expansion_ratio()
+/// ```
+#[pyfunction]
+pub fn get_max_expansion_ratio() -> Option<f64> {
+    match MAX_EXPANSION_RATIO.load(Ordering::SeqCst) {
+        0 => None,
+        bits => Some(f64::from_bits(bits)),
+    }
+}
+
+fn validate(ratio: f64) -> PyResult<f64> {
+    if ratio > 0. {
+        Ok(ratio)
+    } else {
+        Err(PyValueError::new_err(format!(
+            "max_expansion_ratio must be greater than 0, got {}",
+            ratio
+        )))
+    }
+}
+
+/// Most bytes decompressing `input_len` bytes may produce
+#[derive(Clone, Copy)]
+pub(crate) struct Limit {
+    /// Expansion ratio the limit comes from, `None` if it's the `output_len_max` given
+    ratio: Option<f64>,
+    max_len: u64,
+}
+
+/// The limit for decompressing `input_len` bytes, from `ratio` if given, else the process wide
+/// ratio, or `output_len_max` if that's lower
+pub(crate) fn output_limit(
+    input_len: usize,
+    ratio: Option<f64>,
+    output_len_max: Option<usize>,
+) -> PyResult<Option<Limit>> {
+    let ratio = match ratio {
+        Some(ratio) => Some(validate(ratio)?),
+        None => get_max_expansion_ratio(),
+    };
+    // float -> int casts saturate, so an infinite ratio is effectively unlimited
+    let by_ratio = ratio.map(|ratio| Limit {
+        ratio: Some(ratio),
+        max_len: (input_len as f64 * ratio) as u64,
+    });
+    let by_len = output_len_max.map(|max_len| Limit {
+        ratio: None,
+        max_len: max_len as u64,
+    });
+    Ok(match (by_ratio, by_len) {
+        (Some(by_ratio), Some(by_len)) if by_ratio.max_len < by_len.max_len => Some(by_ratio),
+        (by_ratio, by_len) => by_len.or(by_ratio),
+    })
+}
+
+/// Writer which fails once more than the [`Limit`] has been written through it
+pub(crate) struct Limited<W> {
+    inner: W,
+    limit: Option<Limit>,
+    written: u64,
+    exceeded: bool,
+}
+
+impl<W: Write> Limited<W> {
+    pub(crate) fn new(inner: W, limit: Option<Limit>) -> Self {
+        Self {
+            inner,
+            limit,
+            written: 0,
+            exceeded: false,
+        }
+    }
+
+    /// `err` of the decompression written through this, raised as a `DecompressionError` if it
+    /// was the limit which stopped it, whatever error type the codec reports otherwise
+    pub(crate) fn error(&self, err: PyErr) -> PyErr {
+        match self.exceeded {
+            true => DecompressionError::from_err(err),
+            false => err,
+        }
+    }
+}
+
+impl<W: Write> Write for Limited<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if let Some(limit) = self.limit {
+            let result = limit.check(self.written + buf.len() as u64);
+            self.exceeded = result.is_err();
+            result?;
+        }
+        let n = self.inner.write(buf)?;
+        self.written += n as u64;
+        Ok(n)
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl Limit {
+    /// Fails if `len` bytes of output cross the limit
+    fn check(&self, len: u64) -> std::io::Result<()> {
+        if len <= self.max_len {
+            return Ok(());
+        }
+        Err(std::io::Error::other(match self.ratio {
+            Some(ratio) => format!(
+                "Decompressed output exceeds {}x the compressed size, the maximum expansion ratio; \
+                 pass a larger max_expansion_ratio if this input is trusted",
+                ratio
+            ),
+            None => format!("Decompressed output exceeds output_len_max of {} bytes", self.max_len),
+        }))
+    }
+}
+
+/// Output of a decompression which isn't written through a [`Limited`] writer, ie. the
+/// incremental `Decompressor`s decoding into their own buffer, counted as each step produces it
+pub(crate) struct Budget {
+    limit: Option<Limit>,
+    used: u64,
+}
+
+impl Budget {
+    pub(crate) fn new(limit: Option<Limit>) -> Self {
+        Self { limit, used: 0 }
+    }
+
+    /// Count `len` more bytes of output, failing once the [`Limit`] is crossed
+    pub(crate) fn spend(&mut self, len: usize) -> std::io::Result<()> {
+        self.used += len as u64;
+        match self.limit {
+            Some(limit) => limit.check(self.used),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! Iterate over the lines of compressed text, ie. logs, decompressing only as far as needed.
+//!
+//! ### Python Example
+//! ```python
+//! >>> with open("app.log.gz", "rb") as f:
+//! ...     errors = [line for line in cramjam.gzip.iter_lines(f) if "ERROR" in line]
+//! >>> for line in cramjam.iter_lines("zstd", compressed_bytes, encoding=None):
+//! ...     handle(line)  # bytes, as no encoding was given
+//! ```
+use std::io::{Cursor, Error, Read};
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use crate::codecs::{codec_reader, is_codec};
+use crate::exceptions::DecompressionError;
+use crate::BytesType;
+
+/// Decompressed bytes read at a time
+const READ_SIZE: usize = 64 * 1024;
+
+/// Iterate over the lines of `input` decompressed with `codec`, decompressing as lines are
+/// taken rather than all at once, so only the current chunk of output is held.
+///
+/// `input` is the compressed bytes-like data, a `cramjam.File`, or anything else with a `read`
+/// method such as a file opened for binary reading. Lines are split on `\n`, which each keeps
+/// like iterating over a file, and decoded with `encoding` and `errors` as `bytes.decode`
+/// would. `encoding` should be ASCII compatible, ie. utf-8 or latin-1; with `encoding=None`
+/// lines are given as `bytes`.
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> for line in cramjam.iter_lines("gzip", compressed, encoding="utf-8", errors="strict"):
+/// ...     print(line, end="")
+/// ```
+#[pyfunction]
+#[pyo3(signature = (codec, input, encoding=Some("utf-8".to_string()), errors="strict".to_string()))]
+pub fn iter_lines(
+    codec: &str,
+    input: &Bound<'_, PyAny>,
+    encoding: Option<String>,
+    errors: String,
+) -> PyResult<LineIterator> {
+    if !is_codec(codec) {
+        return Err(PyValueError::new_err(format!("Unknown codec: '{}'", codec)));
+    }
+    let source: Box<dyn Read + Send> = match input.extract::<BytesType>() {
+        Ok(BytesType::RustyFile(_) | BytesType::SpooledBuffer(_)) | Err(_) if input.hasattr("read")? => {
+            Box::new(PyReader(input.clone().unbind()))
+        }
+        Ok(data) => Box::new(Cursor::new(data.read_bytes()?.into_owned())),
+        Err(err) => return Err(err),
+    };
+    let reader = codec_reader(codec, source).map_err(DecompressionError::from_err)?;
+    Ok(LineIterator {
+        reader: Some(reader),
+        buffer: vec![],
+        pos: 0,
+        encoding,
+        errors,
+    })
+}
+
+/// Iterator over decompressed lines, see `cramjam.iter_lines`
+#[pyclass]
+pub struct LineIterator {
+    /// `None` once the decompressed data has all been read
+    reader: Option<Box<dyn Read + Send>>,
+    buffer: Vec<u8>,
+    pos: usize,
+    encoding: Option<String>,
+    errors: String,
+}
+
+#[pymethods]
+impl LineIterator {
+    fn __iter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __next__(&mut self, py: Python) -> PyResult<Option<PyObject>> {
+        let line = loop {
+            if let Some(i) = self.buffer[self.pos..].iter().position(|b| *b == b'\n') {
+                let line = &self.buffer[self.pos..self.pos + i + 1];
+                self.pos += i + 1;
+                break line;
+            }
+            if self.reader.is_none() {
+                if self.pos == self.buffer.len() {
+                    return Ok(None);
+                }
+                let line = &self.buffer[self.pos..];
+                self.pos = self.buffer.len();
+                break line;
+            }
+            self.fill(py)?;
+        };
+        let line = PyBytes::new_bound(py, line);
+        match self.encoding.as_deref() {
+            Some(encoding) => Ok(Some(line.call_method1("decode", (encoding, &self.errors))?.unbind())),
+            None => Ok(Some(line.into_any().unbind())),
+        }
+    }
+}
+
+impl LineIterator {
+    /// Decompress the next chunk into the buffer, dropping lines already taken
+    fn fill(&mut self, py: Python) -> PyResult<()> {
+        self.buffer.drain(..self.pos);
+        self.pos = 0;
+        let start = self.buffer.len();
+        self.buffer.resize(start + READ_SIZE, 0);
+        let (reader, buffer) = (self.reader.as_mut().expect("checked by caller"), &mut self.buffer);
+        let result = py.allow_threads(|| reader.read(&mut buffer[start..]));
+        let n_bytes = match result {
+            Ok(n_bytes) => n_bytes,
+            // errors raised by the input's `read` are passed on as they are
+            Err(err) if err.get_ref().is_some_and(|e| e.is::<PyErr>()) => return Err(err.into()),
+            Err(err) => return Err(DecompressionError::from_err(err)),
+        };
+        self.buffer.truncate(start + n_bytes);
+        if n_bytes == 0 {
+            self.reader = None;
+        }
+        Ok(())
+    }
+}
+
+/// Compressed input read from a Python object's `read` method
+struct PyReader(PyObject);
+
+impl Read for PyReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        Python::with_gil(|py| {
+            let chunk = self.0.call_method1(py, "read", (buf.len(),))?;
+            let chunk = chunk.extract::<BytesType>(py)?;
+            let chunk = chunk.read_bytes()?;
+            if chunk.len() > buf.len() {
+                return Err(PyValueError::new_err("read() returned more bytes than requested"));
+            }
+            buf[..chunk.len()].copy_from_slice(&chunk);
+            Ok(chunk.len())
+        })
+        .map_err(Error::other)
+    }
+}
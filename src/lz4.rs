@@ -6,61 +6,216 @@ use pyo3::prelude::*;
 pub mod lz4 {
 
     use crate::exceptions::{CompressionError, DecompressionError};
-    use crate::io::{AsBytes, RustyBuffer};
+    use crate::frames::FrameIterator;
+    use crate::io::{AsBytes, IntoLength, OutputBuffer, PathLike, RustyBuffer, RustyFile};
     use crate::BytesType;
+    use libcramjam::lz4::lz4::liblz4::BlockChecksum;
     use libcramjam::lz4::lz4::{BlockMode, ContentChecksum};
+    use pyo3::exceptions::PyValueError;
     use pyo3::prelude::*;
     use pyo3::PyResult;
-    use std::io::Cursor;
+    use std::fs::File;
+    use std::io::{BufReader, Cursor, Read, Write};
 
     const DEFAULT_COMPRESSION_LEVEL: u32 = 4;
+    // lz4frame accepts up to LZ4HC_CLEVEL_MAX, anything higher is treated as that
+    const MIN_COMPRESSION_LEVEL: u32 = 0;
+    const MAX_COMPRESSION_LEVEL: u32 = 12;
 
     /// LZ4 compression.
     ///
     /// Python Example
     /// --------------
     /// ```python
-    /// >>> # Note, output_len is currently ignored; underlying algorithm does not support reading to slice at this time
-    /// >>> cramjam.lz4.decompress(compressed_bytes, output_len=Optional[int])
+    /// >>> # output_len preallocates the output, which still grows if the data decompresses larger
+    /// >>> cramjam.lz4.decompress(compressed_bytes, output_len=Optional[int], max_expansion_ratio=Optional[float], into=Optional[Buffer])
     /// ```
     #[pyfunction]
-    #[pyo3(signature = (data, output_len=None))]
-    pub fn decompress(py: Python, data: BytesType, output_len: Option<usize>) -> PyResult<RustyBuffer> {
-        crate::generic!(py, libcramjam::lz4::decompress[data], output_len = output_len)
-            .map_err(DecompressionError::from_err)
+    #[pyo3(signature = (data, output_len=None, max_expansion_ratio=None, into=None, *, output_len_max=None))]
+    pub fn decompress<'py>(
+        py: Python<'py>,
+        data: BytesType,
+        output_len: Option<usize>,
+        max_expansion_ratio: Option<f64>,
+        into: Option<Bound<'py, RustyBuffer>>,
+        output_len_max: Option<usize>,
+    ) -> PyResult<OutputBuffer<'py>> {
+        crate::generic!(
+            py,
+            libcramjam::lz4::decompress[data],
+            max_expansion_ratio = max_expansion_ratio,
+            output_len_max = output_len_max,
+            into = into,
+            output_len = output_len
+        )
+        .map_err(DecompressionError::from_err)
     }
 
     /// LZ4 compression.
     ///
+    /// Frames carry an XXH32 checksum of each block and of the whole content. `unsafe_speed=True`
+    /// leaves both out, which is faster to compress and decompress, but corruption of the data
+    /// then goes undetected, or fails decompression only if it breaks the block structure; use
+    /// it only where the transport already guarantees integrity, ie. for ephemeral transfers.
+    ///
     /// Python Example
     /// --------------
     /// ```python
-    /// >>> # Note, output_len is currently ignored; underlying algorithm does not support reading to slice at this time
+    /// >>> # output_len preallocates the output, which still grows if the data compresses larger
     /// >>> cramjam.lz4.compress(b'some bytes here', output_len=Optional[int])
+    /// >>> cramjam.lz4.compress(b'some bytes here', unsafe_speed=True)  # without checksums
     /// ```
     #[pyfunction]
-    #[pyo3(signature = (data, level=None, output_len=None))]
+    #[pyo3(signature = (data, level=None, output_len=None, *, unsafe_speed=false))]
     pub fn compress(
         py: Python,
         data: BytesType,
         level: Option<u32>,
         output_len: Option<usize>,
+        unsafe_speed: bool,
     ) -> PyResult<RustyBuffer> {
-        crate::generic!(py, libcramjam::lz4::compress[data], output_len = output_len, level)
+        crate::generic!(py, compress_frame[data], output_len = output_len, level, unsafe_speed)
             .map_err(CompressionError::from_err)
     }
 
-    /// Compress directly into an output buffer
+    /// Compress directly into an output buffer, `unsafe_speed` as for `compress`
+    #[pyfunction]
+    #[pyo3(signature = (input, output, level=None, *, align=None, unsafe_speed=false))]
+    pub fn compress_into(
+        py: Python,
+        input: BytesType,
+        mut output: BytesType,
+        level: Option<u32>,
+        align: Option<usize>,
+        unsafe_speed: bool,
+    ) -> PyResult<IntoLength> {
+        let written = crate::generic!(py, compress_frame[input, output], level, unsafe_speed)
+            .map_err(CompressionError::from_err)?;
+        crate::io::pad_output(&mut output, written, align)
+    }
+
+    /// Frame compression as `libcramjam::lz4::compress`, without block and content checksums if
+    /// `unsafe_speed`
+    fn compress_frame<W: Write + ?Sized, R: Read>(
+        input: R,
+        output: &mut W,
+        level: Option<u32>,
+        unsafe_speed: bool,
+    ) -> std::io::Result<usize> {
+        if !unsafe_speed {
+            return libcramjam::lz4::compress(input, output, level);
+        }
+        let mut encoder = libcramjam::lz4::lz4::EncoderBuilder::new()
+            .level(level.unwrap_or(DEFAULT_COMPRESSION_LEVEL))
+            .auto_flush(true)
+            .favor_dec_speed(true)
+            .checksum(ContentChecksum::NoChecksum)
+            .block_checksum(BlockChecksum::NoBlockChecksum)
+            .build(vec![])?;
+        std::io::copy(&mut BufReader::new(input), &mut encoder)?;
+        let (compressed, result) = encoder.finish();
+        result?;
+        output.write_all(&compressed)?;
+        Ok(compressed.len())
+    }
+
+    /// Iterate over the frames of concatenated lz4 `data`, giving `(offset, length, decompressed)`
+    /// for each, where `offset` and `length` locate the compressed frame in `data` and
+    /// `decompressed` is a `cramjam.Buffer`; `decompress` stops after the first frame. Skippable
+    /// frames are passed over; a broken frame raises `DecompressionError` naming its offset,
+    /// after the frames before it were given.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> data = bytes(cramjam.lz4.compress(b'first')) + bytes(cramjam.lz4.compress(b'second'))
+    /// >>> [(offset, bytes(frame)) for offset, _, frame in cramjam.lz4.frames(data)]
+    /// [(0, b'first'), (28, b'second')]
+    /// ```
+    #[pyfunction]
+    pub fn frames(data: BytesType) -> PyResult<FrameIterator> {
+        FrameIterator::new(data, split_frame)
+    }
+
+    /// Split the lz4 frame starting `data`, see [`crate::frames::SplitFrame`]
+    fn split_frame(data: &[u8]) -> std::io::Result<(usize, Option<Vec<u8>>)> {
+        if let Some(len) = crate::frames::skippable_frame_len(data) {
+            return match len <= data.len() {
+                true => Ok((len, None)),
+                false => Err(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "Skippable frame is truncated",
+                )),
+            };
+        }
+        // the decoder reads no further than it needs to, so stops at the end of the frame
+        let mut decoder = libcramjam::lz4::lz4::Decoder::new(Cursor::new(data))?;
+        let mut frame = vec![];
+        std::io::copy(&mut decoder, &mut frame)?;
+        let (input, result) = decoder.finish();
+        result.map_err(|_| std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "Frame is truncated"))?;
+        Ok((input.position() as usize, Some(frame)))
+    }
+
+    /// Lowest compression `level` accepted.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.lz4.min_level()
+    /// 0
+    /// ```
     #[pyfunction]
-    #[pyo3(signature = (input, output, level=None))]
-    pub fn compress_into(py: Python, input: BytesType, mut output: BytesType, level: Option<u32>) -> PyResult<usize> {
-        crate::generic!(py, libcramjam::lz4::compress[input, output], level).map_err(CompressionError::from_err)
+    pub fn min_level() -> u32 {
+        MIN_COMPRESSION_LEVEL
+    }
+
+    /// Highest compression `level` accepted.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.lz4.max_level()
+    /// 12
+    /// ```
+    #[pyfunction]
+    pub fn max_level() -> u32 {
+        MAX_COMPRESSION_LEVEL
+    }
+
+    /// Compression `level` used by `compress` when none is given.
+    /// Levels from 3 use lz4's high compression mode.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.lz4.default_level()
+    /// 4
+    /// ```
+    #[pyfunction]
+    pub fn default_level() -> u32 {
+        DEFAULT_COMPRESSION_LEVEL
     }
 
     /// Decompress directly into an output buffer
     #[pyfunction]
     pub fn decompress_into(py: Python, input: BytesType, mut output: BytesType) -> PyResult<usize> {
-        crate::generic!(py, libcramjam::lz4::decompress[input, output]).map_err(DecompressionError::from_err)
+        crate::generic!(py, decompress: libcramjam::lz4::decompress[input, output]).map_err(DecompressionError::from_err)
+    }
+
+    /// Verify lz4 frame data is intact, decompressing all blocks and checking any block
+    /// and content checksums while discarding the output, like `lz4 -t`.
+    /// Returns the number of decompressed bytes.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.lz4.test(compressed_bytes)
+    /// 15
+    /// ```
+    #[pyfunction]
+    pub fn test(py: Python, data: BytesType) -> PyResult<usize> {
+        crate::generic!(py, libcramjam::lz4::decompress[data], discard).map_err(DecompressionError::from_err)
     }
 
     /// LZ4 _block_ decompression.
@@ -69,30 +224,127 @@ pub mod lz4 {
     /// then it's assumed `store_size=True` was used during compression and length will then be taken
     /// from the header, otherwise it's assumed `store_size=False` was used and no prepended size exists in input
     ///
+    /// A stored size is only trusted if the data could decompress to that length, as each compressed byte
+    /// expands to at most 255 bytes; otherwise `MissingStoredSizeError` is raised, and `CorruptBlockError`
+    /// if the data doesn't decompress to its stored size. Both are `DecompressionError`s. `max_output_len`
+    /// additionally caps the size allocated for the output, whether stored or given by `output_len`.
+    ///
     /// Python Example
     /// --------------
     /// ```python
-    /// >>> cramjam.lz4.decompress_block(compressed_bytes, output_len=Optional[int])
+    /// >>> cramjam.lz4.decompress_block(compressed_bytes, output_len=Optional[int], max_output_len=Optional[int])
     /// ```
     #[pyfunction]
-    #[allow(unused_variables)]
-    #[pyo3(signature = (data, output_len=None))]
-    pub fn decompress_block(py: Python, data: BytesType, output_len: Option<usize>) -> PyResult<RustyBuffer> {
-        let bytes = data.as_bytes();
+    #[pyo3(signature = (data, output_len=None, max_output_len=None))]
+    pub fn decompress_block(
+        py: Python,
+        data: BytesType,
+        output_len: Option<usize>,
+        max_output_len: Option<usize>,
+    ) -> PyResult<RustyBuffer> {
+        let data = data.read_bytes()?;
+        let bytes = &*data;
+        let (bytes, len, stored) = match output_len {
+            Some(n) => (bytes, n, false),
+            None => {
+                let len = stored_size(bytes).ok_or_else(|| {
+                    MissingStoredSizeError::new_err(
+                        "Data doesn't start with a plausible stored size; was it compressed with \
+                        `store_size=False`? If so, pass `output_len`",
+                    )
+                })?;
+                (&bytes[STORED_SIZE_LEN..], len, true)
+            }
+        };
+        if let Some(max) = max_output_len.filter(|max| len > *max) {
+            return Err(DecompressionError::new_err(format!(
+                "Output of {} bytes exceeds max_output_len of {}",
+                len, max
+            )));
+        }
 
         py.allow_threads(|| {
-            match output_len {
-                Some(n) => {
-                    let mut buf = vec![0u8; n];
-                    libcramjam::lz4::block::decompress_into(bytes, &mut buf, Some(false)).map(|_| buf)
-                }
-                None => libcramjam::lz4::block::decompress_vec(bytes),
+            let mut buf = vec![0u8; len];
+            match libcramjam::lz4::block::decompress_into(bytes, &mut buf, Some(false)) {
+                Ok(n) if stored && n != len => Err(CorruptBlockError::new_err(format!(
+                    "Decompressed to {} bytes, but its stored size is {}",
+                    n, len
+                ))),
+                Ok(_) => Ok(RustyBuffer::from(buf)),
+                Err(err) => Err(CorruptBlockError::new_err(err.to_string())),
             }
-            .map_err(DecompressionError::from_err)
-            .map(RustyBuffer::from)
         })
     }
 
+    /// LZ4 _block_ decompression of a bare block, without a stored size, known to decompress to
+    /// exactly `uncompressed_len` bytes, as wire protocols such as Kafka's store it alongside the
+    /// data.
+    ///
+    /// Unlike `output_len` of `decompress_block`, which is only an upper bound, a block which
+    /// doesn't decompress to exactly `uncompressed_len` raises `CorruptBlockError`, a
+    /// `DecompressionError`; see also `snappy.decompress_raw_with_len`.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.lz4.decompress_raw_with_len(compressed_block, uncompressed_len)
+    /// ```
+    #[pyfunction]
+    pub fn decompress_raw_with_len(py: Python, data: BytesType, uncompressed_len: usize) -> PyResult<RustyBuffer> {
+        let data = data.read_bytes()?;
+        let bytes = &*data;
+        if uncompressed_len > bytes.len().saturating_mul(MAX_BLOCK_EXPANSION) {
+            return Err(CorruptBlockError::new_err(format!(
+                "A block of {} bytes can't decompress to uncompressed_len of {}",
+                bytes.len(),
+                uncompressed_len
+            )));
+        }
+        py.allow_threads(|| {
+            let mut output = vec![0u8; uncompressed_len];
+            match libcramjam::lz4::block::decompress_into(bytes, &mut output, Some(false)) {
+                Ok(n) if n == uncompressed_len => Ok(RustyBuffer::from(output)),
+                Ok(n) => Err(CorruptBlockError::new_err(format!(
+                    "Decompressed to {} bytes, but uncompressed_len is {}",
+                    n, uncompressed_len
+                ))),
+                Err(err) => Err(CorruptBlockError::new_err(err.to_string())),
+            }
+        })
+    }
+
+    /// Whether `data` looks to start with the decompressed size, as prepended by
+    /// `compress_block(..., store_size=True)`, the default.
+    ///
+    /// This is a heuristic: `False` means it certainly doesn't, since the size is missing or couldn't
+    /// be produced from the rest of the data, but `True` only that the size is plausible.
+    /// A `File` is checked from its current position, which is left as it was.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.lz4.has_stored_size(cramjam.lz4.compress_block(b'some bytes here'))
+    /// True
+    /// ```
+    #[pyfunction]
+    pub fn has_stored_size(data: BytesType) -> PyResult<bool> {
+        Ok(stored_size(&data.peek_bytes(usize::MAX)?).is_some())
+    }
+
+    /// Length of the decompressed size prepended with `store_size=True`
+    const STORED_SIZE_LEN: usize = 4;
+
+    /// Most bytes one byte of an LZ4 block can decompress to, each extra byte of a match's length adding 255
+    const MAX_BLOCK_EXPANSION: usize = 255;
+
+    /// The size stored at the start of block `data`, if it could be the size of the rest decompressed
+    fn stored_size(data: &[u8]) -> Option<usize> {
+        let (prefix, block) = data.split_first_chunk::<STORED_SIZE_LEN>()?;
+        let size = usize::try_from(i32::from_le_bytes(*prefix)).ok()?;
+        // an empty input still compresses to a single token
+        (!block.is_empty() && size <= block.len().saturating_mul(MAX_BLOCK_EXPANSION)).then_some(size)
+    }
+
     /// LZ4 _block_ compression.
     ///
     /// The kwargs mostly follow the same definition found in [python-lz4 block.compress](https://python-lz4.readthedocs.io/en/stable/lz4.block.html#module-lz4.block)
@@ -121,7 +373,8 @@ pub mod lz4 {
         compression: Option<i32>,
         store_size: Option<bool>,
     ) -> PyResult<RustyBuffer> {
-        let bytes = data.as_bytes();
+        let data = data.read_bytes()?;
+        let bytes = &*data;
         py.allow_threads(|| {
             libcramjam::lz4::block::compress_vec(bytes, compression.map(|v| v as _), acceleration, store_size)
         })
@@ -138,7 +391,8 @@ pub mod lz4 {
     /// ```
     #[pyfunction]
     pub fn decompress_block_into(py: Python, input: BytesType, mut output: BytesType) -> PyResult<usize> {
-        let bytes = input.as_bytes();
+        let input = input.read_bytes()?;
+        let bytes = &*input;
         let out_bytes = output.as_bytes_mut()?;
         py.allow_threads(|| libcramjam::lz4::block::decompress_into(bytes, out_bytes, Some(true)))
             .map_err(DecompressionError::from_err)
@@ -173,7 +427,8 @@ pub mod lz4 {
         compression: Option<i32>,
         store_size: Option<bool>,
     ) -> PyResult<usize> {
-        let bytes = data.as_bytes();
+        let data = data.read_bytes()?;
+        let bytes = &*data;
         let out_bytes = output.as_bytes_mut()?;
         py.allow_threads(|| {
             libcramjam::lz4::block::compress_into(
@@ -201,66 +456,191 @@ pub mod lz4 {
         Ok(libcramjam::lz4::block::compress_bound(src.len(), Some(true)))
     }
 
+    /// Where a `Compressor` writes its compressed stream
+    enum Sink {
+        Memory(Cursor<Vec<u8>>),
+        File(File),
+    }
+
+    impl Sink {
+        /// Anything written which hasn't been returned to the caller yet; always empty for files
+        fn into_vec(self) -> std::io::Result<Vec<u8>> {
+            match self {
+                Sink::Memory(cursor) => Ok(cursor.into_inner()),
+                Sink::File(mut file) => file.flush().map(|_| vec![]),
+            }
+        }
+    }
+
+    impl Write for Sink {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            match self {
+                Sink::Memory(cursor) => cursor.write(buf),
+                Sink::File(file) => file.write(buf),
+            }
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            match self {
+                Sink::Memory(cursor) => cursor.flush(),
+                Sink::File(file) => file.flush(),
+            }
+        }
+    }
+
+    /// Destination given to `Compressor(output=...)`
+    #[derive(FromPyObject)]
+    pub enum CompressorOutput<'py> {
+        /// [`cramjam.File`](../io/struct.RustyFile.html), written from its current position
+        File(Bound<'py, RustyFile>),
+        /// Path of a file to create, or truncate if it exists
+        Path(PathLike),
+    }
+
+    mod _paths {
+        use super::*;
+        crate::make_path_helpers!("lz4", "lz4");
+    }
+    #[pymodule_export]
+    use _paths::{compress_path, decompress_path};
+
+    mod _batch {
+        use super::*;
+        crate::make_batch_helpers!("lz4", level);
+    }
+    #[pymodule_export]
+    use _batch::{compress_batch, decompress_batch};
+
     /// lz4 Compressor object for streaming compression
+    ///
+    /// By default compressed data is kept in memory until taken with `flush()` or `finish()`.
+    /// Given an `output` file or path, it's written there as `compress()` is called instead,
+    /// and `flush()`/`finish()` return an empty buffer, so streams much larger than memory
+    /// can be compressed.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> compressor = cramjam.lz4.Compressor(output="data.lz4")
+    /// >>> for chunk in chunks:
+    /// ...     compressor.compress(chunk)
+    /// >>> compressor.finish()  # completes the frame in data.lz4
+    /// ```
     #[pyclass]
     pub struct Compressor {
-        inner: Option<libcramjam::lz4::lz4::Encoder<Cursor<Vec<u8>>>>,
+        inner: Option<libcramjam::lz4::lz4::Encoder<Sink>>,
+        returns_output: bool,
     }
 
     #[pymethods]
     impl Compressor {
         /// Initialize a new `Compressor` instance.
+        ///
+        /// `unsafe_speed=True` leaves out the block and content checksums, as for `compress`.
+        ///
+        /// `returns_output=True` makes `compress` return the compressed bytes produced by each
+        /// call, rather than the number of bytes consumed, to stream output without `flush()`;
+        /// it can't be used with an `output`, which they're written to.
         #[new]
-        #[pyo3(signature = (level=None, content_checksum=None, block_linked=None))]
+        #[pyo3(signature = (level=None, content_checksum=None, block_linked=None, output=None, *, unsafe_speed=false, returns_output=false))]
         pub fn __init__(
             level: Option<u32>,
             content_checksum: Option<bool>,
             block_linked: Option<bool>,
+            output: Option<CompressorOutput>,
+            unsafe_speed: bool,
+            returns_output: bool,
         ) -> PyResult<Self> {
+            if returns_output && output.is_some() {
+                return Err(PyValueError::new_err(
+                    "returns_output=True can't be used with an output, which the compressed bytes are written to",
+                ));
+            }
+            if unsafe_speed && content_checksum == Some(true) {
+                return Err(PyValueError::new_err(
+                    "content_checksum=True can't be used with unsafe_speed=True, which leaves checksums out",
+                ));
+            }
+            let sink = match output {
+                None => Sink::Memory(Cursor::new(vec![])),
+                Some(CompressorOutput::Path(PathLike(path))) => Sink::File(File::create(path)?),
+                Some(CompressorOutput::File(file)) => {
+                    let mut file = file.borrow_mut();
+                    file.settle()?;
+                    // shares the position with the cramjam.File
+                    Sink::File(file.handle()?.try_clone()?)
+                }
+            };
             let inner = libcramjam::lz4::lz4::EncoderBuilder::new()
                 .auto_flush(true)
                 .level(level.unwrap_or(DEFAULT_COMPRESSION_LEVEL))
                 .checksum(match content_checksum {
                     Some(false) => ContentChecksum::NoChecksum,
+                    _ if unsafe_speed => ContentChecksum::NoChecksum,
                     _ => ContentChecksum::ChecksumEnabled,
                 })
+                .block_checksum(match unsafe_speed {
+                    true => BlockChecksum::NoBlockChecksum,
+                    false => BlockChecksum::BlockChecksumEnabled,
+                })
                 .block_mode(match block_linked {
                     Some(false) => BlockMode::Independent,
                     _ => BlockMode::Linked,
                 })
-                .build(Cursor::new(vec![]))?;
-            Ok(Self { inner: Some(inner) })
+                .build(sink)?;
+            Ok(Self {
+                inner: Some(inner),
+                returns_output,
+            })
         }
 
         /// Compress input into the current compressor's stream.
-        pub fn compress(&mut self, input: &[u8]) -> PyResult<usize> {
-            crate::io::stream_compress(&mut self.inner, input)
+        pub fn compress(&mut self, py: Python, input: &[u8]) -> PyResult<PyObject> {
+            crate::io::stream_compress_output(py, &mut self.inner, input, self.returns_output, 0, memory_output)
         }
 
         /// Flush and return current compressed stream
-        #[allow(mutable_transmutes)] // TODO: feature req to lz4 to get mut ref to writer
-        pub fn flush(&mut self) -> PyResult<RustyBuffer> {
-            crate::io::stream_flush(&mut self.inner, |e| {
-                let writer = e.writer();
-                // no other mutations to buf b/c it'll be truncated and return immediately after this
-                unsafe { std::mem::transmute::<&Cursor<Vec<u8>>, &mut Cursor<Vec<u8>>>(writer) }
-            })
+        pub fn flush(&mut self, py: Python) -> PyResult<RustyBuffer> {
+            if let Some(Sink::File(_)) = self.inner.as_ref().map(|e| e.writer()) {
+                let encoder = self.inner.as_mut().unwrap();
+                encoder.flush().map_err(CompressionError::from_err)?;
+                return Ok(RustyBuffer::from(vec![]));
+            }
+            py.allow_threads(|| crate::io::stream_flush(&mut self.inner, 0, memory_output))
         }
 
         /// Consume the current compressor state and return the compressed stream
         /// **NB** The compressor will not be usable after this method is called.
-        pub fn finish(&mut self) -> PyResult<RustyBuffer> {
-            crate::io::stream_finish(&mut self.inner, |inner| {
-                let (cursor, result) = inner.finish();
-                result.map(|_| cursor.into_inner())
+        pub fn finish(&mut self, py: Python) -> PyResult<RustyBuffer> {
+            py.allow_threads(|| {
+                crate::io::stream_finish(&mut self.inner, |inner| {
+                    let (sink, result) = inner.finish();
+                    result.and_then(|_| sink.into_vec())
+                })
             })
         }
     }
 
+    /// The in memory output of a `Compressor` without an `output`
+    #[allow(mutable_transmutes)] // TODO: feature req to lz4 to get mut ref to writer
+    fn memory_output(encoder: &mut libcramjam::lz4::lz4::Encoder<Sink>) -> &mut Cursor<Vec<u8>> {
+        let writer = encoder.writer();
+        // no other mutations to buf b/c it'll be truncated and return immediately after this
+        match unsafe { std::mem::transmute::<&Sink, &mut Sink>(writer) } {
+            Sink::Memory(cursor) => cursor,
+            Sink::File(_) => unreachable!("file output is never taken"),
+        }
+    }
+
     mod _decompressor {
         use super::*;
         crate::make_decompressor!(lz4);
     }
     #[pymodule_export]
     use _decompressor::Decompressor;
+
+    #[pymodule_export]
+    use crate::exceptions::MissingStoredSizeError;
+
+    #[pymodule_export]
+    use crate::exceptions::CorruptBlockError;
 }
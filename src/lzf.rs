@@ -0,0 +1,217 @@
+//! lzf de/compression interface
+//!
+//! LZF is the format of liblzf, as found in Redis RDB dumps and HDF5 / OpenEXR tooling: a bare
+//! sequence of literal runs and back references, without a header, checksum or the decompressed
+//! length, so it ends where the data does. libcramjam has no backend for it, and the format is
+//! small enough to implement here.
+use pyo3::prelude::*;
+use std::io::{self, Error, ErrorKind, Read, Write};
+
+/// Longest run of literals a single control byte covers
+const MAX_LITERAL: usize = 1 << 5;
+
+/// Furthest back a reference reaches, and so the decompressed data kept to resolve them
+const MAX_OFFSET: usize = 1 << 13;
+
+/// Longest match a reference copies: 7 from the control byte, 255 from the next, plus 2
+const MAX_MATCH: usize = (1 << 8) + (1 << 3);
+
+/// Shortest match worth a reference, it taking 2 bytes itself
+const MIN_MATCH: usize = 3;
+
+/// Bits of the hash of the next `MIN_MATCH` bytes, looking up where they were last seen
+const HASH_LOG: u32 = 14;
+
+/// Decompressed bytes gathered before being written out, beyond the `MAX_OFFSET` kept
+const FLUSH_LEN: usize = 64 * 1024;
+
+fn invalid(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("Invalid lzf data: {}", msg))
+}
+
+fn hash(bytes: &[u8]) -> usize {
+    let value = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0]);
+    (value.wrapping_mul(2654435761) >> (32 - HASH_LOG)) as usize
+}
+
+/// Write `literals` as runs of at most `MAX_LITERAL`, each preceded by its length less one
+fn push_literals(literals: &[u8], output: &mut Vec<u8>) {
+    for run in literals.chunks(MAX_LITERAL) {
+        output.push((run.len() - 1) as u8);
+        output.extend_from_slice(run);
+    }
+}
+
+/// Compress all of `input` into `output`, giving the number of bytes written
+pub fn compress<W: Write + ?Sized, R: Read>(mut input: R, output: &mut W) -> io::Result<usize> {
+    let mut data = vec![];
+    input.read_to_end(&mut data)?;
+
+    let mut compressed = Vec::with_capacity(data.len() + data.len() / MAX_LITERAL + 1);
+    // position + 1 each hash was last seen at, 0 for never
+    let mut table = vec![0usize; 1 << HASH_LOG];
+    let (mut pos, mut literal_start) = (0, 0);
+    while pos + MIN_MATCH <= data.len() {
+        let candidate = std::mem::replace(&mut table[hash(&data[pos..])], pos + 1);
+        let matched = candidate
+            .checked_sub(1)
+            .filter(|&start| pos - start <= MAX_OFFSET && data[start..start + MIN_MATCH] == data[pos..pos + MIN_MATCH]);
+        let Some(start) = matched else {
+            pos += 1;
+            continue;
+        };
+        let longest = (data.len() - pos).min(MAX_MATCH);
+        let mut len = MIN_MATCH;
+        while len < longest && data[start + len] == data[pos + len] {
+            len += 1;
+        }
+
+        push_literals(&data[literal_start..pos], &mut compressed);
+        let (len_code, offset) = (len - 2, pos - start - 1);
+        if len_code < 7 {
+            compressed.push(((len_code << 5) | (offset >> 8)) as u8);
+        } else {
+            compressed.push(((7 << 5) | (offset >> 8)) as u8);
+            compressed.push((len_code - 7) as u8);
+        }
+        compressed.push(offset as u8);
+
+        // so later matches may start within this one
+        for inner in pos + 1..(pos + len).min(data.len() + 1 - MIN_MATCH) {
+            table[hash(&data[inner..])] = inner + 1;
+        }
+        pos += len;
+        literal_start = pos;
+    }
+    push_literals(&data[literal_start..], &mut compressed);
+
+    output.write_all(&compressed)?;
+    Ok(compressed.len())
+}
+
+/// Decompress all of `input` into `output`, giving the number of bytes written. Decompressed
+/// data is written out as it goes, keeping only what later references may reach.
+pub fn decompress<W: Write + ?Sized, R: Read>(mut input: R, output: &mut W) -> io::Result<usize> {
+    let mut data = vec![];
+    input.read_to_end(&mut data)?;
+
+    let mut window = Vec::with_capacity(FLUSH_LEN + MAX_OFFSET + MAX_MATCH);
+    let (mut pos, mut written) = (0, 0);
+    while let Some(&control) = data.get(pos) {
+        let control = control as usize;
+        pos += 1;
+        if control < MAX_LITERAL {
+            let literals = data
+                .get(pos..pos + control + 1)
+                .ok_or_else(|| invalid("literal run past the end of the data"))?;
+            window.extend_from_slice(literals);
+            pos += literals.len();
+        } else {
+            let truncated = || invalid("back reference past the end of the data");
+            let mut len = control >> 5;
+            if len == 7 {
+                len += *data.get(pos).ok_or_else(truncated)? as usize;
+                pos += 1;
+            }
+            let low = *data.get(pos).ok_or_else(truncated)? as usize;
+            pos += 1;
+            let distance = ((control & 0x1f) << 8 | low) + 1;
+            let start = window
+                .len()
+                .checked_sub(distance)
+                .ok_or_else(|| invalid("back reference before the start of the data"))?;
+            let len = len + 2;
+            if distance >= len {
+                window.extend_from_within(start..start + len);
+            } else {
+                // overlapping, repeating what it copies
+                for i in start..start + len {
+                    window.push(window[i]);
+                }
+            }
+        }
+        if window.len() >= FLUSH_LEN + MAX_OFFSET {
+            let flushed = window.len() - MAX_OFFSET;
+            output.write_all(&window[..flushed])?;
+            window.drain(..flushed);
+            written += flushed;
+        }
+    }
+    output.write_all(&window)?;
+    Ok(written + window.len())
+}
+
+/// lzf de/compression interface
+#[pymodule]
+pub mod lzf {
+
+    use crate::exceptions::{CompressionError, DecompressionError};
+    use crate::io::{AsBytes, IntoLength, OutputBuffer, RustyBuffer};
+    use crate::BytesType;
+    use pyo3::prelude::*;
+    use pyo3::PyResult;
+    use std::io::Cursor;
+
+    /// LZF decompression.
+    ///
+    /// The decompressed length isn't part of the format, ie. Redis stores it alongside, so
+    /// `output_len` is only a hint to preallocate.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.lzf.decompress(compressed_bytes, output_len=Optional[int], max_expansion_ratio=Optional[float], into=Optional[Buffer])
+    /// ```
+    #[pyfunction]
+    #[pyo3(signature = (data, output_len=None, max_expansion_ratio=None, into=None, *, output_len_max=None))]
+    pub fn decompress<'py>(
+        py: Python<'py>,
+        data: BytesType,
+        output_len: Option<usize>,
+        max_expansion_ratio: Option<f64>,
+        into: Option<Bound<'py, RustyBuffer>>,
+        output_len_max: Option<usize>,
+    ) -> PyResult<OutputBuffer<'py>> {
+        crate::generic!(
+            py,
+            crate::lzf::decompress[data],
+            max_expansion_ratio = max_expansion_ratio,
+            output_len_max = output_len_max,
+            into = into,
+            output_len = output_len
+        )
+        .map_err(DecompressionError::from_err)
+    }
+
+    /// LZF compression.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.lzf.compress(b'some bytes here', output_len=Optional[int])
+    /// ```
+    #[pyfunction]
+    #[pyo3(signature = (data, output_len=None))]
+    pub fn compress(py: Python, data: BytesType, output_len: Option<usize>) -> PyResult<RustyBuffer> {
+        crate::generic!(py, crate::lzf::compress[data], output_len = output_len).map_err(CompressionError::from_err)
+    }
+
+    /// Compress directly into an output buffer
+    #[pyfunction]
+    #[pyo3(signature = (input, output, *, align=None))]
+    pub fn compress_into(
+        py: Python,
+        input: BytesType,
+        mut output: BytesType,
+        align: Option<usize>,
+    ) -> PyResult<IntoLength> {
+        let written = crate::generic!(py, crate::lzf::compress[input, output]).map_err(CompressionError::from_err)?;
+        crate::io::pad_output(&mut output, written, align)
+    }
+
+    /// Decompress directly into an output buffer
+    #[pyfunction]
+    pub fn decompress_into(py: Python, input: BytesType, mut output: BytesType) -> PyResult<usize> {
+        crate::generic!(py, decompress: crate::lzf::decompress[input, output]).map_err(DecompressionError::from_err)
+    }
+}
@@ -0,0 +1,348 @@
+//! Python `lzma` module compatible de/compression interface
+use pyo3::prelude::*;
+
+/// Python `lzma` module compatible de/compression interface, over the same implementation as
+/// `cramjam.xz`. Functions take the stdlib's integer constants and filter chain specifiers, so
+/// code written against `lzma` can switch by changing its import.
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> from cramjam import lzma
+/// >>> filters = [{"id": lzma.FILTER_X86}, {"id": lzma.FILTER_LZMA2, "preset": 9 | lzma.PRESET_EXTREME}]
+/// >>> compressed = lzma.compress(b'some bytes here', format=lzma.FORMAT_XZ, filters=filters)
+/// >>> bytes(lzma.decompress(compressed))
+/// b'some bytes here'
+/// ```
+#[pymodule]
+pub mod lzma {
+
+    use crate::exceptions::{CompressionError, DecompressionError};
+    use crate::io::{AsBytes, RustyBuffer};
+    use crate::xz::xz::{Check, Filter, FilterChain, FilterChainItem, Format, MatchFinder, Mode, Options};
+    use crate::BytesType;
+    use libcramjam::xz::xz2::read::XzDecoder;
+    use libcramjam::xz::xz2::stream::{Stream, CONCATENATED};
+    use pyo3::exceptions::PyValueError;
+    use pyo3::prelude::*;
+    use pyo3::types::PyDict;
+    use pyo3::PyResult;
+    use std::io::{Read, Write};
+
+    const FORMAT_AUTO: u32 = 0;
+    const FORMAT_XZ: u32 = 1;
+    const FORMAT_ALONE: u32 = 2;
+    const FORMAT_RAW: u32 = 3;
+
+    const CHECK_NONE: i32 = 0;
+    const CHECK_CRC32: i32 = 1;
+    const CHECK_CRC64: i32 = 4;
+    const CHECK_SHA256: i32 = 10;
+
+    const FILTER_LZMA1: u64 = 0x4000000000000001;
+    const FILTER_LZMA2: u64 = 0x21;
+    const FILTER_DELTA: u64 = 0x03;
+    const FILTER_X86: u64 = 0x04;
+    const FILTER_POWERPC: u64 = 0x05;
+    const FILTER_IA64: u64 = 0x06;
+    const FILTER_ARM: u64 = 0x07;
+    const FILTER_ARMTHUMB: u64 = 0x08;
+    const FILTER_SPARC: u64 = 0x09;
+
+    const MF_HC3: u32 = 0x03;
+    const MF_HC4: u32 = 0x04;
+    const MF_BT2: u32 = 0x12;
+    const MF_BT3: u32 = 0x13;
+    const MF_BT4: u32 = 0x14;
+
+    const MODE_FAST: u32 = 1;
+    const MODE_NORMAL: u32 = 2;
+
+    const PRESET_DEFAULT: u32 = 6;
+    const PRESET_EXTREME: u32 = 0x80000000;
+
+    #[pymodule_init]
+    fn init(m: &Bound<'_, PyModule>) -> PyResult<()> {
+        m.add("FORMAT_AUTO", FORMAT_AUTO)?;
+        m.add("FORMAT_XZ", FORMAT_XZ)?;
+        m.add("FORMAT_ALONE", FORMAT_ALONE)?;
+        m.add("FORMAT_RAW", FORMAT_RAW)?;
+        m.add("CHECK_NONE", CHECK_NONE)?;
+        m.add("CHECK_CRC32", CHECK_CRC32)?;
+        m.add("CHECK_CRC64", CHECK_CRC64)?;
+        m.add("CHECK_SHA256", CHECK_SHA256)?;
+        m.add("FILTER_LZMA1", FILTER_LZMA1)?;
+        m.add("FILTER_LZMA2", FILTER_LZMA2)?;
+        m.add("FILTER_DELTA", FILTER_DELTA)?;
+        m.add("FILTER_X86", FILTER_X86)?;
+        m.add("FILTER_POWERPC", FILTER_POWERPC)?;
+        m.add("FILTER_IA64", FILTER_IA64)?;
+        m.add("FILTER_ARM", FILTER_ARM)?;
+        m.add("FILTER_ARMTHUMB", FILTER_ARMTHUMB)?;
+        m.add("FILTER_SPARC", FILTER_SPARC)?;
+        m.add("MF_HC3", MF_HC3)?;
+        m.add("MF_HC4", MF_HC4)?;
+        m.add("MF_BT2", MF_BT2)?;
+        m.add("MF_BT3", MF_BT3)?;
+        m.add("MF_BT4", MF_BT4)?;
+        m.add("MODE_FAST", MODE_FAST)?;
+        m.add("MODE_NORMAL", MODE_NORMAL)?;
+        m.add("PRESET_DEFAULT", PRESET_DEFAULT)?;
+        m.add("PRESET_EXTREME", PRESET_EXTREME)?;
+        Ok(())
+    }
+
+    /// Compression, with the arguments of `lzma.compress`.
+    ///
+    /// `FORMAT_RAW` isn't supported, nor is the delta filter.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.lzma.compress(b'some bytes here', format=cramjam.lzma.FORMAT_XZ, check=-1, preset=None, filters=None)
+    /// ```
+    #[pyfunction]
+    #[pyo3(signature = (data, format=FORMAT_XZ, check=-1, preset=None, filters=None, output_len=None))]
+    pub fn compress(
+        py: Python,
+        data: BytesType,
+        format: u32,
+        check: i32,
+        preset: Option<u32>,
+        filters: Option<Vec<Bound<'_, PyDict>>>,
+        output_len: Option<usize>,
+    ) -> PyResult<RustyBuffer> {
+        let Encoder {
+            format,
+            check,
+            filters,
+            options,
+        } = Encoder::new(format, check, preset, filters)?;
+        crate::generic!(
+            py,
+            libcramjam::xz::compress[data],
+            output_len = output_len,
+            preset,
+            format,
+            check,
+            filters,
+            options
+        )
+        .map_err(CompressionError::from_err)
+    }
+
+    /// Decompression, with the arguments of `lzma.decompress`. Concatenated streams are
+    /// decompressed one after the other.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.lzma.decompress(compressed_bytes, format=cramjam.lzma.FORMAT_AUTO, memlimit=Optional[int])
+    /// ```
+    #[pyfunction]
+    #[pyo3(signature = (data, format=FORMAT_AUTO, memlimit=None, filters=None, output_len=None, max_expansion_ratio=None, *, output_len_max=None))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn decompress(
+        py: Python,
+        data: BytesType,
+        format: u32,
+        memlimit: Option<u64>,
+        filters: Option<Vec<Bound<'_, PyDict>>>,
+        output_len: Option<usize>,
+        max_expansion_ratio: Option<f64>,
+        output_len_max: Option<usize>,
+    ) -> PyResult<RustyBuffer> {
+        match format {
+            FORMAT_AUTO | FORMAT_XZ | FORMAT_ALONE if filters.is_none() => (),
+            FORMAT_AUTO | FORMAT_XZ | FORMAT_ALONE => {
+                return Err(PyValueError::new_err("Cannot specify filters except with FORMAT_RAW"))
+            }
+            FORMAT_RAW => return Err(raw_unsupported()),
+            _ => return Err(PyValueError::new_err(format!("Invalid container format: {}", format))),
+        }
+        let memlimit = memlimit.unwrap_or(u64::MAX);
+        crate::generic!(
+            py,
+            decompress_format[data],
+            max_expansion_ratio = max_expansion_ratio,
+            output_len_max = output_len_max,
+            output_len = output_len,
+            format,
+            memlimit
+        )
+        .map_err(DecompressionError::from_err)
+    }
+
+    fn decompress_format<W: Write + ?Sized, R: Read>(
+        input: R,
+        output: &mut W,
+        format: u32,
+        memlimit: u64,
+    ) -> std::io::Result<usize> {
+        let stream = match format {
+            FORMAT_XZ => Stream::new_stream_decoder(memlimit, CONCATENATED),
+            FORMAT_ALONE => Stream::new_lzma_decoder(memlimit),
+            _ => Stream::new_auto_decoder(memlimit, CONCATENATED),
+        }?;
+        let mut decoder = XzDecoder::new_stream(input, stream);
+        std::io::copy(&mut decoder, output).map(|n| n as usize)
+    }
+
+    fn raw_unsupported() -> PyErr {
+        PyValueError::new_err("FORMAT_RAW isn't supported, only FORMAT_XZ and FORMAT_ALONE")
+    }
+
+    /// `lzma.compress` arguments, as the arguments of `libcramjam::xz::compress`
+    struct Encoder {
+        format: Option<Format>,
+        check: Option<Check>,
+        filters: Option<FilterChain>,
+        options: Option<FilterChainItem>,
+    }
+
+    impl Encoder {
+        fn new(format: u32, check: i32, preset: Option<u32>, specs: Option<Vec<Bound<'_, PyDict>>>) -> PyResult<Self> {
+            if preset.is_some() && specs.is_some() {
+                return Err(PyValueError::new_err("Cannot specify both preset and filter chain"));
+            }
+            let items = specs
+                .map(|specs| specs.iter().map(filter_chain_item).collect::<PyResult<Vec<_>>>())
+                .transpose()?;
+            match format {
+                FORMAT_XZ => {
+                    let check = Some(match check {
+                        -1 | CHECK_CRC64 => Check::Crc64,
+                        CHECK_NONE => Check::None,
+                        CHECK_CRC32 => Check::Crc32,
+                        CHECK_SHA256 => Check::Sha256,
+                        _ => return Err(PyValueError::new_err(format!("Invalid integrity check: {}", check))),
+                    });
+                    Ok(match items {
+                        // libcramjam's RAW format is an xz stream with its own filter chain
+                        Some(items) => {
+                            let mut chain = FilterChain::__init__();
+                            items.into_iter().for_each(|(_, item)| chain.append_filter(item));
+                            Self {
+                                format: Some(Format::RAW),
+                                check,
+                                filters: Some(chain),
+                                options: None,
+                            }
+                        }
+                        None => Self {
+                            format: Some(Format::XZ),
+                            check,
+                            filters: None,
+                            options: None,
+                        },
+                    })
+                }
+                FORMAT_ALONE => {
+                    if !matches!(check, -1 | CHECK_NONE) {
+                        return Err(PyValueError::new_err(format!(
+                            "Invalid integrity check for FORMAT_ALONE: {}",
+                            check
+                        )));
+                    }
+                    let options = match items.map(|mut items| (items.len(), items.pop())) {
+                        None => None,
+                        Some((1, Some((Filter::Lzma1, item)))) => Some(item),
+                        Some(_) => {
+                            return Err(PyValueError::new_err(
+                                "Invalid filter chain for FORMAT_ALONE - must be a single LZMA1 filter",
+                            ))
+                        }
+                    };
+                    Ok(Self {
+                        format: Some(Format::ALONE),
+                        check: None,
+                        filters: None,
+                        options,
+                    })
+                }
+                FORMAT_RAW => Err(raw_unsupported()),
+                _ => Err(PyValueError::new_err(format!("Invalid container format: {}", format))),
+            }
+        }
+    }
+
+    /// Convert one of `lzma`'s filter specifiers, ie. `{"id": FILTER_LZMA2, "preset": 9}`
+    fn filter_chain_item(spec: &Bound<'_, PyDict>) -> PyResult<(Filter, FilterChainItem)> {
+        let id = spec
+            .get_item("id")?
+            .ok_or_else(|| PyValueError::new_err("Filter specifier must have an \"id\" entry"))?
+            .extract::<u64>()?;
+        let filter = match id {
+            FILTER_LZMA1 => Filter::Lzma1,
+            FILTER_LZMA2 => Filter::Lzma2,
+            FILTER_X86 => Filter::X86,
+            FILTER_POWERPC => Filter::PowerPC,
+            FILTER_IA64 => Filter::Ia64,
+            FILTER_ARM => Filter::Arm,
+            FILTER_ARMTHUMB => Filter::ArmThumb,
+            FILTER_SPARC => Filter::Sparc,
+            FILTER_DELTA => return Err(PyValueError::new_err("The delta filter isn't supported")),
+            _ => return Err(PyValueError::new_err(format!("Invalid filter ID: {}", id))),
+        };
+        let is_lzma = matches!(filter, Filter::Lzma1 | Filter::Lzma2);
+
+        let mut options = Options::__init__();
+        for (key, value) in spec.iter() {
+            let key = key.extract::<String>()?;
+            match key.as_str() {
+                "id" => (),
+                "preset" if is_lzma => {
+                    let preset = value.extract::<u32>()?;
+                    // validate here, rather than panic when the chain is used
+                    libcramjam::xz::LzmaOptions::new_preset(preset)
+                        .map_err(|e| PyValueError::new_err(format!("Invalid preset {}: {}", preset, e)))?;
+                    options.set_preset(preset);
+                }
+                "dict_size" if is_lzma => {
+                    options.set_dict_size(value.extract()?);
+                }
+                "lc" if is_lzma => {
+                    options.set_lc(value.extract()?);
+                }
+                "lp" if is_lzma => {
+                    options.set_lp(value.extract()?);
+                }
+                "pb" if is_lzma => {
+                    options.set_pb(value.extract()?);
+                }
+                "nice_len" if is_lzma => {
+                    options.set_nice_len(value.extract()?);
+                }
+                "depth" if is_lzma => {
+                    options.set_depth(value.extract()?);
+                }
+                "mode" if is_lzma => {
+                    options.set_mode(match value.extract::<u32>()? {
+                        MODE_FAST => Mode::Fast,
+                        MODE_NORMAL => Mode::Normal,
+                        mode => return Err(PyValueError::new_err(format!("Invalid mode: {}", mode))),
+                    });
+                }
+                "mf" if is_lzma => {
+                    options.set_mf(match value.extract::<u32>()? {
+                        MF_HC3 => MatchFinder::HashChain3,
+                        MF_HC4 => MatchFinder::HashChain4,
+                        MF_BT2 => MatchFinder::BinaryTree2,
+                        MF_BT3 => MatchFinder::BinaryTree3,
+                        MF_BT4 => MatchFinder::BinaryTree4,
+                        mf => return Err(PyValueError::new_err(format!("Invalid match finder: {}", mf))),
+                    });
+                }
+                // BCJ filters are only supported at their default start offset
+                "start_offset" if !is_lzma && value.extract::<u64>()? == 0 => (),
+                _ => {
+                    return Err(PyValueError::new_err(format!(
+                        "Invalid filter specifier for filter {:#x}: '{}'",
+                        id, key
+                    )))
+                }
+            }
+        }
+        Ok((filter.clone(), FilterChainItem::__init__(filter, Some(options))))
+    }
+}
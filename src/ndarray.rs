@@ -0,0 +1,179 @@
+//! Compress numpy arrays along with the dtype and shape needed to restore them.
+//!
+//! Any array is accepted by the de/compression functions through the buffer protocol, those
+//! which aren't C contiguous being gathered in C order, but only its bytes are kept; `compress_ndarray` also records the dtype and
+//! shape in a small header so `decompress_ndarray` returns an equal array.
+//!
+//! ### Python Example
+//! ```python
+//! >>> import numpy as np
+//! >>> arr = np.arange(12, dtype=np.float64).reshape(3, 4)
+//! >>> compressed = cramjam.compress_ndarray(arr, codec="zstd")
+//! >>> np.array_equal(cramjam.decompress_ndarray(compressed), arr)
+//! True
+//! ```
+use std::io::{Cursor, Error, ErrorKind};
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::codecs::{codec_compress, codec_decompress_into, is_codec};
+use crate::exceptions::{CompressionError, DecompressionError};
+use crate::io::{PythonBuffer, RustyBuffer};
+use crate::BytesType;
+
+pub(crate) const MAGIC: &[u8; 4] = b"CJND";
+const VERSION: u8 = 1;
+
+/// Array metadata recorded ahead of the compressed bytes
+pub(crate) struct Header {
+    pub(crate) codec: String,
+    /// dtype in the `.npy` format's descr notation, so structured dtypes survive
+    pub(crate) dtype: String,
+    pub(crate) shape: Vec<u64>,
+}
+
+impl Header {
+    fn encode(&self) -> PyResult<Vec<u8>> {
+        if self.shape.len() > u8::MAX as usize {
+            return Err(PyValueError::new_err("Arrays are limited to 255 dimensions"));
+        }
+        let mut header = MAGIC.to_vec();
+        header.push(VERSION);
+        for field in [&self.codec, &self.dtype] {
+            let len = u16::try_from(field.len()).map_err(|_| PyValueError::new_err("dtype is too complex"))?;
+            header.extend_from_slice(&len.to_le_bytes());
+            header.extend_from_slice(field.as_bytes());
+        }
+        header.push(self.shape.len() as u8);
+        for dim in self.shape.iter() {
+            header.extend_from_slice(&dim.to_le_bytes());
+        }
+        Ok(header)
+    }
+
+    /// Parse the header, leaving `data` at the start of the compressed bytes
+    pub(crate) fn decode(data: &mut &[u8]) -> PyResult<Self> {
+        if take(data, MAGIC.len()).ok() != Some(MAGIC.as_slice()) {
+            return Err(DecompressionError::new_err("Not a cramjam ndarray, header not found"));
+        }
+        let version = take(data, 1).map_err(DecompressionError::from_err)?[0];
+        if version != VERSION {
+            return Err(DecompressionError::new_err(format!(
+                "Unsupported ndarray header version: {}",
+                version
+            )));
+        }
+        let mut text = || -> std::io::Result<String> {
+            let len = take(data, 2)?;
+            let len = u16::from_le_bytes([len[0], len[1]]) as usize;
+            Ok(String::from_utf8_lossy(take(data, len)?).to_string())
+        };
+        let codec = text().map_err(DecompressionError::from_err)?;
+        let dtype = text().map_err(DecompressionError::from_err)?;
+        let ndim = take(data, 1).map_err(DecompressionError::from_err)?[0] as usize;
+        let shape = take(data, ndim * 8)
+            .map_err(DecompressionError::from_err)?
+            .chunks_exact(8)
+            .map(|dim| u64::from_le_bytes(dim.try_into().unwrap()))
+            .collect();
+        Ok(Self { codec, dtype, shape })
+    }
+
+    /// The recorded dtype descr, ie. `'<f8'` or `[('a', '<i4'), ('b', '<f8')]`
+    pub(crate) fn descr<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        py.import_bound("ast")?
+            .call_method1("literal_eval", (&self.dtype,))
+            .map_err(|_| DecompressionError::new_err(format!("Invalid dtype in ndarray header: {}", self.dtype)))
+    }
+}
+
+fn take<'a>(data: &mut &'a [u8], n: usize) -> Result<&'a [u8], Error> {
+    if data.len() < n {
+        return Err(Error::new(ErrorKind::UnexpectedEof, "Header is truncated"));
+    }
+    let (head, tail) = data.split_at(n);
+    *data = tail;
+    Ok(head)
+}
+
+/// Compress a numpy array (or anything `numpy.asarray` accepts), recording its dtype and
+/// shape so it can be restored with `decompress_ndarray`. Non-contiguous arrays are
+/// copied into a contiguous layout first. Object arrays are not supported.
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> cramjam.compress_ndarray(arr, codec="lz4", level=Optional[int])  # codec defaults to zstd
+/// ```
+#[pyfunction]
+#[pyo3(signature = (array, codec="zstd", level=None))]
+pub fn compress_ndarray(py: Python, array: &Bound<'_, PyAny>, codec: &str, level: Option<i32>) -> PyResult<RustyBuffer> {
+    if !is_codec(codec) {
+        return Err(PyValueError::new_err(format!("Unknown codec: '{}'", codec)));
+    }
+    let array = py
+        .import_bound("numpy")?
+        .getattr("ascontiguousarray")?
+        .call1((array,))?;
+    let dtype = array.getattr("dtype")?;
+    if dtype.getattr("hasobject")?.extract::<bool>()? {
+        return Err(PyValueError::new_err(
+            "Arrays containing Python objects cannot be compressed",
+        ));
+    }
+    let descr = py
+        .import_bound("numpy.lib.format")?
+        .call_method1("dtype_to_descr", (dtype,))?;
+    let header = Header {
+        codec: codec.to_string(),
+        dtype: descr.repr()?.extract()?,
+        shape: array.getattr("shape")?.extract()?,
+    };
+    let mut output = header.encode()?;
+
+    // flattened view, as 0-d arrays don't export a shape through the buffer protocol
+    let buffer = PythonBuffer::try_from(&array.call_method1("reshape", (-1,))?)?;
+    let bytes = buffer.as_slice();
+    let compressed = py
+        .allow_threads(|| codec_compress(codec, bytes, level))
+        .map_err(CompressionError::from_err)?;
+    output.extend_from_slice(&compressed);
+    Ok(RustyBuffer::from(output))
+}
+
+/// Decompress the output of `compress_ndarray` into a new numpy array with the
+/// original dtype and shape.
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> cramjam.decompress_ndarray(compressed)
+/// ```
+#[pyfunction]
+pub fn decompress_ndarray<'py>(py: Python<'py>, data: BytesType<'py>) -> PyResult<Bound<'py, PyAny>> {
+    let data = data.read_bytes()?;
+    let mut bytes = &data[..];
+    let header = Header::decode(&mut bytes)?;
+
+    let dtype = py
+        .import_bound("numpy.lib.format")?
+        .call_method1("descr_to_dtype", (header.descr(py)?,))?;
+    let array = py
+        .import_bound("numpy")?
+        .getattr("empty")?
+        .call1((header.shape, dtype))?;
+    let mut buffer = PythonBuffer::try_from(&array.call_method1("reshape", (-1,))?)?;
+    let out = buffer.as_slice_mut()?;
+    let expected = out.len();
+    let written = py
+        .allow_threads(|| codec_decompress_into(&header.codec, bytes, &mut Cursor::new(out)))
+        .map_err(DecompressionError::from_err)?;
+    if written != expected {
+        return Err(DecompressionError::new_err(format!(
+            "Decompressed {} bytes but the array requires {}",
+            written, expected
+        )));
+    }
+    Ok(array)
+}
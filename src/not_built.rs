@@ -0,0 +1,113 @@
+//! Stand-ins for the codec modules which weren't compiled into this build of cramjam: they can
+//! be imported so downstream libraries may feature detect, but using anything from them raises
+//! `CodecUnavailableError`, naming the Cargo feature which builds the codec.
+//!
+//! ### Python Example
+//! ```python
+//! >>> from cramjam.experimental import blosc2  # fine, even when blosc2 wasn't compiled
+//! >>> blosc2.compress(b'some bytes')
+//! cramjam.CodecUnavailableError: cramjam.experimental.blosc2 isn't available, as this build of cramjam was compiled without the `blosc2` feature; ...
+//! >>> "blosc2" in cramjam.available_codecs()
+//! False
+//! ```
+#![allow(unused_imports, unused_macros)]
+use pyo3::prelude::*;
+
+use crate::exceptions::CodecUnavailableError;
+
+/// Error for using the stand-in for `module`, whose codec is compiled in with `feature`
+#[allow(dead_code)] // when every codec was compiled in
+pub(crate) fn unavailable(module: &str, feature: &str) -> PyErr {
+    let reason = match feature {
+        "igzip" | "ideflate" | "izlib" if !cfg!(target_pointer_width = "64") => {
+            "the ISA-L based codecs are only built for 64 bit platforms".to_string()
+        }
+        _ => format!(
+            "this build of cramjam was compiled without the `{}` feature; install a wheel which \
+            includes it, or build cramjam from source with it, ie. `maturin build --release --features {}`",
+            feature, feature
+        ),
+    };
+    CodecUnavailableError::new_err(format!(
+        "cramjam.{} isn't available, as {}. Check `cramjam.available_codecs()` first",
+        module, reason
+    ))
+}
+
+/// Define a stand-in for the module `$name`, found at `cramjam.$path`, when it wasn't compiled
+/// as `$feature` wasn't enabled.
+macro_rules! not_built_module {
+    ($name:ident, $path:literal, $feature:literal) => {
+        /// Not compiled into this build of cramjam, see `cramjam.available_codecs()`
+        #[pymodule]
+        pub mod $name {
+            use pyo3::exceptions::PyAttributeError;
+            use pyo3::prelude::*;
+
+            /// Module level `__getattr__`, called for anything the module doesn't have
+            #[pyfunction]
+            #[pyo3(name = "__getattr__")]
+            fn getattr(name: &str) -> PyResult<PyObject> {
+                // introspection, ie. by `hasattr(module, "__path__")`, sees an ordinary module
+                if name.starts_with("__") {
+                    return Err(PyAttributeError::new_err(name.to_string()));
+                }
+                Err(crate::not_built::unavailable($path, $feature))
+            }
+        }
+    };
+}
+
+#[cfg(not(feature = "snappy"))]
+not_built_module!(snappy, "snappy", "snappy");
+
+#[cfg(not(feature = "lz4"))]
+not_built_module!(lz4, "lz4", "lz4");
+
+#[cfg(not(feature = "lzf"))]
+not_built_module!(lzf, "lzf", "lzf");
+
+#[cfg(not(feature = "bzip2"))]
+not_built_module!(bzip2, "bzip2", "bzip2");
+
+#[cfg(not(feature = "brotli"))]
+not_built_module!(brotli, "brotli", "brotli");
+
+#[cfg(not(feature = "zstd"))]
+not_built_module!(zstd, "zstd", "zstd");
+
+#[cfg(not(any(feature = "xz", feature = "xz-static", feature = "xz-shared")))]
+not_built_module!(xz, "xz", "xz");
+
+#[cfg(not(any(feature = "xz", feature = "xz-static", feature = "xz-shared")))]
+not_built_module!(lzma, "lzma", "xz");
+
+#[cfg(not(any(feature = "gzip", feature = "gzip-static", feature = "gzip-shared")))]
+not_built_module!(gzip, "gzip", "gzip");
+
+#[cfg(not(any(feature = "zlib", feature = "zlib-static", feature = "zlib-shared")))]
+not_built_module!(zlib, "zlib", "zlib");
+
+#[cfg(not(any(feature = "deflate", feature = "deflate-static", feature = "deflate-shared")))]
+not_built_module!(deflate, "deflate", "deflate");
+
+#[cfg(not(any(feature = "blosc2", feature = "blosc2-static", feature = "blosc2-shared")))]
+not_built_module!(blosc2, "experimental.blosc2", "blosc2");
+
+#[cfg(not(all(
+    any(feature = "ideflate", feature = "ideflate-static", feature = "ideflate-shared"),
+    target_pointer_width = "64"
+)))]
+not_built_module!(ideflate, "experimental.ideflate", "ideflate");
+
+#[cfg(not(all(
+    any(feature = "igzip", feature = "igzip-static", feature = "igzip-shared"),
+    target_pointer_width = "64"
+)))]
+not_built_module!(igzip, "experimental.igzip", "igzip");
+
+#[cfg(not(all(
+    any(feature = "izlib", feature = "izlib-static", feature = "izlib-shared"),
+    target_pointer_width = "64"
+)))]
+not_built_module!(izlib, "experimental.izlib", "izlib");
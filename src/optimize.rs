@@ -0,0 +1,168 @@
+//! Search across codecs and levels for the best compression of some data.
+//!
+//! ### Python Example
+//! ```python
+//! >>> compressed, meta = cramjam.optimize(data, target="ratio", budget_ms=500)
+//! >>> meta["codec"], meta["level"]
+//! ('xz', 6)
+//! >>> bytes(getattr(cramjam, meta["codec"]).decompress(compressed)) == data
+//! True
+//! ```
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::codecs::{codec_compress, is_codec};
+use crate::exceptions::CompressionError;
+use crate::io::RustyBuffer;
+use crate::BytesType;
+
+/// Levels tried per codec, roughly cheapest first
+const CANDIDATES: &[(&str, &[Option<i32>])] = &[
+    ("snappy", &[None]),
+    ("lz4", &[Some(1), Some(9)]),
+    ("zstd", &[Some(1), Some(3), Some(9), Some(19)]),
+    ("gzip", &[Some(1), Some(6), Some(9)]),
+    ("zlib", &[Some(1), Some(6), Some(9)]),
+    ("brotli", &[Some(1), Some(5), Some(9), Some(11)]),
+    ("bzip2", &[Some(1), Some(9)]),
+    ("xz", &[Some(1), Some(6), Some(9)]),
+];
+
+struct Trial {
+    codec: &'static str,
+    level: Option<i32>,
+    elapsed: Duration,
+    output: Vec<u8>,
+}
+
+/// Try compressing `data` with a range of codecs and levels, in parallel and within
+/// `budget_ms`, returning the best result as `(compressed, metadata)`.
+///
+/// `target` is `"ratio"` for the smallest output or `"speed"` for the fastest compression.
+/// Cheaper candidates are tried first, so a tight budget favors fast codecs; at least one
+/// candidate is always evaluated, and one already running when the budget expires is allowed
+/// to finish. `codecs` restricts the search to the given codec names.
+///
+/// The metadata dict holds the chosen `codec`, `level`, `ratio` and `elapsed_ms`, and `trials`
+/// listing the same for every candidate evaluated.
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> cramjam.optimize(data, target="ratio", budget_ms=1000, codecs=Optional[List[str]])
+/// ```
+#[pyfunction]
+#[pyo3(signature = (data, target="ratio", budget_ms=1000, codecs=None))]
+pub fn optimize<'py>(
+    py: Python<'py>,
+    data: BytesType,
+    target: &str,
+    budget_ms: u64,
+    codecs: Option<Vec<String>>,
+) -> PyResult<(RustyBuffer, Bound<'py, PyDict>)> {
+    if !matches!(target, "ratio" | "speed") {
+        return Err(PyValueError::new_err(format!(
+            "target must be 'ratio' or 'speed', got '{}'",
+            target
+        )));
+    }
+    if let Some(unknown) = codecs.iter().flatten().find(|c| !is_codec(c)) {
+        return Err(PyValueError::new_err(format!("Unknown codec: '{}'", unknown)));
+    }
+    let candidates = CANDIDATES
+        .iter()
+        .filter(|(codec, _)| match &codecs {
+            Some(codecs) => codecs.iter().any(|name| name == codec),
+            None => true,
+        })
+        .flat_map(|(codec, levels)| levels.iter().map(move |level| (*codec, *level)))
+        .collect::<Vec<_>>();
+
+    let bytes = data.read_bytes()?;
+    let trials = py.allow_threads(|| run(&bytes, &candidates, Duration::from_millis(budget_ms)));
+    let mut trials = trials.map_err(CompressionError::from_err)?;
+    if trials.is_empty() {
+        return Err(PyValueError::new_err("No codecs available to try"));
+    }
+
+    let best = match target {
+        "ratio" => trials
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, t)| (t.output.len(), t.elapsed)),
+        _ => trials
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, t)| (t.elapsed, t.output.len())),
+    }
+    .map(|(i, _)| i)
+    .unwrap();
+
+    let describe = |trial: &Trial| -> PyResult<Bound<'py, PyDict>> {
+        let meta = PyDict::new_bound(py);
+        meta.set_item("codec", trial.codec)?;
+        meta.set_item("level", trial.level)?;
+        meta.set_item("ratio", bytes.len() as f64 / trial.output.len().max(1) as f64)?;
+        meta.set_item("elapsed_ms", trial.elapsed.as_secs_f64() * 1000.)?;
+        Ok(meta)
+    };
+    let meta = describe(&trials[best])?;
+    let summary = trials.iter().map(describe).collect::<PyResult<Vec<_>>>()?;
+    meta.set_item("trials", summary)?;
+    Ok((RustyBuffer::from(trials.swap_remove(best).output), meta))
+}
+
+/// Evaluate candidates on a pool of worker threads until they're exhausted or the budget expires
+fn run(data: &[u8], candidates: &[(&'static str, Option<i32>)], budget: Duration) -> std::io::Result<Vec<Trial>> {
+    let start = Instant::now();
+    let next = AtomicUsize::new(0);
+    let trials = Mutex::new(vec![]);
+    let workers = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(candidates.len());
+
+    std::thread::scope(|scope| {
+        let handles = (0..workers)
+            .map(|_| {
+                scope.spawn(|| loop {
+                    let i = next.fetch_add(1, Ordering::SeqCst);
+                    // keep going past the budget until there's at least one result
+                    let expired = start.elapsed() >= budget && !trials.lock().unwrap().is_empty();
+                    if i >= candidates.len() || expired {
+                        return Ok(());
+                    }
+                    let (codec, level) = candidates[i];
+                    let timer = Instant::now();
+                    let output = match codec_compress(codec, data, level) {
+                        Ok(output) => output,
+                        // not compiled into this build
+                        Err(e) if e.kind() == std::io::ErrorKind::Unsupported => continue,
+                        Err(e) => return Err(e),
+                    };
+                    let elapsed = timer.elapsed();
+                    trials.lock().unwrap().push((
+                        i,
+                        Trial {
+                            codec,
+                            level,
+                            elapsed,
+                            output,
+                        },
+                    ));
+                })
+            })
+            .collect::<Vec<_>>();
+        handles.into_iter().try_for_each(|h| h.join().unwrap())
+    })?;
+
+    // report trials in candidate order regardless of which worker finished first
+    let mut trials = trials.into_inner().unwrap();
+    trials.sort_by_key(|(i, _)| *i);
+    Ok(trials.into_iter().map(|(_, trial)| trial).collect())
+}
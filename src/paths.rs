@@ -0,0 +1,94 @@
+//! File to file de/compression by path, for each codec module's `compress_path` and
+//! `decompress_path`, see [`make_path_helpers`](../macro.make_path_helpers.html).
+use std::ffi::OsString;
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, BufWriter, Error, Write};
+use std::path::{Path, PathBuf};
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::codecs::{codec_compress_into, codec_reader};
+use crate::exceptions::{CompressionError, DecompressionError};
+use crate::io::PathLike;
+
+/// Compress the file at `src` with `codec` into `dst`, by default `src` with `.<extension>`
+/// appended, returning the path written to.
+pub(crate) fn compress_path(
+    py: Python,
+    codec: &str,
+    extension: &str,
+    src: PathLike,
+    dst: Option<PathLike>,
+    level: Option<i32>,
+    overwrite: bool,
+) -> PyResult<PathBuf> {
+    let PathLike(src) = src;
+    let dst = match dst {
+        Some(PathLike(dst)) => dst,
+        None => {
+            let mut dst = OsString::from(src.as_os_str());
+            dst.push(".");
+            dst.push(extension);
+            PathBuf::from(dst)
+        }
+    };
+    let input = File::open(&src)?;
+    let mut output = create(&dst, overwrite)?;
+    py.allow_threads(|| {
+        codec_compress_into(codec, BufReader::new(input), &mut output, level).and_then(|_| output.flush())
+    })
+    .map_err(|err| discard(&dst, err, CompressionError::from_err))?;
+    Ok(dst)
+}
+
+/// Decompress the file at `src` with `codec` into `dst`, by default `src` without its
+/// `.<extension>`, returning the path written to.
+pub(crate) fn decompress_path(
+    py: Python,
+    codec: &str,
+    extension: &str,
+    src: PathLike,
+    dst: Option<PathLike>,
+    overwrite: bool,
+) -> PyResult<PathBuf> {
+    let PathLike(src) = src;
+    let dst = match dst {
+        Some(PathLike(dst)) => dst,
+        None => match src.extension() {
+            Some(ext) if ext == extension => src.with_extension(""),
+            _ => {
+                return Err(PyValueError::new_err(format!(
+                    "Can't infer where to decompress {} to, as it doesn't end with '.{}'; please give `dst`",
+                    src.display(),
+                    extension
+                )))
+            }
+        },
+    };
+    let input = File::open(&src)?;
+    let mut output = create(&dst, overwrite)?;
+    py.allow_threads(|| {
+        let mut reader = codec_reader(codec, BufReader::new(input))?;
+        std::io::copy(&mut reader, &mut output).and_then(|_| output.flush())
+    })
+    .map_err(|err| discard(&dst, err, DecompressionError::from_err))?;
+    Ok(dst)
+}
+
+/// Create `path` for writing, only replacing an existing file if `overwrite` is given
+fn create(path: &Path, overwrite: bool) -> PyResult<BufWriter<File>> {
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .create_new(!overwrite)
+        .open(path)?;
+    Ok(BufWriter::new(file))
+}
+
+/// Remove the partly written `path` after `err`, converted with `codec_err`
+fn discard(path: &Path, err: Error, codec_err: fn(Error) -> PyErr) -> PyErr {
+    let _ = std::fs::remove_file(path);
+    codec_err(err)
+}
@@ -0,0 +1,221 @@
+//! Composable pipelines of filters, codecs and Python transforms.
+//!
+//! A pipeline runs each stage over the output of the previous one, in one pass, and
+//! `pipeline_decompress` reverses it given the same stages, with any Python callables
+//! replaced by their inverse. The output is only what the last stage gives, so the stages
+//! are for the caller to keep alongside it.
+//!
+//! ### Python Example
+//! ```python
+//! >>> import cramjam
+//! >>> stages = [("shuffle", {"typesize": 4}), ("zstd", {"level": 5})]
+//! >>> compressed = cramjam.pipeline(data, stages)
+//! >>> bytes(cramjam.pipeline_decompress(compressed, stages)) == data
+//! True
+//! >>> # Python callables are bridged in, their inverse taking their place to decompress
+//! >>> def negate(b): return bytes(255 - x for x in b)
+//! >>> compressed = cramjam.pipeline(data, [negate, "snappy"])
+//! >>> _ = cramjam.pipeline_decompress(compressed, [negate, "snappy"])
+//! ```
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict, PyTuple};
+
+use crate::codecs::{codec_compress, codec_decompress, is_codec};
+use crate::exceptions::{CompressionError, DecompressionError};
+use crate::io::RustyBuffer;
+use crate::limits::output_limit;
+use crate::BytesType;
+
+/// A single step in a pipeline
+enum Stage<'py> {
+    Codec { name: String, level: Option<i32> },
+    Filter { name: String, typesize: Option<i32> },
+    Python(Bound<'py, PyAny>),
+}
+
+impl<'py> Stage<'py> {
+    fn from_py(stage: &Bound<'py, PyAny>) -> PyResult<Self> {
+        if let Ok(name) = stage.extract::<String>() {
+            return Self::from_name(name, None);
+        }
+        if let Ok(tuple) = stage.downcast::<PyTuple>() {
+            let name = tuple.get_item(0)?.extract::<String>()?;
+            let params = match tuple.len() {
+                1 => None,
+                2 => Some(tuple.get_item(1)?.downcast_into::<PyDict>()?),
+                _ => {
+                    return Err(PyValueError::new_err(
+                        "Pipeline stage tuples should be (name, {params})",
+                    ))
+                }
+            };
+            return Self::from_name(name, params);
+        }
+        if stage.is_callable() {
+            return Ok(Stage::Python(stage.clone()));
+        }
+        Err(PyValueError::new_err(format!(
+            "Unsupported pipeline stage: {}, expected a name, (name, params) tuple or callable",
+            stage.repr()?
+        )))
+    }
+
+    fn from_name(name: String, params: Option<Bound<'py, PyDict>>) -> PyResult<Self> {
+        let param = |key: &str| -> PyResult<Option<i32>> {
+            match params.as_ref().map(|p| p.get_item(key)).transpose()?.flatten() {
+                Some(value) => Ok(Some(value.extract()?)),
+                None => Ok(None),
+            }
+        };
+        match name.as_str() {
+            "shuffle" | "delta" => Ok(Stage::Filter {
+                typesize: param("typesize")?,
+                name,
+            }),
+            _ if is_codec(&name) => Ok(Stage::Codec {
+                level: param("level")?,
+                name,
+            }),
+            _ => Err(PyValueError::new_err(format!(
+                "Unknown pipeline codec or filter: '{}'",
+                name
+            ))),
+        }
+    }
+}
+
+/// Byte shuffle, grouping the n-th byte of every `typesize` wide item together
+fn shuffle(input: &[u8], typesize: usize) -> Vec<u8> {
+    let nitems = input.len() / typesize;
+    let mut output = Vec::with_capacity(input.len());
+    for byte in 0..typesize {
+        output.extend((0..nitems).map(|item| input[item * typesize + byte]));
+    }
+    // trailing bytes which don't make a full item are left as is
+    output.extend_from_slice(&input[nitems * typesize..]);
+    output
+}
+
+fn unshuffle(input: &[u8], typesize: usize) -> Vec<u8> {
+    let nitems = input.len() / typesize;
+    let mut output = vec![0; input.len()];
+    for byte in 0..typesize {
+        for item in 0..nitems {
+            output[item * typesize + byte] = input[byte * nitems + item];
+        }
+    }
+    output[nitems * typesize..].copy_from_slice(&input[nitems * typesize..]);
+    output
+}
+
+/// Byte delta, each byte stored as the difference from the byte `typesize` before it
+fn delta(input: &[u8], typesize: usize) -> Vec<u8> {
+    let mut output = input.to_vec();
+    for i in (typesize..input.len()).rev() {
+        output[i] = input[i].wrapping_sub(input[i - typesize]);
+    }
+    output
+}
+
+fn undelta(input: &[u8], typesize: usize) -> Vec<u8> {
+    let mut output = input.to_vec();
+    for i in typesize..output.len() {
+        output[i] = output[i].wrapping_add(output[i - typesize]);
+    }
+    output
+}
+
+fn filter_typesize(typesize: Option<i32>) -> PyResult<usize> {
+    match typesize.unwrap_or(1) {
+        n if n > 0 => Ok(n as usize),
+        n => Err(PyValueError::new_err(format!(
+            "Filter typesize must be positive, got {}",
+            n
+        ))),
+    }
+}
+
+fn call_transform(py: Python, func: &Bound<'_, PyAny>, data: Vec<u8>) -> PyResult<Vec<u8>> {
+    let result = func.call1((PyBytes::new_bound(py, &data),))?;
+    let bytes = result.extract::<BytesType>()?;
+    Ok(bytes.read_bytes()?.into_owned())
+}
+
+fn parse_stages<'py>(stages: &[Bound<'py, PyAny>]) -> PyResult<Vec<Stage<'py>>> {
+    stages.iter().map(Stage::from_py).collect()
+}
+
+/// Run `data` through each of `stages` in order, returning what the last one gives.
+///
+/// Stages are either a filter (`"shuffle"`, `"delta"`) or codec name (ie `"zstd"`),
+/// a `(name, {params})` tuple where params are `level` for codecs and `typesize` for filters,
+/// or a Python callable taking and returning bytes.
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> cramjam.pipeline(data, [("delta", {"typesize": 8}), ("zstd", {"level": 5})])
+/// ```
+#[pyfunction]
+pub fn pipeline(py: Python, data: BytesType, stages: Vec<Bound<'_, PyAny>>) -> PyResult<RustyBuffer> {
+    let stages = parse_stages(&stages)?;
+    let mut buf = data.read_bytes()?.into_owned();
+    for stage in stages.iter() {
+        buf = match stage {
+            Stage::Codec { name, level } => py
+                .allow_threads(|| codec_compress(name, &buf, *level))
+                .map_err(CompressionError::from_err)?,
+            Stage::Filter { name, typesize } => {
+                let typesize = filter_typesize(*typesize)?;
+                match name.as_str() {
+                    "shuffle" => py.allow_threads(|| shuffle(&buf, typesize)),
+                    _ => py.allow_threads(|| delta(&buf, typesize)),
+                }
+            }
+            Stage::Python(func) => call_transform(py, func, buf)?,
+        };
+    }
+    Ok(RustyBuffer::from(buf))
+}
+
+/// Reverse a `pipeline` of `stages`, as given to it but with each Python callable replaced by
+/// its inverse; they're run last to first.
+///
+/// The output of each codec stage is limited as by a codec's `decompress`, to
+/// `max_expansion_ratio` times the size of `data`, or the process wide ratio, and `output_len_max`.
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> cramjam.pipeline_decompress(compressed, [("delta", {"typesize": 8}), "zstd"], max_expansion_ratio=Optional[float])
+/// ```
+#[pyfunction]
+#[pyo3(signature = (data, stages, *, max_expansion_ratio=None, output_len_max=None))]
+pub fn pipeline_decompress(
+    py: Python,
+    data: BytesType,
+    stages: Vec<Bound<'_, PyAny>>,
+    max_expansion_ratio: Option<f64>,
+    output_len_max: Option<usize>,
+) -> PyResult<RustyBuffer> {
+    let stages = parse_stages(&stages)?;
+    let mut buf = data.read_bytes()?.into_owned();
+    let limit = output_limit(buf.len(), max_expansion_ratio, output_len_max)?;
+    for stage in stages.iter().rev() {
+        buf = match stage {
+            Stage::Codec { name, .. } => py
+                .allow_threads(|| codec_decompress(name, &buf, limit))
+                .map_err(DecompressionError::from_err)?,
+            Stage::Filter { name, typesize } => {
+                let typesize = filter_typesize(*typesize)?;
+                match name.as_str() {
+                    "shuffle" => py.allow_threads(|| unshuffle(&buf, typesize)),
+                    _ => py.allow_threads(|| undelta(&buf, typesize)),
+                }
+            }
+            Stage::Python(func) => call_transform(py, func, buf)?,
+        };
+    }
+    Ok(RustyBuffer::from(buf))
+}
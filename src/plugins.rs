@@ -0,0 +1,85 @@
+//! Discover third party codecs installed alongside cramjam.
+//!
+//! A package provides a codec by registering a module, which has at least `compress` and
+//! `decompress` functions following cramjam's API, under the `cramjam.codecs` entry point group:
+//!
+//! ```toml
+//! [project.entry-points."cramjam.codecs"]
+//! lzo = "cramjam_lzo"
+//! ```
+//!
+//! ### Python Example
+//! ```python
+//! >>> cramjam.load_plugins()
+//! ['lzo']
+//! >>> cramjam.lzo.decompress(cramjam.lzo.compress(b'some bytes here'))
+//! ```
+use pyo3::exceptions::{PyImportError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+/// Entry point group codec packages register their module under
+pub const ENTRY_POINT_GROUP: &str = "cramjam.codecs";
+
+/// Functions a plugin module must provide
+const REQUIRED: &[&str] = &["compress", "decompress"];
+
+/// Import the codec modules registered under the `cramjam.codecs` entry point group, and attach
+/// each as `cramjam.<name>` (also importable as `from cramjam import <name>`).
+///
+/// Returns the names of the codecs loaded. Calling it again is harmless; plugins already
+/// attached are kept as they are. A plugin may not replace one of cramjam's own codecs.
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> cramjam.load_plugins()
+/// ```
+#[pyfunction]
+pub fn load_plugins(py: Python) -> PyResult<Vec<String>> {
+    let cramjam = py.import_bound("cramjam")?;
+    let modules = py.import_bound("sys")?.getattr("modules")?;
+
+    let mut loaded = vec![];
+    for entry_point in entry_points(py)? {
+        let name = entry_point.getattr("name")?.extract::<String>()?;
+        let module = entry_point.call_method0("load")?;
+        if let Some(missing) = REQUIRED.iter().find(|f| !module.hasattr(**f).unwrap_or(false)) {
+            return Err(PyImportError::new_err(format!(
+                "cramjam plugin '{}' ({}) has no '{}' function",
+                name,
+                entry_point.getattr("value")?,
+                missing
+            )));
+        }
+        match cramjam.getattr(name.as_str()) {
+            Ok(existing) if !existing.is(&module) => {
+                return Err(PyValueError::new_err(format!(
+                    "cramjam plugin '{}' conflicts with the existing cramjam.{}",
+                    name, name
+                )))
+            }
+            _ => {
+                cramjam.setattr(name.as_str(), &module)?;
+                modules.set_item(format!("cramjam.{}", name), &module)?;
+            }
+        }
+        loaded.push(name);
+    }
+    Ok(loaded)
+}
+
+/// Entry points in the plugin group, across the `importlib.metadata` APIs of Python 3.8 onwards
+fn entry_points<'py>(py: Python<'py>) -> PyResult<Vec<Bound<'py, PyAny>>> {
+    let entry_points = py.import_bound("importlib.metadata")?.getattr("entry_points")?;
+    let kwargs = PyDict::new_bound(py);
+    kwargs.set_item("group", ENTRY_POINT_GROUP)?;
+    match entry_points.call((), Some(&kwargs)) {
+        Ok(selected) => selected.iter()?.collect(),
+        // before 3.10, all entry points are returned as a dict of group -> entry points
+        Err(_) => match entry_points.call0()?.get_item(ENTRY_POINT_GROUP) {
+            Ok(selected) => selected.iter()?.collect(),
+            Err(_) => Ok(vec![]),
+        },
+    }
+}
@@ -5,25 +5,331 @@ use pyo3::prelude::*;
 #[pymodule]
 pub mod snappy {
     use crate::exceptions::{CompressionError, DecompressionError};
-    use crate::io::{AsBytes, RustyBuffer};
+    use crate::io::{AsBytes, IntoLength, OutputBuffer, ReadSource, RustyBuffer};
     use crate::BytesType;
+    use libcramjam::snappy::snap;
+    use libcramjam::snappy::snap::write::FrameEncoder;
+    use pyo3::exceptions::PyValueError;
     use pyo3::prelude::*;
     use pyo3::PyResult;
-    use std::io::Cursor;
+    use std::io::{Cursor, Read, SeekFrom, Write};
+
+    /// Largest chunk of uncompressed data allowed by the snappy framing format
+    const MAX_CHUNK_SIZE: usize = 65536;
+
+    /// Frame encoder which emits a chunk every `chunk_size` uncompressed bytes,
+    /// rather than only once the format's maximum chunk size is buffered.
+    pub struct ChunkedEncoder<W: Write> {
+        inner: FrameEncoder<W>,
+        chunk_size: usize,
+        pending: usize,
+    }
+
+    impl<W: Write> ChunkedEncoder<W> {
+        fn new(output: W, chunk_size: usize) -> Self {
+            Self {
+                inner: FrameEncoder::new(output),
+                chunk_size,
+                pending: 0,
+            }
+        }
+        fn get_mut(&mut self) -> &mut W {
+            self.inner.get_mut()
+        }
+        fn into_inner(self) -> std::io::Result<W> {
+            self.inner.into_inner().map_err(|e| e.into_error())
+        }
+    }
+
+    impl<W: Write> Write for ChunkedEncoder<W> {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            let n = buf.len().min(self.chunk_size - self.pending);
+            let n = self.inner.write(&buf[..n])?;
+            self.pending += n;
+            if self.pending == self.chunk_size {
+                self.flush()?;
+            }
+            Ok(n)
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            self.pending = 0;
+            self.inner.flush()
+        }
+    }
+
+    /// Counts the bytes written through to `inner`
+    struct CountingWriter<'a, W: Write + ?Sized> {
+        inner: &'a mut W,
+        count: usize,
+    }
+
+    impl<'a, W: Write + ?Sized> Write for CountingWriter<'a, W> {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            let n = self.inner.write(buf)?;
+            self.count += n;
+            Ok(n)
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            self.inner.flush()
+        }
+    }
+
+    /// Frame compression with a chunk size other than the default
+    fn compress_chunked<W: Write + ?Sized, R: Read>(
+        mut input: R,
+        output: &mut W,
+        chunk_size: usize,
+    ) -> std::io::Result<usize> {
+        let mut output = CountingWriter {
+            inner: output,
+            count: 0,
+        };
+        let mut encoder = ChunkedEncoder::new(&mut output, chunk_size);
+        std::io::copy(&mut input, &mut encoder)?;
+        encoder.flush()?;
+        drop(encoder);
+        Ok(output.count)
+    }
+
+    fn check_chunk_size(chunk_size: usize) -> PyResult<usize> {
+        if (1..=MAX_CHUNK_SIZE).contains(&chunk_size) {
+            Ok(chunk_size)
+        } else {
+            Err(PyValueError::new_err(format!(
+                "chunk_size must be between 1 and {}, got {}",
+                MAX_CHUNK_SIZE, chunk_size
+            )))
+        }
+    }
+
+    /// Uncompressed bytes per block of Hadoop framing, which is what Hadoop's own codec leaves
+    /// room for in its default 256KiB buffer after the worst case compression overhead
+    const HADOOP_BLOCK_SIZE: usize = 256 * 1024 - (256 * 1024 / 6 + 32);
+
+    /// Hadoop block framing: each block is its uncompressed length followed by the raw snappy
+    /// chunks it was compressed into, each prefixed by its compressed length, all lengths being
+    /// 4 byte big endian. Blocks written here always hold a single chunk.
+    fn compress_hadoop_blocks<W: Write + ?Sized, R: Read>(mut input: R, output: &mut W) -> std::io::Result<usize> {
+        let mut encoder = libcramjam::snappy::snap::raw::Encoder::new();
+        let mut block = Vec::with_capacity(HADOOP_BLOCK_SIZE);
+        let mut written = 0;
+        loop {
+            block.clear();
+            (&mut input).take(HADOOP_BLOCK_SIZE as u64).read_to_end(&mut block)?;
+            if block.is_empty() {
+                return Ok(written);
+            }
+            let chunk = encoder.compress_vec(&block)?;
+            output.write_all(&(block.len() as u32).to_be_bytes())?;
+            output.write_all(&(chunk.len() as u32).to_be_bytes())?;
+            output.write_all(&chunk)?;
+            written += 8 + chunk.len();
+        }
+    }
+
+    /// Decode Hadoop block framing, see [`compress_hadoop_blocks`]
+    fn decompress_hadoop_blocks<W: Write + ?Sized, R: Read>(mut input: R, output: &mut W) -> std::io::Result<usize> {
+        let mut decoder = libcramjam::snappy::snap::raw::Decoder::new();
+        let mut chunk = vec![];
+        let mut written = 0;
+        while let Some(block_len) = read_hadoop_len(&mut input, true)? {
+            let mut remaining = block_len;
+            while remaining > 0 {
+                let chunk_len = read_hadoop_len(&mut input, false)?.unwrap_or_default();
+                chunk.clear();
+                (&mut input).take(chunk_len as u64).read_to_end(&mut chunk)?;
+                if chunk.len() != chunk_len {
+                    return Err(truncated());
+                }
+                let decompressed = decoder.decompress_vec(&chunk)?;
+                remaining = remaining.checked_sub(decompressed.len()).ok_or_else(|| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!(
+                            "Hadoop snappy block decompressed larger than its length of {}",
+                            block_len
+                        ),
+                    )
+                })?;
+                output.write_all(&decompressed)?;
+                written += decompressed.len();
+            }
+        }
+        Ok(written)
+    }
+
+    /// Read a 4 byte big endian length; `None` at the end of input if `at_boundary`
+    fn read_hadoop_len<R: Read>(input: &mut R, at_boundary: bool) -> std::io::Result<Option<usize>> {
+        let mut len = [0; 4];
+        let mut filled = 0;
+        while filled < len.len() {
+            match input.read(&mut len[filled..])? {
+                0 if filled == 0 && at_boundary => return Ok(None),
+                0 => return Err(truncated()),
+                n => filled += n,
+            }
+        }
+        Ok(Some(u32::from_be_bytes(len) as usize))
+    }
+
+    fn truncated() -> std::io::Error {
+        std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "Hadoop snappy data is truncated")
+    }
+
+    /// Chunk types of the snappy framing format
+    const CHUNK_COMPRESSED: u8 = 0x00;
+    const CHUNK_UNCOMPRESSED: u8 = 0x01;
+    const CHUNK_STREAM_IDENTIFIER: u8 = 0xff;
+
+    /// Body of the stream identifier chunk starting each frame
+    const STREAM_IDENTIFIER: &[u8] = b"sNaPpY";
+
+    /// Framed decompression, only checking each chunk's CRC32C checksum if `verify_checksums`;
+    /// otherwise chunks are decoded as they are, so data with bad checksums can be salvaged.
+    fn decompress_frames<W: Write + ?Sized, R: Read>(
+        mut input: R,
+        output: &mut W,
+        verify_checksums: bool,
+    ) -> std::io::Result<usize> {
+        if verify_checksums {
+            return libcramjam::snappy::decompress(input, output);
+        }
+        let mut decoder = snap::raw::Decoder::new();
+        let mut chunk = vec![];
+        let mut decompressed = vec![0; MAX_CHUNK_SIZE];
+        let mut written = 0;
+        let mut started = false;
+        while let Some((chunk_type, chunk_len)) = read_chunk_header(&mut input)? {
+            if !started && chunk_type != CHUNK_STREAM_IDENTIFIER {
+                return Err(invalid_frame(
+                    std::io::ErrorKind::InvalidData,
+                    "data doesn't start with a stream identifier",
+                ));
+            }
+            chunk.clear();
+            (&mut input).take(chunk_len as u64).read_to_end(&mut chunk)?;
+            if chunk.len() != chunk_len {
+                return Err(invalid_frame(std::io::ErrorKind::UnexpectedEof, "chunk is truncated"));
+            }
+            let data = match chunk_type {
+                CHUNK_STREAM_IDENTIFIER if chunk == STREAM_IDENTIFIER => {
+                    started = true;
+                    continue;
+                }
+                CHUNK_STREAM_IDENTIFIER => {
+                    return Err(invalid_frame(
+                        std::io::ErrorKind::InvalidData,
+                        "stream identifier is corrupt",
+                    ))
+                }
+                // the chunk's data follows its 4 byte checksum, which is skipped
+                CHUNK_COMPRESSED | CHUNK_UNCOMPRESSED if chunk_len < 4 => {
+                    return Err(invalid_frame(std::io::ErrorKind::InvalidData, "chunk is too short"))
+                }
+                CHUNK_COMPRESSED => {
+                    let len = snap::raw::decompress_len(&chunk[4..])?;
+                    if len > MAX_CHUNK_SIZE {
+                        return Err(invalid_frame(std::io::ErrorKind::InvalidData, "chunk is too large"));
+                    }
+                    let len = decoder.decompress(&chunk[4..], &mut decompressed[..len])?;
+                    &decompressed[..len]
+                }
+                CHUNK_UNCOMPRESSED if chunk_len - 4 > MAX_CHUNK_SIZE => {
+                    return Err(invalid_frame(std::io::ErrorKind::InvalidData, "chunk is too large"))
+                }
+                CHUNK_UNCOMPRESSED => &chunk[4..],
+                0x02..=0x7f => {
+                    return Err(invalid_frame(
+                        std::io::ErrorKind::InvalidData,
+                        &format!("chunk type {:#04x} is unsupported", chunk_type),
+                    ))
+                }
+                // padding, and reserved chunks which are to be skipped
+                _ => continue,
+            };
+            output.write_all(data)?;
+            written += data.len();
+        }
+        Ok(written)
+    }
+
+    /// Read a chunk's type and 3 byte little endian length; `None` at the end of input
+    fn read_chunk_header<R: Read>(input: &mut R) -> std::io::Result<Option<(u8, usize)>> {
+        let mut header = [0; 4];
+        let mut filled = 0;
+        while filled < header.len() {
+            match input.read(&mut header[filled..])? {
+                0 if filled == 0 => return Ok(None),
+                0 => {
+                    return Err(invalid_frame(
+                        std::io::ErrorKind::UnexpectedEof,
+                        "chunk header is truncated",
+                    ))
+                }
+                n => filled += n,
+            }
+        }
+        let len = u32::from_le_bytes([header[1], header[2], header[3], 0]) as usize;
+        Ok(Some((header[0], len)))
+    }
+
+    fn invalid_frame(kind: std::io::ErrorKind, msg: &str) -> std::io::Error {
+        std::io::Error::new(kind, format!("Snappy framed data is invalid: {}", msg))
+    }
+
+    /// `DecompressionError`, or `ChecksumError` for a chunk failing its checksum
+    fn decompress_error(err: std::io::Error) -> PyErr {
+        match err.get_ref().and_then(|e| e.downcast_ref::<snap::Error>()) {
+            Some(snap::Error::Checksum { .. }) => ChecksumError::new_err(err.to_string()),
+            _ => DecompressionError::from_err(err),
+        }
+    }
+
+    /// [`decompress_frames`] for the `Decompressor`, with its errors as raised from `decompress`
+    fn decompress_stream<W: Write + ?Sized, R: Read>(
+        input: R,
+        output: &mut W,
+        verify_checksums: bool,
+    ) -> PyResult<usize> {
+        decompress_frames(input, output, verify_checksums).map_err(decompress_error)
+    }
 
     /// Snappy decompression.
     ///
+    /// Each chunk of the framed data is checked against its CRC32C checksum, raising
+    /// `cramjam.snappy.ChecksumError` (a `DecompressionError`) on a mismatch; to salvage data
+    /// with bad checksums, `verify_checksums=False` skips the check, as does
+    /// `Decompressor(verify_checksums=False)` for streaming.
+    ///
     /// Python Example
     /// --------------
     /// ```python
     /// >>> # bytes or bytearray; bytearray is faster
-    /// >>> cramjam.snappy.decompress(compressed_bytes, output_len=Optional[None])
+    /// >>> cramjam.snappy.decompress(compressed_bytes, output_len=Optional[None], max_expansion_ratio=Optional[float], into=Optional[Buffer])
+    /// >>> # output_len preallocates the output, which still grows if the data decompresses larger
+    /// >>> cramjam.snappy.decompress(compressed_bytes, verify_checksums=False)
     /// ```
     #[pyfunction]
-    #[pyo3(signature = (data, output_len=None))]
-    pub fn decompress(py: Python, data: BytesType, output_len: Option<usize>) -> PyResult<RustyBuffer> {
-        crate::generic!(py, libcramjam::snappy::decompress[data], output_len = output_len)
-            .map_err(DecompressionError::from_err)
+    #[pyo3(signature = (data, output_len=None, max_expansion_ratio=None, into=None, *, output_len_max=None, verify_checksums=true))]
+    pub fn decompress<'py>(
+        py: Python<'py>,
+        data: BytesType,
+        output_len: Option<usize>,
+        max_expansion_ratio: Option<f64>,
+        into: Option<Bound<'py, RustyBuffer>>,
+        output_len_max: Option<usize>,
+        verify_checksums: bool,
+    ) -> PyResult<OutputBuffer<'py>> {
+        crate::generic!(
+            py,
+            decompress_frames[data],
+            max_expansion_ratio = max_expansion_ratio,
+            output_len_max = output_len_max,
+            into = into,
+            output_len = output_len,
+            verify_checksums
+        )
+        .map_err(decompress_error)
     }
 
     /// Snappy compression.
@@ -33,12 +339,21 @@ pub mod snappy {
     /// ```python
     /// >>> _ = cramjam.snappy.compress(b'some bytes here')
     /// >>> _ = cramjam.snappy.compress(bytearray(b'this avoids double allocation in rust side, and thus faster!'))  # <- use bytearray where possible
+    /// >>> _ = cramjam.snappy.compress(b'some bytes here', chunk_size=4096)  # smaller frame chunks, default and max is 65536
     /// ```
     #[pyfunction]
-    #[pyo3(signature = (data, output_len=None))]
-    pub fn compress(py: Python, data: BytesType, output_len: Option<usize>) -> PyResult<RustyBuffer> {
-        crate::generic!(py, libcramjam::snappy::compress[data], output_len = output_len)
-            .map_err(CompressionError::from_err)
+    #[pyo3(signature = (data, output_len=None, chunk_size=None))]
+    pub fn compress(
+        py: Python,
+        data: BytesType,
+        output_len: Option<usize>,
+        chunk_size: Option<usize>,
+    ) -> PyResult<RustyBuffer> {
+        match chunk_size.map(check_chunk_size).transpose()? {
+            Some(chunk_size) => crate::generic!(py, compress_chunked[data], output_len = output_len, chunk_size),
+            None => crate::generic!(py, libcramjam::snappy::compress[data], output_len = output_len),
+        }
+        .map_err(CompressionError::from_err)
     }
 
     /// Snappy decompression, raw
@@ -53,12 +368,42 @@ pub mod snappy {
     #[allow(unused_variables)]
     #[pyo3(signature = (data, output_len=None))]
     pub fn decompress_raw(py: Python, data: BytesType, output_len: Option<usize>) -> PyResult<RustyBuffer> {
-        let bytes = data.as_bytes();
-        py.allow_threads(|| libcramjam::snappy::raw::decompress_vec(bytes))
+        let bytes = data.read_bytes()?;
+        py.allow_threads(|| libcramjam::snappy::raw::decompress_vec(&bytes))
             .map_err(DecompressionError::from_err)
             .map(From::from)
     }
 
+    /// Snappy decompression, raw, of data known to decompress to exactly `uncompressed_len`
+    /// bytes, as wire protocols such as Kafka's and Redis' store it alongside the data.
+    ///
+    /// Unlike `output_len` elsewhere, which only preallocates, a raw block whose own length
+    /// preamble or decompressed data doesn't match `uncompressed_len` raises `DecompressionError`,
+    /// and nothing more than `uncompressed_len` is allocated; see also `lz4.decompress_raw_with_len`.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.snappy.decompress_raw_with_len(compressed_raw_bytes, uncompressed_len)
+    /// ```
+    #[pyfunction]
+    pub fn decompress_raw_with_len(py: Python, data: BytesType, uncompressed_len: usize) -> PyResult<RustyBuffer> {
+        let bytes = data.read_bytes()?;
+        let len = libcramjam::snappy::snap::raw::decompress_len(&bytes).map_err(DecompressionError::from_err)?;
+        if len != uncompressed_len {
+            return Err(DecompressionError::new_err(format!(
+                "Raw block decompresses to {} bytes, but uncompressed_len is {}",
+                len, uncompressed_len
+            )));
+        }
+        py.allow_threads(|| {
+            let mut output = vec![0; len];
+            libcramjam::snappy::raw::decompress(&bytes, &mut output).map(|_| output)
+        })
+        .map_err(DecompressionError::from_err)
+        .map(From::from)
+    }
+
     /// Snappy compression raw.
     /// This does not use the snappy 'framed' encoding of compressed bytes.
     ///
@@ -71,42 +416,107 @@ pub mod snappy {
     #[allow(unused_variables)]
     #[pyo3(signature = (data, output_len=None))]
     pub fn compress_raw(py: Python, data: BytesType, output_len: Option<usize>) -> PyResult<RustyBuffer> {
-        let bytes = data.as_bytes();
-        py.allow_threads(|| libcramjam::snappy::raw::compress_vec(bytes))
+        let bytes = data.read_bytes()?;
+        py.allow_threads(|| libcramjam::snappy::raw::compress_vec(&bytes))
             .map_err(CompressionError::from_err)
             .map(From::from)
     }
 
     /// Compress directly into an output buffer
     #[pyfunction]
-    pub fn compress_into(py: Python, input: BytesType, mut output: BytesType) -> PyResult<usize> {
-        crate::generic!(py, libcramjam::snappy::compress[input, output]).map_err(CompressionError::from_err)
+    #[pyo3(signature = (input, output, chunk_size=None, *, align=None))]
+    pub fn compress_into(
+        py: Python,
+        input: BytesType,
+        mut output: BytesType,
+        chunk_size: Option<usize>,
+        align: Option<usize>,
+    ) -> PyResult<IntoLength> {
+        let written = match chunk_size.map(check_chunk_size).transpose()? {
+            Some(chunk_size) => crate::generic!(py, compress_chunked[input, output], chunk_size),
+            None => crate::generic!(py, libcramjam::snappy::compress[input, output]),
+        }
+        .map_err(CompressionError::from_err)?;
+        crate::io::pad_output(&mut output, written, align)
     }
 
     /// Decompress directly into an output buffer
     #[pyfunction]
     pub fn decompress_into(py: Python, input: BytesType, mut output: BytesType) -> PyResult<usize> {
-        crate::generic!(py, libcramjam::snappy::decompress[input, output]).map_err(DecompressionError::from_err)
+        crate::generic!(py, decompress: libcramjam::snappy::decompress[input, output])
+            .map_err(DecompressionError::from_err)
     }
 
     /// Compress raw format directly into an output buffer
     #[pyfunction]
     pub fn compress_raw_into(py: Python, input: BytesType, mut output: BytesType) -> PyResult<usize> {
-        let bytes_in = input.as_bytes();
+        let bytes_in = input.read_bytes()?;
         let bytes_out = output.as_bytes_mut()?;
-        py.allow_threads(|| libcramjam::snappy::raw::compress(bytes_in, bytes_out))
+        py.allow_threads(|| libcramjam::snappy::raw::compress(&bytes_in, bytes_out))
             .map_err(CompressionError::from_err)
     }
 
     /// Decompress raw format directly into an output buffer
     #[pyfunction]
     pub fn decompress_raw_into(py: Python, input: BytesType, mut output: BytesType) -> PyResult<usize> {
-        let bytes_in = input.as_bytes();
+        let bytes_in = input.read_bytes()?;
         let bytes_out = output.as_bytes_mut()?;
-        py.allow_threads(|| libcramjam::snappy::raw::decompress(bytes_in, bytes_out))
+        py.allow_threads(|| libcramjam::snappy::raw::decompress(&bytes_in, bytes_out))
             .map_err(DecompressionError::from_err)
     }
 
+    /// Snappy compression with the block framing used by Hadoop and Parquet's `hadoop_snappy`,
+    /// where raw snappy chunks are prefixed by 4 byte big endian lengths.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.snappy.compress_hadoop(b'some bytes here', output_len=Optional[int])
+    /// ```
+    #[pyfunction]
+    #[pyo3(signature = (data, output_len=None))]
+    pub fn compress_hadoop(py: Python, data: BytesType, output_len: Option<usize>) -> PyResult<RustyBuffer> {
+        crate::generic!(py, compress_hadoop_blocks[data], output_len = output_len).map_err(CompressionError::from_err)
+    }
+
+    /// Snappy decompression of Hadoop block framed data, see `compress_hadoop`.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.snappy.decompress_hadoop(compressed_bytes, output_len=Optional[int], max_expansion_ratio=Optional[float])
+    /// ```
+    #[pyfunction]
+    #[pyo3(signature = (data, output_len=None, max_expansion_ratio=None, *, output_len_max=None))]
+    pub fn decompress_hadoop(
+        py: Python,
+        data: BytesType,
+        output_len: Option<usize>,
+        max_expansion_ratio: Option<f64>,
+        output_len_max: Option<usize>,
+    ) -> PyResult<RustyBuffer> {
+        crate::generic!(
+            py,
+            decompress_hadoop_blocks[data],
+            max_expansion_ratio = max_expansion_ratio,
+            output_len_max = output_len_max,
+            output_len = output_len
+        )
+        .map_err(DecompressionError::from_err)
+    }
+
+    /// Compress Hadoop block framed data directly into an output buffer
+    #[pyfunction]
+    pub fn compress_hadoop_into(py: Python, input: BytesType, mut output: BytesType) -> PyResult<usize> {
+        crate::generic!(py, compress_hadoop_blocks[input, output]).map_err(CompressionError::from_err)
+    }
+
+    /// Decompress Hadoop block framed data directly into an output buffer
+    #[pyfunction]
+    pub fn decompress_hadoop_into(py: Python, input: BytesType, mut output: BytesType) -> PyResult<usize> {
+        crate::generic!(py, decompress: decompress_hadoop_blocks[input, output]).map_err(DecompressionError::from_err)
+    }
+
     /// Get the expected max compressed length for snappy raw compression; this is the size
     /// of buffer that should be passed to `compress_raw_into`
     #[pyfunction]
@@ -118,45 +528,177 @@ pub mod snappy {
     /// that should be passed to `decompress_raw_into`
     #[pyfunction]
     pub fn decompress_raw_len(data: BytesType) -> PyResult<usize> {
-        libcramjam::snappy::snap::raw::decompress_len(data.as_bytes()).map_err(DecompressionError::from_err)
+        libcramjam::snappy::snap::raw::decompress_len(&data.peek_bytes(10)?).map_err(DecompressionError::from_err)
+    }
+
+    /// Iterate over the chunks of snappy framed `data`, giving `(chunk_type, offset, length, crc)`
+    /// for each without decompressing them, ie. to build an index into a large `.sz` file for
+    /// random access: `offset` is where the chunk's 4 byte header starts, `length` that of its
+    /// body after the header, and `crc` the CRC32C of its uncompressed data, as stored in compressed
+    /// (`0x00`) and uncompressed (`0x01`) chunks, else `None`.
+    ///
+    /// A `cramjam.File` or `SpooledBuffer` is read from its current position, only reading each
+    /// chunk's header and checksum, with `offset`s being positions in it; other data is copied
+    /// up front.
+    /// Data not starting with a stream identifier chunk, or a truncated chunk, raises
+    /// `DecompressionError`.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> index = [(offset, length) for kind, offset, length, _ in cramjam.snappy.chunks(cramjam.File("data.sz")) if kind == 0]
+    /// ```
+    #[pyfunction]
+    pub fn chunks(py: Python, data: BytesType) -> PyResult<ChunkIterator> {
+        let mut source = ReadSource::new(data);
+        let (pos, end) = source
+            .with_reader(py, |reader| {
+                let pos = reader.stream_position()?;
+                let end = reader.seek(SeekFrom::End(0))?;
+                Ok((pos, end))
+            })
+            .map_err(DecompressionError::from_err)?;
+        Ok(ChunkIterator {
+            source,
+            pos,
+            end,
+            started: false,
+        })
+    }
+
+    /// `(chunk_type, offset, length, crc)` of a chunk
+    type Chunk = (u8, u64, usize, Option<u32>);
+
+    /// Iterator over the chunks of snappy framed data, see `chunks`
+    #[pyclass]
+    pub struct ChunkIterator {
+        source: ReadSource,
+        /// Offset of the next chunk
+        pos: u64,
+        /// Length of the data
+        end: u64,
+        /// Whether the stream identifier has been seen
+        started: bool,
+    }
+
+    #[pymethods]
+    impl ChunkIterator {
+        fn __iter__(slf: PyRef<Self>) -> PyRef<Self> {
+            slf
+        }
+
+        fn __next__(&mut self, py: Python) -> PyResult<Option<Chunk>> {
+            let (pos, end) = (self.pos, self.end);
+            let chunk = self.source.with_reader(py, |mut reader| {
+                reader.seek(SeekFrom::Start(pos))?;
+                let (chunk_type, len) = match read_chunk_header(&mut reader)? {
+                    Some(header) => header,
+                    None => return Ok(None),
+                };
+                if pos + 4 + len as u64 > end {
+                    return Err(invalid_frame(std::io::ErrorKind::UnexpectedEof, "chunk is truncated"));
+                }
+                let crc = match chunk_type {
+                    CHUNK_COMPRESSED | CHUNK_UNCOMPRESSED if len < 4 => {
+                        return Err(invalid_frame(std::io::ErrorKind::InvalidData, "chunk is too short"))
+                    }
+                    CHUNK_COMPRESSED | CHUNK_UNCOMPRESSED => {
+                        let mut crc = [0; 4];
+                        reader.read_exact(&mut crc)?;
+                        // stored masked, as the framing format has it
+                        let masked = u32::from_le_bytes(crc).wrapping_sub(0xa282_ead8);
+                        Some(masked.rotate_left(15))
+                    }
+                    _ => None,
+                };
+                Ok(Some((chunk_type, len, crc)))
+            });
+            let result = match chunk {
+                Ok(Some((chunk_type, _, _))) if !self.started && chunk_type != CHUNK_STREAM_IDENTIFIER => {
+                    Err(invalid_frame(
+                        std::io::ErrorKind::InvalidData,
+                        "data doesn't start with a stream identifier",
+                    ))
+                }
+                Ok(Some((chunk_type, len, crc))) => {
+                    self.started = true;
+                    self.pos += 4 + len as u64;
+                    return Ok(Some((chunk_type, pos, len, crc)));
+                }
+                Ok(None) => return Ok(None),
+                Err(err) => Err(err),
+            };
+            // nothing after a broken chunk can be found
+            self.pos = self.end;
+            result.map_err(|err| DecompressionError::new_err(format!("Chunk at offset {}: {}", pos, err)))
+        }
     }
 
+    mod _paths {
+        use super::*;
+        crate::make_path_helpers!("snappy", "sz");
+    }
+    #[pymodule_export]
+    use _paths::{compress_path, decompress_path};
+
+    mod _batch {
+        use super::*;
+        crate::make_batch_helpers!("snappy");
+    }
+    #[pymodule_export]
+    use _batch::{compress_batch, decompress_batch};
+
     /// Snappy Compressor object for streaming compression
     #[pyclass]
     pub struct Compressor {
-        inner: Option<libcramjam::snappy::snap::write::FrameEncoder<Cursor<Vec<u8>>>>,
+        inner: Option<ChunkedEncoder<Cursor<Vec<u8>>>>,
+        returns_output: bool,
     }
 
     #[pymethods]
     impl Compressor {
-        /// Initialize a new `Compressor` instance.
+        /// Initialize a new `Compressor` instance, optionally emitting a frame chunk every
+        /// `chunk_size` bytes of input (default and max is 65536) to trade ratio for latency.
+        ///
+        /// `returns_output=True` makes `compress` return the compressed bytes produced by each
+        /// call, rather than the number of bytes consumed, to stream output without `flush()`.
         #[new]
-        pub fn __init__() -> PyResult<Self> {
-            let inner = libcramjam::snappy::snap::write::FrameEncoder::new(Cursor::new(vec![]));
-            Ok(Self { inner: Some(inner) })
+        #[pyo3(signature = (chunk_size=None, *, returns_output=false))]
+        pub fn __init__(chunk_size: Option<usize>, returns_output: bool) -> PyResult<Self> {
+            let chunk_size = check_chunk_size(chunk_size.unwrap_or(MAX_CHUNK_SIZE))?;
+            let inner = ChunkedEncoder::new(Cursor::new(vec![]), chunk_size);
+            Ok(Self {
+                inner: Some(inner),
+                returns_output,
+            })
         }
 
         /// Compress input into the current compressor's stream.
-        pub fn compress(&mut self, input: &[u8]) -> PyResult<usize> {
-            crate::io::stream_compress(&mut self.inner, input)
+        pub fn compress(&mut self, py: Python, input: &[u8]) -> PyResult<PyObject> {
+            crate::io::stream_compress_output(py, &mut self.inner, input, self.returns_output, 0, |e| e.get_mut())
         }
 
         /// Flush and return current compressed stream
-        pub fn flush(&mut self) -> PyResult<RustyBuffer> {
-            crate::io::stream_flush(&mut self.inner, |e| e.get_mut())
+        pub fn flush(&mut self, py: Python) -> PyResult<RustyBuffer> {
+            py.allow_threads(|| crate::io::stream_flush(&mut self.inner, 0, |e| e.get_mut()))
         }
 
         /// Consume the current compressor state and return the compressed stream
         /// **NB** The compressor will not be usable after this method is called.
-        pub fn finish(&mut self) -> PyResult<RustyBuffer> {
-            crate::io::stream_finish(&mut self.inner, |inner| inner.into_inner().map(|c| c.into_inner()))
+        pub fn finish(&mut self, py: Python) -> PyResult<RustyBuffer> {
+            py.allow_threads(|| {
+                crate::io::stream_finish(&mut self.inner, |inner| inner.into_inner().map(|c| c.into_inner()))
+            })
         }
     }
 
     mod _decompressor {
         use super::*;
-        crate::make_decompressor!(snappy);
+        crate::make_decompressor!(snappy, decompress_stream, verify_checksums: bool = true);
     }
     #[pymodule_export]
     use _decompressor::Decompressor;
+
+    #[pymodule_export]
+    use crate::exceptions::ChecksumError;
 }
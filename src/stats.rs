@@ -0,0 +1,123 @@
+//! Opt-in counters of de/compression calls, for exporting metrics from services.
+//!
+//! Disabled by default, in which case the only cost to each call is checking an atomic flag.
+//! Once enabled, calls through the codecs' `compress`, `decompress`, `compress_into` and
+//! `decompress_into` functions are counted per codec and direction; `store` isn't counted.
+//!
+//! ### Python Example
+//! ```python
+//! >>> cramjam.enable_stats()
+//! >>> _ = cramjam.zstd.compress(b'some bytes here')
+//! >>> cramjam.stats()
+//! {'zstd': {'compress': {'calls': 1, 'bytes_in': 15, 'bytes_out': 24, 'time_ms': 0.01}}}
+//! ```
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Counters keyed by (codec, "compress" | "decompress")
+static REGISTRY: Mutex<BTreeMap<(&'static str, &'static str), Counter>> = Mutex::new(BTreeMap::new());
+
+#[derive(Default)]
+struct Counter {
+    calls: u64,
+    bytes_in: u64,
+    bytes_out: u64,
+    time: Duration,
+}
+
+/// Start, or with `enabled=False` stop, collecting statistics; counts so far are kept.
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> cramjam.enable_stats(enabled=True)
+/// ```
+#[pyfunction]
+#[pyo3(signature = (enabled=true))]
+pub fn enable_stats(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Statistics collected since they were enabled, or last reset, as
+/// `{codec: {"compress" | "decompress": {"calls", "bytes_in", "bytes_out", "time_ms"}}}`.
+/// Only successful calls are counted.
+///
+/// Python Example
+/// --------------
+/// ```python
+/// >>> cramjam.stats(reset=False)  # reset=True zeroes the counters after reading them
+/// ```
+#[pyfunction]
+#[pyo3(signature = (reset=false))]
+pub fn stats<'py>(py: Python<'py>, reset: bool) -> PyResult<Bound<'py, PyDict>> {
+    let mut registry = REGISTRY.lock().unwrap();
+    let output = PyDict::new_bound(py);
+    for ((codec, direction), counter) in registry.iter() {
+        let codec_stats = match output.get_item(codec)? {
+            Some(codec_stats) => codec_stats.downcast_into::<PyDict>()?,
+            None => {
+                let codec_stats = PyDict::new_bound(py);
+                output.set_item(codec, &codec_stats)?;
+                codec_stats
+            }
+        };
+        let counts = PyDict::new_bound(py);
+        counts.set_item("calls", counter.calls)?;
+        counts.set_item("bytes_in", counter.bytes_in)?;
+        counts.set_item("bytes_out", counter.bytes_out)?;
+        counts.set_item("time_ms", counter.time.as_secs_f64() * 1000.)?;
+        codec_stats.set_item(direction, counts)?;
+    }
+    if reset {
+        registry.clear();
+    }
+    Ok(output)
+}
+
+#[inline(always)]
+pub(crate) fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Run `op`, counting it against the codec named by the last segment of `module` (ie.
+/// `cramjam::zstd::zstd`) when enabled. `op_name` tells compression from decompression.
+#[inline(always)]
+pub(crate) fn timed<E>(
+    module: &'static str,
+    op_name: &'static str,
+    bytes_in: usize,
+    op: impl FnOnce() -> Result<usize, E>,
+) -> Result<usize, E> {
+    if !enabled() {
+        return op();
+    }
+    let start = Instant::now();
+    let result = op();
+    if let Ok(bytes_out) = result {
+        record(module, op_name, bytes_in, bytes_out, start.elapsed());
+    }
+    result
+}
+
+fn record(module: &'static str, op_name: &'static str, bytes_in: usize, bytes_out: usize, time: Duration) {
+    let codec = module.rsplit("::").next().unwrap_or(module);
+    let direction = match op_name.rsplit("::").next().map(str::trim) {
+        Some(op) if op.starts_with("decompress") => "decompress",
+        Some(op) if op.starts_with("compress") => "compress",
+        // ie. store's copy, which is the same either way
+        _ => return,
+    };
+    let mut registry = REGISTRY.lock().unwrap();
+    let counter = registry.entry((codec, direction)).or_default();
+    counter.calls += 1;
+    counter.bytes_in += bytes_in as u64;
+    counter.bytes_out += bytes_out as u64;
+    counter.time += time;
+}
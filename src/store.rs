@@ -0,0 +1,85 @@
+//! store (identity) de/compression interface
+use pyo3::prelude::*;
+
+/// store (identity) de/compression interface, copying data unchanged.
+///
+/// Useful as a stand-in codec, ie. to disable compression in a pipeline without special
+/// casing it. Between two `cramjam.File` objects the copy is done by the OS
+/// (`copy_file_range`/`sendfile` on Linux) without passing through userspace or holding the GIL.
+#[pymodule]
+pub mod store {
+
+    use crate::exceptions::{CompressionError, DecompressionError};
+    use crate::io::RustyBuffer;
+    use crate::{AsBytes, BytesType};
+    use pyo3::prelude::*;
+    use pyo3::PyResult;
+    use std::io::{Cursor, Read, Write};
+
+    /// Copy all of `input` to `output`, matching the signature of libcramjam's codecs
+    pub(crate) fn copy<W: Write + ?Sized, R: Read>(mut input: R, output: &mut W) -> std::io::Result<usize> {
+        std::io::copy(&mut input, output).map(|n| n as usize)
+    }
+
+    /// Copy `input` into `output`, using the OS fast path when both are files
+    fn copy_into(py: Python, input: BytesType, mut output: BytesType) -> std::io::Result<usize> {
+        if let (BytesType::RustyFile(f_in), BytesType::RustyFile(f_out)) = (&input, &output) {
+            let mut f_in = f_in.borrow_mut();
+            let mut f_out = f_out.borrow_mut();
+            let (f_in, f_out) = (&mut *f_in, &mut *f_out);
+            return py.allow_threads(|| f_in.copy_to(f_out)).map(|n| n as usize);
+        }
+        crate::generic!(py, copy[input, output])
+    }
+
+    /// store decompression, returns the data unchanged.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.store.decompress(compressed_bytes, output_len=Optional[int], max_expansion_ratio=Optional[float])
+    /// ```
+    #[pyfunction]
+    #[pyo3(signature = (data, output_len=None, max_expansion_ratio=None, *, output_len_max=None))]
+    pub fn decompress(
+        py: Python,
+        data: BytesType,
+        output_len: Option<usize>,
+        max_expansion_ratio: Option<f64>,
+        output_len_max: Option<usize>,
+    ) -> PyResult<RustyBuffer> {
+        crate::generic!(
+            py,
+            copy[data],
+            max_expansion_ratio = max_expansion_ratio,
+            output_len_max = output_len_max,
+            output_len = output_len
+        )
+        .map_err(DecompressionError::from_err)
+    }
+
+    /// store compression, returns the data unchanged.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.store.compress(b'some bytes here', output_len=Optional[int])
+    /// ```
+    #[pyfunction]
+    #[pyo3(signature = (data, output_len=None))]
+    pub fn compress(py: Python, data: BytesType, output_len: Option<usize>) -> PyResult<RustyBuffer> {
+        crate::generic!(py, copy[data], output_len = output_len).map_err(CompressionError::from_err)
+    }
+
+    /// Compress directly into an output buffer
+    #[pyfunction]
+    pub fn compress_into(py: Python, input: BytesType, output: BytesType) -> PyResult<usize> {
+        copy_into(py, input, output).map_err(CompressionError::from_err)
+    }
+
+    /// Decompress directly into an output buffer
+    #[pyfunction]
+    pub fn decompress_into(py: Python, input: BytesType, output: BytesType) -> PyResult<usize> {
+        copy_into(py, input, output).map_err(DecompressionError::from_err)
+    }
+}
@@ -0,0 +1,135 @@
+//! Helpers for downstream libraries to check their interop with cramjam in their own test suites.
+//!
+//! ### Python Example
+//! ```python
+//! >>> for plaintext, compressed in cramjam.testing.reference_vectors("zstd"):
+//! ...     assert my_zstd_decompress(compressed) == plaintext
+//! ...     cramjam.testing.roundtrip("zstd", plaintext, level=3)
+//! ```
+use pyo3::prelude::*;
+
+/// Helpers for downstream libraries to check their interop with cramjam in their own test suites.
+#[pymodule]
+pub mod testing {
+    use pyo3::exceptions::{PyAssertionError, PyValueError};
+    use pyo3::prelude::*;
+    use pyo3::types::{PyBytes, PyDict};
+
+    use crate::io::{AsBytes, RustyBuffer};
+    use crate::BytesType;
+
+    /// Text which each reference vector decompresses to
+    const PLAINTEXT: &[u8] = include_bytes!("../tests/data/integration/plaintext.txt");
+
+    /// [`PLAINTEXT`] compressed by each codec's reference implementation, rather than by cramjam
+    const VECTORS: &[(&str, &[u8])] = &[
+        ("brotli", include_bytes!("../tests/data/integration/plaintext.txt.br")),
+        ("bzip2", include_bytes!("../tests/data/integration/plaintext.txt.bz2")),
+        (
+            "deflate",
+            include_bytes!("../tests/data/integration/plaintext.txt.deflate"),
+        ),
+        ("gzip", include_bytes!("../tests/data/integration/plaintext.txt.gz")),
+        ("lz4", include_bytes!("../tests/data/integration/plaintext.txt.lz4")),
+        (
+            "snappy",
+            include_bytes!("../tests/data/integration/plaintext.txt.snappy"),
+        ),
+        ("xz", include_bytes!("../tests/data/integration/plaintext.txt.lzma")),
+        ("zlib", include_bytes!("../tests/data/integration/plaintext.txt.zz")),
+        ("zstd", include_bytes!("../tests/data/integration/plaintext.txt.zst")),
+    ];
+
+    /// Compress `data` with `cramjam.<codec>.compress(data, **opts)`, then check both
+    /// `decompress` and `decompress_into` give `data` back, raising `AssertionError` if not.
+    ///
+    /// Returns the compressed data, ie. for decompressing with another implementation.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> compressed = cramjam.testing.roundtrip("gzip", b'some bytes here', level=9)
+    /// ```
+    #[pyfunction]
+    #[pyo3(signature = (codec, data, **opts))]
+    pub fn roundtrip(
+        py: Python,
+        codec: &str,
+        data: BytesType,
+        opts: Option<&Bound<'_, PyDict>>,
+    ) -> PyResult<RustyBuffer> {
+        let module = py
+            .import_bound("cramjam")?
+            .getattr(codec)
+            .map_err(|_| PyValueError::new_err(format!("Unknown codec: '{}'", codec)))?;
+        let expected = &*data.read_bytes()?;
+
+        let compressed = module.call_method("compress", (PyBytes::new_bound(py, expected),), opts)?;
+        let compressed = compressed.extract::<BytesType>()?.read_bytes()?.into_owned();
+
+        let decompressed = module.call_method1("decompress", (PyBytes::new_bound(py, &compressed),))?;
+        check(
+            codec,
+            "decompress",
+            expected,
+            &decompressed.extract::<BytesType>()?.read_bytes()?,
+        )?;
+
+        let output = Bound::new(py, RustyBuffer::from(vec![0; expected.len()]))?;
+        let n_bytes = module
+            .call_method1("decompress_into", (PyBytes::new_bound(py, &compressed), &output))?
+            .extract::<usize>()?;
+        check(
+            codec,
+            "decompress_into",
+            expected,
+            &output.borrow().as_bytes()[..n_bytes],
+        )?;
+
+        Ok(RustyBuffer::from(compressed))
+    }
+
+    /// Pairs of `(plaintext, compressed)` for `codec`, the compressed data having been produced
+    /// by the format's reference implementation (ie. the `zstd` CLI), so decompressing it checks
+    /// compatibility with that rather than only with cramjam.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.testing.reference_vectors("snappy")
+    /// [(b'...', b'...')]
+    /// ```
+    #[pyfunction]
+    pub fn reference_vectors<'py>(
+        py: Python<'py>,
+        codec: &str,
+    ) -> PyResult<Vec<(Bound<'py, PyBytes>, Bound<'py, PyBytes>)>> {
+        let vectors = VECTORS
+            .iter()
+            .filter(|(name, _)| *name == codec)
+            .map(|(_, compressed)| (PyBytes::new_bound(py, PLAINTEXT), PyBytes::new_bound(py, compressed)))
+            .collect::<Vec<_>>();
+        if vectors.is_empty() {
+            return Err(PyValueError::new_err(format!(
+                "No reference vectors for codec: '{}'",
+                codec
+            )));
+        }
+        Ok(vectors)
+    }
+
+    fn check(codec: &str, op: &str, expected: &[u8], actual: &[u8]) -> PyResult<()> {
+        if expected == actual {
+            return Ok(());
+        }
+        let mismatch = expected.iter().zip(actual).position(|(a, b)| a != b);
+        Err(PyAssertionError::new_err(format!(
+            "cramjam.{}.{} roundtrip mismatch: expected {} bytes, got {} bytes, first differing at {}",
+            codec,
+            op,
+            expected.len(),
+            actual.len(),
+            mismatch.unwrap_or(expected.len().min(actual.len()))
+        )))
+    }
+}
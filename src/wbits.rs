@@ -0,0 +1,265 @@
+//! zlib's `wbits`, as `zlib.compressobj` and `zlib.decompressobj` take it, for the zlib and
+//! deflate modules: which container deflate data is in, raw, zlib or gzip, and the size of its
+//! window, for protocols and formats needing exactly that, ie. HTTP's permessage-deflate or PNG.
+//!
+//! Its sign and range select the container:
+//!
+//! - `-15` to `-9`: raw deflate, as `cramjam.deflate` by default
+//! - `9` to `15`: a zlib wrapper, as `cramjam.zlib` by default
+//! - `25` to `31`: a gzip wrapper
+//!
+//! And the rest, `9` to `15`, is the base two log of the window size. Compression with a window
+//! smaller than 32KB (`15`) needs cramjam built with the `zlib-ng` feature; with `miniz_oxide`,
+//! it raises `ValueError`. Decompression takes any, and fails on a zlib header of a window
+//! larger than `wbits`, as zlib does.
+//!
+//! ### Python Example
+//! ```python
+//! >>> compressed = cramjam.deflate.compress(b'some bytes here', wbits=31)  # gzip wrapped
+//! >>> gzip.decompress(bytes(compressed))
+//! b'some bytes here'
+//! >>> cramjam.zlib.Decompressor(wbits=-15)  # raw deflate
+//! ```
+use std::io::{self, Error, ErrorKind, Read, Write};
+
+use pyo3::exceptions::PyValueError;
+use pyo3::PyResult;
+
+#[cfg(not(any(feature = "zlib", feature = "zlib-static", feature = "zlib-shared")))]
+use libcramjam::deflate::flate2;
+#[cfg(any(feature = "zlib", feature = "zlib-static", feature = "zlib-shared"))]
+use libcramjam::zlib::flate2;
+
+use flate2::{read, write, Compress, Compression, Decompress};
+
+/// Container of the deflate data
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Container {
+    Raw,
+    Zlib,
+    Gzip,
+}
+
+/// Container and window size given by a `wbits`
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct WindowBits {
+    container: Container,
+    bits: u8,
+}
+
+impl WindowBits {
+    /// Parse `wbits`, `InvalidInput` if it's out of range
+    pub(crate) fn new(wbits: i32) -> io::Result<Self> {
+        let (container, bits) = match wbits {
+            -15..=-9 => (Container::Raw, -wbits),
+            9..=15 => (Container::Zlib, wbits),
+            25..=31 => (Container::Gzip, wbits - 16),
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!(
+                        "Invalid wbits: {}; expected -15 to -9 for raw deflate, 9 to 15 for zlib or 25 to 31 for gzip",
+                        wbits
+                    ),
+                ))
+            }
+        };
+        Ok(Self {
+            container,
+            bits: bits as u8,
+        })
+    }
+
+    /// `wbits` to decompress with, `ValueError` if it's out of range
+    pub(crate) fn decompression(wbits: i32) -> PyResult<Self> {
+        Self::new(wbits).map_err(|err| PyValueError::new_err(err.to_string()))
+    }
+
+    /// `wbits` to compress with, `ValueError` if it's out of range or of a window this build
+    /// can't compress with
+    pub(crate) fn compression(wbits: i32) -> PyResult<Self> {
+        let wbits = Self::decompression(wbits)?;
+        wbits
+            .check_window()
+            .map_err(|err| PyValueError::new_err(err.to_string()))?;
+        Ok(wbits)
+    }
+
+    /// Compressing with a window smaller than 32KB needs zlib's `deflateInit2`
+    fn check_window(&self) -> io::Result<()> {
+        if cfg!(feature = "zlib-ng") || self.bits == 15 {
+            return Ok(());
+        }
+        Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "A window of 2**{} bytes needs cramjam built with the zlib-ng feature, \
+                miniz_oxide only compresses with 2**15",
+                self.bits
+            ),
+        ))
+    }
+
+    /// Streaming encoder writing to `output`
+    pub(crate) fn encoder<W: Write>(&self, output: W, level: Option<u32>) -> io::Result<Encoder<W>> {
+        self.check_window()?;
+        let level = level.map(Compression::new).unwrap_or_default();
+        let compress = match self.container {
+            Container::Raw | Container::Zlib => self.deflate_compress(level),
+            Container::Gzip => match self.gzip_compress(level) {
+                Some(compress) => compress,
+                None => return Ok(Encoder::Gzip(write::GzEncoder::new(output, level))),
+            },
+        };
+        Ok(Encoder::Deflate(write::ZlibEncoder::new_with_compress(
+            output, compress,
+        )))
+    }
+
+    #[cfg(feature = "zlib-ng")]
+    fn deflate_compress(&self, level: Compression) -> Compress {
+        Compress::new_with_window_bits(level, self.container == Container::Zlib, self.bits)
+    }
+
+    #[cfg(not(feature = "zlib-ng"))]
+    fn deflate_compress(&self, level: Compression) -> Compress {
+        Compress::new(level, self.container == Container::Zlib)
+    }
+
+    /// `Compress` writing the gzip header itself, for windows `GzEncoder` doesn't do
+    #[cfg(feature = "zlib-ng")]
+    fn gzip_compress(&self, level: Compression) -> Option<Compress> {
+        (self.bits != 15).then(|| Compress::new_gzip(level, self.bits))
+    }
+
+    #[cfg(not(feature = "zlib-ng"))]
+    fn gzip_compress(&self, _level: Compression) -> Option<Compress> {
+        None
+    }
+
+    /// Check a zlib header, the first two bytes of the data, declares a window within `bits`, as
+    /// zlib does but miniz_oxide doesn't
+    fn check_header(&self, header: &[u8]) -> io::Result<()> {
+        match header.first() {
+            Some(cmf) if self.container == Container::Zlib && (cmf >> 4) + 8 > self.bits => Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "Invalid window size: the data needs wbits of at least {}",
+                    (cmf >> 4) + 8
+                ),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    #[cfg(feature = "zlib-ng")]
+    fn decompressor<'a, R: Read + 'a>(&self, input: R) -> Box<dyn Read + 'a> {
+        let decompress = match self.container {
+            Container::Gzip => Decompress::new_gzip(self.bits),
+            _ => Decompress::new_with_window_bits(self.container == Container::Zlib, self.bits),
+        };
+        Box::new(read::ZlibDecoder::new_with_decompress(input, decompress))
+    }
+
+    #[cfg(not(feature = "zlib-ng"))]
+    fn decompressor<'a, R: Read + 'a>(&self, input: R) -> Box<dyn Read + 'a> {
+        match self.container {
+            Container::Gzip => Box::new(read::GzDecoder::new(input)),
+            _ => Box::new(read::ZlibDecoder::new_with_decompress(
+                input,
+                Decompress::new(self.container == Container::Zlib),
+            )),
+        }
+    }
+}
+
+/// Streaming compression in the container of a `WindowBits`
+pub(crate) enum Encoder<W: Write> {
+    /// Raw and zlib wrapped data, and gzip wrapped with zlib-ng, which `Compress` writes
+    Deflate(write::ZlibEncoder<W>),
+    Gzip(write::GzEncoder<W>),
+}
+
+impl<W: Write> Encoder<W> {
+    pub(crate) fn get_mut(&mut self) -> &mut W {
+        match self {
+            Encoder::Deflate(encoder) => encoder.get_mut(),
+            Encoder::Gzip(encoder) => encoder.get_mut(),
+        }
+    }
+
+    pub(crate) fn finish(self) -> io::Result<W> {
+        match self {
+            Encoder::Deflate(encoder) => encoder.finish(),
+            Encoder::Gzip(encoder) => encoder.finish(),
+        }
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Encoder::Deflate(encoder) => encoder.write(buf),
+            Encoder::Gzip(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Encoder::Deflate(encoder) => encoder.flush(),
+            Encoder::Gzip(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// Counts the bytes written through to `inner`
+struct CountingWriter<'a, W: Write + ?Sized> {
+    inner: &'a mut W,
+    count: usize,
+}
+
+impl<W: Write + ?Sized> Write for CountingWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.count += n;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Compress `input` in the container of `wbits`, returning the bytes written to `output`
+pub(crate) fn compress<W: Write + ?Sized, R: Read>(
+    mut input: R,
+    output: &mut W,
+    level: Option<u32>,
+    wbits: WindowBits,
+) -> io::Result<usize> {
+    let output = CountingWriter {
+        inner: output,
+        count: 0,
+    };
+    let mut encoder = wbits.encoder(output, level)?;
+    io::copy(&mut input, &mut encoder)?;
+    Ok(encoder.finish()?.count)
+}
+
+/// Decompress `input` in the container of `wbits`, returning the bytes written to `output`
+pub(crate) fn decompress<W: Write + ?Sized, R: Read>(
+    mut input: R,
+    output: &mut W,
+    wbits: WindowBits,
+) -> io::Result<usize> {
+    let mut header = Vec::with_capacity(2);
+    (&mut input).take(2).read_to_end(&mut header)?;
+    wbits.check_header(&header)?;
+    let mut decoder = wbits.decompressor(header.as_slice().chain(input));
+    io::copy(&mut decoder, output).map(|n| n as usize)
+}
+
+/// `decompress` taking `wbits` as given to a `Decompressor`
+pub(crate) fn decompress_wbits<W: Write + ?Sized, R: Read>(input: R, output: &mut W, wbits: i32) -> io::Result<usize> {
+    decompress(input, output, WindowBits::new(wbits)?)
+}
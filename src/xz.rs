@@ -9,10 +9,20 @@ pub mod xz {
     use pyo3::PyResult;
 
     use crate::exceptions::{CompressionError, DecompressionError};
-    use crate::io::{AsBytes, RustyBuffer};
+    use crate::frames::FrameIterator;
+    use crate::io::{AsBytes, IntoLength, OutputBuffer, RustyBuffer};
+    use crate::limits::Budget;
     use crate::BytesType;
-    use pyo3::exceptions::PyNotImplementedError;
-    use std::io::Cursor;
+    use libcramjam::xz::xz2::read::XzDecoder;
+    use libcramjam::xz::xz2::stream::{Action, Status, Stream, CONCATENATED, TELL_ANY_CHECK};
+    use pyo3::exceptions::PyValueError;
+    use pyo3::types::PyBytes;
+    use std::io::{BufRead, BufReader, Cursor, Read, Write};
+
+    // liblzma's presets, as for `xz -0` to `xz -9`
+    const MIN_PRESET: u32 = 0;
+    const MAX_PRESET: u32 = 9;
+    const DEFAULT_PRESET: u32 = 6;
 
     /// LZMA compression.
     ///
@@ -23,8 +33,14 @@ pub mod xz {
     /// >>> # Defaults to XZ format, you can use the deprecated LZMA format like this:
     /// >>> _ = cramjam.xz.compress(b'some bytes here', format=cramjam.xz.Format.ALONE)
     /// ```
+    ///
+    /// `unsafe_speed=True` writes XZ streams without an integrity check, as `check=Check.None`;
+    /// corrupted data then decompresses to garbage rather than failing. The check is cheap next
+    /// to LZMA itself, so this gains little, and is only worth it where the transport already
+    /// guarantees integrity.
     #[pyfunction]
-    #[pyo3(signature = (data, preset=None, format=None, check=None, filters=None, options=None, output_len=None))]
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(signature = (data, preset=None, format=None, check=None, filters=None, options=None, output_len=None, *, unsafe_speed=false))]
     pub fn compress(
         py: Python,
         data: BytesType,
@@ -34,7 +50,9 @@ pub mod xz {
         filters: Option<FilterChain>,
         options: Option<Options>,
         output_len: Option<usize>,
+        unsafe_speed: bool,
     ) -> PyResult<RustyBuffer> {
+        let check = unchecked(check, unsafe_speed)?;
         crate::generic!(
             py,
             libcramjam::xz::compress[data],
@@ -48,9 +66,10 @@ pub mod xz {
         .map_err(CompressionError::from_err)
     }
 
-    /// Compress directly into an output buffer
+    /// Compress directly into an output buffer, `unsafe_speed` as for `compress`
     #[pyfunction]
-    #[pyo3(signature = (input, output, preset=None, format=None, check=None, filters=None, options=None))]
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(signature = (input, output, preset=None, format=None, check=None, filters=None, options=None, *, align=None, unsafe_speed=false))]
     pub fn compress_into(
         py: Python,
         input: BytesType,
@@ -60,73 +79,524 @@ pub mod xz {
         check: Option<Check>,
         filters: Option<FilterChain>,
         options: Option<Options>,
-    ) -> PyResult<usize> {
-        crate::generic!(py, libcramjam::xz::compress[input, output], preset, format, check, filters, options)
-            .map_err(CompressionError::from_err)
+        align: Option<usize>,
+        unsafe_speed: bool,
+    ) -> PyResult<IntoLength> {
+        let check = unchecked(check, unsafe_speed)?;
+        let written =
+            crate::generic!(py, libcramjam::xz::compress[input, output], preset, format, check, filters, options)
+                .map_err(CompressionError::from_err)?;
+        crate::io::pad_output(&mut output, written, align)
+    }
+
+    /// `check`, or no check at all if `unsafe_speed`
+    fn unchecked(check: Option<Check>, unsafe_speed: bool) -> PyResult<Option<Check>> {
+        match check {
+            Some(check) if unsafe_speed && check != Check::None => Err(PyValueError::new_err(format!(
+                "check={:?} can't be used with unsafe_speed=True, which leaves the check out",
+                check
+            ))),
+            None if unsafe_speed => Ok(Some(Check::None)),
+            check => Ok(check),
+        }
+    }
+
+    /// Lowest compression `preset` accepted.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.xz.min_level()
+    /// 0
+    /// ```
+    #[pyfunction]
+    pub fn min_level() -> u32 {
+        MIN_PRESET
+    }
+
+    /// Highest compression `preset` accepted.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.xz.max_level()
+    /// 9
+    /// ```
+    #[pyfunction]
+    pub fn max_level() -> u32 {
+        MAX_PRESET
+    }
+
+    /// Compression `preset` used by `compress` when none is given.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.xz.default_level()
+    /// 6
+    /// ```
+    #[pyfunction]
+    pub fn default_level() -> u32 {
+        DEFAULT_PRESET
+    }
+
+    /// Decompress xz or lzma alone data, where xz data may be several streams concatenated, as
+    /// `xz -d` and `lzma.decompress` take it; libcramjam stops after the first stream.
+    pub(crate) fn decompress_streams<W: Write + ?Sized, R: Read>(input: R, output: &mut W) -> std::io::Result<usize> {
+        let mut input = BufReader::new(input);
+        let stream = if input.fill_buf()?.starts_with(XZ_MAGIC) {
+            Stream::new_auto_decoder(u64::MAX, TELL_ANY_CHECK | CONCATENATED)?
+        } else {
+            Stream::new_lzma_decoder(u64::MAX)?
+        };
+        let mut decoder = XzDecoder::new_stream(input, stream);
+        let n_bytes = std::io::copy(&mut decoder, output)?;
+        Ok(n_bytes as usize)
     }
 
     /// LZMA decompression.
     ///
+    /// Streams compressed with `Format.RAW` have no integrity check by default, so decoding them
+    /// can't tell when the data isn't what it should be. `expected_len` and `expected_crc32`, the
+    /// CRC32 of the decompressed data as from `zlib.crc32`, make a mismatch raise
+    /// `DecompressionError` instead.
+    ///
     /// Python Example
     /// --------------
     /// ```python
     /// >>> # bytes or bytearray; bytearray is faster
-    /// >>> cramjam.xz.decompress(compressed_bytes, output_len=Optional[None])
+    /// >>> cramjam.xz.decompress(compressed_bytes, output_len=Optional[None], max_expansion_ratio=Optional[float], into=Optional[Buffer])
+    /// >>> cramjam.xz.decompress(raw_stream, expected_len=len(data), expected_crc32=zlib.crc32(data))
     /// ```
     #[pyfunction]
-    #[pyo3(signature = (data, output_len=None))]
-    pub fn decompress(py: Python, data: BytesType, output_len: Option<usize>) -> PyResult<RustyBuffer> {
-        crate::generic!(py, libcramjam::xz::decompress[data], output_len = output_len)
-            .map_err(DecompressionError::from_err)
+    #[pyo3(signature = (data, output_len=None, max_expansion_ratio=None, into=None, *, output_len_max=None, expected_len=None, expected_crc32=None))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn decompress<'py>(
+        py: Python<'py>,
+        data: BytesType,
+        output_len: Option<usize>,
+        max_expansion_ratio: Option<f64>,
+        into: Option<Bound<'py, RustyBuffer>>,
+        output_len_max: Option<usize>,
+        expected_len: Option<usize>,
+        expected_crc32: Option<u32>,
+    ) -> PyResult<OutputBuffer<'py>> {
+        let output = crate::generic!(
+            py,
+            decompress_streams[data],
+            max_expansion_ratio = max_expansion_ratio,
+            output_len_max = output_len_max,
+            into = into,
+            output_len = output_len
+        )
+        .map_err(DecompressionError::from_err)?;
+        crate::io::verify_output(output, expected_len, expected_crc32)
     }
 
     /// Decompress directly into an output buffer
     #[pyfunction]
     pub fn decompress_into(py: Python, input: BytesType, mut output: BytesType) -> PyResult<usize> {
-        crate::generic!(py, libcramjam::xz::decompress[input, output]).map_err(DecompressionError::from_err)
+        crate::generic!(py, decompress: decompress_streams[input, output]).map_err(DecompressionError::from_err)
+    }
+
+    mod _paths {
+        use super::*;
+        crate::make_path_helpers!("xz", "xz");
     }
+    #[pymodule_export]
+    use _paths::{compress_path, decompress_path};
+
     /// XZ Compressor object for streaming compression
     #[pyclass]
     pub struct Compressor {
         inner: Option<libcramjam::xz::xz2::write::XzEncoder<Cursor<Vec<u8>>>>,
+        returns_output: bool,
+        output_len_hint: usize,
     }
 
     #[pymethods]
     impl Compressor {
         /// Initialize a new `Compressor` instance.
+        ///
+        /// `output_len_hint` is the expected size of the compressed stream, reserved up front
+        /// rather than growing the output repeatedly while compressing large streams.
+        ///
+        /// `unsafe_speed=True` writes the stream without an integrity check, as for `compress`.
+        ///
+        /// `returns_output=True` makes `compress` return the compressed bytes produced by each
+        /// call, rather than the number of bytes consumed, to stream output without `flush()`.
         #[new]
-        #[pyo3(signature = (preset=None))]
-        pub fn __init__(preset: Option<u32>) -> PyResult<Self> {
+        #[pyo3(signature = (preset=None, *, output_len_hint=None, unsafe_speed=false, returns_output=false))]
+        pub fn __init__(
+            preset: Option<u32>,
+            output_len_hint: Option<usize>,
+            unsafe_speed: bool,
+            returns_output: bool,
+        ) -> PyResult<Self> {
             let preset = preset.unwrap_or(5);
-            let inner = libcramjam::xz::xz2::write::XzEncoder::new(Cursor::new(vec![]), preset);
-            Ok(Self { inner: Some(inner) })
+            let output_len_hint = output_len_hint.unwrap_or_default();
+            let output = Cursor::new(Vec::with_capacity(output_len_hint));
+            let check = match unsafe_speed {
+                true => libcramjam::xz::xz2::stream::Check::None,
+                false => libcramjam::xz::xz2::stream::Check::Crc64,
+            };
+            let stream = Stream::new_easy_encoder(preset, check).map_err(CompressionError::from_err)?;
+            let inner = libcramjam::xz::xz2::write::XzEncoder::new_stream(output, stream);
+            Ok(Self {
+                inner: Some(inner),
+                returns_output,
+                output_len_hint,
+            })
         }
 
         /// Compress input into the current compressor's stream.
-        pub fn compress(&mut self, input: &[u8]) -> PyResult<usize> {
-            crate::io::stream_compress(&mut self.inner, input)
+        pub fn compress(&mut self, py: Python, input: &[u8]) -> PyResult<PyObject> {
+            crate::io::stream_compress_output(
+                py,
+                &mut self.inner,
+                input,
+                self.returns_output,
+                self.output_len_hint,
+                |e| e.get_mut(),
+            )
         }
 
-        /// Flush and return current compressed stream
-        pub fn flush(&mut self) -> PyResult<RustyBuffer> {
-            Err(PyNotImplementedError::new_err(
-                "`.flush` for XZ/LZMA not implemented, just use `.finish()` instead when your done.",
-            ))
+        /// Flush and return current compressed stream.
+        ///
+        /// This is liblzma's full flush, which ends the current block, so everything compressed
+        /// so far can be decoded from what's been returned, without waiting for `finish()`. Each
+        /// flush costs a block header and restarts compression without the history before it,
+        /// so flushing often lowers the compression ratio.
+        pub fn flush(&mut self, py: Python) -> PyResult<RustyBuffer> {
+            py.allow_threads(|| {
+                if let Some(inner) = self.inner.as_mut() {
+                    // xz2 leaves the end of the flushed block in its own buffer, writing
+                    // nothing hands it over to the output
+                    inner
+                        .flush()
+                        .and_then(|_| inner.write(&[]))
+                        .map_err(CompressionError::from_err)?;
+                }
+                crate::io::stream_flush(&mut self.inner, self.output_len_hint, |e| e.get_mut())
+            })
         }
 
         /// Consume the current compressor state and return the compressed stream
         /// **NB** The compressor will not be usable after this method is called.
+        pub fn finish(&mut self, py: Python) -> PyResult<RustyBuffer> {
+            py.allow_threads(|| {
+                crate::io::stream_finish(&mut self.inner, |inner| inner.finish().map(|c| c.into_inner()))
+            })
+        }
+
+        /// Bytes currently reserved for the compressed output, ie. to check `output_len_hint`
+        /// was taken up; `0` once finished.
+        pub fn reserved_capacity(&self) -> usize {
+            crate::io::stream_capacity(&self.inner, |e| e.get_ref())
+        }
+    }
+
+    /// Iterate over the streams of concatenated xz `data`, giving `(offset, length, decompressed)`
+    /// for each, where `offset` and `length` locate the compressed stream in `data` and
+    /// `decompressed` is a `cramjam.Buffer`. Stream padding between them is passed over; a
+    /// broken stream raises `DecompressionError` naming its offset, after the streams before it
+    /// were given.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> data = bytes(cramjam.xz.compress(b'first')) + bytes(cramjam.xz.compress(b'second'))
+    /// >>> [(offset, bytes(stream)) for offset, _, stream in cramjam.xz.frames(data)]
+    /// [(0, b'first'), (64, b'second')]
+    /// ```
+    #[pyfunction]
+    pub fn frames(data: BytesType) -> PyResult<FrameIterator> {
+        FrameIterator::new(data, split_stream)
+    }
+
+    /// Split the xz stream starting `data`, see [`crate::frames::SplitFrame`]
+    fn split_stream(data: &[u8]) -> std::io::Result<(usize, Option<Vec<u8>>)> {
+        // stream padding, null bytes which may follow a stream
+        let padding = data.iter().take_while(|b| **b == 0).count();
+        if padding > 0 {
+            return Ok((padding, None));
+        }
+        if !data.starts_with(XZ_MAGIC) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "Not the start of an xz stream",
+            ));
+        }
+        let mut stream = new_decoder()?;
+        let mut output = vec![];
+        loop {
+            if output.capacity() - output.len() < 1024 {
+                // doubling, as `data` may run on well past this stream
+                output.reserve(output.len().max(32 * 1024));
+            }
+            let (in_before, out_before) = (stream.total_in() as usize, output.len());
+            if stream.process_vec(&data[in_before..], &mut output, Action::Run)? == Status::StreamEnd {
+                return Ok((stream.total_in() as usize, Some(output)));
+            }
+            if stream.total_in() as usize == in_before && output.len() == out_before {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "Stream is truncated",
+                ));
+            }
+        }
+    }
+
+    /// Magic bytes starting each xz stream
+    const XZ_MAGIC: &[u8] = b"\xfd7zXZ\x00";
+
+    /// Decompressor object for streaming decompression, decoding input incrementally as it's
+    /// given rather than buffering it, like `lzma.LZMADecompressor()`.
+    ///
+    /// Either format is detected from the start of the data. `eof` is set once a stream has ended,
+    /// and is cleared again if another xz stream follows. Bytes after the last stream which aren't
+    /// another stream are collected in `unused_data`.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> decompressor = cramjam.xz.Decompressor()
+    /// >>> decompressor.decompress(compressed_bytes + b'trailing')
+    /// >>> decompressor.eof, bytes(decompressor.unused_data)
+    /// (True, b'trailing')
+    /// >>> decompressor.finish()
+    /// ```
+    #[pyclass]
+    pub struct Decompressor {
+        stream: Stream,
+        /// A stream has ended, and no other has started since
+        eof: bool,
+        /// Bytes after the end of a stream which may yet be the start of another
+        pending: Vec<u8>,
+        /// Decompressed output, `None` once finished
+        output: Option<Vec<u8>>,
+        unused_data: Vec<u8>,
+        /// Data which isn't a stream was found after the last one
+        trailing: bool,
+    }
+
+    #[pymethods]
+    #[allow(clippy::len_without_is_empty)]
+    impl Decompressor {
+        /// Initialize a new `Decompressor` instance.
+        #[new]
+        pub fn __init__() -> PyResult<Self> {
+            Ok(Self {
+                stream: new_decoder().map_err(DecompressionError::from_err)?,
+                eof: false,
+                pending: vec![],
+                output: Some(vec![]),
+                unused_data: vec![],
+                trailing: false,
+            })
+        }
+
+        /// Length of internal buffer containing decompressed data.
+        pub fn len(&self) -> usize {
+            self.output.as_ref().map(|o| o.len()).unwrap_or_default()
+        }
+
+        /// Decompress this input into the inner buffer, returning the number of bytes decompressed.
+        /// Its output is limited as `decompress` limits it, by `max_expansion_ratio` times the
+        /// size of this input and `output_len_max`.
+        #[pyo3(signature = (input, max_expansion_ratio=None, *, output_len_max=None))]
+        pub fn decompress(
+            &mut self,
+            py: Python,
+            input: BytesType,
+            max_expansion_ratio: Option<f64>,
+            output_len_max: Option<usize>,
+        ) -> PyResult<usize> {
+            if self.output.is_none() {
+                return Err(DecompressionError::new_err(
+                    "Appears `finish()` was called on this instance",
+                ));
+            }
+            let mut budget = Budget::new(crate::limits::output_limit(
+                input.len(),
+                max_expansion_ratio,
+                output_len_max,
+            )?);
+            let mut data = std::mem::take(&mut self.pending);
+            match input {
+                BytesType::RustyFile(f) => {
+                    let mut borrowed = f.borrow_mut();
+                    let f_in = &mut *borrowed;
+                    py.allow_threads(|| f_in.read_to_end(&mut data))?;
+                }
+                BytesType::SpooledBuffer(b) => {
+                    let mut borrowed = b.borrow_mut();
+                    let b_in = &mut *borrowed;
+                    py.allow_threads(|| b_in.read_to_end(&mut data))?;
+                }
+                _ => data.extend_from_slice(input.as_bytes()),
+            }
+            py.allow_threads(|| self.advance(&data, &mut budget))
+                .map_err(DecompressionError::from_err)
+        }
+
+        /// Whether the end of a stream has been reached, with no further stream started.
+        #[getter]
+        pub fn eof(&self) -> bool {
+            self.eof
+        }
+
+        /// Bytes found after the end of the last stream.
+        #[getter]
+        pub fn unused_data<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+            PyBytes::new_bound(py, &self.unused_data)
+        }
+
+        /// Flush and return current decompressed stream.
+        pub fn flush(&mut self) -> PyResult<RustyBuffer> {
+            match self.output.as_mut() {
+                Some(output) => Ok(RustyBuffer::from(std::mem::take(output))),
+                None => Err(DecompressionError::new_err(
+                    "Appears `finish()` was called on this instance",
+                )),
+            }
+        }
+
+        /// Consume the current Decompressor state and return the decompressed stream
+        /// **NB** The Decompressor will not be usable after this method is called.
         pub fn finish(&mut self) -> PyResult<RustyBuffer> {
-            crate::io::stream_finish(&mut self.inner, |inner| inner.finish().map(|c| c.into_inner()))
+            let output = match std::mem::take(&mut self.output) {
+                Some(output) => output,
+                None => {
+                    return Err(DecompressionError::new_err(
+                        "Appears `finish()` was called on this instance",
+                    ))
+                }
+            };
+            if !self.eof && self.stream.total_in() > 0 {
+                return Err(DecompressionError::new_err(
+                    "Compressed data ended before the end-of-stream marker was reached",
+                ));
+            }
+            Ok(RustyBuffer::from(output))
+        }
+
+        /// Read and remove up to `n` bytes, or all by default, from the front of the decompressed
+        /// data; with `readinto` and friends a Decompressor can be wrapped by `io.TextIOWrapper`
+        /// or given to parsers expecting a readable binary file, once it's been fed the input.
+        #[pyo3(signature = (n=-1))]
+        pub fn read<'py>(&mut self, py: Python<'py>, n: isize) -> PyResult<Bound<'py, PyBytes>> {
+            crate::io::stream_read(py, self.output.as_mut(), n)
+        }
+
+        /// Same as `read`, for `io` wrappers
+        #[pyo3(signature = (n=-1))]
+        pub fn read1<'py>(&mut self, py: Python<'py>, n: isize) -> PyResult<Bound<'py, PyBytes>> {
+            self.read(py, n)
+        }
+
+        /// Read decompressed data into `output`, returning the number of bytes read.
+        pub fn readinto(&mut self, mut output: BytesType) -> PyResult<usize> {
+            let buf = output.as_bytes_mut()?;
+            crate::io::stream_readinto(self.output.as_mut(), buf)
+        }
+
+        /// Whether `read` may be called; `True` until the Decompressor is finished or closed.
+        pub fn readable(&self) -> bool {
+            !self.closed()
+        }
+
+        /// Always `False`
+        pub fn writable(&self) -> bool {
+            false
+        }
+
+        /// Always `False`
+        pub fn seekable(&self) -> bool {
+            false
+        }
+
+        /// Discard the Decompressor's state, like `finish()` without returning the output.
+        pub fn close(&mut self) {
+            self.output = None;
+        }
+
+        /// Whether `finish()` or `close()` has been called.
+        #[getter]
+        pub fn closed(&self) -> bool {
+            self.output.is_none()
+        }
+
+        fn __len__(&self) -> usize {
+            self.len()
+        }
+        fn __contains__(&self, py: Python, x: BytesType) -> PyResult<bool> {
+            let bytes = &*x.peek_bytes(usize::MAX)?;
+            Ok(py.allow_threads(|| {
+                self.output
+                    .as_ref()
+                    .map(|o| o.windows(bytes.len()).any(|w| w == bytes))
+                    .unwrap_or_default()
+            }))
+        }
+        fn __repr__(&self) -> String {
+            format!("Decompressor<len={}>", self.len())
+        }
+        fn __bool__(&self) -> bool {
+            self.output.is_some() && self.len() > 0
         }
     }
 
-    mod _decompressor {
-        use super::*;
-        crate::make_decompressor!(xz);
+    impl Decompressor {
+        /// Decode `data`, returning the number of bytes decompressed
+        fn advance(&mut self, mut data: &[u8], budget: &mut Budget) -> std::io::Result<usize> {
+            let output = self.output.as_mut().expect("checked by caller");
+            let output_start = output.len();
+            loop {
+                if self.trailing {
+                    self.unused_data.extend_from_slice(data);
+                    break;
+                }
+                if self.eof {
+                    if data.len() < XZ_MAGIC.len() && XZ_MAGIC.starts_with(data) {
+                        self.pending = data.to_vec();
+                        break;
+                    }
+                    match data.starts_with(XZ_MAGIC) {
+                        true => {
+                            self.stream = new_decoder()?;
+                            self.eof = false;
+                        }
+                        false => self.trailing = true,
+                    }
+                    continue;
+                }
+
+                if output.capacity() - output.len() < 1024 {
+                    output.reserve(data.len().saturating_mul(4).max(32 * 1024));
+                }
+                let (in_before, out_before) = (self.stream.total_in(), output.len());
+                let status = self.stream.process_vec(data, output, Action::Run)?;
+                let consumed = (self.stream.total_in() - in_before) as usize;
+                budget.spend(output.len() - out_before)?;
+                data = &data[consumed..];
+                if status == Status::StreamEnd {
+                    self.eof = true;
+                } else if (data.is_empty() && output.len() < output.capacity())
+                    || (consumed == 0 && output.len() == out_before)
+                {
+                    // needs more input
+                    break;
+                }
+            }
+            Ok(output.len() - output_start)
+        }
+    }
+
+    /// Decoder of a single xz or lzma stream, whichever the data turns out to be
+    fn new_decoder() -> Result<Stream, libcramjam::xz::xz2::stream::Error> {
+        Stream::new_auto_decoder(u64::MAX, 0)
     }
-    #[pymodule_export]
-    use _decompressor::Decompressor;
 
     /// Available Filter IDs
     #[derive(Clone, Debug, PartialEq)]
@@ -205,6 +675,78 @@ pub mod xz {
         pub fn append_filter(&mut self, filter_chain_item: FilterChainItem) {
             self.0.push(filter_chain_item);
         }
+
+        /// Branch/call/jump (BCJ) filter `bcj` followed by LZMA2, the usual chain for compressing
+        /// executables of that architecture. Filters apply when compressing with `Format.RAW`.
+        ///
+        /// Note builds using the bundled liblzma (the default `xz` feature) only include the x86
+        /// and SPARC BCJ filters; chains with others fail to compress unless built with `xz-shared`.
+        ///
+        /// Python Example
+        /// --------------
+        /// ```python
+        /// >>> chain = cramjam.xz.FilterChain.preset_bcj_lzma2(cramjam.xz.Filter.X86, preset=9, dict_size=Optional[int])
+        /// >>> cramjam.xz.compress(data, format=cramjam.xz.Format.RAW, filters=chain)
+        /// ```
+        #[staticmethod]
+        #[pyo3(signature = (bcj, preset=6, dict_size=None))]
+        pub fn preset_bcj_lzma2(bcj: Filter, preset: u32, dict_size: Option<u32>) -> PyResult<Self> {
+            if matches!(bcj, Filter::Lzma1 | Filter::Lzma2) {
+                return Err(PyValueError::new_err(format!("{:?} is not a BCJ filter", bcj)));
+            }
+            // validate here, rather than panic when the chain is used
+            libcramjam::xz::LzmaOptions::new_preset(preset)
+                .map_err(|e| PyValueError::new_err(format!("Invalid preset {}: {}", preset, e)))?;
+            let options = Options {
+                preset: Some(preset),
+                dict_size,
+                ..Default::default()
+            };
+            Ok(Self(vec![
+                FilterChainItem::__init__(bcj, None),
+                FilterChainItem::__init__(Filter::Lzma2, Some(options)),
+            ]))
+        }
+        /// x86 BCJ filter followed by LZMA2, see `preset_bcj_lzma2`
+        #[staticmethod]
+        #[pyo3(signature = (preset=6, dict_size=None))]
+        pub fn preset_x86_lzma2(preset: u32, dict_size: Option<u32>) -> PyResult<Self> {
+            Self::preset_bcj_lzma2(Filter::X86, preset, dict_size)
+        }
+        /// ARM BCJ filter followed by LZMA2, see `preset_bcj_lzma2`
+        #[staticmethod]
+        #[pyo3(signature = (preset=6, dict_size=None))]
+        pub fn preset_arm_lzma2(preset: u32, dict_size: Option<u32>) -> PyResult<Self> {
+            Self::preset_bcj_lzma2(Filter::Arm, preset, dict_size)
+        }
+        /// ARM-Thumb BCJ filter followed by LZMA2, see `preset_bcj_lzma2`
+        #[staticmethod]
+        #[pyo3(signature = (preset=6, dict_size=None))]
+        pub fn preset_armthumb_lzma2(preset: u32, dict_size: Option<u32>) -> PyResult<Self> {
+            Self::preset_bcj_lzma2(Filter::ArmThumb, preset, dict_size)
+        }
+        /// PowerPC BCJ filter followed by LZMA2, see `preset_bcj_lzma2`
+        #[staticmethod]
+        #[pyo3(signature = (preset=6, dict_size=None))]
+        pub fn preset_powerpc_lzma2(preset: u32, dict_size: Option<u32>) -> PyResult<Self> {
+            Self::preset_bcj_lzma2(Filter::PowerPC, preset, dict_size)
+        }
+        /// IA-64 BCJ filter followed by LZMA2, see `preset_bcj_lzma2`
+        #[staticmethod]
+        #[pyo3(signature = (preset=6, dict_size=None))]
+        pub fn preset_ia64_lzma2(preset: u32, dict_size: Option<u32>) -> PyResult<Self> {
+            Self::preset_bcj_lzma2(Filter::Ia64, preset, dict_size)
+        }
+        /// SPARC BCJ filter followed by LZMA2, see `preset_bcj_lzma2`
+        #[staticmethod]
+        #[pyo3(signature = (preset=6, dict_size=None))]
+        pub fn preset_sparc_lzma2(preset: u32, dict_size: Option<u32>) -> PyResult<Self> {
+            Self::preset_bcj_lzma2(Filter::Sparc, preset, dict_size)
+        }
+        /// Number of filters in the chain
+        pub fn __len__(&self) -> usize {
+            self.0.len()
+        }
     }
 
     impl Into<libcramjam::xz::Filters> for FilterChain {
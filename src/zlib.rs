@@ -6,7 +6,8 @@ use pyo3::prelude::*;
 pub mod zlib {
 
     use crate::exceptions::{CompressionError, DecompressionError};
-    use crate::io::{AsBytes, RustyBuffer};
+    use crate::io::{AsBytes, IntoLength, OutputBuffer, RustyBuffer};
+    use crate::wbits::WindowBits;
     use crate::BytesType;
     use pyo3::prelude::*;
     use pyo3::PyResult;
@@ -16,90 +17,198 @@ pub mod zlib {
 
     /// zlib decompression.
     ///
+    /// `wbits` selects the container and window size as for `zlib.decompress`: `9` to `15` for
+    /// zlib, `-15` to `-9` for raw deflate or `25` to `31` for gzip.
+    ///
     /// Python Example
     /// --------------
     /// ```python
-    /// >>> cramjam.zlib.decompress(compressed_bytes, output_len=Optional[int])
+    /// >>> cramjam.zlib.decompress(compressed_bytes, output_len=Optional[int], max_expansion_ratio=Optional[float], into=Optional[Buffer])
+    /// >>> cramjam.zlib.decompress(raw_deflate_bytes, wbits=-15)
     /// ```
     #[pyfunction]
-    #[pyo3(signature = (data, output_len=None))]
-    pub fn decompress(py: Python, data: BytesType, output_len: Option<usize>) -> PyResult<RustyBuffer> {
-        crate::generic!(py, libcramjam::zlib::decompress[data], output_len = output_len)
-            .map_err(DecompressionError::from_err)
+    #[pyo3(signature = (data, output_len=None, max_expansion_ratio=None, into=None, *, output_len_max=None, wbits=15))]
+    pub fn decompress<'py>(
+        py: Python<'py>,
+        data: BytesType,
+        output_len: Option<usize>,
+        max_expansion_ratio: Option<f64>,
+        into: Option<Bound<'py, RustyBuffer>>,
+        output_len_max: Option<usize>,
+        wbits: i32,
+    ) -> PyResult<OutputBuffer<'py>> {
+        let wbits = WindowBits::decompression(wbits)?;
+        crate::generic!(
+            py,
+            crate::wbits::decompress[data],
+            max_expansion_ratio = max_expansion_ratio,
+            output_len_max = output_len_max,
+            into = into,
+            output_len = output_len,
+            wbits
+        )
+        .map_err(DecompressionError::from_err)
     }
 
     /// zlib compression.
     ///
+    /// `wbits` selects the container and window size as for `zlib.compressobj`: `9` to `15` for
+    /// zlib, `-15` to `-9` for raw deflate or `25` to `31` for gzip. Windows smaller than `15`
+    /// need cramjam built with the `zlib-ng` feature.
+    ///
     /// Python Example
     /// --------------
     /// ```python
     /// >>> cramjam.zlib.compress(b'some bytes here', level=2, output_len=Optional[int])  # Level defaults to 6
+    /// >>> cramjam.zlib.compress(b'some bytes here', wbits=-15)  # raw deflate
     /// ```
     #[pyfunction]
-    #[pyo3(signature = (data, level=None, output_len=None))]
+    #[pyo3(signature = (data, level=None, output_len=None, *, wbits=15))]
     pub fn compress(
         py: Python,
         data: BytesType,
         level: Option<u32>,
         output_len: Option<usize>,
+        wbits: i32,
     ) -> PyResult<RustyBuffer> {
-        crate::generic!(py, libcramjam::zlib::compress[data], output_len = output_len, level)
+        let wbits = WindowBits::compression(wbits)?;
+        crate::generic!(py, crate::wbits::compress[data], output_len = output_len, level, wbits)
             .map_err(CompressionError::from_err)
     }
 
     /// Compress directly into an output buffer
     #[pyfunction]
-    #[pyo3(signature = (input, output, level=None))]
-    pub fn compress_into(py: Python, input: BytesType, mut output: BytesType, level: Option<u32>) -> PyResult<usize> {
-        crate::generic!(py, libcramjam::zlib::compress[input, output], level).map_err(CompressionError::from_err)
+    #[pyo3(signature = (input, output, level=None, *, align=None, wbits=15))]
+    pub fn compress_into(
+        py: Python,
+        input: BytesType,
+        mut output: BytesType,
+        level: Option<u32>,
+        align: Option<usize>,
+        wbits: i32,
+    ) -> PyResult<IntoLength> {
+        let wbits = WindowBits::compression(wbits)?;
+        let written = crate::generic!(py, crate::wbits::compress[input, output], level, wbits)
+            .map_err(CompressionError::from_err)?;
+        crate::io::pad_output(&mut output, written, align)
+    }
+
+    /// Lowest compression `level` accepted.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.zlib.min_level()
+    /// 0
+    /// ```
+    #[pyfunction]
+    pub fn min_level() -> u32 {
+        libcramjam::zlib::flate2::Compression::none().level()
+    }
+
+    /// Highest compression `level` accepted.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.zlib.max_level()
+    /// 9
+    /// ```
+    #[pyfunction]
+    pub fn max_level() -> u32 {
+        libcramjam::zlib::flate2::Compression::best().level()
+    }
+
+    /// Compression `level` used by `compress` when none is given.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.zlib.default_level()
+    /// 6
+    /// ```
+    #[pyfunction]
+    pub fn default_level() -> u32 {
+        DEFAULT_COMPRESSION_LEVEL
+    }
+
+    /// Implementation zlib data is de/compressed with: `"zlib-ng"` when cramjam was built with
+    /// the `zlib-ng` feature, otherwise `"miniz_oxide"`.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.zlib.backend()
+    /// 'miniz_oxide'
+    /// ```
+    #[pyfunction]
+    pub fn backend() -> &'static str {
+        crate::flate2_backend()
     }
 
     /// Decompress directly into an output buffer
     #[pyfunction]
-    pub fn decompress_into(py: Python, input: BytesType, mut output: BytesType) -> PyResult<usize> {
-        crate::generic!(py, libcramjam::zlib::decompress[input, output]).map_err(DecompressionError::from_err)
+    #[pyo3(signature = (input, output, *, wbits=15))]
+    pub fn decompress_into(py: Python, input: BytesType, mut output: BytesType, wbits: i32) -> PyResult<usize> {
+        let wbits = WindowBits::decompression(wbits)?;
+        crate::generic!(py, decompress: crate::wbits::decompress[input, output], wbits)
+            .map_err(DecompressionError::from_err)
     }
 
+    mod _paths {
+        use super::*;
+        crate::make_path_helpers!("zlib", "zz");
+    }
+    #[pymodule_export]
+    use _paths::{compress_path, decompress_path};
+
     /// zlib Compressor object for streaming compression
     #[pyclass]
     pub struct Compressor {
-        inner: Option<libcramjam::zlib::flate2::write::GzEncoder<Cursor<Vec<u8>>>>,
+        inner: Option<crate::wbits::Encoder<Cursor<Vec<u8>>>>,
+        returns_output: bool,
     }
 
     #[pymethods]
     impl Compressor {
-        /// Initialize a new `Compressor` instance.
+        /// Initialize a new `Compressor` instance, in the container and window size of `wbits`
+        /// as for `compress`.
+        ///
+        /// `returns_output=True` makes `compress` return the compressed bytes produced by each
+        /// call, rather than the number of bytes consumed, to stream output without `flush()`.
         #[new]
-        #[pyo3(signature = (level=None))]
-        pub fn __init__(level: Option<u32>) -> PyResult<Self> {
+        #[pyo3(signature = (level=None, wbits=15, *, returns_output=false))]
+        pub fn __init__(level: Option<u32>, wbits: i32, returns_output: bool) -> PyResult<Self> {
             let level = level.unwrap_or(DEFAULT_COMPRESSION_LEVEL);
-            let inner = libcramjam::zlib::flate2::write::GzEncoder::new(
-                Cursor::new(vec![]),
-                libcramjam::zlib::flate2::Compression::new(level),
-            );
-            Ok(Self { inner: Some(inner) })
+            let inner = WindowBits::compression(wbits)?.encoder(Cursor::new(vec![]), Some(level))?;
+            Ok(Self {
+                inner: Some(inner),
+                returns_output,
+            })
         }
 
         /// Compress input into the current compressor's stream.
-        pub fn compress(&mut self, input: &[u8]) -> PyResult<usize> {
-            crate::io::stream_compress(&mut self.inner, input)
+        pub fn compress(&mut self, py: Python, input: &[u8]) -> PyResult<PyObject> {
+            crate::io::stream_compress_output(py, &mut self.inner, input, self.returns_output, 0, |e| e.get_mut())
         }
 
         /// Flush and return current compressed stream
-        pub fn flush(&mut self) -> PyResult<RustyBuffer> {
-            crate::io::stream_flush(&mut self.inner, |e| e.get_mut())
+        pub fn flush(&mut self, py: Python) -> PyResult<RustyBuffer> {
+            py.allow_threads(|| crate::io::stream_flush(&mut self.inner, 0, |e| e.get_mut()))
         }
 
         /// Consume the current compressor state and return the compressed stream
         /// **NB** The compressor will not be usable after this method is called.
-        pub fn finish(&mut self) -> PyResult<RustyBuffer> {
-            crate::io::stream_finish(&mut self.inner, |inner| inner.finish().map(|c| c.into_inner()))
+        pub fn finish(&mut self, py: Python) -> PyResult<RustyBuffer> {
+            py.allow_threads(|| {
+                crate::io::stream_finish(&mut self.inner, |inner| inner.finish().map(|c| c.into_inner()))
+            })
         }
     }
 
     mod _decompressor {
         use super::*;
-        crate::make_decompressor!(zlib);
+        crate::make_decompressor!(zlib, crate::wbits::decompress_wbits, wbits: i32 = 15);
     }
     #[pymodule_export]
     use _decompressor::Decompressor;
@@ -5,26 +5,49 @@ use pyo3::prelude::*;
 #[pymodule]
 pub mod zstd {
     use crate::exceptions::{CompressionError, DecompressionError};
-    use crate::io::RustyBuffer;
+    use crate::frames::FrameIterator;
+    use crate::io::{IntoLength, OutputBuffer, RustyBuffer};
     use crate::{AsBytes, BytesType};
+    use pyo3::exceptions::PyValueError;
     use pyo3::prelude::*;
     use pyo3::PyResult;
     use std::io::Cursor;
 
     const DEFAULT_COMPRESSION_LEVEL: i32 = 0;
 
+    /// Longest a zstd frame header can be, the magic number included
+    const FRAME_HEADER_LEN_MAX: usize = 18;
+
+    /// Length of a skippable frame's magic number and size
+    const SKIPPABLE_HEADER_LEN: usize = 8;
+
     /// ZSTD decompression.
     ///
     /// Python Example
     /// --------------
     /// ```python
-    /// >>> cramjam.zstd.decompress(compressed_bytes, output_len=Optional[int])
+    /// >>> cramjam.zstd.decompress(compressed_bytes, output_len=Optional[int], max_expansion_ratio=Optional[float], into=Optional[Buffer])
     /// ```
     #[pyfunction]
-    #[pyo3(signature = (data, output_len=None))]
-    pub fn decompress(py: Python, data: BytesType, output_len: Option<usize>) -> PyResult<RustyBuffer> {
-        crate::generic!(py, libcramjam::zstd::decompress[data], output_len = output_len)
-            .map_err(DecompressionError::from_err)
+    #[pyo3(signature = (data, output_len=None, max_expansion_ratio=None, into=None, *, output_len_max=None))]
+    pub fn decompress<'py>(
+        py: Python<'py>,
+        data: BytesType,
+        output_len: Option<usize>,
+        max_expansion_ratio: Option<f64>,
+        into: Option<Bound<'py, RustyBuffer>>,
+        output_len_max: Option<usize>,
+    ) -> PyResult<OutputBuffer<'py>> {
+        let dict_id = frame_dict_id(&data);
+        crate::generic!(
+            py,
+            libcramjam::zstd::decompress[data],
+            max_expansion_ratio = max_expansion_ratio,
+            output_len_max = output_len_max,
+            into = into,
+            output_len = output_len
+        )
+        .map_err(|err| decompression_error(py, err, dict_id))
     }
 
     /// ZSTD compression.
@@ -48,50 +71,382 @@ pub mod zstd {
 
     /// Compress directly into an output buffer
     #[pyfunction]
-    #[pyo3(signature = (input, output, level=None))]
-    pub fn compress_into(py: Python, input: BytesType, mut output: BytesType, level: Option<i32>) -> PyResult<usize> {
-        crate::generic!(py, libcramjam::zstd::compress[input, output], level).map_err(CompressionError::from_err)
+    #[pyo3(signature = (input, output, level=None, *, align=None))]
+    pub fn compress_into(
+        py: Python,
+        input: BytesType,
+        mut output: BytesType,
+        level: Option<i32>,
+        align: Option<usize>,
+    ) -> PyResult<IntoLength> {
+        let written =
+            crate::generic!(py, libcramjam::zstd::compress[input, output], level).map_err(CompressionError::from_err)?;
+        crate::io::pad_output(&mut output, written, align)
+    }
+
+    /// Lowest compression `level` accepted.
+    /// Negative levels trade ratio for speed.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.zstd.min_level()
+    /// -131072
+    /// ```
+    #[pyfunction]
+    pub fn min_level() -> i32 {
+        *libcramjam::zstd::zstd::compression_level_range().start()
+    }
+
+    /// Highest compression `level` accepted.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.zstd.max_level()
+    /// 22
+    /// ```
+    #[pyfunction]
+    pub fn max_level() -> i32 {
+        *libcramjam::zstd::zstd::compression_level_range().end()
+    }
+
+    /// Compression `level` used by `compress` when none is given.
+    /// Level `0`, used when no level is given, also means this default.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.zstd.default_level()
+    /// 3
+    /// ```
+    #[pyfunction]
+    pub fn default_level() -> i32 {
+        libcramjam::zstd::zstd::DEFAULT_COMPRESSION_LEVEL
     }
 
     /// Decompress directly into an output buffer
     #[pyfunction]
     pub fn decompress_into<'a>(py: Python<'a>, input: BytesType<'a>, mut output: BytesType<'a>) -> PyResult<usize> {
-        crate::generic!(py, libcramjam::zstd::decompress[input, output]).map_err(DecompressionError::from_err)
+        let dict_id = frame_dict_id(&input);
+        crate::generic!(py, decompress: libcramjam::zstd::decompress[input, output])
+            .map_err(|err| decompression_error(py, err, dict_id))
     }
 
+    /// Verify zstd data is intact, decompressing all frames and checking any content
+    /// checksums while discarding the output, like `zstd -t`.
+    /// Returns the number of decompressed bytes.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.zstd.test(compressed_bytes)
+    /// 15
+    /// ```
+    #[pyfunction]
+    pub fn test(py: Python, data: BytesType) -> PyResult<usize> {
+        let dict_id = frame_dict_id(&data);
+        crate::generic!(py, libcramjam::zstd::decompress[data], discard)
+            .map_err(|err| decompression_error(py, err, dict_id))
+    }
+
+    /// Iterate over the frames of concatenated zstd `data`, giving `(offset, length, decompressed)`
+    /// for each, where `offset` and `length` locate the compressed frame in `data` and
+    /// `decompressed` is a `cramjam.Buffer`. Skippable frames are passed over; a broken frame
+    /// raises `DecompressionError` naming its offset, after the frames before it were given.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> data = bytes(cramjam.zstd.compress(b'first')) + bytes(cramjam.zstd.compress(b'second'))
+    /// >>> [(offset, length, bytes(frame)) for offset, length, frame in cramjam.zstd.frames(data)]
+    /// [(0, 14, b'first'), (14, 15, b'second')]
+    /// ```
+    #[pyfunction]
+    pub fn frames(data: BytesType) -> PyResult<FrameIterator> {
+        FrameIterator::new(data, split_frame)
+    }
+
+    /// Split the zstd frame starting `data`, see [`crate::frames::SplitFrame`]
+    fn split_frame(data: &[u8]) -> std::io::Result<(usize, Option<Vec<u8>>)> {
+        let len = compressed_frame_len(data)?;
+        if crate::frames::skippable_frame_len(data).is_some() {
+            return Ok((len, None));
+        }
+        let mut frame = vec![];
+        libcramjam::zstd::decompress(&data[..len], &mut frame)?;
+        Ok((len, Some(frame)))
+    }
+
+    /// Compressed length of the frame starting `data`
+    fn compressed_frame_len(data: &[u8]) -> std::io::Result<usize> {
+        libcramjam::zstd::zstd::zstd_safe::find_frame_compressed_size(data).map_err(|code| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                libcramjam::zstd::zstd::zstd_safe::get_error_name(code),
+            )
+        })
+    }
+
+    /// Get the dictionary ID recorded in the zstd frame header, or `None` if the frame
+    /// was compressed without a dictionary (or its ID was not recorded).
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.zstd.get_dict_id(compressed_bytes)
+    /// 32768
+    /// ```
+    #[pyfunction]
+    pub fn get_dict_id(data: BytesType) -> Option<u32> {
+        frame_dict_id(&data)
+    }
+
+    /// Decompressed size recorded in the header of the zstd frame starting `data`, or `None` if
+    /// it wasn't recorded, ie. by streaming compression; `0` for a skippable frame. Raises
+    /// `DecompressionError` if `data` doesn't start with a frame header. A `File` is read from its
+    /// current position, which is left as it was, as for the other frame functions.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> size = cramjam.zstd.frame_content_size(compressed)
+    /// >>> if size is None:  # fall back to a bound of it
+    /// ...     size = cramjam.zstd.decompress_bound(compressed)
+    /// >>> cramjam.zstd.decompress_into(compressed, cramjam.Buffer(capacity=size))
+    /// ```
+    #[pyfunction]
+    pub fn frame_content_size(data: BytesType) -> PyResult<Option<u64>> {
+        libcramjam::zstd::zstd::zstd_safe::get_frame_content_size(&data.peek_bytes(FRAME_HEADER_LEN_MAX)?)
+            .map_err(|_| DecompressionError::new_err("Invalid or truncated zstd frame header"))
+    }
+
+    /// Compressed size of the zstd or skippable frame starting `data`, where the next frame of
+    /// concatenated frames starts. Raises `DecompressionError` if the frame is invalid or truncated.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.zstd.frame_compressed_size(bytes(cramjam.zstd.compress(b'first')) + b'...')
+    /// 14
+    /// ```
+    #[pyfunction]
+    pub fn frame_compressed_size(data: BytesType) -> PyResult<usize> {
+        compressed_frame_len(&data.peek_bytes(usize::MAX)?).map_err(DecompressionError::from_err)
+    }
+
+    /// Whether `data` starts with a skippable frame, which holds no compressed data, ie. metadata
+    /// such as the seek table of `cramjam.zstd.seekable`.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> cramjam.zstd.is_skippable_frame(b'\x50\x2a\x4d\x18\x00\x00\x00\x00')
+    /// True
+    /// ```
+    #[pyfunction]
+    pub fn is_skippable_frame(data: BytesType) -> PyResult<bool> {
+        Ok(crate::frames::skippable_frame_len(&data.peek_bytes(SKIPPABLE_HEADER_LEN)?).is_some())
+    }
+
+    /// Upper bound of the decompressed size of all the zstd frames of `data`, exact for frames
+    /// recording their content size, to size an output for `decompress_into`. Raises
+    /// `DecompressionError` if a frame is invalid or truncated.
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> output = cramjam.Buffer(capacity=cramjam.zstd.decompress_bound(compressed))
+    /// ```
+    #[pyfunction]
+    pub fn decompress_bound(data: BytesType) -> PyResult<u64> {
+        libcramjam::zstd::zstd::zstd_safe::decompress_bound(&data.peek_bytes(usize::MAX)?)
+            .map_err(|code| DecompressionError::new_err(libcramjam::zstd::zstd::zstd_safe::get_error_name(code)))
+    }
+
+    fn frame_dict_id(data: &BytesType) -> Option<u32> {
+        match data {
+            BytesType::RustyFile(_) | BytesType::SpooledBuffer(_) => None,
+            _ => libcramjam::zstd::zstd::zstd_safe::get_dict_id_from_frame(data.as_bytes()).map(|id| id.get()),
+        }
+    }
+
+    /// A failure to decompress a frame which requires a dictionary is otherwise reported as
+    /// generic corruption; point at the dictionary which is needed instead, also available
+    /// as the `dict_id` attribute of the raised `DecompressionError`.
+    fn decompression_error(py: Python, err: std::io::Error, dict_id: Option<u32>) -> PyErr {
+        match dict_id {
+            Some(id) => {
+                let err = DecompressionError::new_err(format!(
+                    "{} (frame was compressed with dictionary ID {}, which must be used for decompression)",
+                    err, id
+                ));
+                let _ = err.value_bound(py).setattr("dict_id", id);
+                err
+            }
+            None => DecompressionError::from_err(err),
+        }
+    }
+
+    mod _paths {
+        use super::*;
+        crate::make_path_helpers!("zstd", "zst");
+    }
+    #[pymodule_export]
+    use _paths::{compress_path, decompress_path};
+
+    mod _batch {
+        use super::*;
+        crate::make_batch_helpers!("zstd", level);
+    }
+    #[pymodule_export]
+    use _batch::{compress_batch, decompress_batch};
+
+    /// Streaming encoder, holding the zstd context directly so it can be reset between frames
+    type Encoder = libcramjam::zstd::zstd::stream::zio::Writer<
+        Cursor<Vec<u8>>,
+        libcramjam::zstd::zstd::stream::raw::Encoder<'static>,
+    >;
+
     /// ZSTD Compressor object for streaming compression
     #[pyclass]
     pub struct Compressor {
-        inner: Option<libcramjam::zstd::zstd::stream::write::Encoder<'static, Cursor<Vec<u8>>>>,
+        inner: Option<Encoder>,
+        returns_output: bool,
+        output_len_hint: usize,
     }
 
     #[pymethods]
     impl Compressor {
         /// Initialize a new `Compressor` instance.
+        ///
+        /// `output_len_hint` is the expected size of the compressed stream, reserved up front
+        /// rather than growing the output repeatedly while compressing large streams.
+        ///
+        /// `workers` compresses on that many background threads, like the zstd CLI's `-T`.
+        /// With workers, `rsyncable=True` makes the output rsync friendly, so a change to the
+        /// input only changes the output near it (`--rsyncable`), and `job_size` sets the bytes
+        /// of input each worker compresses at a time (`--block-size`).
+        ///
+        /// Python Example
+        /// --------------
+        /// ```python
+        /// >>> compressor = cramjam.zstd.Compressor(level=3, workers=4, rsyncable=True, job_size=8 << 20)
+        /// ```
+        ///
+        /// `returns_output=True` makes `compress` return the compressed bytes produced by each
+        /// call, rather than the number of bytes consumed, to stream output without `flush()`.
         #[new]
-        #[pyo3(signature = (level=None))]
-        pub fn __init__(level: Option<i32>) -> PyResult<Self> {
-            let inner = libcramjam::zstd::zstd::stream::write::Encoder::new(
-                Cursor::new(vec![]),
-                level.unwrap_or(DEFAULT_COMPRESSION_LEVEL),
-            )?;
-            Ok(Self { inner: Some(inner) })
+        #[pyo3(signature = (level=None, *, output_len_hint=None, workers=None, rsyncable=false, job_size=None, returns_output=false))]
+        pub fn __init__(
+            level: Option<i32>,
+            output_len_hint: Option<usize>,
+            workers: Option<u32>,
+            rsyncable: bool,
+            job_size: Option<u32>,
+            returns_output: bool,
+        ) -> PyResult<Self> {
+            use libcramjam::zstd::zstd::stream::raw::CParameter;
+
+            let mut operation =
+                libcramjam::zstd::zstd::stream::raw::Encoder::new(level.unwrap_or(DEFAULT_COMPRESSION_LEVEL))?;
+            let workers = workers.unwrap_or_default();
+            if workers == 0 && (rsyncable || job_size.is_some()) {
+                return Err(PyValueError::new_err(
+                    "rsyncable and job_size need workers of at least 1",
+                ));
+            }
+            let mut parameters = vec![];
+            if workers > 0 {
+                parameters.push(CParameter::NbWorkers(workers));
+            }
+            if rsyncable {
+                parameters.push(CParameter::RSyncable(true));
+            }
+            if let Some(job_size) = job_size {
+                parameters.push(CParameter::JobSize(job_size));
+            }
+            for parameter in parameters {
+                operation.set_parameter(parameter).map_err(CompressionError::from_err)?;
+            }
+            let output_len_hint = output_len_hint.unwrap_or_default();
+            let output = Cursor::new(Vec::with_capacity(output_len_hint));
+            let inner = Encoder::new(output, operation);
+            Ok(Self {
+                inner: Some(inner),
+                returns_output,
+                output_len_hint,
+            })
         }
 
         /// Compress input into the current compressor's stream.
-        pub fn compress(&mut self, input: &[u8]) -> PyResult<usize> {
-            crate::io::stream_compress(&mut self.inner, input)
+        pub fn compress(&mut self, py: Python, input: &[u8]) -> PyResult<PyObject> {
+            crate::io::stream_compress_output(
+                py,
+                &mut self.inner,
+                input,
+                self.returns_output,
+                self.output_len_hint,
+                |e| e.writer_mut(),
+            )
         }
 
         /// Flush and return current compressed stream
-        pub fn flush(&mut self) -> PyResult<RustyBuffer> {
-            crate::io::stream_flush(&mut self.inner, |e| e.get_mut())
+        pub fn flush(&mut self, py: Python) -> PyResult<RustyBuffer> {
+            py.allow_threads(|| crate::io::stream_flush(&mut self.inner, self.output_len_hint, |e| e.writer_mut()))
+        }
+
+        /// End the current frame and return the compressed stream up to and including it.
+        ///
+        /// Unlike `finish()`, the compressor remains usable; anything compressed afterwards
+        /// goes into a new frame, reusing the same zstd context and buffers. Useful for
+        /// long lived compressors emitting a self contained frame per message.
+        ///
+        /// Python Example
+        /// --------------
+        /// ```python
+        /// >>> compressor = cramjam.zstd.Compressor()
+        /// >>> compressor.compress(b'first message')
+        /// >>> frame = compressor.end_frame()
+        /// >>> compressor.compress(b'second message')
+        /// >>> bytes(cramjam.zstd.decompress(compressor.end_frame()))
+        /// b'second message'
+        /// ```
+        pub fn end_frame(&mut self, py: Python) -> PyResult<RustyBuffer> {
+            use libcramjam::zstd::zstd::stream::raw::Operation;
+
+            let inner = self.inner.as_mut().ok_or_else(|| {
+                CompressionError::new_err(
+                    "Compressor looks to have been consumed via `finish()`. \
+                    please create a new compressor instance.",
+                )
+            })?;
+            py.allow_threads(|| inner.finish())
+                .map_err(CompressionError::from_err)?;
+            let frame =
+                py.allow_threads(|| crate::io::stream_flush(&mut self.inner, self.output_len_hint, |e| e.writer_mut()))?;
+
+            // the writer won't start another frame once finished, so rebuild it around the same parts
+            let (output, mut operation) = self.inner.take().unwrap().into_inner();
+            operation.reinit().map_err(CompressionError::from_err)?;
+            self.inner = Some(Encoder::new(output, operation));
+            Ok(frame)
         }
 
         /// Consume the current compressor state and return the compressed stream
         /// **NB** The compressor will not be usable after this method is called.
-        pub fn finish(&mut self) -> PyResult<RustyBuffer> {
-            crate::io::stream_finish(&mut self.inner, |inner| inner.finish().map(|v| v.into_inner()))
+        pub fn finish(&mut self, py: Python) -> PyResult<RustyBuffer> {
+            py.allow_threads(|| {
+                crate::io::stream_finish(&mut self.inner, |mut inner| {
+                    inner.finish().map(|_| inner.into_inner().0.into_inner())
+                })
+            })
+        }
+
+        /// Bytes currently reserved for the compressed output, ie. to check `output_len_hint`
+        /// was taken up; `0` once finished.
+        pub fn reserved_capacity(&self) -> usize {
+            crate::io::stream_capacity(&self.inner, |e| e.writer())
         }
     }
 
@@ -101,4 +456,514 @@ pub mod zstd {
     }
     #[pymodule_export]
     use _decompressor::Decompressor;
+
+    /// The zstd seekable format: data compressed into independent frames of a fixed decompressed
+    /// size, followed by a seek table of their sizes in a skippable frame, so a reader can jump to
+    /// any offset and decompress only the frames covering it. Any zstd decompressor reads the data
+    /// as a whole, passing over the seek table.
+    ///
+    /// See the [format](https://github.com/facebook/zstd/blob/dev/contrib/seekable_format/zstd_seekable_compression_format.md).
+    ///
+    /// Python Example
+    /// --------------
+    /// ```python
+    /// >>> with open("data.zst", "wb") as f, cramjam.zstd.seekable.Writer(f, frame_size=1 << 20) as writer:
+    /// ...     writer.write(b'some bytes here' * 1_000_000)
+    /// >>> reader = cramjam.zstd.seekable.Reader(cramjam.File("data.zst", read=True))
+    /// >>> reader.seek(10_000_000)
+    /// >>> bytes(reader.read(15))
+    /// b'some bytes here'
+    /// ```
+    #[pymodule]
+    pub mod seekable {
+        use super::DEFAULT_COMPRESSION_LEVEL;
+        use crate::exceptions::{CompressionError, DecompressionError};
+        use crate::io::{AsBytes, ReadSeek, ReadSource, RustyBuffer};
+        use crate::BytesType;
+        use libcramjam::zstd::zstd::bulk;
+        use libcramjam::zstd::zstd::stream::raw::CParameter;
+        use pyo3::exceptions::{PyTypeError, PyValueError};
+        use pyo3::prelude::*;
+        use pyo3::types::PyBytes;
+        use std::io::{Error, ErrorKind, Read, SeekFrom};
+
+        /// Magic number of the skippable frame holding the seek table
+        const SKIPPABLE_MAGIC: u32 = 0x184d_2a5e;
+
+        /// Magic number ending the seek table
+        const SEEKABLE_MAGIC: u32 = 0x8f92_eab1;
+
+        /// Number of frames, descriptor and magic number ending the seek table
+        const FOOTER_LEN: u64 = 9;
+
+        /// Largest decompressed frame the reference implementation reads
+        const MAX_FRAME_SIZE: usize = 1 << 30;
+
+        /// Most frames the reference implementation reads
+        const MAX_FRAMES: usize = 1 << 27;
+
+        const DEFAULT_FRAME_SIZE: usize = 1 << 20;
+
+        /// Write the seekable format to `sink`, anything with a `write` method such as a file opened
+        /// for binary writing or a `cramjam.Buffer`, compressing every `frame_size` bytes of data
+        /// written into a frame of its own at `level`.
+        ///
+        /// Smaller frames make reading a few bytes at an offset quicker, as less needs decompressing,
+        /// at the cost of compression ratio. Data is held until a frame is complete; `flush()` ends
+        /// the current frame early, and `close()`, or leaving the `with` block, writes what's left
+        /// and the seek table, without which the data isn't seekable.
+        ///
+        /// Python Example
+        /// --------------
+        /// ```python
+        /// >>> writer = cramjam.zstd.seekable.Writer(sink, level=Optional[int], frame_size=1048576)
+        /// >>> writer.write(b'some bytes here')
+        /// >>> writer.close()
+        /// ```
+        #[pyclass]
+        pub struct Writer {
+            sink: PyObject,
+            frame_size: usize,
+            compressor: bulk::Compressor<'static>,
+            /// Data of the frame not yet compressed
+            pending: Vec<u8>,
+            /// Seek table entries of the frames written so far
+            entries: Vec<Entry>,
+            closed: bool,
+        }
+
+        #[pymethods]
+        impl Writer {
+            /// Initialize a new `Writer` instance.
+            #[new]
+            #[pyo3(signature = (sink, level=None, frame_size=DEFAULT_FRAME_SIZE))]
+            pub fn __init__(sink: Bound<'_, PyAny>, level: Option<i32>, frame_size: usize) -> PyResult<Self> {
+                if !sink.hasattr("write")? {
+                    return Err(PyTypeError::new_err("sink must have a `write` method"));
+                }
+                if !(1..=MAX_FRAME_SIZE).contains(&frame_size) {
+                    return Err(PyValueError::new_err(format!(
+                        "frame_size must be between 1 and {}, got {}",
+                        MAX_FRAME_SIZE, frame_size
+                    )));
+                }
+                let mut compressor = bulk::Compressor::new(level.unwrap_or(DEFAULT_COMPRESSION_LEVEL))
+                    .map_err(CompressionError::from_err)?;
+                // a frame's checksum is the seek table's checksum of it
+                compressor
+                    .set_parameter(CParameter::ChecksumFlag(true))
+                    .map_err(CompressionError::from_err)?;
+                Ok(Self {
+                    sink: sink.unbind(),
+                    frame_size,
+                    compressor,
+                    pending: Vec::with_capacity(frame_size.min(DEFAULT_FRAME_SIZE)),
+                    entries: vec![],
+                    closed: false,
+                })
+            }
+
+            /// Write `data`, returning the number of bytes written to the sink, which is `0` until
+            /// a frame is complete.
+            pub fn write(&mut self, py: Python, data: BytesType) -> PyResult<usize> {
+                self.check_open()?;
+                let mut frames = vec![];
+                match data.with_stream(|reader| self.write_from(py, reader, &mut frames)) {
+                    Some(result) => result?,
+                    None => self.write_from(py, data.as_bytes(), &mut frames)?,
+                }
+                self.write_sink(py, &frames)
+            }
+
+            /// End the current frame, write it and flush the sink if it has a `flush` method,
+            /// returning the number of bytes written to the sink.
+            pub fn flush(&mut self, py: Python) -> PyResult<usize> {
+                self.check_open()?;
+                let mut frame = vec![];
+                self.end_frame(py, &mut frame)?;
+                let n = self.write_sink(py, &frame)?;
+                if self.sink.bind(py).hasattr("flush")? {
+                    self.sink.call_method0(py, "flush")?;
+                }
+                Ok(n)
+            }
+
+            /// Write the last frame and the seek table, returning the number of bytes written to the
+            /// sink. The sink itself isn't closed, and the writer can't be written to afterwards.
+            pub fn close(&mut self, py: Python) -> PyResult<usize> {
+                if self.closed {
+                    return Ok(0);
+                }
+                let mut output = vec![];
+                self.end_frame(py, &mut output)?;
+                write_seek_table(&self.entries, &mut output);
+                self.closed = true;
+                self.write_sink(py, &output)
+            }
+
+            /// Whether `close()` was called
+            #[getter]
+            pub fn closed(&self) -> bool {
+                self.closed
+            }
+
+            fn __enter__(slf: PyRef<Self>) -> PyRef<Self> {
+                slf
+            }
+
+            #[pyo3(signature = (*_args))]
+            fn __exit__(&mut self, py: Python, _args: &Bound<'_, pyo3::types::PyTuple>) -> PyResult<bool> {
+                self.close(py).map(|_| false)
+            }
+        }
+
+        impl Writer {
+            fn check_open(&self) -> PyResult<()> {
+                if self.closed {
+                    return Err(PyValueError::new_err("I/O operation on closed Writer"));
+                }
+                Ok(())
+            }
+
+            /// Add all of `reader` to the pending data, a frame's worth at a time, appending the
+            /// frames completed to `output`
+            fn write_from(&mut self, py: Python, mut reader: impl Read, output: &mut Vec<u8>) -> PyResult<()> {
+                loop {
+                    let n = self.frame_size - self.pending.len();
+                    reader
+                        .by_ref()
+                        .take(n as u64)
+                        .read_to_end(&mut self.pending)
+                        .map_err(CompressionError::from_err)?;
+                    if self.pending.len() < self.frame_size {
+                        return Ok(());
+                    }
+                    self.end_frame(py, output)?;
+                }
+            }
+
+            /// Compress the pending data into a frame appended to `output`, if there's any
+            fn end_frame(&mut self, py: Python, output: &mut Vec<u8>) -> PyResult<()> {
+                if self.pending.is_empty() {
+                    return Ok(());
+                }
+                if self.entries.len() == MAX_FRAMES {
+                    return Err(CompressionError::new_err(format!(
+                        "The seekable format holds at most {} frames, use a larger frame_size",
+                        MAX_FRAMES
+                    )));
+                }
+                let (compressor, pending) = (&mut self.compressor, &self.pending);
+                let frame = py
+                    .allow_threads(|| compressor.compress(pending))
+                    .map_err(CompressionError::from_err)?;
+                let checksum = u32::from_le_bytes(frame[frame.len() - 4..].try_into().unwrap());
+                self.entries.push(Entry {
+                    compressed_size: frame.len() as u32,
+                    decompressed_size: self.pending.len() as u32,
+                    checksum: Some(checksum),
+                });
+                output.extend_from_slice(&frame);
+                self.pending.clear();
+                Ok(())
+            }
+
+            fn write_sink(&self, py: Python, output: &[u8]) -> PyResult<usize> {
+                if !output.is_empty() {
+                    self.sink.call_method1(py, "write", (PyBytes::new_bound(py, output),))?;
+                }
+                Ok(output.len())
+            }
+        }
+
+        /// Read data in the seekable format from `source`, a `cramjam.File`, `SpooledBuffer` or the
+        /// bytes-like data itself, from any offset of the decompressed data, decompressing only the
+        /// frames covering what's read; the last frame decompressed is kept for reads following it.
+        ///
+        /// A `cramjam.File` or `SpooledBuffer` is read from as needed, as are the seek table at its
+        /// end on creating the reader, which raises `DecompressionError` if there's none.
+        ///
+        /// Python Example
+        /// --------------
+        /// ```python
+        /// >>> reader = cramjam.zstd.seekable.Reader(cramjam.File("data.zst", read=True))
+        /// >>> reader.seek(-100, 2)  # the last 100 bytes, only decompressing the last frame
+        /// >>> reader.read()
+        /// ```
+        #[pyclass]
+        pub struct Reader {
+            source: ReadSource,
+            frames: Vec<Frame>,
+            decompressor: bulk::Decompressor<'static>,
+            /// Position in the decompressed data
+            pos: u64,
+            /// Index and data of the frame last decompressed
+            cache: Option<(usize, Vec<u8>)>,
+        }
+
+        #[pymethods]
+        #[allow(clippy::len_without_is_empty)]
+        impl Reader {
+            /// Initialize a new `Reader` instance.
+            #[new]
+            pub fn __init__(py: Python, source: BytesType) -> PyResult<Self> {
+                let mut source = ReadSource::new(source);
+                let frames = source
+                    .with_reader(py, read_seek_table)
+                    .map_err(|err| match err.kind() {
+                        ErrorKind::InvalidInput => PyValueError::new_err(err.to_string()),
+                        _ => DecompressionError::from_err(err),
+                    })?;
+                Ok(Self {
+                    source,
+                    frames,
+                    decompressor: bulk::Decompressor::new().map_err(DecompressionError::from_err)?,
+                    pos: 0,
+                    cache: None,
+                })
+            }
+
+            /// Read `n_bytes` of the decompressed data from the current position, or all that's
+            /// left if not given or negative.
+            #[pyo3(signature = (n_bytes=None))]
+            pub fn read(&mut self, py: Python, n_bytes: Option<isize>) -> PyResult<RustyBuffer> {
+                let len = self.len();
+                let end = match n_bytes {
+                    Some(n) if n >= 0 => self.pos.saturating_add(n as u64).min(len),
+                    _ => len,
+                };
+                // the seek table's sizes aren't trusted until the frames decompress to them
+                let mut output =
+                    Vec::with_capacity(end.saturating_sub(self.pos).min(DEFAULT_FRAME_SIZE as u64) as usize);
+                while self.pos < end {
+                    let index = self
+                        .frames
+                        .partition_point(|frame| frame.decompressed_end() <= self.pos);
+                    let frame = self.frames[index];
+                    let start = (self.pos - frame.decompressed_offset) as usize;
+                    let stop = (end.min(frame.decompressed_end()) - frame.decompressed_offset) as usize;
+                    let data = self.decompress_frame(py, index)?;
+                    output.extend_from_slice(&data[start..stop]);
+                    self.pos += (stop - start) as u64;
+                }
+                Ok(RustyBuffer::from(output))
+            }
+
+            /// Seek to a position of the decompressed data, relative to `whence`, returning it.
+            ///
+            /// whence:
+            /// 0: from start of the data
+            /// 1: from current position
+            /// 2: from end of the data
+            ///
+            /// Positions past the end are allowed, with nothing to read from there.
+            #[pyo3(signature = (position, whence=0))]
+            pub fn seek(&mut self, position: i64, whence: usize) -> PyResult<u64> {
+                let base = match whence {
+                    0 => 0,
+                    1 => self.pos,
+                    2 => self.len(),
+                    _ => {
+                        return Err(PyValueError::new_err(
+                            "whence should be one of 0: seek from start, 1: seek from current, or 2: seek from end",
+                        ))
+                    }
+                };
+                self.pos = base.checked_add_signed(position).ok_or_else(|| {
+                    PyValueError::new_err(format!("Negative seek position {}", base as i64 + position))
+                })?;
+                Ok(self.pos)
+            }
+
+            /// Current position in the decompressed data
+            pub fn tell(&self) -> u64 {
+                self.pos
+            }
+
+            /// Length of the decompressed data, as given by the seek table
+            pub fn len(&self) -> u64 {
+                self.frames.last().map(Frame::decompressed_end).unwrap_or_default()
+            }
+
+            /// Number of frames
+            #[getter]
+            pub fn num_frames(&self) -> usize {
+                self.frames.len()
+            }
+
+            /// Here for compatibility with file objects, always True.
+            pub fn seekable(&self) -> bool {
+                true
+            }
+
+            /// Here for compatibility with file objects, always True.
+            pub fn readable(&self) -> bool {
+                true
+            }
+        }
+
+        impl Reader {
+            /// Data of the frame at `index`, decompressing it unless it's the one decompressed last
+            fn decompress_frame(&mut self, py: Python, index: usize) -> PyResult<&[u8]> {
+                if !matches!(self.cache, Some((cached, _)) if cached == index) {
+                    let frame = self.frames[index];
+                    let compressed = self
+                        .source
+                        .with_reader(py, |reader| {
+                            reader.seek(SeekFrom::Start(frame.compressed_offset))?;
+                            let mut compressed = vec![0; frame.compressed_size as usize];
+                            reader.read_exact(&mut compressed)?;
+                            Ok(compressed)
+                        })
+                        .map_err(|err| frame_error(index, err))?;
+                    let decompressor = &mut self.decompressor;
+                    let data = py
+                        .allow_threads(|| decompressor.decompress(&compressed, frame.decompressed_size as usize))
+                        .map_err(|err| frame_error(index, err))?;
+                    if data.len() != frame.decompressed_size as usize {
+                        return Err(frame_error(
+                            index,
+                            format!(
+                                "decompressed to {} bytes, the seek table gives {}",
+                                data.len(),
+                                frame.decompressed_size
+                            ),
+                        ));
+                    }
+                    self.cache = Some((index, data));
+                }
+                Ok(&self.cache.as_ref().unwrap().1)
+            }
+        }
+
+        fn frame_error<E: ToString>(index: usize, err: E) -> PyErr {
+            DecompressionError::new_err(format!("Frame {}: {}", index, err.to_string()))
+        }
+
+        /// Seek table entry of a frame
+        struct Entry {
+            compressed_size: u32,
+            decompressed_size: u32,
+            /// Low 32 bits of the XXH64 of the decompressed data
+            checksum: Option<u32>,
+        }
+
+        /// Where a frame is in the compressed and decompressed data
+        #[derive(Clone, Copy)]
+        struct Frame {
+            compressed_offset: u64,
+            compressed_size: u32,
+            decompressed_offset: u64,
+            decompressed_size: u32,
+        }
+
+        impl Frame {
+            fn decompressed_end(&self) -> u64 {
+                self.decompressed_offset + self.decompressed_size as u64
+            }
+        }
+
+        /// Append the skippable frame holding the seek table of `entries` to `output`
+        fn write_seek_table(entries: &[Entry], output: &mut Vec<u8>) {
+            let checksums = entries.iter().all(|entry| entry.checksum.is_some());
+            let entry_len = if checksums { 12 } else { 8 };
+            output.extend_from_slice(&SKIPPABLE_MAGIC.to_le_bytes());
+            output.extend_from_slice(&((entries.len() * entry_len) as u32 + FOOTER_LEN as u32).to_le_bytes());
+            for entry in entries {
+                output.extend_from_slice(&entry.compressed_size.to_le_bytes());
+                output.extend_from_slice(&entry.decompressed_size.to_le_bytes());
+                if let (true, Some(checksum)) = (checksums, entry.checksum) {
+                    output.extend_from_slice(&checksum.to_le_bytes());
+                }
+            }
+            output.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+            output.push(if checksums { 0x80 } else { 0 });
+            output.extend_from_slice(&SEEKABLE_MAGIC.to_le_bytes());
+        }
+
+        /// Read the seek table at the end of `reader`, giving where each frame is; the frames
+        /// are taken to directly precede it.
+        fn read_seek_table(reader: &mut dyn ReadSeek) -> std::io::Result<Vec<Frame>> {
+            let invalid = |msg: String| Error::new(ErrorKind::InvalidData, format!("Invalid seek table: {}", msg));
+            let start = reader.stream_position()?;
+            let end = reader.seek(SeekFrom::End(0))?;
+            let len = end.checked_sub(start).ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("position {} is past the end of the data, {}", start, end),
+                )
+            })?;
+            if len < 8 + FOOTER_LEN {
+                return Err(invalid("data is too short to hold one".to_string()));
+            }
+            let mut footer = [0; FOOTER_LEN as usize];
+            reader.seek(SeekFrom::End(-(FOOTER_LEN as i64)))?;
+            reader.read_exact(&mut footer)?;
+            let num_frames = u32::from_le_bytes(footer[..4].try_into().unwrap()) as u64;
+            let descriptor = footer[4];
+            if u32::from_le_bytes(footer[5..].try_into().unwrap()) != SEEKABLE_MAGIC {
+                return Err(invalid("no seekable magic number at the end of the data".to_string()));
+            }
+            if descriptor & 0x7c != 0 {
+                return Err(invalid(format!(
+                    "reserved bits of its descriptor are set: {:#x}",
+                    descriptor
+                )));
+            }
+            let entry_len = if descriptor & 0x80 != 0 { 12 } else { 8 };
+
+            let table_len = num_frames * entry_len + FOOTER_LEN;
+            if len < 8 + table_len {
+                return Err(invalid(format!(
+                    "data is too short to hold one of {} frames",
+                    num_frames
+                )));
+            }
+            let mut table = vec![0; 8 + table_len as usize];
+            reader.seek(SeekFrom::Start(end - 8 - table_len))?;
+            reader.read_exact(&mut table)?;
+            let header = |i: usize| u32::from_le_bytes(table[i..i + 4].try_into().unwrap());
+            if header(0) != SKIPPABLE_MAGIC || header(4) as u64 != table_len {
+                return Err(invalid("no skippable frame holding it".to_string()));
+            }
+
+            let mut frames = Vec::with_capacity(num_frames as usize);
+            let (mut compressed_offset, mut decompressed_offset) = (0u64, 0u64);
+            for (index, entry) in table[8..8 + (num_frames * entry_len) as usize]
+                .chunks(entry_len as usize)
+                .enumerate()
+            {
+                let frame = Frame {
+                    compressed_offset,
+                    compressed_size: u32::from_le_bytes(entry[..4].try_into().unwrap()),
+                    decompressed_offset,
+                    decompressed_size: u32::from_le_bytes(entry[4..8].try_into().unwrap()),
+                };
+                // what's allocated to decompress the frame into
+                if frame.decompressed_size as usize > MAX_FRAME_SIZE {
+                    return Err(invalid(format!(
+                        "frame {} decompresses to {} bytes, more than the {} a frame may",
+                        index, frame.decompressed_size, MAX_FRAME_SIZE
+                    )));
+                }
+                compressed_offset += frame.compressed_size as u64;
+                decompressed_offset = frame.decompressed_end();
+                frames.push(frame);
+            }
+            // offsets so far are from the start of the first frame
+            let data_start = (end - 8 - table_len)
+                .checked_sub(compressed_offset)
+                .filter(|data_start| *data_start >= start)
+                .ok_or_else(|| {
+                    invalid(format!(
+                        "its frames take {} bytes, more than precede it",
+                        compressed_offset
+                    ))
+                })?;
+            for frame in frames.iter_mut() {
+                frame.compressed_offset += data_start;
+            }
+            Ok(frames)
+        }
+    }
 }
@@ -0,0 +1,75 @@
+import asyncio
+
+import pytest
+import cramjam
+
+
+DATA = b"some bytes here" * 10_000
+
+
+@pytest.mark.parametrize("codec", ("zstd", "gzip", "lz4", "snappy", "brotli", "xz"))
+def test_asyncio_roundtrip(codec):
+    async def roundtrip():
+        compressed = await cramjam.asyncio.compress(codec, DATA, level=1 if codec != "snappy" else None)
+        assert bytes(cramjam.decompress(codec, compressed)) == DATA
+        return await cramjam.asyncio.decompress(codec, bytes(compressed))
+
+    assert bytes(asyncio.run(roundtrip())) == DATA
+
+
+def test_asyncio_concurrent():
+    inputs = [bytes([i]) * (i * 1000) for i in range(64)]
+
+    async def compress_all():
+        # a bytearray stays exported to the worker, a Buffer is copied
+        data = [bytearray(data) if i % 2 else cramjam.Buffer(data) for i, data in enumerate(inputs)]
+        compressed = await asyncio.gather(*(cramjam.asyncio.compress("zstd", d) for d in data))
+        return await asyncio.gather(*(cramjam.asyncio.decompress("zstd", c) for c in compressed))
+
+    assert [bytes(output) for output in asyncio.run(compress_all())] == inputs
+
+
+def test_asyncio_expansion_limit():
+    compressed = bytes(cramjam.zstd.compress(b"\0" * 1_000_000))
+
+    async def decompress(**kwargs):
+        return await cramjam.asyncio.decompress("zstd", compressed, **kwargs)
+
+    with pytest.raises(cramjam.DecompressionError, match="expansion ratio"):
+        asyncio.run(decompress(max_expansion_ratio=100))
+    with pytest.raises(cramjam.DecompressionError, match="output_len_max"):
+        asyncio.run(decompress(output_len_max=1024))
+
+    cramjam.set_max_expansion_ratio(100)
+    try:
+        with pytest.raises(cramjam.DecompressionError, match="expansion ratio"):
+            asyncio.run(decompress())
+        assert len(asyncio.run(decompress(max_expansion_ratio=float("inf")))) == 1_000_000
+    finally:
+        cramjam.set_max_expansion_ratio(None)
+
+
+def test_asyncio_errors(tmp_path):
+    async def decompress_corrupt():
+        return await cramjam.asyncio.decompress("zstd", b"corrupt")
+
+    with pytest.raises(cramjam.DecompressionError):
+        asyncio.run(decompress_corrupt())
+
+    async def check_arguments():
+        with pytest.raises(ValueError):
+            cramjam.asyncio.compress("unknown", DATA)
+        with pytest.raises(TypeError):
+            cramjam.asyncio.compress("zstd", cramjam.File(str(tmp_path / "file")))
+
+        # cancelled before it's resolved, which is then skipped
+        future = cramjam.asyncio.compress("zstd", DATA)
+        future.cancel()
+        await asyncio.sleep(0.1)
+        assert future.cancelled()
+
+    asyncio.run(check_arguments())
+
+    # only within a running event loop
+    with pytest.raises(RuntimeError):
+        cramjam.asyncio.compress("zstd", DATA)
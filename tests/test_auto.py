@@ -0,0 +1,51 @@
+import lzma
+
+import pytest
+
+import cramjam
+
+DATA = b"some bytes here" * 100
+
+
+@pytest.mark.parametrize("codec", ("gzip", "zstd", "lz4", "xz", "bzip2", "snappy", "zlib"))
+def test_auto_decompress(codec):
+    compressed = getattr(cramjam, codec).compress(DATA)
+    assert cramjam.guess_codec(compressed) == codec
+    assert bytes(cramjam.auto.decompress(compressed)) == DATA
+    assert bytes(cramjam.auto.decompress(compressed, output_len=len(DATA))) == DATA
+
+
+def test_auto_decompress_lzma_alone():
+    compressed = lzma.compress(DATA, format=lzma.FORMAT_ALONE)
+    assert cramjam.guess_codec(compressed) == "xz"
+    assert bytes(cramjam.auto.decompress(compressed)) == DATA
+
+
+def test_auto_decompress_brotli():
+    # brotli has no signature, but is tried when nothing else matches
+    compressed = cramjam.brotli.compress(DATA)
+    assert cramjam.guess_codec(compressed) is None
+    assert bytes(cramjam.auto.decompress(compressed)) == DATA
+
+
+@pytest.mark.parametrize("data", (b"\x00\x01 not compressed", cramjam.deflate.compress(DATA)))
+def test_auto_decompress_unrecognized(data):
+    assert cramjam.guess_codec(data) is None
+    with pytest.raises(cramjam.DecompressionError):
+        cramjam.auto.decompress(data)
+
+
+def test_auto_decompress_empty():
+    # like each codec's decompress, empty input decompresses to empty output
+    assert cramjam.guess_codec(b"") is None
+    assert bytes(cramjam.auto.decompress(b"")) == b""
+
+
+@pytest.mark.parametrize("codec", ("zstd", "xz"))
+def test_auto_decompress_file(codec, tmp_path):
+    path = tmp_path / "compressed"
+    path.write_bytes(bytes(getattr(cramjam, codec).compress(DATA)))
+    file = cramjam.File(str(path))
+    assert cramjam.guess_codec(file) == codec
+    assert file.tell() == 0
+    assert bytes(cramjam.auto.decompress(file)) == DATA
@@ -0,0 +1,162 @@
+import os
+import random
+
+import pytest
+import cramjam
+
+
+ITEMS = [bytes([i]) * (i * 1000) for i in range(64)]
+
+
+@pytest.mark.parametrize("codec", ("zstd", "gzip", "lz4", "snappy", "brotli"))
+@pytest.mark.parametrize("nthreads", (None, 1, 4))
+def test_decompress_batch(codec, nthreads):
+    variant = getattr(cramjam, codec)
+    inputs = [bytes(variant.compress(item)) for item in ITEMS]
+    # corrupt items fail on their own, without affecting the items after them on the same thread
+    for i in (3, 4, 40):
+        inputs[i] = inputs[i][: len(inputs[i]) // 2] + b"corrupt"
+
+    outputs, errors = cramjam.decompress_batch(inputs, codec, nthreads=nthreads)
+    assert len(outputs) == len(errors) == len(ITEMS)
+    for i, (output, error) in enumerate(zip(outputs, errors)):
+        if i in (3, 4, 40):
+            assert output is None
+            assert isinstance(error, cramjam.DecompressionError)
+        else:
+            assert error is None
+            assert bytes(output) == ITEMS[i]
+
+
+def test_decompress_batch_errors():
+    assert cramjam.decompress_batch([], "zstd") == ([], [])
+    with pytest.raises(ValueError):
+        cramjam.decompress_batch([b""], "unknown")
+    with pytest.raises(ValueError):
+        cramjam.decompress_batch([b""], "zstd", nthreads=0)
+
+
+def test_decompress_batch_error_index():
+    inputs = [bytes(cramjam.zstd.compress(item)) for item in ITEMS[:8]]
+    inputs[5] = b"corrupt"
+    _, errors = cramjam.decompress_batch(inputs, "zstd", nthreads=4)
+    assert [i for i, error in enumerate(errors) if error is not None] == [5]
+    assert errors[5].index == 5
+    assert str(errors[5]).startswith("item 5: ")
+
+
+@pytest.mark.parametrize("nthreads", (1, 4))
+def test_decompress_batch_shared_input(nthreads):
+    # the same Buffer given as every input still gets a separate output per item
+    buffer = cramjam.Buffer(cramjam.lz4.compress(ITEMS[10]))
+    outputs, errors = cramjam.decompress_batch([buffer] * 16, "lz4", nthreads=nthreads)
+    assert errors == [None] * 16
+    assert len({id(output) for output in outputs}) == 16
+    outputs[0].write(b"overwritten")
+    assert all(bytes(output) == ITEMS[10] for output in outputs[1:])
+
+
+@pytest.mark.parametrize("codec", ("zstd", "gzip"))
+def test_decompress_batch_expansion_limit(codec):
+    variant = getattr(cramjam, codec)
+    bomb = bytes(variant.compress(b"\0" * 10_000_000))
+    inputs = [bytes(variant.compress(ITEMS[1])), bomb]
+
+    outputs, errors = cramjam.decompress_batch(inputs, codec, max_expansion_ratio=100)
+    assert bytes(outputs[0]) == ITEMS[1] and errors[0] is None
+    assert outputs[1] is None and "expansion ratio" in str(errors[1])
+    _, errors = cramjam.decompress_batch(inputs, codec, output_len_max=len(ITEMS[1]))
+    assert errors[0] is None and "output_len_max" in str(errors[1])
+
+    cramjam.set_max_expansion_ratio(100)
+    try:
+        _, errors = cramjam.decompress_batch(inputs, codec)
+        assert errors[0] is None and errors[1].index == 1
+        _, errors = cramjam.decompress_batch(inputs, codec, max_expansion_ratio=float("inf"))
+        assert errors == [None, None]
+    finally:
+        cramjam.set_max_expansion_ratio(None)
+
+
+def test_decompress_batch_file(tmp_path):
+    path = tmp_path / "compressed"
+    path.write_bytes(bytes(cramjam.zstd.compress(ITEMS[3])))
+    outputs, errors = cramjam.decompress_batch([cramjam.File(str(path)), b""], "zstd")
+    assert errors == [None, None]
+    assert [bytes(output) for output in outputs] == [ITEMS[3], b""]
+
+
+# Scale up with CRAMJAM_STRESS_ROUNDS, ie. against a build with `-Zsanitizer=thread`
+STRESS_ROUNDS = int(os.getenv("CRAMJAM_STRESS_ROUNDS", "5"))
+
+
+@pytest.mark.parametrize("codec", ("zstd", "gzip", "lz4"))
+def test_decompress_batch_stress(codec):
+    variant = getattr(cramjam, codec)
+    compressed = [bytes(variant.compress(item)) for item in ITEMS]
+    rng = random.Random(codec)
+    for _ in range(STRESS_ROUNDS):
+        picks = [rng.randrange(len(ITEMS)) for _ in range(256)]
+        corrupt = set(rng.sample(range(len(picks)), 16))
+        inputs = [
+            b"corrupt" + compressed[p] if i in corrupt else compressed[p]
+            for i, p in enumerate(picks)
+        ]
+        outputs, errors = cramjam.decompress_batch(
+            inputs, codec, nthreads=rng.choice((2, 8, 32))
+        )
+        for i, (output, error) in enumerate(zip(outputs, errors)):
+            if i in corrupt:
+                assert output is None and error.index == i
+            else:
+                assert error is None and bytes(output) == ITEMS[picks[i]]
+
+
+@pytest.mark.parametrize("codec", ("snappy", "zstd", "lz4", "gzip"))
+@pytest.mark.parametrize("nthreads", (None, 1, 4))
+def test_codec_batch(codec, nthreads):
+    variant = getattr(cramjam, codec)
+    compressed = variant.compress_batch(ITEMS, nthreads=nthreads)
+    assert [bytes(c) for c in compressed] == [bytes(variant.compress(item)) for item in ITEMS]
+    assert [bytes(d) for d in variant.decompress_batch(compressed, nthreads=nthreads)] == ITEMS
+    assert variant.compress_batch([]) == variant.decompress_batch([]) == []
+
+    if codec != "snappy":
+        compressed = variant.compress_batch(ITEMS, level=1, nthreads=nthreads)
+        assert [bytes(variant.decompress(c)) for c in compressed] == ITEMS
+
+    # the first failing item's error is raised
+    inputs = [bytes(c) for c in compressed]
+    inputs[7] = inputs[9] = b"corrupt"
+    with pytest.raises(cramjam.DecompressionError, match="^item 7: ") as exc:
+        variant.decompress_batch(inputs, nthreads=nthreads)
+    assert exc.value.index == 7
+    with pytest.raises(ValueError):
+        variant.compress_batch(ITEMS, nthreads=0)
+
+
+@pytest.mark.parametrize("codec", ("zstd", "gzip"))
+def test_codec_batch_expansion_limit_and_file(codec, tmp_path):
+    variant = getattr(cramjam, codec)
+    bomb = bytes(variant.compress(b"\0" * 10_000_000))
+    inputs = [bytes(variant.compress(ITEMS[1])), bomb]
+    with pytest.raises(cramjam.DecompressionError, match="^item 1: .*expansion ratio") as exc:
+        variant.decompress_batch(inputs, max_expansion_ratio=100)
+    assert exc.value.index == 1
+    with pytest.raises(cramjam.DecompressionError, match="output_len_max"):
+        variant.decompress_batch(inputs, output_len_max=len(ITEMS[1]))
+
+    cramjam.set_max_expansion_ratio(100)
+    try:
+        with pytest.raises(cramjam.DecompressionError, match="expansion ratio"):
+            variant.decompress_batch(inputs)
+        assert len(variant.decompress_batch(inputs, max_expansion_ratio=float("inf"))) == 2
+    finally:
+        cramjam.set_max_expansion_ratio(None)
+
+    # Files are read from their current position
+    path = tmp_path / "data"
+    path.write_bytes(ITEMS[3])
+    (compressed,) = variant.compress_batch([cramjam.File(str(path))])
+    path.write_bytes(bytes(compressed))
+    assert [bytes(d) for d in variant.decompress_batch([cramjam.File(str(path))])] == [ITEMS[3]]
@@ -0,0 +1,86 @@
+import os
+import zlib
+
+import pytest
+import cramjam
+
+
+DATA = os.urandom(100_003)
+
+CHECKSUMS = (
+    ("crc32", "Crc32"),
+    ("adler32", "Adler32"),
+    ("crc32c", "Crc32c"),
+    ("xxh32", "Xxh32"),
+    ("xxh64", "Xxh64"),
+)
+
+
+def test_checksum_zlib():
+    assert cramjam.checksum.crc32(DATA) == zlib.crc32(DATA)
+    assert cramjam.checksum.crc32(DATA, value=5) == zlib.crc32(DATA, 5)
+    assert cramjam.checksum.adler32(DATA) == zlib.adler32(DATA)
+    assert cramjam.checksum.adler32(DATA, value=5) == zlib.adler32(DATA, 5)
+
+
+@pytest.mark.parametrize(
+    "name,data,kwargs,expected",
+    (
+        ("crc32c", b"123456789", {}, 0xE3069283),
+        ("crc32c", b"", {}, 0),
+        ("xxh32", b"", {}, 0x02CC5D05),
+        ("xxh32", b"a", {}, 0x550D7456),
+        ("xxh64", b"", {}, 0xEF46DB3751D8E999),
+        ("xxh64", b"a", {}, 0xD24EC4F1A98C6E5B),
+        ("xxh64", b"", {"seed": 1}, 0xD5AFBA1336A3BE4B),
+    ),
+)
+def test_checksum_known(name, data, kwargs, expected):
+    assert getattr(cramjam.checksum, name)(data, **kwargs) == expected
+
+
+def test_checksum_crc32c_snappy():
+    # snappy frames hold the masked CRC32C of each chunk's uncompressed data
+    crc = cramjam.checksum.crc32c(b"some bytes")
+    masked = (((crc >> 15) | (crc << 17)) + 0xA282EAD8) & 0xFFFFFFFF
+    frame = bytes(cramjam.snappy.compress(b"some bytes"))
+    assert int.from_bytes(frame[14:18], "little") == masked
+
+
+@pytest.mark.parametrize("function,cls", CHECKSUMS)
+def test_checksum_streaming(function, cls):
+    function = getattr(cramjam.checksum, function)
+    checksum = getattr(cramjam.checksum, cls)()
+    for i in range(0, len(DATA), 7777):
+        checksum.update(DATA[i : i + 7777])
+    assert checksum.digest() == function(DATA)
+    assert function(cramjam.Buffer(DATA)) == function(DATA)
+
+    copy = checksum.copy()
+    copy.update(b"more")
+    assert checksum.digest() == function(DATA)
+    assert copy.digest() == function(DATA + b"more")
+
+
+@pytest.mark.parametrize("function,cls", CHECKSUMS)
+def test_checksum_file(function, cls, tmp_path):
+    function = getattr(cramjam.checksum, function)
+    path = tmp_path / "data"
+    path.write_bytes(DATA)
+
+    # read from its current position, in chunks rather than all at once
+    file = cramjam.File(str(path))
+    file.seek(10)
+    assert function(file) == function(DATA[10:])
+    assert file.tell() == len(DATA)
+
+    checksum = getattr(cramjam.checksum, cls)()
+    checksum.update(cramjam.File(str(path)))
+    assert checksum.digest() == function(DATA)
+
+    # as is a SpooledBuffer, whether or not it spilled to disk
+    for max_memory in (16, len(DATA)):
+        spooled = cramjam.SpooledBuffer(max_memory=max_memory, data=DATA, dir=tmp_path)
+        spooled.seek(10)
+        assert function(spooled) == function(DATA[10:])
+        assert spooled.tell() == len(DATA)
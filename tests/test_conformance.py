@@ -0,0 +1,272 @@
+"""
+Byte level interop with the standard library: what cramjam compresses, `zlib`, `gzip`, `bz2`
+and `lzma` decompress, and the other way around, across levels and edge cases.
+
+Set CRAMJAM_LARGE_TESTS=1 to also stream more than 2GB through each zlib based codec.
+"""
+import bz2
+import gzip
+import io
+import lzma
+import os
+import zlib
+
+import pytest
+import cramjam
+
+
+def deflate_compress(data, level):
+    compressor = zlib.compressobj(level, wbits=-15)
+    return compressor.compress(data) + compressor.flush()
+
+
+def deflate_decompress(data):
+    return zlib.decompress(data, wbits=-15)
+
+
+# cramjam module: (level argument, levels, stdlib compress, stdlib decompress)
+CODECS = {
+    "gzip": ("level", range(0, 10), lambda d, l: gzip.compress(d, compresslevel=l), gzip.decompress),
+    "zlib": ("level", range(0, 10), zlib.compress, zlib.decompress),
+    "deflate": ("level", range(0, 10), deflate_compress, deflate_decompress),
+    "bzip2": ("level", range(1, 10), bz2.compress, bz2.decompress),
+    "xz": ("preset", range(0, 10), lambda d, l: lzma.compress(d, preset=l), lzma.decompress),
+}
+
+DATA = {
+    "empty": b"",
+    "one-byte": b"x",
+    "repetitive": b"some bytes here " * 10_000,
+    "incompressible": os.urandom(100_000),
+    "zeros": bytes(1_000_000),
+}
+
+
+@pytest.mark.parametrize("data", DATA.values(), ids=DATA.keys())
+@pytest.mark.parametrize("codec", CODECS)
+def test_conformance_stdlib(codec, data):
+    variant = getattr(cramjam, codec)
+    level_arg, levels, stdlib_compress, stdlib_decompress = CODECS[codec]
+    for level in levels:
+        compressed = bytes(variant.compress(data, **{level_arg: level}))
+        assert stdlib_decompress(compressed) == data, f"level {level}"
+
+        compressed = stdlib_compress(data, level)
+        assert bytes(variant.decompress(compressed)) == data, f"level {level}"
+
+
+@pytest.mark.parametrize("codec", CODECS)
+def test_conformance_stdlib_streaming(codec):
+    variant = getattr(cramjam, codec)
+    data = b"".join(b"line %d\n" % i for i in range(100_000))
+
+    compressor = variant.Compressor()
+    compressed = b""
+    for i in range(0, len(data), 10_000):
+        compressor.compress(data[i : i + 10_000])
+        compressed += bytes(compressor.flush())
+    compressed += bytes(compressor.finish())
+    assert CODECS[codec][3](compressed) == data
+
+    compressed = CODECS[codec][2](data, 6)
+    assert b"".join(cramjam.iter_lines(codec, io.BytesIO(compressed), encoding=None)) == data
+
+
+@pytest.mark.parametrize("codec", ("gzip", "bzip2", "xz"))
+def test_conformance_stdlib_multi_member(codec):
+    variant = getattr(cramjam, codec)
+    _, _, stdlib_compress, stdlib_decompress = CODECS[codec]
+    members = [b"first member ", b"", b"third member " * 1000]
+
+    # concatenated streams are one stream of their data concatenated, as the stdlib has it
+    compressed = b"".join(stdlib_compress(member, 6) for member in members)
+    assert bytes(variant.decompress(compressed)) == b"".join(members)
+
+    compressed = b"".join(bytes(variant.compress(member)) for member in members)
+    assert stdlib_decompress(compressed) == b"".join(members)
+
+
+@pytest.mark.parametrize("wbits", range(9, 16))
+def test_conformance_zlib_wbits(wbits):
+    data = b"".join(b"line %d\n" % i for i in range(10_000))
+
+    for wrapper, codec in ((0, "zlib"), (-1, "deflate"), (16, "gzip")):
+        bits = -wbits if wrapper < 0 else wbits + wrapper
+        compressor = zlib.compressobj(9, zlib.DEFLATED, bits, 9)
+        compressed = compressor.compress(data) + compressor.flush()
+        assert bytes(getattr(cramjam, codec).decompress(compressed)) == data, codec
+
+
+@pytest.mark.parametrize("codec", ("zlib", "deflate"))
+@pytest.mark.parametrize("wbits", (*range(-15, -8), *range(9, 16), *range(25, 32)))
+def test_conformance_wbits(codec, wbits):
+    variant = getattr(cramjam, codec)
+    data = b"".join(b"line %d\n" % i for i in range(10_000))
+    compressor = zlib.compressobj(6, zlib.DEFLATED, wbits)
+    compressed = compressor.compress(data) + compressor.flush()
+
+    assert bytes(variant.decompress(compressed, wbits=wbits)) == data
+    decompressor = variant.Decompressor(wbits=wbits)
+    decompressor.decompress(compressed)
+    assert bytes(decompressor.finish()) == data
+
+    if abs(wbits) % 16 != 15 and variant.backend() == "miniz_oxide":
+        with pytest.raises(ValueError, match="zlib-ng"):
+            variant.compress(data, wbits=wbits)
+        return
+    assert zlib.decompress(bytes(variant.compress(data, wbits=wbits)), wbits) == data
+    compressor = variant.Compressor(wbits=wbits)
+    compressor.compress(data)
+    assert zlib.decompress(bytes(compressor.finish()), wbits) == data
+
+
+def test_conformance_wbits_invalid():
+    data = b"some bytes here" * 100
+    for invalid in (0, 8, 16, 24, 32, -8, -16):
+        with pytest.raises(ValueError, match="wbits"):
+            cramjam.zlib.compress(data, wbits=invalid)
+        with pytest.raises(ValueError, match="wbits"):
+            cramjam.deflate.decompress(data, wbits=invalid)
+
+    # a zlib header of a window larger than wbits is refused, as by zlib
+    with pytest.raises(cramjam.DecompressionError, match="window size"):
+        cramjam.zlib.decompress(zlib.compress(data), wbits=9)
+
+
+def test_conformance_gzip_header():
+    data = b"some bytes here" * 100
+    out = io.BytesIO()
+    with gzip.GzipFile("data.txt", "wb", fileobj=out, mtime=1234) as f:
+        f.write(data)
+    assert bytes(cramjam.gzip.decompress(out.getvalue())) == data
+
+    header = cramjam.gzip.inspect_header(out.getvalue())
+    assert (header["filename"], header["mtime"], header["os"]) == ("data.txt", 1234, 255)
+    assert (header["comment"], header["extra"], header["header_len"]) == (None, None, 19)
+
+    compressed = bytes(cramjam.gzip.compress(data, level=9, filename="data.txt", mtime=1234))
+    assert compressed[:19] == out.getvalue()[:19]
+    assert gzip.decompress(compressed) == data
+
+
+def test_conformance_gzip_header_fields():
+    data = b"some bytes here" * 100
+    fields = dict(filename="dätä.txt", comment="a comment", mtime=2**32 - 1, os=3, extra=b"ab\x02\x00xy")
+
+    compressor = cramjam.gzip.Compressor(**fields)
+    compressor.compress(data)
+    for compressed in (bytes(cramjam.gzip.compress(data, **fields)), bytes(compressor.finish())):
+        assert gzip.decompress(compressed) == data
+        header = cramjam.gzip.inspect_header(compressed)
+        assert {key: header[key] for key in fields} == fields
+        with gzip.GzipFile(fileobj=io.BytesIO(compressed)) as f:
+            f.read()
+            assert f.mtime == fields["mtime"]
+
+    for invalid in ({"filename": "a\x00b"}, {"comment": "\u20ac"}, {"extra": bytes(2**16)}):
+        with pytest.raises(ValueError):
+            cramjam.gzip.compress(data, **invalid)
+    with pytest.raises(cramjam.DecompressionError):
+        cramjam.gzip.inspect_header(compressed[:12])
+
+
+def test_conformance_gzip_header_file(tmp_path):
+    # a header longer than first read, from a File whose position is left as it was
+    filename = "long" * 1000 + ".txt"
+    path = tmp_path / "data.gz"
+    path.write_bytes(bytes(cramjam.gzip.compress(b"some bytes here", filename=filename, mtime=1234)))
+    file = cramjam.File(str(path))
+    header = cramjam.gzip.inspect_header(file)
+    assert (header["filename"], header["mtime"], header["header_len"]) == (filename, 1234, 10 + len(filename) + 1)
+    assert file.tell() == 0
+
+    path.write_bytes(path.read_bytes()[:100])
+    with pytest.raises(cramjam.DecompressionError, match="truncated"):
+        cramjam.gzip.inspect_header(cramjam.File(str(path)))
+
+
+def test_conformance_zip_member():
+    import struct
+    import zipfile
+
+    members = {"empty.txt": b"", "data.txt": b"some bytes here" * 1000, "random.bin": os.urandom(10_000)}
+
+    # a ZIP archive written by hand from compress_member, as a Python zip writer would
+    archive, central = io.BytesIO(), b""
+    for name, data in members.items():
+        compressed, crc32, file_size, compress_size = cramjam.deflate.compress_member(data)
+        assert (crc32, file_size, compress_size) == (zlib.crc32(data), len(data), len(compressed))
+        fields = struct.pack("<HHHHHIIIH", 20, 0, 8, 0, 0, crc32, compress_size, file_size, len(name))
+        central += b"PK\x01\x02" + struct.pack("<H", 20) + fields + struct.pack("<HHHHII", 0, 0, 0, 0, 0, archive.tell())
+        central += name.encode()
+        archive.write(b"PK\x03\x04" + fields + struct.pack("<H", 0) + name.encode() + bytes(compressed))
+    offset = archive.tell()
+    archive.write(central + b"PK\x05\x06" + struct.pack("<HHHHIIH", 0, 0, 3, 3, len(central), offset, 0))
+
+    with zipfile.ZipFile(archive) as f:
+        assert f.testzip() is None
+        assert {name: f.read(name) for name in f.namelist()} == members
+
+
+def test_conformance_xz_formats():
+    data = b"some bytes here" * 100
+    assert bytes(cramjam.xz.decompress(lzma.compress(data, format=lzma.FORMAT_ALONE))) == data
+    compressed = bytes(cramjam.xz.compress(data, format=cramjam.xz.Format.ALONE))
+    assert lzma.decompress(compressed, format=lzma.FORMAT_ALONE) == data
+
+    for check in (lzma.CHECK_NONE, lzma.CHECK_CRC32, lzma.CHECK_CRC64, lzma.CHECK_SHA256):
+        assert bytes(cramjam.xz.decompress(lzma.compress(data, check=check))) == data
+
+
+# Past 2**31 bytes, where 32 bit lengths and counters overflow
+LARGE_SIZE = 2**31 + 2**20
+# a line of a MB, for iterating over lines of the decompressed data to be quick
+LARGE_CHUNK = b"".join(b"word %d " % i for i in range(120_000))[: 2**20 - 1] + b"\n"
+
+WBITS = {"gzip": 31, "zlib": 15, "deflate": -15}
+
+
+class CompressingReader:
+    """Compress LARGE_SIZE bytes with the stdlib as they're read, to not hold them"""
+
+    def __init__(self, wbits):
+        self.compressor = zlib.compressobj(1, zlib.DEFLATED, wbits)
+        self.remaining = LARGE_SIZE // len(LARGE_CHUNK)
+        self.buffer = b""
+
+    def read(self, n):
+        while len(self.buffer) < n and self.compressor is not None:
+            if self.remaining:
+                self.buffer += self.compressor.compress(LARGE_CHUNK)
+                self.remaining -= 1
+            else:
+                self.buffer += self.compressor.flush()
+                self.compressor = None
+        out, self.buffer = self.buffer[:n], self.buffer[n:]
+        return out
+
+
+@pytest.mark.skipif(not os.getenv("CRAMJAM_LARGE_TESTS"), reason="set CRAMJAM_LARGE_TESTS=1 to run")
+@pytest.mark.parametrize("codec", WBITS)
+def test_conformance_stdlib_large(codec):
+    n_chunks = LARGE_SIZE // len(LARGE_CHUNK)
+    expected_crc = 0
+    for _ in range(n_chunks):
+        expected_crc = zlib.crc32(LARGE_CHUNK, expected_crc)
+
+    compressor = getattr(cramjam, codec).Compressor(level=1)
+    decompressor = zlib.decompressobj(WBITS[codec])
+    length, crc = 0, 0
+    for i in range(n_chunks):
+        compressor.compress(LARGE_CHUNK)
+        if i % 64 == 0 or i == n_chunks - 1:
+            compressed = bytes(compressor.flush() if i < n_chunks - 1 else compressor.finish())
+            out = decompressor.decompress(compressed)
+            length, crc = length + len(out), zlib.crc32(out, crc)
+    assert decompressor.eof
+    assert (length, crc) == (n_chunks * len(LARGE_CHUNK), expected_crc)
+
+    length, crc = 0, 0
+    for line in cramjam.iter_lines(codec, CompressingReader(WBITS[codec]), encoding=None):
+        length, crc = length + len(line), zlib.crc32(line, crc)
+    assert (length, crc) == (n_chunks * len(LARGE_CHUNK), expected_crc)
@@ -0,0 +1,53 @@
+import pytest
+
+import cramjam
+
+CODECS = ("snappy", "lz4", "zstd", "brotli", "gzip", "bzip2", "zlib", "deflate", "xz")
+DATA = b"some bytes here" * 100
+
+
+@pytest.mark.parametrize("codec", CODECS)
+def test_dispatch(codec):
+    compressed = cramjam.compress(codec, DATA)
+    assert bytes(compressed) == bytes(getattr(cramjam, codec).compress(DATA))
+    assert bytes(cramjam.decompress(codec, compressed)) == DATA
+
+    # the module itself, or a name from ie. a config file, work too
+    assert bytes(cramjam.decompress(getattr(cramjam, codec), compressed)) == DATA
+    assert bytes(cramjam.decompress(codec.upper(), compressed)) == DATA
+
+
+def test_dispatch_kwargs():
+    compressed = cramjam.compress("gzip", DATA, level=9)
+    assert bytes(compressed) == bytes(cramjam.gzip.compress(DATA, level=9))
+    output = cramjam.decompress("gzip", compressed, output_len=len(DATA))
+    assert bytes(output) == DATA
+
+    with pytest.raises(TypeError):
+        cramjam.compress("snappy", DATA, level=1)
+
+
+@pytest.mark.parametrize("codec", CODECS)
+def test_dispatch_return_info(codec):
+    compressed, info = cramjam.compress(codec, DATA, return_info=True)
+    assert bytes(cramjam.decompress(codec, compressed)) == DATA
+    assert info["codec"] == codec
+    assert isinstance(info["codec_version"], str)
+    assert info["cramjam_version"] == cramjam.__version__
+    assert (info["bytes_in"], info["bytes_out"]) == (len(DATA), len(compressed))
+    assert info["time_ms"] >= 0
+
+    module = getattr(cramjam, codec)
+    assert info["level"] == (module.default_level() if hasattr(module, "default_level") else None)
+
+
+def test_dispatch_return_info_level():
+    assert cramjam.compress("gzip", DATA, level=9, return_info=True)[1]["level"] == 9
+    assert cramjam.compress("gzip", DATA, compresslevel=2, return_info=True)[1]["level"] == 2
+    assert cramjam.compress(cramjam.xz, DATA, preset=1, return_info=True)[1]["level"] == 1
+
+
+@pytest.mark.parametrize("codec", ("not-a-codec", "File", "info", cramjam.Buffer))
+def test_dispatch_unknown(codec):
+    with pytest.raises(ValueError):
+        cramjam.compress(codec, DATA)
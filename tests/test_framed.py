@@ -0,0 +1,69 @@
+import io
+
+import pytest
+
+import cramjam
+
+RECORDS = [b"first event", b"", b"x" * 200_000, b"last event"]
+
+
+@pytest.mark.parametrize("codec", ("snappy", "lz4", "zstd", "gzip", "store"))
+def test_framed_roundtrip(codec):
+    sink = io.BytesIO()
+    with cramjam.framed.Writer(sink, codec) as writer:
+        writer.write(RECORDS[0])
+        writer.writelines(RECORDS[1:])
+
+    # from a file-like source, read in chunks, or the data itself
+    sink.seek(0)
+    assert [bytes(r) for r in cramjam.framed.Reader(sink, codec)] == RECORDS
+    assert [bytes(r) for r in cramjam.framed.Reader(sink.getvalue(), codec).read_all()] == RECORDS
+
+    # each record is compressed by itself, after its length as a varint
+    compressed = bytes(getattr(cramjam, codec).compress(RECORDS[0]))
+    assert sink.getvalue().startswith(bytes([len(compressed)]) + compressed)
+
+
+def test_framed_buffer_sink():
+    sink = cramjam.Buffer()
+    n_bytes = cramjam.framed.Writer(sink, "zstd", level=3).writelines(RECORDS)
+    assert len(sink) == n_bytes
+    assert [bytes(r) for r in cramjam.framed.Reader(sink, "zstd")] == RECORDS
+
+
+def test_framed_truncated():
+    sink = io.BytesIO()
+    cramjam.framed.Writer(sink, "snappy").writelines(RECORDS)
+    reader = cramjam.framed.Reader(sink.getvalue()[:-1], "snappy")
+    assert bytes(next(reader)) == RECORDS[0]
+    with pytest.raises(cramjam.DecompressionError):
+        reader.read_all()
+
+
+def test_framed_unknown_codec():
+    with pytest.raises(ValueError):
+        cramjam.framed.Writer(io.BytesIO(), "not-a-codec")
+    with pytest.raises(ValueError):
+        cramjam.framed.Reader(b"", "not-a-codec")
+
+
+def test_framed_file(tmp_path):
+    path = tmp_path / "records"
+    with cramjam.File(str(path)) as sink:
+        cramjam.framed.Writer(sink, "lz4").writelines(RECORDS)
+    with cramjam.File(str(path)) as source:
+        assert [bytes(r) for r in cramjam.framed.Reader(source, "lz4")] == RECORDS
+
+
+def test_framed_expansion_limit():
+    sink = io.BytesIO()
+    cramjam.framed.Writer(sink, "zstd").writelines(RECORDS)
+    cramjam.set_max_expansion_ratio(100)
+    try:
+        reader = cramjam.framed.Reader(sink.getvalue(), "zstd")
+        assert bytes(next(reader)) == RECORDS[0]
+        next(reader)
+        with pytest.raises(cramjam.DecompressionError, match="expansion ratio"):
+            next(reader)
+    finally:
+        cramjam.set_max_expansion_ratio(None)
@@ -0,0 +1,62 @@
+import gzip
+import lzma
+
+import pytest
+import cramjam
+
+
+DATA = b"some bytes here" * 100
+
+
+@pytest.mark.parametrize(
+    "codec,compress",
+    (
+        ("zstd", cramjam.zstd.compress),
+        ("lz4", cramjam.lz4.compress),
+        ("gzip", cramjam.gzip.compress),
+        ("xz", cramjam.xz.compress),
+        ("bzip2", cramjam.bzip2.compress),
+        ("snappy", cramjam.snappy.compress),
+        ("zlib", cramjam.zlib.compress),
+    ),
+)
+def test_info_codec(codec, compress):
+    info = cramjam.info(compress(DATA))
+    assert info["codec"] == codec
+    assert set(info) >= {"codec", "level", "content_size", "checksum", "frames"}
+
+
+def test_info_content_size_and_frames():
+    info = cramjam.info(gzip.compress(DATA, compresslevel=9, mtime=5) + gzip.compress(DATA))
+    assert (info["frames"], info["content_size"], info["level"], info["mtime"]) == (2, 2 * len(DATA), "best", 5)
+
+    info = cramjam.info(lzma.compress(DATA) + lzma.compress(DATA))
+    assert (info["frames"], info["content_size"], info["checksum"]) == (2, 2 * len(DATA), "crc64")
+
+    assert cramjam.info(cramjam.snappy.compress(DATA))["content_size"] == len(DATA)
+    assert cramjam.info(cramjam.bzip2.compress(DATA, level=9))["level"] == 9
+    assert cramjam.info(cramjam.lz4.compress(DATA))["frames"] == 1
+
+
+def test_info_pipeline():
+    # a pipeline's output is that of its last stage, without a header of its own
+    info = cramjam.info(cramjam.pipeline(DATA, ["shuffle", "zstd"]))
+    assert info["codec"] == "zstd"
+
+
+def test_info_unrecognized():
+    assert cramjam.info(b"not compressed")["codec"] is None
+
+
+def test_info_truncated():
+    with pytest.raises(cramjam.DecompressionError):
+        cramjam.info(bytes(cramjam.zstd.compress(DATA))[:-3])
+
+
+def test_info_file(tmp_path):
+    path = tmp_path / "data.gz"
+    path.write_bytes(b"prefix" + gzip.compress(DATA))
+    file = cramjam.File(str(path))
+    file.seek(len(b"prefix"))
+    assert cramjam.info(file)["content_size"] == len(DATA)
+    assert file.tell() == len(b"prefix")
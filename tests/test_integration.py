@@ -41,6 +41,8 @@ Variant = namedtuple("Variant", ("name", "suffix"))
         Variant("lz4", "lz4"),
         Variant("snappy", "snappy"),
         Variant("xz", "lzma"),
+        Variant("zlib", "zz"),
+        Variant("deflate", "deflate"),
     ),
 )
 def test_variant(variant: Variant, integration_dir: pathlib.Path, plaintext: bytes):
@@ -65,3 +67,18 @@ def test_lzma_compat(data, format):
     compressed = cramjam.xz.compress(data, format=cjformat)
     uncompressed = lzma.decompress(bytes(compressed), format=format)
     assert same_same(uncompressed, data)
+
+
+@pytest.mark.parametrize("preset", ("preset_x86_lzma2", "preset_sparc_lzma2"))
+def test_xz_bcj_presets(preset):
+    # the bundled liblzma only includes the x86 and SPARC BCJ filters
+    data = pathlib.Path(cramjam.__file__).read_bytes()[: 1 << 20]
+    chain = getattr(cramjam.xz.FilterChain, preset)(preset=9, dict_size=1 << 22)
+    assert len(chain) == 2
+
+    compressed = cramjam.xz.compress(data, format=cramjam.xz.Format.RAW, filters=chain)
+    assert lzma.decompress(bytes(compressed)) == data
+    assert bytes(cramjam.xz.decompress(compressed)) == data
+
+    with pytest.raises(ValueError):
+        cramjam.xz.FilterChain.preset_bcj_lzma2(cramjam.xz.Filter.Lzma2)
@@ -0,0 +1,54 @@
+import io
+
+import pytest
+import cramjam
+
+CODECS = ("brotli", "bzip2", "deflate", "gzip", "lz4", "snappy", "xz", "zlib", "zstd")
+
+TEXT = "".join(f"line {i} ✓\n" for i in range(50_000)) + "no trailing newline"
+
+
+@pytest.mark.parametrize("codec", CODECS)
+def test_iter_lines(codec):
+    compressed = bytes(cramjam.compress(codec, TEXT.encode()))
+
+    lines = list(cramjam.iter_lines(codec, compressed))
+    assert lines == TEXT.splitlines(keepends=True)
+
+    # from anything with a read method, and as bytes without an encoding
+    lines = list(cramjam.iter_lines(codec, io.BytesIO(compressed), encoding=None))
+    assert lines == TEXT.encode().splitlines(keepends=True)
+
+
+def test_gzip_iter_lines(tmp_path):
+    path = tmp_path / "app.log.gz"
+    path.write_bytes(
+        bytes(cramjam.gzip.compress(b"first\nsecond\n"))
+        + bytes(cramjam.gzip.compress(b"third\n"))
+    )
+    expected = ["first\n", "second\n", "third\n"]
+    with open(path, "rb") as f:
+        assert list(cramjam.gzip.iter_lines(f)) == expected
+    assert list(cramjam.gzip.iter_lines(cramjam.File(str(path)))) == expected
+    assert list(cramjam.gzip.iter_lines(cramjam.gzip.compress(b""))) == []
+
+    latin = cramjam.gzip.compress("café\n".encode("latin-1"))
+    with pytest.raises(UnicodeDecodeError):
+        list(cramjam.gzip.iter_lines(latin))
+    assert list(cramjam.gzip.iter_lines(latin, encoding="latin-1")) == ["café\n"]
+    assert list(cramjam.gzip.iter_lines(latin, errors="replace")) == ["caf�\n"]
+
+
+def test_iter_lines_errors():
+    with pytest.raises(ValueError):
+        cramjam.iter_lines("unknown", b"")
+    with pytest.raises(cramjam.DecompressionError):
+        list(cramjam.gzip.iter_lines(b"not gzip data" * 10))
+
+    class Source:
+        def read(self, n):
+            raise KeyError("from read")
+
+    # errors raised by the source itself are passed on
+    with pytest.raises(KeyError):
+        list(cramjam.gzip.iter_lines(Source()))
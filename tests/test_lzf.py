@@ -0,0 +1,79 @@
+import os
+
+import pytest
+
+import cramjam
+from cramjam import lzf
+
+DATA = b"some bytes here" * 1000
+
+
+@pytest.mark.parametrize(
+    "data",
+    (
+        b"",
+        b"a",
+        b"hello",
+        DATA,
+        os.urandom(100_000),
+        # references reaching across where decompression writes out what it has so far
+        os.urandom(8192) * 20,
+    ),
+)
+def test_lzf_roundtrip(data):
+    compressed = lzf.compress(data)
+    assert bytes(lzf.decompress(compressed)) == data
+    assert bytes(lzf.decompress(compressed, output_len=len(data))) == data
+
+
+def test_lzf_format():
+    # a run of 5 literals
+    assert bytes(lzf.compress(b"hello")) == b"\x04hello"
+    # a literal then a reference to it, of length 7 + 0 + 2, one back
+    assert bytes(lzf.compress(b"a" * 10)) == b"\x00a\xe0\x00\x00"
+    assert bytes(lzf.decompress(b"\x00a\xe0\x00\x00")) == b"a" * 10
+    assert len(lzf.compress(DATA)) < len(DATA) // 10
+
+
+@pytest.mark.parametrize(
+    "data",
+    (
+        b"\x04hell",  # literal run past the end
+        b"\x00a\xe0\x00",  # reference past the end
+        b"\x00a\x20\x01",  # reference before the start
+    ),
+)
+def test_lzf_invalid(data):
+    with pytest.raises(cramjam.DecompressionError, match="Invalid lzf data"):
+        lzf.decompress(data)
+
+
+def test_lzf_into():
+    compressed = bytes(lzf.compress(DATA))
+
+    output = bytearray(len(compressed))
+    assert lzf.compress_into(DATA, output) == len(compressed)
+    assert bytes(output) == compressed
+
+    output = bytearray(len(DATA))
+    assert lzf.decompress_into(compressed, output) == len(DATA)
+    assert bytes(output) == DATA
+
+
+def test_lzf_expansion_limit():
+    compressed = lzf.compress(b"\x00" * 100_000)
+    with pytest.raises(cramjam.DecompressionError, match="expansion ratio"):
+        lzf.decompress(compressed, max_expansion_ratio=2)
+    assert bytes(lzf.decompress(compressed, output_len_max=100_000)) == b"\x00" * 100_000
+
+
+def test_lzf_file(tmp_path):
+    path = tmp_path / "data.lzf"
+    path.write_bytes(bytes(lzf.compress(DATA)))
+    assert bytes(lzf.decompress(cramjam.File(str(path)))) == DATA
+
+
+def test_lzf_feature():
+    assert cramjam.features()["lzf"]
+    assert "lzf" in cramjam.available_codecs()
+    assert bytes(cramjam.decompress("lzf", cramjam.compress("lzf", DATA))) == DATA
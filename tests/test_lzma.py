@@ -0,0 +1,70 @@
+import lzma
+
+import pytest
+
+import cramjam
+
+DATA = b"some bytes here" * 1000
+
+
+@pytest.mark.parametrize("check", (-1, lzma.CHECK_NONE, lzma.CHECK_CRC32, lzma.CHECK_SHA256))
+def test_compress_xz(check):
+    compressed = bytes(cramjam.lzma.compress(DATA, check=check, preset=9))
+    assert lzma.decompress(compressed) == DATA
+    assert bytes(cramjam.lzma.decompress(compressed)) == DATA
+
+
+@pytest.mark.parametrize(
+    "filters",
+    (
+        [{"id": lzma.FILTER_LZMA2, "preset": 9 | lzma.PRESET_EXTREME}],
+        [
+            {"id": lzma.FILTER_X86},
+            {"id": lzma.FILTER_LZMA2, "dict_size": 1 << 20, "mf": lzma.MF_BT4},
+        ],
+        [{"id": lzma.FILTER_LZMA2, "mode": lzma.MODE_FAST, "nice_len": 32, "lc": 3}],
+    ),
+)
+def test_compress_filters(filters):
+    compressed = bytes(cramjam.lzma.compress(DATA, format=lzma.FORMAT_XZ, filters=filters))
+    assert lzma.decompress(compressed) == DATA
+
+
+def test_compress_alone():
+    filters = [{"id": lzma.FILTER_LZMA1, "preset": 1, "pb": 0}]
+    compressed = bytes(cramjam.lzma.compress(DATA, format=lzma.FORMAT_ALONE, filters=filters))
+    assert lzma.decompress(compressed, format=lzma.FORMAT_ALONE) == DATA
+
+    stdlib = lzma.compress(DATA, format=lzma.FORMAT_ALONE)
+    assert bytes(cramjam.lzma.decompress(stdlib, format=lzma.FORMAT_ALONE)) == DATA
+
+
+def test_constants_match_stdlib():
+    for name in dir(lzma):
+        if name.split("_")[0] in ("FORMAT", "CHECK", "FILTER", "MF", "MODE", "PRESET"):
+            if hasattr(cramjam.lzma, name):
+                assert getattr(cramjam.lzma, name) == getattr(lzma, name), name
+
+
+def test_decompress_concatenated_and_memlimit():
+    compressed = lzma.compress(DATA)
+    assert bytes(cramjam.lzma.decompress(compressed * 2)) == DATA * 2
+    with pytest.raises(cramjam.DecompressionError):
+        cramjam.lzma.decompress(compressed, memlimit=1024)
+
+
+@pytest.mark.parametrize(
+    "kwargs",
+    (
+        dict(preset=6, filters=[{"id": lzma.FILTER_LZMA2}]),
+        dict(filters=[{"id": lzma.FILTER_DELTA, "dist": 4}]),
+        dict(filters=[{"id": lzma.FILTER_LZMA2, "not_an_option": 1}]),
+        dict(filters=[{"id": lzma.FILTER_LZMA2, "preset": 10}]),
+        dict(format=lzma.FORMAT_ALONE, filters=[{"id": lzma.FILTER_LZMA2}]),
+        dict(format=lzma.FORMAT_ALONE, check=lzma.CHECK_CRC32),
+        dict(format=lzma.FORMAT_RAW, filters=[{"id": lzma.FILTER_LZMA2}]),
+    ),
+)
+def test_compress_invalid(kwargs):
+    with pytest.raises(ValueError):
+        cramjam.lzma.compress(DATA, **kwargs)
@@ -0,0 +1,71 @@
+import numpy as np
+import pytest
+import cramjam
+
+
+@pytest.mark.parametrize("codec", ("zstd", "lz4", "snappy", "gzip", "xz"))
+@pytest.mark.parametrize(
+    "arr",
+    (
+        np.arange(24, dtype=np.float64).reshape(2, 3, 4),
+        np.arange(10, dtype=">i4"),
+        np.array(5, dtype=np.int16),
+        np.zeros((0, 3), dtype=np.complex128),
+        np.array([(1, 2.0)], dtype=[("a", "<i4"), ("b", "<f8")]),
+    ),
+)
+def test_ndarray_roundtrip(codec, arr):
+    compressed = cramjam.compress_ndarray(arr, codec=codec)
+    out = cramjam.decompress_ndarray(compressed)
+    assert out.dtype == arr.dtype
+    assert out.shape == arr.shape
+    np.testing.assert_array_equal(out, arr)
+    assert out.flags.writeable
+
+
+def test_ndarray_non_contiguous():
+    arr = np.arange(100, dtype=np.uint32).reshape(10, 10)[::2, 1::3]
+    np.testing.assert_array_equal(cramjam.decompress_ndarray(cramjam.compress_ndarray(arr)), arr)
+
+
+def test_ndarray_multi_dimensional_io():
+    # plain de/compress functions accept any contiguous array, including as output
+    arr = np.arange(12, dtype=np.float32).reshape(3, 4)
+    compressed = cramjam.zstd.compress(arr)
+    out = np.empty_like(arr)
+    assert cramjam.zstd.decompress_into(compressed, out) == arr.nbytes
+    np.testing.assert_array_equal(out, arr)
+
+
+@pytest.mark.parametrize("codec", ("zstd", "snappy", "gzip"))
+def test_ndarray_strided_input(codec):
+    # plain de/compress functions gather non C contiguous arrays in C order, as `tobytes` does
+    arr = np.arange(100, dtype=np.float64).reshape(10, 10)
+    variant = getattr(cramjam, codec)
+    for view in (arr[:, 3], arr[::2, 1::3], np.asfortranarray(arr)):
+        assert not view.flags.c_contiguous
+        assert bytes(variant.decompress(variant.compress(view))) == view.tobytes()
+
+    # outputs are written in place, so they still have to be contiguous
+    out = np.empty((10, 10), dtype=np.uint8)[:, 1]
+    with pytest.raises(BufferError):
+        variant.decompress_into(variant.compress(b"0123456789"), out)
+
+
+def test_ndarray_errors():
+    arr = np.arange(10)
+    with pytest.raises(ValueError):
+        cramjam.compress_ndarray(arr, codec="unknown")
+    with pytest.raises(ValueError):
+        cramjam.compress_ndarray(np.array([object()]))
+    with pytest.raises(cramjam.DecompressionError):
+        cramjam.decompress_ndarray(cramjam.zstd.compress(arr))
+    with pytest.raises(cramjam.DecompressionError):
+        cramjam.decompress_ndarray(bytes(cramjam.compress_ndarray(arr))[:-4])
+
+
+def test_ndarray_file(tmp_path):
+    arr = np.arange(24, dtype=np.float64).reshape(2, 3, 4)
+    path = tmp_path / "arr"
+    path.write_bytes(bytes(cramjam.compress_ndarray(arr)))
+    np.testing.assert_array_equal(cramjam.decompress_ndarray(cramjam.File(str(path))), arr)
@@ -0,0 +1,40 @@
+import pytest
+import cramjam
+
+
+DATA = b"".join(b"line %d of some fairly repetitive text\n" % i for i in range(5000))
+
+
+@pytest.mark.parametrize("target", ("ratio", "speed"))
+def test_optimize(target):
+    compressed, meta = cramjam.optimize(DATA, target=target)
+    assert bytes(getattr(cramjam, meta["codec"]).decompress(compressed)) == DATA
+    assert meta["trials"]
+    if target == "ratio":
+        assert meta["ratio"] == max(t["ratio"] for t in meta["trials"])
+    else:
+        assert meta["elapsed_ms"] == min(t["elapsed_ms"] for t in meta["trials"])
+
+
+def test_optimize_codecs_and_budget():
+    _, meta = cramjam.optimize(DATA, codecs=["zstd"])
+    assert {t["codec"] for t in meta["trials"]} == {"zstd"}
+
+    # at least one candidate is evaluated, regardless of budget
+    _, meta = cramjam.optimize(DATA, budget_ms=0, codecs=["xz"])
+    assert meta["codec"] == "xz"
+    assert len(meta["trials"]) >= 1
+
+
+def test_optimize_errors():
+    with pytest.raises(ValueError):
+        cramjam.optimize(DATA, target="smallest")
+    with pytest.raises(ValueError):
+        cramjam.optimize(DATA, codecs=["unknown"])
+
+
+def test_optimize_file(tmp_path):
+    path = tmp_path / "data"
+    path.write_bytes(DATA)
+    compressed, meta = cramjam.optimize(cramjam.File(str(path)), codecs=["zstd"], budget_ms=100)
+    assert bytes(cramjam.zstd.decompress(compressed)) == DATA
@@ -0,0 +1,69 @@
+import struct
+
+import pytest
+import cramjam
+
+
+DATA = b"".join(struct.pack("<I", i) for i in range(1000)) + b"trailing"
+
+
+@pytest.mark.parametrize(
+    "stages",
+    (
+        ["zstd"],
+        [("shuffle", {"typesize": 4}), ("zstd", {"level": 5})],
+        [("delta", {"typesize": 4}), "gzip", "snappy"],
+        ["shuffle", ("brotli", {"level": 1}), "lz4"],
+    ),
+)
+def test_pipeline_roundtrip(stages):
+    compressed = cramjam.pipeline(DATA, stages)
+    assert bytes(cramjam.pipeline_decompress(compressed, stages)) == DATA
+
+
+def test_pipeline_python_transform():
+    def negate(b):
+        return bytes(255 - x for x in b)
+
+    compressed = cramjam.pipeline(DATA, [negate, "snappy"])
+    assert bytes(compressed) == bytes(cramjam.snappy.compress(negate(DATA)))
+
+    # a python transform is reversed by the inverse given in its place
+    out = cramjam.pipeline_decompress(compressed, [negate, "snappy"])
+    assert bytes(out) == DATA
+
+
+def test_pipeline_errors():
+    with pytest.raises(ValueError):
+        cramjam.pipeline(DATA, ["not-a-codec"])
+    with pytest.raises(ValueError):
+        cramjam.pipeline_decompress(DATA, ["not-a-codec"])
+    with pytest.raises(cramjam.DecompressionError):
+        cramjam.pipeline_decompress(b"not zstd data", ["zstd"])
+
+
+def test_pipeline_expansion_limit():
+    bomb = cramjam.pipeline(b"0" * 1_000_000, ["zstd"])
+    with pytest.raises(cramjam.DecompressionError, match="expansion ratio"):
+        cramjam.pipeline_decompress(bomb, ["zstd"], max_expansion_ratio=100)
+    with pytest.raises(cramjam.DecompressionError, match="output_len_max"):
+        cramjam.pipeline_decompress(bomb, ["zstd"], output_len_max=1024)
+
+    cramjam.set_max_expansion_ratio(100)
+    try:
+        with pytest.raises(cramjam.DecompressionError, match="expansion ratio"):
+            cramjam.pipeline_decompress(bomb, ["zstd"])
+        assert len(cramjam.pipeline_decompress(bomb, ["zstd"], max_expansion_ratio=float("inf"))) == 1_000_000
+    finally:
+        cramjam.set_max_expansion_ratio(None)
+
+
+def test_pipeline_file(tmp_path):
+    path = tmp_path / "data"
+    path.write_bytes(DATA)
+    compressed = cramjam.pipeline(cramjam.File(str(path)), ["zstd"])
+
+    path.write_bytes(bytes(compressed))
+    file = cramjam.File(str(path))
+    assert bytes(cramjam.pipeline_decompress(file, ["zstd"])) == DATA
+    assert file.tell() == len(compressed)
@@ -0,0 +1,51 @@
+import sys
+import pytest
+import cramjam
+
+
+def install_plugin(path, name, module, source):
+    """Install a codec package with a `cramjam.codecs` entry point into `path`"""
+    dist_info = path / f"{module}-0.1.dist-info"
+    dist_info.mkdir()
+    (dist_info / "METADATA").write_text(f"Metadata-Version: 2.1\nName: {module}\nVersion: 0.1\n")
+    (dist_info / "entry_points.txt").write_text(f"[cramjam.codecs]\n{name} = {module}\n")
+    (path / f"{module}.py").write_text(source)
+
+
+REVERSE = """
+def compress(data, level=None):
+    return bytes(data)[::-1]
+
+def decompress(data, output_len=None):
+    return bytes(data)[::-1]
+"""
+
+
+def test_load_plugins(tmp_path, monkeypatch):
+    install_plugin(tmp_path, "reverse", "cramjam_reverse", REVERSE)
+    monkeypatch.syspath_prepend(str(tmp_path))
+    try:
+        assert "reverse" in cramjam.load_plugins()
+        assert cramjam.reverse.decompress(cramjam.reverse.compress(b"bytes")) == b"bytes"
+
+        from cramjam import reverse
+
+        assert reverse is cramjam.reverse
+
+        # loading again keeps the plugin already attached
+        assert "reverse" in cramjam.load_plugins()
+        assert reverse is cramjam.reverse
+    finally:
+        delattr(cramjam, "reverse")
+        sys.modules.pop("cramjam.reverse", None)
+
+
+def test_load_plugins_invalid(tmp_path, monkeypatch):
+    install_plugin(tmp_path, "zstd", "cramjam_fake_zstd", REVERSE)
+    install_plugin(tmp_path, "incomplete", "cramjam_incomplete", "def compress(data): pass\n")
+    monkeypatch.syspath_prepend(str(tmp_path))
+
+    with pytest.raises((ValueError, ImportError)):
+        cramjam.load_plugins()
+    assert cramjam.zstd.decompress is not None
+    assert not hasattr(cramjam, "incomplete")
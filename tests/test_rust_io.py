@@ -1,4 +1,7 @@
+import io
+import os
 import pytest
+import cramjam
 
 from cramjam import File, Buffer
 
@@ -60,3 +63,337 @@ def test_obj_api(tmpdir, Obj, is_pypy):
     buf.truncate()
     buf.seek(0)
     assert buf.read() == b""
+
+
+@pytest.mark.parametrize("readahead", (1, 7, 1024))
+def test_file_readahead(tmpdir, readahead):
+    import cramjam
+
+    data = b"some bytes to prefetch " * 1000
+    path = str(tmpdir.join("file.zst"))
+    File(path).write(cramjam.zstd.compress(data))
+
+    file = File(path, readahead=readahead)
+    assert bytes(cramjam.zstd.decompress(file)) == data
+
+    # position reflects what's been consumed, not what's been prefetched
+    file.seek(0)
+    assert file.read(4) == bytes(cramjam.zstd.compress(data))[:4]
+    assert file.tell() == 4
+
+    # writes after a partial read land at the logical position
+    file.seek(0)
+    file.read(2)
+    file.write(b"xx")
+    file.seek(0)
+    assert file.read(4)[2:] == b"xx"
+
+
+def test_file_pathlike(tmp_path):
+    import os
+
+    file = File(tmp_path / "pathlib.txt")
+    assert file.write(b"bytes") == 5
+    assert (tmp_path / "pathlib.txt").read_bytes() == b"bytes"
+
+    file = File(os.fsencode(tmp_path / "bytes.txt"))
+    assert file.write(b"bytes") == 5
+    assert (tmp_path / "bytes.txt").read_bytes() == b"bytes"
+
+    if os.name != "nt" and os.uname().sysname != "Darwin":
+        # Non-UTF8 file names are valid on most unix filesystems
+        path = os.fsencode(tmp_path) + b"/\xff\xfe.txt"
+        File(path).write(b"bytes")
+        assert os.path.exists(path)
+
+    with pytest.raises(TypeError):
+        File(1)
+
+
+def test_file_context_manager(tmp_path):
+    path = tmp_path / "file.txt"
+    with File(path) as file:
+        file.write(b"bytes")
+    assert path.read_bytes() == b"bytes"
+    assert "closed" in repr(file)
+
+    # closed on exit, further operations raise and close is idempotent
+    with pytest.raises(OSError):
+        file.write(b"bytes")
+    file.close()
+
+    # without delete_on_error, partial output is kept
+    with pytest.raises(RuntimeError):
+        with File(path) as file:
+            raise RuntimeError()
+    assert path.exists()
+
+    with pytest.raises(RuntimeError):
+        with File(path, delete_on_error=True) as file:
+            file.write(b"partial")
+            raise RuntimeError()
+    assert not path.exists()
+
+
+def test_buffer_context_manager():
+    with Buffer(b"bytes") as buf:
+        assert buf.read() == b"bytes"
+    assert buf.read() == b""
+
+
+def test_file_binaryio_api(tmp_path):
+    path = str(tmp_path / "file.txt")
+    for kwargs, mode, readable in (
+        (dict(), "rb+", True),
+        (dict(read=False), "wb", False),
+        (dict(truncate=True), "wb+", True),
+        (dict(append=True), "ab+", True),
+        (dict(append=True, read=False), "ab", False),
+    ):
+        file = File(path, **kwargs)
+        assert file.mode == mode
+        assert file.readable() is readable
+        assert file.writable()
+        assert not file.isatty()
+        assert file.name == path
+        assert not file.closed
+
+    file = File(path, truncate=True)
+    file.write(b"bytes")
+    file.flush()
+    if os.name == "posix":
+        assert os.pread(file.fileno(), 5, 0) == b"bytes"
+    file.close()
+    assert file.closed
+
+    # write only without truncate reports "wb", as io.FileIO does, but keeps what's there
+    file = File(path, read=False)
+    assert file.mode == "wb"
+    file.close()
+    with open(path, "rb") as f:
+        assert f.read() == b"bytes"
+
+
+def test_buffer_binaryio_api():
+    buf = Buffer(b"bytes")
+    assert buf.readable() and buf.writable()
+    assert not buf.isatty()
+    assert not buf.closed
+    assert buf.mode == "rb+"
+    assert isinstance(buf.name, str)
+    buf.flush()
+    with pytest.raises(io.UnsupportedOperation):
+        buf.fileno()
+
+
+def test_io_abcs(tmp_path):
+    import tarfile
+
+    file = File(str(tmp_path / "file.txt"))
+    assert isinstance(file, io.RawIOBase) and isinstance(file, io.IOBase)
+    for buffer in (Buffer(), cramjam.SpooledBuffer(max_memory=10)):
+        assert isinstance(buffer, io.BufferedIOBase) and isinstance(buffer, io.IOBase)
+    assert not isinstance(Buffer(), io.TextIOBase)
+
+    # usable by libraries checking for file objects
+    archive = io.BytesIO()
+    with tarfile.open(fileobj=archive, mode="w") as tar:
+        info = tarfile.TarInfo("data.txt")
+        info.size = 15
+        tar.addfile(info, io.BytesIO(b"some bytes here"))
+    with tarfile.open(fileobj=Buffer(archive.getvalue())) as tar:
+        assert tar.extractfile("data.txt").read() == b"some bytes here"
+
+
+@pytest.mark.parametrize("Obj", (File, Buffer))
+def test_read_n_stops_at_end(tmp_path, Obj):
+    obj = File(str(tmp_path / "file.txt")) if Obj is File else Buffer()
+    obj.write(b"bytes")
+    obj.seek(0)
+    assert obj.read(3) == b"byt"
+    assert obj.read(10) == b"es"
+    assert obj.read(10) == b""
+
+
+def test_file_exclusive(tmp_path):
+    path = tmp_path / "file.txt"
+    with File(str(path), exclusive=True) as file:
+        assert file.mode == "xb+"
+        file.write(b"bytes")
+    with pytest.raises(FileExistsError):
+        File(str(path), exclusive=True)
+    assert path.read_bytes() == b"bytes"
+
+
+def test_file_buffering(tmp_path):
+    path = tmp_path / "file.txt"
+    file = File(str(path), buffering=10)
+    for _ in range(3):
+        file.write(b"byt")
+    # gathered in memory until `buffering` bytes are pending
+    assert path.stat().st_size == 0
+    file.write(b"es")
+    assert path.read_bytes() == b"bytbytbyt"
+    # len, reads and seeks see what's pending
+    assert len(file) == 11
+    file.seek(0)
+    assert file.read() == b"bytbytbytes"
+
+    file.write(b"more")
+    del file
+    assert path.read_bytes() == b"bytbytbytesmore"
+
+    with File(str(path), truncate=True, buffering=1024) as file:
+        cramjam.snappy.compress_into(b"some bytes here", file)
+    assert bytes(cramjam.snappy.decompress(path.read_bytes())) == b"some bytes here"
+
+
+def test_buffer_capacity_and_extend():
+    buffer = Buffer(b"some", capacity=1024)
+    assert len(buffer) == 4
+    assert buffer.capacity() >= 1024
+
+    buffer.reserve(4096)
+    assert len(buffer) == 4
+    assert buffer.capacity() >= 4100
+
+    buffer.extend(b" bytes")
+    buffer.extend(Buffer(b" here"))
+    assert buffer.tell() == 0
+    assert bytes(buffer) == b"some bytes here"
+    buffer.extend(buffer)
+    assert bytes(buffer) == b"some bytes heresome bytes here"
+
+    buffer.shrink_to_fit()
+    assert len(buffer) <= buffer.capacity() < 4100
+
+    output = Buffer(capacity=1024)
+    cramjam.snappy.compress_into(b"some bytes here", output)
+    assert bytes(cramjam.snappy.decompress(output)) == b"some bytes here"
+
+    zeros = Buffer.zeros(15)
+    assert (len(zeros), zeros.tell(), bytes(zeros)) == (15, 0, bytes(15))
+    assert cramjam.snappy.decompress_into(cramjam.snappy.compress(b"some bytes here"), zeros) == 15
+    assert bytes(zeros) == b"some bytes here"
+    assert len(Buffer.zeros(0)) == 0
+
+
+def test_buffer_take_and_split_to():
+    buffer = Buffer(b"header:payload")
+    header = buffer.take(6)
+    assert bytes(header) == b"header"
+    assert header.tell() == 0
+    assert buffer.tell() == 6
+    assert buffer.read(1) == b":"
+    # only what's left is taken
+    assert bytes(buffer.take(100)) == b"payload"
+    assert buffer.tell() == len(buffer)
+    assert bytes(buffer.take(1)) == b""
+
+    buffer = Buffer(b"header:payload")
+    buffer.seek(10)
+    head = buffer.split_to(7)
+    assert bytes(head) == b"header:"
+    assert bytes(buffer) == b"payload"
+    assert buffer.tell() == 3
+    assert bytes(buffer.split_to(0)) == b""
+    with pytest.raises(ValueError):
+        buffer.split_to(100)
+
+
+def test_spooled_buffer(tmp_path):
+    buffer = cramjam.SpooledBuffer(max_memory=16, data=b"some bytes", dir=tmp_path)
+    assert (len(buffer), buffer.tell(), buffer.spilled) == (10, 0, False)
+    assert buffer.read(4) == b"some"
+
+    buffer.seek(0, 2)
+    buffer.write(b" here, past 16")
+    assert buffer.spilled and len(buffer) == 24
+    assert buffer.seek(0) == 0 and buffer.read() == b"some bytes here, past 16"
+    if os.name == "posix":
+        assert list(tmp_path.iterdir()) == []
+
+    # as an output, spilling once it's written past max_memory, and as an input once spilled
+    data = b"some bytes here" * 10_000
+    for max_memory, spilled in ((1024, True), (1024 * 1024, False)):
+        output = cramjam.SpooledBuffer(max_memory, dir=tmp_path)
+        cramjam.zstd.decompress_into(cramjam.zstd.compress(data), output)
+        assert (output.spilled, len(output)) == (spilled, len(data))
+        output.seek(0)
+        compressed = cramjam.gzip.compress(output)
+        assert bytes(cramjam.gzip.decompress(compressed)) == data
+
+        output.truncate()
+        assert (len(output), output.tell(), output.spilled) == (0, 0, spilled)
+
+    buffer = cramjam.SpooledBuffer(1024)
+    buffer.rollover()
+    assert buffer.spilled and not buffer
+
+
+def test_spooled_buffer_input_position(tmp_path):
+    # read from its current position as a File is, whether or not it spilled to disk
+    data = bytes(range(100))
+    for max_memory in (16, 1024):
+        buffer = cramjam.SpooledBuffer(max_memory=max_memory, data=data, dir=tmp_path)
+        buffer.seek(50)
+        assert bytes(cramjam.snappy.decompress(cramjam.snappy.compress(buffer))) == data[50:]
+        assert buffer.tell() == 100
+        buffer.seek(50)
+        assert cramjam.checksum.crc32(buffer) == cramjam.checksum.crc32(data[50:])
+        assert buffer.tell() == 100
+
+        # peeking at a header leaves it where it was
+        compressed = b"x" * 50 + bytes(cramjam.gzip.compress(data))
+        buffer = cramjam.SpooledBuffer(max_memory=max_memory, data=compressed, dir=tmp_path)
+        buffer.seek(50)
+        assert cramjam.guess_codec(buffer) == "gzip"
+        assert buffer.tell() == 50
+
+
+def test_spooled_buffer_spilled_input(tmp_path):
+    data = b"some bytes here" * 10_000
+
+    def spilled(data):
+        buffer = cramjam.SpooledBuffer(max_memory=16, data=data, dir=tmp_path)
+        assert buffer.spilled
+        return buffer
+
+    # inspected in place rather than as bytes, which a spilled buffer can't give
+    assert cramjam.checksum.crc32(spilled(data)) == cramjam.checksum.crc32(data)
+    assert cramjam.info(spilled(cramjam.zstd.compress(data)))["codec"] == "zstd"
+    assert cramjam.guess_codec(spilled(cramjam.gzip.compress(data))) == "gzip"
+    assert bytes(cramjam.snappy.compress_raw(spilled(data))) == bytes(cramjam.snappy.compress_raw(data))
+    framed = bytes(cramjam.snappy.compress(data))
+    assert list(cramjam.snappy.chunks(spilled(framed))) == list(cramjam.snappy.chunks(framed))
+    raw = bytes(cramjam.snappy.compress_raw(data))
+    assert cramjam.snappy.decompress_raw_len(spilled(raw)) == len(data)
+    assert spilled(raw) not in cramjam.Buffer(data)
+
+    # or read whole, where the codec needs it all at once
+    block = bytes(cramjam.lz4.compress_block(data))
+    assert bytes(cramjam.lz4.decompress_block(spilled(block))) == data
+    assert bytes(cramjam.lz4.compress_block(spilled(data))) == block
+    bare = bytes(cramjam.lz4.compress_block(data, store_size=False))
+    assert bytes(cramjam.lz4.decompress_raw_with_len(spilled(bare), len(data))) == data
+    sink = io.BytesIO()
+    cramjam.framed.Writer(sink, "snappy").write(spilled(data))
+    assert [bytes(record) for record in cramjam.framed.Reader(sink.getvalue(), "snappy")] == [data]
+
+    # and read as needed for random access
+    compressed = bytes(cramjam.gzip.compress(data))
+    index = cramjam.gzip.build_index(spilled(compressed), spacing=10_000)
+    reader = cramjam.gzip.Reader(spilled(compressed), index)
+    reader.seek(100_000)
+    assert bytes(reader.read(15)) == data[100_000:100_015]
+
+    sink = io.BytesIO()
+    with cramjam.zstd.seekable.Writer(sink, frame_size=10_000) as writer:
+        writer.write(data)
+    reader = cramjam.zstd.seekable.Reader(spilled(sink.getvalue()))
+    reader.seek(100_000)
+    assert bytes(reader.read(15)) == data[100_000:100_015]
+
+    # nor can it be written into as a buffer
+    with pytest.raises(TypeError, match="spilled"):
+        cramjam.snappy.compress_raw_into(data, spilled(bytes(100)))
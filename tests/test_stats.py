@@ -0,0 +1,33 @@
+import cramjam
+
+
+def test_stats():
+    cramjam.stats(reset=True)
+    cramjam.zstd.compress(b"data")
+    assert cramjam.stats() == {}, "collected while disabled"
+
+    cramjam.enable_stats()
+    try:
+        data = b"some bytes here" * 100
+        compressed = bytes(cramjam.zstd.compress(data))
+        cramjam.zstd.decompress(compressed)
+        cramjam.gzip.compress_into(data, cramjam.Buffer())
+        try:
+            cramjam.zstd.decompress(b"not zstd")
+        except cramjam.DecompressionError:
+            pass
+
+        stats = cramjam.stats(reset=True)
+        assert set(stats) == {"zstd", "gzip"}
+        assert stats["zstd"]["compress"]["calls"] == 1
+        assert stats["zstd"]["compress"]["bytes_in"] == len(data)
+        assert stats["zstd"]["compress"]["bytes_out"] == len(compressed)
+        # failed calls aren't counted
+        assert stats["zstd"]["decompress"]["calls"] == 1
+        assert stats["zstd"]["decompress"]["bytes_out"] == len(data)
+        assert stats["gzip"]["compress"]["calls"] == 1
+        assert all(d["time_ms"] >= 0 for codec in stats.values() for d in codec.values())
+
+        assert cramjam.stats() == {}
+    finally:
+        cramjam.enable_stats(False)
@@ -0,0 +1,43 @@
+import pytest
+
+import cramjam
+
+CODECS = ("brotli", "bzip2", "deflate", "gzip", "lz4", "snappy", "xz", "zlib", "zstd")
+
+
+@pytest.mark.parametrize("codec", CODECS)
+def test_reference_vectors(codec):
+    vectors = cramjam.testing.reference_vectors(codec)
+    assert vectors
+    for plaintext, compressed in vectors:
+        assert plaintext != compressed
+        assert bytes(getattr(cramjam, codec).decompress(compressed)) == plaintext
+
+
+@pytest.mark.parametrize("codec", CODECS)
+def test_roundtrip(codec):
+    data = b"some bytes here" * 100
+    compressed = cramjam.testing.roundtrip(codec, data)
+    assert bytes(getattr(cramjam, codec).decompress(compressed)) == data
+
+
+def test_roundtrip_opts():
+    compressed = cramjam.testing.roundtrip("gzip", b"some bytes here", level=9)
+    assert bytes(compressed)[8] == 2  # gzip's XFL byte, set for maximum compression
+
+    with pytest.raises(TypeError):
+        cramjam.testing.roundtrip("gzip", b"some bytes here", not_an_option=1)
+
+
+def test_unknown_codec():
+    with pytest.raises(ValueError):
+        cramjam.testing.roundtrip("not-a-codec", b"data")
+    with pytest.raises(ValueError):
+        cramjam.testing.reference_vectors("not-a-codec")
+
+
+def test_roundtrip_file(tmp_path):
+    path = tmp_path / "data"
+    path.write_bytes(b"some bytes here" * 100)
+    compressed = cramjam.testing.roundtrip("zstd", cramjam.File(str(path)))
+    assert bytes(cramjam.zstd.decompress(compressed)) == b"some bytes here" * 100
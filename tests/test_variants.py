@@ -1,5 +1,9 @@
+import io
 import os
+import subprocess
+import sys
 import gzip
+import zlib
 import pytest
 import numpy as np
 import cramjam
@@ -21,11 +25,9 @@ VARIANTS = (
 )
 
 for experimental_feat in ("blosc2", "igzip", "ideflate", "izlib"):
-    if not hasattr(cramjam, experimental_feat) and hasattr(cramjam, "experimental"):
-        mod = getattr(cramjam.experimental, experimental_feat, None)
-        if mod:
-            setattr(cramjam, experimental_feat, mod)
-            VARIANTS = (*VARIANTS, experimental_feat)
+    if not hasattr(cramjam, experimental_feat) and cramjam.features()[experimental_feat]:
+        setattr(cramjam, experimental_feat, getattr(cramjam.experimental, experimental_feat))
+        VARIANTS = (*VARIANTS, experimental_feat)
 
 # Some OS can be slow or have higher variability in their runtimes on CI
 settings.register_profile("local", deadline=None, max_examples=20)
@@ -46,6 +48,39 @@ def test_has_version():
     assert isinstance(__version__, str)
 
 
+def test_features():
+    features = cramjam.features()
+    for variant_str in VARIANTS:
+        assert features[variant_str]
+
+    # modules which weren't compiled can still be imported, but raise when used
+    for name in ("igzip", "ideflate", "izlib", "blosc2"):
+        module = getattr(cramjam.experimental, name)
+        if not features[name]:
+            assert not hasattr(module, "__path__")
+            with pytest.raises(cramjam.CramjamNotBuiltError, match=name):
+                module.compress(b"bytes")
+            assert issubclass(cramjam.CramjamNotBuiltError, ImportError)
+            with pytest.raises(cramjam.CodecUnavailableError, match=f"`{name}` feature|64 bit"):
+                module.decompress(b"bytes")
+
+
+def test_available_codecs():
+    import importlib
+
+    features = cramjam.features()
+    available = cramjam.available_codecs()
+    assert available == [name for name, built in features.items() if built and name not in ("zstdmt", "zlib-ng")]
+    assert set(VARIANTS) <= set(available)
+
+    # codec modules, built or not, can be imported by name
+    from cramjam.experimental import blosc2
+
+    assert importlib.import_module("cramjam.snappy") is cramjam.snappy
+    assert importlib.import_module("cramjam.experimental.blosc2") is blosc2
+    assert issubclass(cramjam.CodecUnavailableError, cramjam.CramjamNotBuiltError)
+
+
 @pytest.mark.parametrize("variant_str", VARIANTS)
 @given(arr=st_np.arrays(st_np.scalar_dtypes(), shape=st.integers(0, int(1e4))))
 def test_variants_different_dtypes(variant_str, arr, is_pypy):
@@ -253,6 +288,37 @@ def test_variant_snappy_raw_into(data):
     assert same_same(decompressed_buffer[:n_bytes], data)
 
 
+def test_variant_snappy_hadoop():
+    data = os.urandom(300_000) + b"some bytes here" * 10_000
+    compressed = bytes(cramjam.snappy.compress_hadoop(data))
+
+    # blocks of <uncompressed len><compressed len><raw snappy>, lengths 4 byte big endian
+    block_len = int.from_bytes(compressed[:4], "big")
+    chunk_len = int.from_bytes(compressed[4:8], "big")
+    chunk = compressed[8 : 8 + chunk_len]
+    assert bytes(cramjam.snappy.decompress_raw(chunk)) == data[:block_len]
+
+    assert bytes(cramjam.snappy.decompress_hadoop(compressed)) == data
+
+    output = bytearray(len(data))
+    assert cramjam.snappy.decompress_hadoop_into(compressed, output) == len(data)
+    assert output == data
+    output = bytearray(len(compressed))
+    assert cramjam.snappy.compress_hadoop_into(data, output) == len(compressed)
+    assert output == compressed
+
+    # a block may be split over several chunks
+    chunks = [cramjam.snappy.compress_raw(part) for part in (b"some ", b"bytes")]
+    framed = (10).to_bytes(4, "big") + b"".join(
+        len(c).to_bytes(4, "big") + bytes(c) for c in chunks
+    )
+    assert bytes(cramjam.snappy.decompress_hadoop(framed)) == b"some bytes"
+
+    assert bytes(cramjam.snappy.decompress_hadoop(b"")) == b""
+    with pytest.raises(cramjam.DecompressionError):
+        cramjam.snappy.decompress_hadoop(compressed[:-1])
+
+
 @given(data=st.binary())
 def test_variant_lz4_block_into(data):
     """
@@ -327,6 +393,56 @@ def test_lz4_block(compress_kwargs):
     assert same_same(bytes(out), data)
 
 
+def test_lz4_block_stored_size(tmp_path):
+    from cramjam import lz4
+
+    data = b"howdy neighbor" * 50
+    compressed = bytes(lz4.compress_block(data))
+    raw = bytes(lz4.compress_block(data, store_size=False))
+    assert lz4.has_stored_size(compressed)
+    assert lz4.has_stored_size(lz4.compress_block(b""))
+    assert not lz4.has_stored_size(raw)
+    assert not lz4.has_stored_size(b"\x0e\x00")
+
+    # likewise of a File, without moving its position
+    path = tmp_path / "compressed"
+    path.write_bytes(compressed)
+    file = cramjam.File(str(path))
+    assert lz4.has_stored_size(file)
+    assert file.tell() == 0
+
+    # both are DecompressionErrors, distinguishing what went wrong
+    with pytest.raises(lz4.MissingStoredSizeError):
+        lz4.decompress_block(raw)
+    # a size the rest of the data can't possibly decompress to isn't allocated
+    with pytest.raises(lz4.MissingStoredSizeError):
+        lz4.decompress_block(b"\xff\xff\xff\x7f" + compressed[4:])
+    with pytest.raises(lz4.CorruptBlockError):
+        lz4.decompress_block(compressed[:-3])
+    assert issubclass(lz4.CorruptBlockError, cramjam.DecompressionError)
+
+    with pytest.raises(cramjam.DecompressionError, match="max_output_len"):
+        lz4.decompress_block(compressed, max_output_len=len(data) - 1)
+    with pytest.raises(cramjam.DecompressionError, match="max_output_len"):
+        lz4.decompress_block(raw, output_len=len(data), max_output_len=len(data) - 1)
+    assert bytes(lz4.decompress_block(compressed, max_output_len=len(data))) == data
+
+
+@pytest.mark.parametrize("variant_str", ("snappy", "lz4"))
+def test_decompress_raw_with_len(variant_str):
+    variant = getattr(cramjam, variant_str)
+    data = b"howdy neighbor" * 50
+    if variant_str == "snappy":
+        compressed = bytes(variant.compress_raw(data))
+    else:
+        compressed = bytes(variant.compress_block(data, store_size=False))
+
+    assert bytes(variant.decompress_raw_with_len(compressed, len(data))) == data
+    for wrong_len in (0, len(data) - 1, len(data) + 1, 2**40):
+        with pytest.raises(cramjam.DecompressionError):
+            variant.decompress_raw_with_len(compressed, wrong_len)
+
+
 @given(first=st.binary(), second=st.binary())
 def test_gzip_multiple_streams(first: bytes, second: bytes):
     out1 = gzip.compress(first)
@@ -353,6 +469,8 @@ def test_gzip_multiple_streams(first: bytes, second: bytes):
         cramjam.gzip,
         cramjam.lz4,
         cramjam.snappy,
+        cramjam.xz,
+        cramjam.zlib,
         cramjam.zstd,
     ),
 )
@@ -379,6 +497,51 @@ def test_streams_compressor(mod, first: bytes, second: bytes):
         compressor.compress(b"data")
 
 
+@pytest.mark.parametrize("variant_str", [v for v in VARIANTS if v != "blosc2"])
+def test_variants_compressor_returns_output(variant_str):
+    variant = getattr(cramjam, variant_str)
+    data = os.urandom(1 << 20) + b"some bytes here" * 100_000
+
+    compressor = variant.Compressor(returns_output=True)
+    out = b""
+    for i in range(0, len(data), 1 << 16):
+        produced = compressor.compress(data[i : i + (1 << 16)])
+        assert isinstance(produced, cramjam.Buffer)
+        out += bytes(produced)
+    # compressed output comes out as it's produced, not all at the end
+    assert len(out) > 0
+    out += bytes(compressor.finish())
+    assert bytes(variant.decompress(out)) == data
+
+    # flushing still returns whatever compress hasn't
+    compressor = variant.Compressor(returns_output=True)
+    out = bytes(compressor.compress(b"some bytes here")) + bytes(compressor.flush()) + bytes(compressor.finish())
+    assert bytes(variant.decompress(out)) == b"some bytes here"
+    with pytest.raises(cramjam.CompressionError):
+        compressor.compress(b"data")
+
+    assert variant.Compressor().compress(b"some bytes here") == 15
+
+
+def test_lz4_compressor_returns_output_with_output(tmp_path):
+    with pytest.raises(ValueError, match="returns_output"):
+        cramjam.lz4.Compressor(output=str(tmp_path / "data.lz4"), returns_output=True)
+
+
+def test_xz_compressor_flush_is_decodable():
+    # each flush ends an xz block, so what's been returned decodes without waiting for finish
+    import lzma
+
+    compressor = cramjam.xz.Compressor()
+    decompressor = lzma.LZMADecompressor()
+    for record in (b"first record " * 10, b"second record"):
+        compressor.compress(record)
+        assert decompressor.decompress(bytes(compressor.flush())) == record
+    assert bytes(compressor.flush()) == b""
+    assert decompressor.decompress(bytes(compressor.finish())) == b""
+    assert decompressor.eof
+
+
 @pytest.mark.parametrize("variant_str", VARIANTS)
 def test_variants_stream_decompressors(variant_str):
     variant = getattr(cramjam, variant_str)
@@ -400,7 +563,1044 @@ def test_variants_stream_decompressors(variant_str):
         decompressor.finish()
 
 
+@pytest.mark.parametrize("variant_str", VARIANTS)
+def test_variants_stream_decompressors_read(variant_str):
+    variant = getattr(cramjam, variant_str)
+    decompressor = variant.Decompressor()
+    decompressor.decompress(bytes(variant.compress(b"first line\nsecond line\n")))
+
+    assert decompressor.readable() and not decompressor.seekable()
+    assert bytes(decompressor.read(6)) == b"first "
+    buf = bytearray(5)
+    assert decompressor.readinto(buf) == 5
+    assert buf == b"line\n"
+
+    # what's left is still there after more input, and can be read as text
+    decompressor.decompress(bytes(variant.compress(b"third line\n")))
+    with io.TextIOWrapper(decompressor, encoding="utf-8") as text:
+        assert text.readlines() == ["second line\n", "third line\n"]
+    assert decompressor.closed
+    with pytest.raises(cramjam.DecompressionError):
+        decompressor.read()
+
+
+def test_brotli_compressor_flush_is_decodable():
+    compressor = cramjam.brotli.Compressor()
+    sent = b""
+    for event in (b"data: first event\n\n", b"data: second event\n\n"):
+        compressor.compress(event)
+        sent += bytes(compressor.flush())
+        # a flush leaves the stream byte aligned between meta-blocks, so appending an
+        # empty last meta-block completes it, which shows everything so far is decodable
+        assert bytes(cramjam.brotli.decompress(sent + b"\x03")).endswith(event)
+    sent += bytes(compressor.finish())
+    assert bytes(cramjam.brotli.decompress(sent)) == (
+        b"data: first event\n\ndata: second event\n\n"
+    )
+
+
+def test_brotli_lgwin_mode():
+    data = b"".join(b"<p>paragraph %d</p>\n" % i for i in range(10_000))
+    Mode = cramjam.brotli.Mode
+
+    default = bytes(cramjam.brotli.compress(data))
+    assert bytes(cramjam.brotli.compress(data, lgwin=22, mode=Mode.GENERIC)) == default
+    # the first bit of the stream header is unset only for a window of 2**16 bytes
+    assert default[0] & 1 == 1
+    compressed = bytes(cramjam.brotli.compress(data, lgwin=16, mode=Mode.TEXT))
+    assert compressed[0] & 1 == 0
+    assert bytes(cramjam.brotli.decompress(compressed)) == data
+
+    for mode in (Mode.GENERIC, Mode.TEXT, Mode.FONT):
+        compressor = cramjam.brotli.Compressor(level=5, lgwin=24, mode=mode)
+        compressor.compress(data)
+        assert bytes(cramjam.brotli.decompress(compressor.finish())) == data
+        out = cramjam.Buffer()
+        cramjam.brotli.compress_into(data, out, lgwin=10, mode=mode)
+        assert bytes(cramjam.brotli.decompress(out)) == data
+
+    for invalid in (9, 25):
+        with pytest.raises(ValueError, match="lgwin"):
+            cramjam.brotli.compress(data, lgwin=invalid)
+        with pytest.raises(ValueError, match="lgwin"):
+            cramjam.brotli.Compressor(lgwin=invalid)
+
+
+def test_gzip_stream_decompressor_members():
+    first, second = b"first member", b"second member" * 100
+    compressed = bytes(cramjam.gzip.compress(first)) + bytes(cramjam.gzip.compress(second))
+
+    # fed a byte at a time, output is produced incrementally
+    decompressor = cramjam.gzip.Decompressor()
+    assert not decompressor.eof
+    total = sum(decompressor.decompress(compressed[i : i + 1]) for i in range(len(compressed)))
+    assert total == len(first) + len(second)
+    assert decompressor.eof
+    assert decompressor.unused_data == b""
+
+    # trailing data which isn't another member is kept aside
+    decompressor.decompress(b"trailing")
+    decompressor.decompress(b" garbage")
+    assert decompressor.eof
+    assert decompressor.unused_data == b"trailing garbage"
+    assert bytes(decompressor.finish()) == first + second
+
+    decompressor = cramjam.gzip.Decompressor()
+    decompressor.decompress(compressed[:-4])
+    assert not decompressor.eof
+    with pytest.raises(cramjam.DecompressionError):
+        decompressor.finish()
+
+    decompressor = cramjam.gzip.Decompressor()
+    with pytest.raises(cramjam.DecompressionError):
+        decompressor.decompress(b"not gzip")
+
+
+def test_bzip2_stream_decompressor_multistream():
+    import bz2
+
+    streams = [b"first stream " * 1000, b"second stream", b"third stream " * 5000]
+    compressed = b"".join(bz2.compress(stream) for stream in streams)
+
+    # fed in small chunks, output is produced incrementally across the streams
+    decompressor = cramjam.bzip2.Decompressor()
+    assert (decompressor.eof, decompressor.needs_input) == (False, True)
+    output = b""
+    for i in range(0, len(compressed), 7):
+        decompressor.decompress(compressed[i : i + 7])
+        output += bytes(decompressor.flush())
+    assert output == b"".join(streams)
+    assert (decompressor.eof, decompressor.needs_input) == (True, False)
+    assert decompressor.unused_data == b""
+
+    # trailing data which isn't another stream is kept aside
+    decompressor.decompress(b"BZ trailing")
+    assert decompressor.unused_data == b"BZ trailing"
+    assert bytes(decompressor.finish()) == b""
+
+    decompressor = cramjam.bzip2.Decompressor()
+    decompressor.decompress(compressed[:-4])
+    assert not decompressor.eof
+    with pytest.raises(cramjam.DecompressionError):
+        decompressor.finish()
+
+    with pytest.raises(cramjam.DecompressionError):
+        cramjam.bzip2.Decompressor().decompress(b"not bzip2")
+
+
+def test_xz_stream_decompressor_incremental():
+    data = os.urandom(64_000)
+    compressed = bytes(cramjam.xz.compress(data))
+
+    # output is produced as input arrives, rather than once it's all been given
+    decompressor = cramjam.xz.Decompressor()
+    half = len(compressed) // 2
+    assert decompressor.decompress(compressed[:half]) > 0
+    assert not decompressor.eof
+    decompressor.decompress(compressed[half:])
+    assert decompressor.eof
+
+    # another stream may follow, anything else is unused data
+    decompressor.decompress(compressed + b"trailing")
+    assert decompressor.eof
+    assert decompressor.unused_data == b"trailing"
+    assert bytes(decompressor.finish()) == data * 2
+
+    alone = bytes(cramjam.xz.compress(b"some bytes here", format=cramjam.xz.Format.ALONE))
+    decompressor = cramjam.xz.Decompressor()
+    for i in range(len(alone)):
+        decompressor.decompress(alone[i : i + 1])
+    assert bytes(decompressor.finish()) == b"some bytes here"
+
+    decompressor = cramjam.xz.Decompressor()
+    decompressor.decompress(compressed[:-1])
+    with pytest.raises(cramjam.DecompressionError):
+        decompressor.finish()
+
+
+def test_brotli_stream_decompressor_incremental(tmp_path):
+    data = os.urandom(64_000) + b"a" * 1_000_000
+    compressed = bytes(cramjam.brotli.compress(data, level=1))
+
+    # output is produced as input arrives, and can be taken as it is
+    decompressor = cramjam.brotli.Decompressor()
+    assert decompressor.needs_more_input
+    half = len(compressed) // 2
+    assert decompressor.decompress(compressed[:half]) > 0
+    assert decompressor.needs_more_input and not decompressor.eof
+    first = bytes(decompressor.flush())
+    assert data.startswith(first)
+    decompressor.decompress(compressed[half:])
+    assert decompressor.eof and not decompressor.needs_more_input
+    assert first + bytes(decompressor.flush()) == data
+
+    decompressor = cramjam.brotli.Decompressor()
+    for i in range(0, len(compressed), 1000):
+        decompressor.decompress(compressed[i : i + 1000])
+    assert bytes(decompressor.finish()) == data
+
+    path = tmp_path / "compressed.br"
+    path.write_bytes(compressed)
+    decompressor = cramjam.brotli.Decompressor()
+    assert decompressor.decompress(cramjam.File(str(path))) == len(data)
+    assert bytes(decompressor.finish()) == data
+
+    decompressor = cramjam.brotli.Decompressor()
+    decompressor.decompress(compressed[:-1])
+    with pytest.raises(cramjam.DecompressionError):
+        decompressor.finish()
+
+    with pytest.raises(cramjam.DecompressionError):
+        cramjam.brotli.Decompressor().decompress(b"not brotli data" * 100)
+
+
+@pytest.mark.parametrize("variant_str", VARIANTS)
+def test_variants_decompress_into_buffer(variant_str):
+    variant = getattr(cramjam, variant_str)
+    if "into" not in variant.decompress.__text_signature__:
+        pytest.skip(f"{variant_str}.decompress has no into= parameter")
+
+    buffer = cramjam.Buffer()
+    for data in (b"some bytes here" * 1000, b"fewer bytes"):
+        out = variant.decompress(variant.compress(data), into=buffer)
+        # the same Buffer is returned, cleared and holding only the new output
+        assert out is buffer
+        assert bytes(out) == data
+        assert out.tell() == 0
+
+    out = variant.decompress(variant.compress(b"bytes"), output_len=5, into=buffer)
+    assert out is buffer and bytes(out) == b"bytes"
+
+    with pytest.raises(ValueError):
+        variant.decompress(buffer, into=buffer)
+
+
+@pytest.mark.parametrize("variant_str", VARIANTS)
+def test_variants_compress_into_align(variant_str):
+    variant = getattr(cramjam, variant_str)
+    data = b"some data to be padded" * 10
+
+    output = bytearray(1024)
+    written, padded = variant.compress_into(data, output, align=64)
+    assert padded % 64 == 0 and written <= padded < written + 64
+    assert bytes(output[written:padded]) == bytes(padded - written)
+    assert bytes(variant.decompress(bytes(output[:written]))) == data
+
+    buffer = cramjam.Buffer()
+    assert variant.compress_into(data, buffer, align=64) == (written, padded)
+    assert len(buffer) == padded
+
+    assert isinstance(variant.compress_into(data, bytearray(1024)), int)
+
+    with pytest.raises(ValueError, match="align"):
+        variant.compress_into(data, bytearray(1024), align=0)
+    with pytest.raises(cramjam.CompressionError, match="too small to pad"):
+        variant.compress_into(data, bytearray(written + 1), align=written + 2)
+
+
+@pytest.mark.parametrize("variant_str", VARIANTS)
+def test_variants_empty_input(variant_str):
+    variant = getattr(cramjam, variant_str)
+
+    compressed = bytes(variant.compress(b""))
+    assert bytes(variant.decompress(compressed)) == b""
+    output = bytearray(len(compressed) + 1024)
+    assert variant.compress_into(b"", output) == len(compressed)
+    assert bytes(output[: len(compressed)]) == compressed
+
+    assert bytes(variant.decompress(b"")) == b""
+    assert bytes(variant.decompress(b"", output_len=10)) == b""
+    assert variant.decompress_into(b"", bytearray(10)) == 0
+
+    decompressor = variant.Decompressor()
+    assert decompressor.decompress(b"") == 0
+    assert bytes(decompressor.finish()) == b""
+
+
+@pytest.mark.parametrize("variant_str", ("gzip", "zlib", "deflate"))
+def test_flate2_backend(variant_str):
+    variant = getattr(cramjam, variant_str)
+    assert variant.backend() in ("miniz_oxide", "zlib-ng")
+    assert variant.backend() == cramjam.gzip.backend()
+
+
+@pytest.mark.parametrize(
+    "variant_str, extension",
+    (
+        ("snappy", "sz"),
+        ("brotli", "br"),
+        ("bzip2", "bz2"),
+        ("lz4", "lz4"),
+        ("gzip", "gz"),
+        ("deflate", "deflate"),
+        ("zstd", "zst"),
+        ("zlib", "zz"),
+        ("xz", "xz"),
+    ),
+)
+def test_variants_path_helpers(variant_str, extension, tmp_path):
+    variant = getattr(cramjam, variant_str)
+    data = os.urandom(100_000) * 3
+    src = tmp_path / "data.csv"
+    src.write_bytes(data)
+
+    compressed = variant.compress_path(src)
+    assert compressed == f"{src}.{extension}"
+    assert bytes(variant.decompress(open(compressed, "rb").read())) == data
+
+    # existing files are only replaced when asked to
+    with pytest.raises(FileExistsError):
+        variant.decompress_path(compressed)
+    assert variant.decompress_path(compressed, overwrite=True) == str(src)
+    assert src.read_bytes() == data
+
+    other = tmp_path / "other.bin"
+    assert variant.decompress_path(str(compressed), dst=other) == str(other)
+    assert other.read_bytes() == data
+
+    with pytest.raises(ValueError):
+        variant.decompress_path(other)
+
+    # nothing is left behind when decompression fails
+    corrupt = tmp_path / f"corrupt.{extension}"
+    corrupt.write_bytes(b"corrupt" * 100)
+    with pytest.raises(cramjam.DecompressionError):
+        variant.decompress_path(corrupt)
+    assert not (tmp_path / "corrupt").exists()
+
+
+@pytest.mark.parametrize("variant_str", ("deflate", "xz"))
+def test_variants_decompress_expected(variant_str):
+    variant = getattr(cramjam, variant_str)
+    data = b"some data " * 1000
+    compressed = bytes(variant.compress(data))
+    crc32 = zlib.crc32(data)
+
+    out = variant.decompress(compressed, expected_len=len(data), expected_crc32=crc32)
+    assert bytes(out) == data
+
+    with pytest.raises(cramjam.DecompressionError, match="expected_len"):
+        variant.decompress(compressed, expected_len=len(data) - 1)
+    with pytest.raises(cramjam.DecompressionError, match="expected_crc32"):
+        variant.decompress(compressed, expected_crc32=crc32 ^ 1)
+    with pytest.raises(cramjam.DecompressionError, match="expected_crc32"):
+        variant.decompress(compressed, into=cramjam.Buffer(), expected_crc32=crc32 ^ 1)
+
+
+@pytest.mark.parametrize("variant_str", ("lz4", "xz"))
+def test_variants_unsafe_speed(variant_str):
+    variant = getattr(cramjam, variant_str)
+    data = b"some data " * 1000
+
+    compressed = bytes(variant.compress(data, unsafe_speed=True))
+    assert len(compressed) < len(bytes(variant.compress(data)))
+    assert bytes(variant.decompress(compressed)) == data
+
+    output = cramjam.Buffer()
+    variant.compress_into(data, output, unsafe_speed=True)
+    assert bytes(output) == compressed
+
+    compressor = variant.Compressor(unsafe_speed=True)
+    compressor.compress(data)
+    assert bytes(variant.decompress(compressor.finish())) == data
+
+    if variant_str == "xz":
+        # no integrity check in the stream header
+        assert compressed[7] == 0
+        with pytest.raises(ValueError):
+            variant.compress(data, check=cramjam.xz.Check.Crc32, unsafe_speed=True)
+    else:
+        # neither block nor content checksum in the frame descriptor
+        assert compressed[4] & 0x14 == 0
+        with pytest.raises(ValueError):
+            variant.Compressor(content_checksum=True, unsafe_speed=True)
+
+
+@pytest.mark.parametrize("variant_str", ("zstd", "lz4", "xz"))
+def test_variants_frames(variant_str, tmp_path):
+    variant = getattr(cramjam, variant_str)
+    members = [b"first frame " * 1000, b"", b"third frame"]
+    compressed = [bytes(variant.compress(member)) for member in members]
+    # skippable frames, or stream padding for xz, hold no data and are passed over
+    filler = b"\x00" * 4 if variant_str == "xz" else b"\x50\x2a\x4d\x18\x03\x00\x00\x00abc"
+    data = compressed[0] + filler + compressed[1] + compressed[2]
+
+    frames = list(variant.frames(data))
+    assert [bytes(frame) for _, _, frame in frames] == members
+    offsets = [0, len(compressed[0]) + len(filler), len(compressed[0]) + len(filler) + len(compressed[1])]
+    assert [(offset, length) for offset, length, _ in frames] == list(zip(offsets, map(len, compressed)))
+    assert list(variant.frames(b"")) == []
+
+    # frames before a broken one are given, then the error names its offset
+    iterator = variant.frames(data + compressed[0][:-3])
+    assert len([next(iterator) for _ in members]) == 3
+    with pytest.raises(cramjam.DecompressionError, match=f"offset {len(data)}"):
+        next(iterator)
+    assert list(iterator) == []
+
+    # a File is read from its current position, which the offsets are relative to
+    path = tmp_path / "frames"
+    path.write_bytes(b"header" + data)
+    file = cramjam.File(str(path))
+    file.seek(len(b"header"))
+    assert [(offset, length, bytes(frame)) for offset, length, frame in variant.frames(file)] == [
+        (offset, length, bytes(frame)) for offset, length, frame in frames
+    ]
+
+
 def test_buffer_cmp():
     assert cramjam.Buffer() == cramjam.Buffer()
     assert cramjam.Buffer(b"some bytes") == cramjam.Buffer(b"some bytes")
     assert cramjam.Buffer(b"some bytes") != cramjam.Buffer(b"other bytes")
+
+
+def test_buffer_cmp_bytes_like():
+    buf = cramjam.Buffer(b"some bytes")
+    assert buf == b"some bytes"
+    assert buf == bytearray(b"some bytes")
+    assert buf == memoryview(b"some bytes")
+    assert buf != b"other bytes"
+    assert buf != "some bytes"
+
+    assert buf.startswith(b"some")
+    assert buf.startswith((b"other", b"some"))
+    assert not buf.startswith(b"bytes")
+    assert buf.endswith(bytearray(b"bytes"))
+    assert buf.endswith((b"x", cramjam.Buffer(b"tes")))
+    assert not buf.endswith(b"some")
+
+
+def test_zstd_dict_id_in_error():
+    # frame header with a 1 byte dictionary ID of 42, followed by an empty raw block
+    frame = b"\x28\xb5\x2f\xfd" + b"\x01\x00\x2a" + b"\x01\x00\x00"
+    assert cramjam.zstd.get_dict_id(frame) == 42
+    assert cramjam.zstd.get_dict_id(cramjam.zstd.compress(b"bytes")) is None
+
+    with pytest.raises(cramjam.DecompressionError, match="dictionary ID 42") as exc:
+        cramjam.zstd.decompress(frame)
+    assert exc.value.dict_id == 42
+
+
+@pytest.mark.parametrize("variant_str", ("gzip", "zstd", "lz4"))
+def test_variants_test_integrity(variant_str, tmp_path):
+    variant = getattr(cramjam, variant_str)
+    data = b"some bytes here" * 100
+    compressed = bytes(variant.compress(data))
+
+    assert variant.test(compressed) == len(data)
+    if variant_str != "lz4":  # lz4 decompression stops after the first frame
+        assert variant.test(compressed + compressed) == 2 * len(data)
+
+    path = tmp_path / "compressed"
+    path.write_bytes(compressed)
+    assert variant.test(cramjam.File(str(path))) == len(data)
+
+    with pytest.raises(cramjam.DecompressionError):
+        variant.test(compressed[:-5])
+
+
+@pytest.mark.parametrize("readahead", (None, 7))
+def test_store_file_to_file(tmp_path, readahead):
+    data = os.urandom(100_000)
+    assert bytes(cramjam.store.decompress(cramjam.store.compress(data))) == data
+
+    src_path = tmp_path / "src"
+    src_path.write_bytes(data)
+    src = cramjam.File(str(src_path), readahead=readahead)
+    src.read(10)  # copies continue from the current position
+
+    dst = cramjam.File(str(tmp_path / "dst"))
+    assert cramjam.store.compress_into(src, dst) == len(data) - 10
+    assert (tmp_path / "dst").read_bytes() == data[10:]
+
+    src.seek(0)
+    other = cramjam.File(str(tmp_path / "other"))
+    assert other.write(src) == len(data)
+    assert (tmp_path / "other").read_bytes() == data
+
+
+@pytest.mark.parametrize("chunk_size", (1, 1000, 65536))
+def test_snappy_chunk_size(chunk_size):
+    data = bytes(range(256)) * 1000
+    compressed = cramjam.snappy.compress(data, chunk_size=chunk_size)
+    assert bytes(cramjam.snappy.decompress(compressed)) == data
+
+    output = cramjam.Buffer()
+    assert cramjam.snappy.compress_into(data, output, chunk_size=chunk_size) == len(output)
+
+    compressor = cramjam.snappy.Compressor(chunk_size=chunk_size)
+    for _ in range(10):
+        compressor.compress(data[:3000])
+    assert bytes(cramjam.snappy.decompress(compressor.finish())) == data[:3000] * 10
+
+    for invalid in (0, 65537):
+        with pytest.raises(ValueError):
+            cramjam.snappy.compress(data, chunk_size=invalid)
+
+
+def test_snappy_verify_checksums():
+    data = bytes(range(256)) * 1000
+    compressed = bytearray(bytes(cramjam.snappy.compress(data, chunk_size=1000)))
+    assert bytes(cramjam.snappy.decompress(compressed, verify_checksums=False)) == data
+
+    # corrupt the checksum of the first chunk, following the 10 byte stream identifier
+    compressed[14] ^= 0xFF
+    with pytest.raises(cramjam.snappy.ChecksumError):
+        cramjam.snappy.decompress(compressed)
+    assert issubclass(cramjam.snappy.ChecksumError, cramjam.DecompressionError)
+    with pytest.raises(cramjam.snappy.ChecksumError):
+        cramjam.snappy.Decompressor().decompress(bytes(compressed))
+
+    assert bytes(cramjam.snappy.decompress(compressed, verify_checksums=False)) == data
+    decompressor = cramjam.snappy.Decompressor(verify_checksums=False)
+    decompressor.decompress(bytes(compressed))
+    assert bytes(decompressor.finish()) == data
+
+    # corruption other than of checksums is still an error
+    for invalid in (compressed[:20], b"not snappy", compressed[:10] + b"\x05\x00\x00\x00data!"):
+        with pytest.raises(cramjam.DecompressionError):
+            cramjam.snappy.decompress(invalid, verify_checksums=False)
+
+
+def test_snappy_chunks(tmp_path):
+    data = os.urandom(1000) + bytes(range(256)) * 100
+    compressed = bytes(cramjam.snappy.compress(data, chunk_size=1000))
+
+    chunks = list(cramjam.snappy.chunks(compressed))
+    assert chunks[0] == (0xFF, 0, 6, None)
+    assert sum(4 + length for _, _, length, _ in chunks) == len(compressed)
+    # each data chunk decodes on its own after the stream identifier, and has the CRC32C of its data
+    for i, (chunk_type, offset, length, crc) in enumerate(chunks[1:]):
+        assert chunk_type in (0x00, 0x01)
+        chunk = bytes(cramjam.snappy.decompress(compressed[:10] + compressed[offset : offset + 4 + length]))
+        assert chunk == data[i * 1000 : (i + 1) * 1000]
+        assert crc == cramjam.checksum.crc32c(chunk)
+
+    # a File is read from its position, giving offsets within it
+    path = tmp_path / "data.sz"
+    path.write_bytes(b"header" + compressed)
+    file = cramjam.File(str(path), read=True)
+    file.seek(6)
+    assert [(t, o - 6, l, c) for t, o, l, c in cramjam.snappy.chunks(file)] == chunks
+
+    assert list(cramjam.snappy.chunks(b"")) == []
+    for invalid in (compressed[:-1], b"\x00\x05\x00\x00data!"):
+        with pytest.raises(cramjam.DecompressionError):
+            list(cramjam.snappy.chunks(invalid))
+
+
+@pytest.mark.parametrize("variant_str", ("lz4", "snappy"))
+@pytest.mark.parametrize("output_len", (1, 5000, 10000, 20000))
+def test_frame_decompress_output_len(variant_str, output_len):
+    variant = getattr(cramjam, variant_str)
+    data = b"some data" * 1000
+    compressed = variant.compress(data)
+
+    # output_len only sizes the initial allocation, the result is always the exact data
+    decompressed = variant.decompress(compressed, output_len=output_len)
+    assert len(decompressed) == len(data)
+    assert bytes(decompressed) == data
+
+
+@pytest.mark.parametrize("variant_str", VARIANTS)
+def test_variants_output_len_hint(variant_str):
+    variant = getattr(cramjam, variant_str)
+    data = b"some data" * 1000
+    compressed = bytes(variant.compress(data))
+
+    # output_len is only a hint, neither padding the output to it nor truncating the output
+    for output_len in (1, len(compressed), len(data), len(data) * 2):
+        assert bytes(variant.compress(data, output_len=output_len)) == compressed
+        assert bytes(variant.decompress(compressed, output_len=output_len)) == data
+
+
+@pytest.mark.parametrize("variant_str", VARIANTS)
+def test_variants_max_expansion_ratio(variant_str):
+    variant = getattr(cramjam, variant_str)
+    data = b"\0" * 1_000_000
+    compressed = bytes(variant.compress(data))
+
+    with pytest.raises(cramjam.DecompressionError, match="expansion ratio"):
+        variant.decompress(compressed, max_expansion_ratio=2)
+
+    cramjam.set_max_expansion_ratio(2)
+    try:
+        assert cramjam.get_max_expansion_ratio() == 2
+        with pytest.raises(cramjam.DecompressionError, match="expansion ratio"):
+            variant.decompress(compressed)
+
+        # a per call ratio overrides the process wide one, and compression is never limited
+        assert bytes(variant.decompress(compressed, max_expansion_ratio=float("inf"))) == data
+        assert bytes(variant.decompress(variant.compress(data), max_expansion_ratio=len(data))) == data
+    finally:
+        cramjam.set_max_expansion_ratio(None)
+    assert cramjam.get_max_expansion_ratio() is None
+
+    for invalid in (0, -1.0):
+        with pytest.raises(ValueError):
+            variant.decompress(compressed, max_expansion_ratio=invalid)
+
+
+@pytest.mark.parametrize("variant_str", VARIANTS)
+def test_variants_output_len_max(variant_str):
+    variant = getattr(cramjam, variant_str)
+    data = b"\0" * 1_000_000
+    compressed = bytes(variant.compress(data))
+
+    with pytest.raises(cramjam.DecompressionError, match="output_len_max"):
+        variant.decompress(compressed, output_len_max=len(data) - 1)
+    assert bytes(variant.decompress(compressed, output_len_max=len(data))) == data
+
+    # the lower of the two limits applies
+    with pytest.raises(cramjam.DecompressionError, match="expansion ratio"):
+        variant.decompress(compressed, max_expansion_ratio=2, output_len_max=len(data))
+    with pytest.raises(cramjam.DecompressionError):
+        variant.decompress(compressed, max_expansion_ratio=float("inf"), output_len_max=10)
+
+
+@pytest.mark.skipif(not sys.platform.startswith("linux"), reason="ru_maxrss is in KB on Linux")
+def test_expansion_limit_before_inflating():
+    # a 1GiB bomb fails once it's past the limit, rather than after being inflated in full; run
+    # in a fresh process so its peak memory isn't that of other tests
+    script = """
+import resource, zlib, cramjam
+
+def bomb(wbits):
+    compressor = zlib.compressobj(1, zlib.DEFLATED, wbits)
+    chunk = bytes(1 << 20)
+    return b"".join([compressor.compress(chunk) for _ in range(1024)] + [compressor.flush()])
+
+start = resource.getrusage(resource.RUSAGE_SELF).ru_maxrss
+for codec, wbits in (("gzip", 31), ("zlib", 15)):
+    data, variant = bomb(wbits), getattr(cramjam, codec)
+    for limit in (dict(max_expansion_ratio=10), dict(output_len_max=1 << 20)):
+        try:
+            variant.decompress(data, **limit)
+        except cramjam.DecompressionError:
+            pass
+        else:
+            raise AssertionError(codec)
+        _, errors = cramjam.decompress_batch([data], codec, **limit)
+        assert errors[0] is not None, codec
+print(resource.getrusage(resource.RUSAGE_SELF).ru_maxrss - start)
+"""
+    result = subprocess.run([sys.executable, "-c", script], capture_output=True, text=True, check=True)
+    assert int(result.stdout) < 256 * 1024, "peak memory grew by {}KB".format(result.stdout.strip())
+
+
+@pytest.mark.parametrize("variant_str", VARIANTS)
+def test_variants_decompressor_expansion_limit(variant_str, tmp_path):
+    variant = getattr(cramjam, variant_str)
+    data = b"\0" * 1_000_000
+    compressed = bytes(variant.compress(data))
+    path = tmp_path / "compressed"
+    path.write_bytes(compressed)
+
+    for input in (compressed, cramjam.File(str(path))):
+        with pytest.raises(cramjam.DecompressionError, match="expansion ratio"):
+            variant.Decompressor().decompress(input, max_expansion_ratio=2)
+    with pytest.raises(cramjam.DecompressionError, match="output_len_max"):
+        variant.Decompressor().decompress(compressed, output_len_max=len(data) - 1)
+
+    cramjam.set_max_expansion_ratio(2)
+    try:
+        with pytest.raises(cramjam.DecompressionError, match="expansion ratio"):
+            variant.Decompressor().decompress(compressed)
+        decompressor = variant.Decompressor()
+        decompressor.decompress(compressed, max_expansion_ratio=float("inf"))
+        assert bytes(decompressor.finish()) == data
+    finally:
+        cramjam.set_max_expansion_ratio(None)
+
+
+@pytest.mark.parametrize("variant_str", VARIANTS)
+def test_variants_output_allocator(variant_str):
+    variant = getattr(cramjam, variant_str)
+    if "into" not in variant.decompress.__text_signature__:
+        pytest.skip(f"{variant_str}.decompress has no into= parameter, nor output allocator")
+    data = b"some data " * 10_000
+    compressed = bytes(variant.compress(data))
+
+    allocated = []
+
+    def allocator(n):
+        allocated.append(np.zeros(n + 10, dtype=np.uint8))
+        return allocated[-1]
+
+    cramjam.set_output_allocator(allocator)
+    try:
+        assert cramjam.get_output_allocator() is allocator
+
+        # decompressed directly into the allocator's array, of which a view is returned
+        out = variant.decompress(compressed, output_len=len(data))
+        assert isinstance(out, memoryview)
+        assert bytes(out) == data
+        assert len(allocated) == 1 and bytes(allocated[0][: len(data)]) == data
+
+        # only used when the output size is given, and not into= a Buffer
+        assert isinstance(variant.decompress(compressed), cramjam.Buffer)
+        into = cramjam.Buffer()
+        assert variant.decompress(compressed, output_len=len(data), into=into) is into
+        assert len(allocated) == 1
+
+        with pytest.raises(cramjam.DecompressionError, match="output allocator"):
+            variant.decompress(compressed, output_len=100)
+
+        cramjam.set_output_allocator(lambda n: bytes(n))
+        with pytest.raises(TypeError):
+            variant.decompress(compressed, output_len=len(data))
+    finally:
+        cramjam.set_output_allocator(None)
+    assert cramjam.get_output_allocator() is None
+    assert isinstance(variant.decompress(compressed, output_len=len(data)), cramjam.Buffer)
+
+    with pytest.raises(TypeError):
+        cramjam.set_output_allocator(1)
+
+
+def test_zstd_compressor_end_frame():
+    compressor = cramjam.zstd.Compressor()
+    messages = [b"message %d " % i * 100 for i in range(5)]
+    frames = []
+    for message in messages:
+        compressor.compress(message)
+        frames.append(bytes(compressor.end_frame()))
+
+    # each frame stands alone, and together they form a valid multi-frame stream
+    assert [bytes(cramjam.zstd.decompress(frame)) for frame in frames] == messages
+    assert cramjam.info(b"".join(frames))["frames"] == len(messages)
+
+    compressor.compress(b"last")
+    assert bytes(cramjam.zstd.decompress(compressor.finish())) == b"last"
+    with pytest.raises(cramjam.CompressionError):
+        compressor.end_frame()
+
+
+def test_zstd_compressor_workers():
+    data = os.urandom(1 << 20) + b"a" * (4 << 20)
+    for options in ({"workers": 2}, {"workers": 4, "rsyncable": True, "job_size": 1 << 20}):
+        compressor = cramjam.zstd.Compressor(level=3, **options)
+        for i in range(0, len(data), 1 << 18):
+            compressor.compress(data[i : i + (1 << 18)])
+        first = bytes(compressor.end_frame())
+        compressor.compress(b"last")
+        compressed = first + bytes(compressor.finish())
+        assert bytes(cramjam.zstd.decompress(compressed)) == data + b"last"
+
+    # rsyncable and job_size only apply to multithreaded compression
+    with pytest.raises(ValueError):
+        cramjam.zstd.Compressor(rsyncable=True)
+    with pytest.raises(ValueError):
+        cramjam.zstd.Compressor(job_size=1 << 20)
+
+
+def test_zstd_frame_inspection(tmp_path):
+    first, second = b"first frame " * 100, b"second frame"
+    skippable = b"\x50\x2a\x4d\x18\x04\x00\x00\x00meta"
+    streamed = bytes(cramjam.zstd.compress(first))
+    # a frame recording its content size, of one raw block
+    sized = b"\x28\xb5\x2f\xfd\x20\x0c\x61\x00\x00" + second
+    data = streamed + skippable + sized
+
+    # walk the concatenated frames
+    offset, frames = 0, []
+    while offset < len(data):
+        size = cramjam.zstd.frame_compressed_size(data[offset:])
+        frames.append((cramjam.zstd.is_skippable_frame(data[offset:]), cramjam.zstd.frame_content_size(data[offset:])))
+        offset += size
+    assert offset == len(data)
+    assert frames == [(False, None), (True, 0), (False, len(second))]
+
+    assert cramjam.zstd.decompress_bound(sized) == len(second)
+    bound = cramjam.zstd.decompress_bound(data)
+    assert bound >= len(first + second)
+    output = cramjam.Buffer(capacity=bound)
+    assert cramjam.zstd.decompress_into(data, output) == len(first + second)
+
+    for invalid in (b"", b"not zstd", streamed[:3]):
+        with pytest.raises(cramjam.DecompressionError):
+            cramjam.zstd.frame_content_size(invalid)
+        with pytest.raises(cramjam.DecompressionError):
+            cramjam.zstd.frame_compressed_size(invalid)
+        assert not cramjam.zstd.is_skippable_frame(invalid)
+    with pytest.raises(cramjam.DecompressionError):
+        cramjam.zstd.decompress_bound(streamed[:-1])
+
+    # likewise through a File, from its current position, which is left as it was
+    path = tmp_path / "frames.zst"
+    path.write_bytes(data)
+    file = cramjam.File(str(path))
+    offset, file_frames = 0, []
+    while offset < len(data):
+        file.seek(offset)
+        size = cramjam.zstd.frame_compressed_size(file)
+        file_frames.append((cramjam.zstd.is_skippable_frame(file), cramjam.zstd.frame_content_size(file)))
+        assert file.tell() == offset
+        offset += size
+    assert file_frames == frames
+    file.seek(0)
+    assert cramjam.zstd.decompress_bound(file) == bound
+
+
+def test_zstd_seekable(tmp_path):
+    data = os.urandom(5000) + b"some bytes here" * 100_000
+    sink = io.BytesIO()
+    with cramjam.zstd.seekable.Writer(sink, level=3, frame_size=100_000) as writer:
+        writer.write(data[:1234])
+        writer.write(data[1234:50_000])
+        # flush ends the frame early
+        assert writer.flush() > 0
+        writer.write(data[50_000:])
+    assert writer.closed
+    compressed = sink.getvalue()
+
+    # any zstd decompressor passes over the seek table
+    assert bytes(cramjam.zstd.decompress(compressed)) == data
+    frames = list(cramjam.zstd.frames(compressed))
+    assert sum(len(frame) for _, _, frame in frames) == len(data)
+
+    reader = cramjam.zstd.seekable.Reader(compressed)
+    assert (reader.num_frames, reader.len()) == (len(frames), len(data))
+    assert bytes(reader.read()) == data
+    for offset, n in ((0, 10), (49_990, 20), (99_990, 300_000), (len(data) - 5, 100), (len(data) + 5, 3)):
+        assert reader.seek(offset) == offset
+        assert bytes(reader.read(n)) == data[offset : offset + n]
+    reader.seek(-100, 2)
+    assert bytes(reader.read()) == data[-100:]
+    assert reader.tell() == len(data)
+    with pytest.raises(ValueError):
+        reader.seek(-1)
+
+    # the seek table's checksums are those of the frames, the low 32 bits of their XXH64
+    table = compressed[-9 - 12 * len(frames) : -9]
+    checksums = [int.from_bytes(table[i + 8 : i + 12], "little") for i in range(0, len(table), 12)]
+    assert checksums == [cramjam.checksum.xxh64(frame) & 0xFFFFFFFF for _, _, frame in frames]
+
+    # a File is read from its position, only as needed
+    path = tmp_path / "data.zst"
+    path.write_bytes(b"header" + compressed)
+    file = cramjam.File(str(path), read=True)
+    file.seek(6)
+    reader = cramjam.zstd.seekable.Reader(file)
+    reader.seek(300_000)
+    assert bytes(reader.read(10)) == data[300_000:300_010]
+    # past its end, there's nothing to read a seek table from
+    file.seek(len(compressed) + 10)
+    with pytest.raises(ValueError, match="past the end"):
+        cramjam.zstd.seekable.Reader(file)
+
+    # a File or SpooledBuffer is written from its position, a frame at a time
+    path = tmp_path / "data"
+    path.write_bytes(b"header" + data)
+    file = cramjam.File(str(path), read=True)
+    file.seek(6)
+    spooled = cramjam.SpooledBuffer(max_memory=16, data=data, dir=tmp_path)
+    assert spooled.spilled
+    for source in (file, spooled):
+        sink = io.BytesIO()
+        with cramjam.zstd.seekable.Writer(sink, frame_size=100_000) as file_writer:
+            file_writer.write(source)
+        reader = cramjam.zstd.seekable.Reader(sink.getvalue())
+        assert reader.num_frames == -(-len(data) // 100_000)
+        assert bytes(reader.read()) == data
+    assert (file.tell(), spooled.tell()) == (6 + len(data), len(data))
+
+    with pytest.raises(ValueError):
+        writer.write(b"more")
+    with pytest.raises(ValueError):
+        cramjam.zstd.seekable.Writer(io.BytesIO(), frame_size=0)
+    for invalid in (b"", compressed[:-1], compressed[-100:], bytes(cramjam.zstd.compress(data))):
+        with pytest.raises(cramjam.DecompressionError, match="seek table"):
+            cramjam.zstd.seekable.Reader(invalid)
+    # a frame's size is checked before it's allocated to decompress into
+    forged = bytearray(compressed)
+    forged[-9 - 12 * len(frames) + 4 : -9 - 12 * len(frames) + 8] = (0xF0000000).to_bytes(4, "little")
+    with pytest.raises(cramjam.DecompressionError, match="frame 0 decompresses to"):
+        cramjam.zstd.seekable.Reader(bytes(forged))
+
+    # no data is a seek table of no frames
+    sink = io.BytesIO()
+    cramjam.zstd.seekable.Writer(sink).close()
+    assert bytes(cramjam.zstd.seekable.Reader(sink.getvalue()).read()) == b""
+    assert bytes(cramjam.zstd.decompress(sink.getvalue())) == b""
+
+
+def test_gzip_index(tmp_path):
+    import random
+
+    rng = random.Random(0)
+    words = [b"alpha", b"beta", b"gamma", os.urandom(7)]
+    data = b"".join(rng.choice(words) + b" %d\n" % i for i in range(200_000))
+    half = len(data) // 2
+    compressed = {
+        "level 1": gzip.compress(data, compresslevel=1),
+        "level 9": gzip.compress(data, compresslevel=9),
+        "stored": gzip.compress(data, compresslevel=0),
+        "members": gzip.compress(data[:half]) + gzip.compress(data[half:]),
+        "cramjam": bytes(cramjam.gzip.compress(data)),
+        # zero padded, between and after members, as gzip and Python's gzip module skip
+        "padded": gzip.compress(data[:half]) + bytes(10) + gzip.compress(data[half:]) + bytes(512),
+    }
+    assert gzip.decompress(compressed["padded"]) == data
+    for name, gz in compressed.items():
+        index = cramjam.gzip.build_index(gz, spacing=100_000)
+        assert (index.length, index.spacing) == (len(data), 100_000), name
+        assert len(index) > 1 and index.points[0][1] == 0, name
+        assert all(b - a >= 100_000 for (_, a), (_, b) in zip(index.points, index.points[1:])), name
+
+        reader = cramjam.gzip.Reader(gz, index)
+        for offset, n in ((0, 100), (half - 50, 100), (123_456, 300_000), (len(data) - 10, 100), (len(data) + 1, 4)):
+            assert reader.seek(offset) == offset
+            assert bytes(reader.read(n)) == data[offset : offset + n], (name, offset)
+        reader.seek(-100, 2)
+        assert bytes(reader.read()) == data[-100:]
+
+        # an index is reused through its serialized form
+        loaded = cramjam.gzip.Index.from_bytes(index.to_bytes())
+        assert (loaded.points, loaded.length) == (index.points, index.length)
+
+    # a File is read from its position, only as needed
+    path = tmp_path / "data.gz"
+    path.write_bytes(b"header" + compressed["level 9"])
+    file = cramjam.File(str(path), read=True)
+    file.seek(6)
+    index = cramjam.gzip.build_index(file)
+    file.seek(6)
+    reader = cramjam.gzip.Reader(file, index)
+    reader.seek(500_000)
+    assert bytes(reader.read(10)) == data[500_000:500_010]
+
+    empty = cramjam.gzip.build_index(b"")
+    assert (len(empty), empty.length, bytes(cramjam.gzip.Reader(b"", empty).read())) == (0, 0, b"")
+
+    gz = bytearray(compressed["level 9"])
+    gz[-8] ^= 1
+    trailing_junk = compressed["level 9"] + bytes(4) + b"junk"
+    for invalid in (bytes(gz), compressed["level 9"][:-3], trailing_junk, b"not gzip data"):
+        with pytest.raises(cramjam.DecompressionError):
+            cramjam.gzip.build_index(invalid)
+    with pytest.raises(ValueError):
+        cramjam.gzip.build_index(compressed["level 9"], spacing=0)
+    with pytest.raises(ValueError):
+        cramjam.gzip.Index.from_bytes(bytes(index.to_bytes())[:-1])
+
+    # a length past the end of the data isn't allocated up front, the data ending first
+    serialized = bytearray(index.to_bytes())
+    serialized[16:24] = (1 << 62).to_bytes(8, "little")
+    reader = cramjam.gzip.Reader(compressed["level 9"], cramjam.gzip.Index.from_bytes(serialized))
+    with pytest.raises(cramjam.DecompressionError):
+        reader.read()
+
+
+def test_lz4_compressor_output_file(tmp_path):
+    chunks = [os.urandom(100) + b"x" * 10_000 for _ in range(10)]
+
+    compressor = cramjam.lz4.Compressor(output=tmp_path / "path.lz4")
+    for chunk in chunks:
+        compressor.compress(chunk)
+    # everything goes straight to the file
+    assert bytes(compressor.flush()) == b""
+    assert bytes(compressor.finish()) == b""
+    assert bytes(cramjam.lz4.decompress((tmp_path / "path.lz4").read_bytes())) == b"".join(chunks)
+
+    # a cramjam.File is written from its current position
+    file = cramjam.File(str(tmp_path / "file.lz4"))
+    file.write(b"header")
+    compressor = cramjam.lz4.Compressor(output=file)
+    for chunk in chunks:
+        compressor.compress(chunk)
+    compressor.finish()
+    file.write(b"footer")
+    written = (tmp_path / "file.lz4").read_bytes()
+    assert written[:6] == b"header" and written[-6:] == b"footer"
+    assert bytes(cramjam.lz4.decompress(written[6:-6])) == b"".join(chunks)
+
+
+@pytest.mark.parametrize(
+    "variant_str, alias",
+    (("gzip", "compresslevel"), ("bzip2", "compresslevel"), ("brotli", "quality")),
+)
+def test_level_stdlib_aliases(variant_str, alias):
+    variant = getattr(cramjam, variant_str)
+    data = b"some bytes here" * 1000
+
+    expected = bytes(variant.compress(data, level=1))
+    assert bytes(variant.compress(data, **{alias: 1})) == expected
+
+    output = cramjam.Buffer()
+    variant.compress_into(data, output, **{alias: 1})
+    assert bytes(output) == expected
+
+    compressor = variant.Compressor(**{alias: 1})
+    compressor.compress(data)
+    assert bytes(variant.decompress(compressor.finish())) == data
+
+    with pytest.raises(TypeError):
+        variant.compress(data, level=1, **{alias: 1})
+
+
+@pytest.mark.parametrize("variant_str", ("zstd", "brotli", "gzip", "zlib", "deflate", "bzip2", "xz", "lz4"))
+def test_level_range(variant_str):
+    variant = getattr(cramjam, variant_str)
+    data = b"some bytes here" * 100
+    level = "preset" if variant_str == "xz" else "level"
+
+    assert variant.min_level() <= variant.default_level() <= variant.max_level()
+    for value in (variant.min_level(), variant.max_level()):
+        compressed = variant.compress(data, **{level: value})
+        assert bytes(variant.decompress(compressed)) == data
+    assert bytes(variant.compress(data, **{level: variant.default_level()})) == bytes(variant.compress(data))
+
+
+@pytest.mark.parametrize("variant_str", ("xz", "zstd", "gzip"))
+def test_compressor_output_len_hint(variant_str):
+    variant = getattr(cramjam, variant_str)
+    data = os.urandom(100_000)
+
+    compressor = variant.Compressor(output_len_hint=200_000)
+    assert compressor.reserved_capacity() >= 200_000
+    compressor.compress(data)
+    # the compressed output fits within what was reserved
+    assert compressor.reserved_capacity() >= 200_000
+    assert bytes(variant.decompress(compressor.finish())) == data
+    assert compressor.reserved_capacity() == 0
+
+    assert variant.Compressor().reserved_capacity() == 0
+
+
+@pytest.mark.parametrize("variant_str", ("xz", "zstd", "gzip"))
+def test_compressor_flush_hands_over_output(variant_str):
+    variant = getattr(cramjam, variant_str)
+    compressor = variant.Compressor(output_len_hint=200_000)
+    compressor.compress(b"some bytes here")
+
+    # the output's allocation is moved into the returned Buffer rather than copied
+    flushed = compressor.flush()
+    assert flushed.capacity() >= 200_000
+    # while output_len_hint is reserved afresh for what follows
+    assert compressor.reserved_capacity() >= 200_000
+
+    compressor.compress(b" and more")
+    flushed_again = compressor.flush()
+    assert flushed_again.capacity() >= 200_000
+    assert compressor.reserved_capacity() >= 200_000
+    output = bytes(flushed) + bytes(flushed_again) + bytes(compressor.finish())
+    assert bytes(variant.decompress(output)) == b"some bytes here and more"
+
+
+@pytest.mark.parametrize("variant_str", VARIANTS)
+def test_compressors_across_threads(variant_str):
+    # Compressors release the GIL while compressing, so several can be driven concurrently
+    from concurrent.futures import ThreadPoolExecutor
+
+    variant = getattr(cramjam, variant_str)
+    data = [os.urandom(64) * 1_000 for _ in range(8)]
+
+    def roundtrip(chunk):
+        compressor = variant.Compressor()
+        for i in range(0, len(chunk), 8_000):
+            compressor.compress(chunk[i : i + 8_000])
+        return bytes(variant.decompress(compressor.finish()))
+
+    with ThreadPoolExecutor(4) as pool:
+        assert list(pool.map(roundtrip, data)) == data